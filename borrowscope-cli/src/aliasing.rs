@@ -0,0 +1,475 @@
+//! Aliasing checks for raw pointers, loosely following Stacked Borrows
+//!
+//! Every tracked value gets a borrow stack. References and raw pointers are
+//! pushed when they are created from the value or from another item on its
+//! stack. Accessing an item pops what sits above it: a write removes
+//! everything, a read only removes `&mut` references. Using an item that was
+//! already popped is reported.
+//!
+//! Only uses visible in the trace count: reborrows, raw pointers taken from a
+//! reference, and raw pointer dereferences. Items are keyed by runtime ID,
+//! which `#[trace_borrow]` and the instrumenter with `tracking.unsafe_code`
+//! keep unique across calls, so same-named variables never share a stack,
+//! even in different functions. Conflicts between safe references alone are
+//! left to the compiler, so every report involves a raw pointer.
+
+use std::collections::HashMap;
+
+use borrowscope_runtime::Event;
+use serde::Serialize;
+
+/// What an item on a borrow stack is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Owner,
+    Unique,
+    Shared,
+    Raw,
+}
+
+impl ItemKind {
+    fn describe(self) -> &'static str {
+        match self {
+            ItemKind::Owner => "variable",
+            ItemKind::Unique => "&mut",
+            ItemKind::Shared => "&",
+            ItemKind::Raw => "raw pointer",
+        }
+    }
+}
+
+/// A use of a reference or pointer that an earlier access invalidated
+#[derive(Debug, Clone, Serialize)]
+pub struct AliasingReport {
+    /// Runtime ID of the item that was used
+    pub item_id: String,
+    pub item: String,
+    pub kind: ItemKind,
+    pub timestamp: u64,
+    /// Where the use was recorded, for raw pointer dereferences
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    pub invalidated_by: String,
+    pub invalidated_at: u64,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+}
+
+struct Item {
+    name: String,
+    kind: ItemKind,
+    /// Key of the borrow stack the item lives on
+    allocation: String,
+}
+
+/// Why an item was popped
+struct Invalidation {
+    cause: String,
+    kind: ItemKind,
+    at: u64,
+}
+
+#[derive(Default)]
+struct Model {
+    items: HashMap<String, Item>,
+    stacks: HashMap<String, Vec<String>>,
+    invalidated: HashMap<String, Invalidation>,
+    reports: Vec<AliasingReport>,
+}
+
+/// Replay the `events` through per-value borrow stacks
+pub fn check(events: &[Event]) -> Vec<AliasingReport> {
+    let mut model = Model::default();
+
+    for event in events {
+        match event {
+            Event::New {
+                var_name, var_id, ..
+            }
+            | Event::RcNew {
+                var_name, var_id, ..
+            }
+            | Event::ArcNew {
+                var_name, var_id, ..
+            }
+            | Event::RefCellNew {
+                var_name, var_id, ..
+            }
+            | Event::CellNew {
+                var_name, var_id, ..
+            } => model.allocate(var_id, var_name),
+
+            Event::Borrow {
+                timestamp,
+                borrower_name,
+                borrower_id,
+                owner_id,
+                mutable,
+            } => {
+                let kind = if *mutable {
+                    ItemKind::Unique
+                } else {
+                    ItemKind::Shared
+                };
+                let access = if *mutable {
+                    Access::Write
+                } else {
+                    Access::Read
+                };
+                model.derive(
+                    owner_id,
+                    borrower_id,
+                    borrower_name,
+                    kind,
+                    access,
+                    *timestamp,
+                );
+            }
+
+            Event::RawPtrCreated {
+                timestamp,
+                var_name,
+                var_id,
+                ptr_type,
+                source_id: Some(source),
+                ..
+            } => {
+                let access = if ptr_type.trim_start().starts_with("*const") {
+                    Access::Read
+                } else {
+                    Access::Write
+                };
+                model.derive(source, var_id, var_name, ItemKind::Raw, access, *timestamp);
+            }
+
+            Event::RawPtrDeref {
+                timestamp,
+                ptr_id,
+                location,
+                is_write,
+            } => {
+                let access = if *is_write {
+                    Access::Write
+                } else {
+                    Access::Read
+                };
+                if model.items.contains_key(ptr_id) {
+                    model.access(ptr_id, access, None, *timestamp, Some(location));
+                }
+            }
+
+            Event::Drop { var_id, .. }
+                if model
+                    .items
+                    .get(var_id)
+                    .is_some_and(|item| item.kind == ItemKind::Owner) =>
+            {
+                model.stacks.remove(var_id);
+            }
+
+            _ => {}
+        }
+    }
+
+    model.reports
+}
+
+fn verb(access: Access) -> &'static str {
+    match access {
+        Access::Read => "read",
+        Access::Write => "write",
+    }
+}
+
+impl Model {
+    fn allocate(&mut self, id: &str, name: &str) {
+        self.items.insert(
+            id.to_string(),
+            Item {
+                name: name.to_string(),
+                kind: ItemKind::Owner,
+                allocation: id.to_string(),
+            },
+        );
+        self.stacks.insert(id.to_string(), vec![id.to_string()]);
+    }
+
+    /// Create `id` from `parent`: an access through the parent, then a push
+    fn derive(
+        &mut self,
+        parent: &str,
+        id: &str,
+        name: &str,
+        kind: ItemKind,
+        access: Access,
+        at: u64,
+    ) {
+        if !self.items.contains_key(parent) {
+            return;
+        }
+        let cause = format!("the creation of {} '{}'", kind.describe(), name);
+        self.access(parent, access, Some((cause, kind)), at, None);
+
+        let allocation = self.items[parent].allocation.clone();
+        let Some(stack) = self.stacks.get_mut(&allocation) else {
+            return;
+        };
+        // A pointer taken from an item that is no longer valid is not valid either
+        if !stack.iter().any(|entry| entry == parent) {
+            return;
+        }
+        stack.push(id.to_string());
+        self.items.insert(
+            id.to_string(),
+            Item {
+                name: name.to_string(),
+                kind,
+                allocation,
+            },
+        );
+    }
+
+    /// Access the value through `id`, popping the items the access invalidates.
+    ///
+    /// `cause` describes the access for reports, when it is more than a plain
+    /// read or write through `id`.
+    fn access(
+        &mut self,
+        id: &str,
+        access: Access,
+        cause: Option<(String, ItemKind)>,
+        at: u64,
+        location: Option<&str>,
+    ) {
+        let item = &self.items[id];
+        let Some(stack) = self.stacks.get_mut(&item.allocation) else {
+            return;
+        };
+
+        let Some(position) = stack.iter().position(|entry| entry == id) else {
+            let Some(invalidation) = self.invalidated.get(id) else {
+                return;
+            };
+            if item.kind == ItemKind::Raw || invalidation.kind == ItemKind::Raw {
+                self.reports.push(AliasingReport {
+                    item_id: id.to_string(),
+                    item: item.name.clone(),
+                    kind: item.kind,
+                    timestamp: at,
+                    location: location.map(str::to_string),
+                    invalidated_by: invalidation.cause.clone(),
+                    invalidated_at: invalidation.at,
+                    message: format!(
+                        "{} '{}' was used after {} invalidated it",
+                        item.kind.describe(),
+                        item.name,
+                        invalidation.cause
+                    ),
+                });
+            }
+            return;
+        };
+
+        let (cause, cause_kind) = cause.unwrap_or_else(|| {
+            let cause = format!("a {} through '{}'", verb(access), item.name);
+            (cause, item.kind)
+        });
+        let above = stack.split_off(position + 1);
+        for entry in above {
+            let kind = self.items[&entry].kind;
+            if access == Access::Write || kind == ItemKind::Unique {
+                self.invalidated.insert(
+                    entry,
+                    Invalidation {
+                        cause: cause.clone(),
+                        kind: cause_kind,
+                        at,
+                    },
+                );
+            } else {
+                stack.push(entry);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new(timestamp: u64, name: &str) -> Event {
+        Event::New {
+            timestamp,
+            var_name: name.into(),
+            var_id: format!("{}_0", name),
+            type_name: "i32".into(),
+            scope_depth: None,
+        }
+    }
+
+    fn borrow(timestamp: u64, name: &str, owner: &str, mutable: bool) -> Event {
+        Event::Borrow {
+            timestamp,
+            borrower_name: name.into(),
+            borrower_id: format!("{}_b", name),
+            owner_id: owner.into(),
+            mutable,
+        }
+    }
+
+    fn ptr(timestamp: u64, name: &str, id: &str, source: &str) -> Event {
+        Event::RawPtrCreated {
+            timestamp,
+            var_name: name.into(),
+            var_id: id.into(),
+            ptr_type: "*mut i32".into(),
+            address: 4096,
+            location: "src/lib.rs:3:5".into(),
+            source_id: Some(source.into()),
+        }
+    }
+
+    fn deref(timestamp: u64, id: &str, is_write: bool) -> Event {
+        Event::RawPtrDeref {
+            timestamp,
+            ptr_id: id.into(),
+            location: format!("src/lib.rs:{}:9", timestamp),
+            is_write,
+        }
+    }
+
+    #[test]
+    fn test_raw_pointer_invalidated_by_mutable_borrow() {
+        let events = vec![
+            new(1, "x"),
+            ptr(2, "p", "1", "x_0"),
+            deref(3, "1", true),
+            borrow(4, "r", "x_0", true),
+            deref(5, "1", true),
+        ];
+
+        let reports = check(&events);
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.kind, ItemKind::Raw);
+        assert_eq!(report.invalidated_at, 4);
+        assert_eq!(report.location.as_deref(), Some("src/lib.rs:5:9"));
+        assert_eq!(
+            report.message,
+            "raw pointer 'p' was used after the creation of &mut 'r' invalidated it"
+        );
+    }
+
+    #[test]
+    fn test_mutable_borrow_invalidated_by_raw_write() {
+        let events = vec![
+            new(1, "x"),
+            ptr(2, "p", "1", "x_0"),
+            borrow(3, "r", "1", true),
+            deref(4, "1", true),
+            // Using `r` again, here by taking a pointer from it
+            ptr(5, "q", "2", "r_b"),
+        ];
+
+        let reports = check(&events);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].kind, ItemKind::Unique);
+        assert_eq!(
+            reports[0].message,
+            "&mut 'r' was used after a write through 'p' invalidated it"
+        );
+    }
+
+    #[test]
+    fn test_mutable_borrow_invalidated_by_new_raw_pointer() {
+        let events = vec![
+            new(1, "x"),
+            borrow(2, "r", "x_0", true),
+            ptr(3, "p", "1", "x_0"),
+            borrow(4, "s", "r_b", false),
+        ];
+
+        let reports = check(&events);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].invalidated_by, "the creation of raw pointer 'p'");
+    }
+
+    #[test]
+    fn test_pointer_derived_from_reference_stays_valid() {
+        let events = vec![
+            new(1, "x"),
+            borrow(2, "r", "x_0", true),
+            ptr(3, "p", "1", "r_b"),
+            deref(4, "1", true),
+            deref(5, "1", false),
+            borrow(6, "s", "r_b", false),
+        ];
+
+        assert!(check(&events).is_empty());
+    }
+
+    #[test]
+    fn test_read_keeps_shared_references() {
+        let events = vec![
+            new(1, "x"),
+            ptr(2, "p", "1", "x_0"),
+            borrow(3, "s", "1", false),
+            deref(4, "1", false),
+            ptr(5, "q", "2", "s_b"),
+        ];
+
+        assert!(check(&events).is_empty());
+    }
+
+    #[test]
+    fn test_same_named_variables_have_separate_stacks() {
+        // The IDs `#[trace_borrow(unsafe_code)]` records when `main` (IDs 1 to 3)
+        // calls `fill` (IDs from 4), each with its own `buf` and pointer:
+        //
+        //     fn fill() { let mut buf = 0; let q = &raw mut buf; unsafe { *q = 1 }; }
+        //     fn main() { let mut buf = 0; let p = &raw mut buf; fill(); unsafe { *p = 2 }; }
+        let buf = |timestamp, id: &str| Event::New {
+            timestamp,
+            var_name: "buf".into(),
+            var_id: id.into(),
+            type_name: "i32".into(),
+            scope_depth: None,
+        };
+        let events = vec![
+            buf(1, "buf_1"),
+            ptr(2, "p", "2", "buf_1"),
+            buf(3, "buf_4"),
+            ptr(4, "q", "5", "buf_4"),
+            deref(5, "5", true),
+            // `fill`'s `buf` is reborrowed mutably, popping `q` but not `p`
+            borrow(6, "r", "buf_4", true),
+            deref(7, "2", true),
+        ];
+
+        assert!(check(&events).is_empty());
+
+        let mut events = events;
+        events.push(deref(8, "5", true));
+        let reports = check(&events);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].item, "q");
+    }
+
+    #[test]
+    fn test_safe_references_alone_are_not_reported() {
+        let events = vec![
+            new(1, "x"),
+            borrow(2, "a", "x_0", true),
+            borrow(3, "b", "x_0", true),
+            borrow(4, "c", "a_b", false),
+        ];
+
+        assert!(check(&events).is_empty());
+    }
+}
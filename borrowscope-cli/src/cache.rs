@@ -0,0 +1,367 @@
+//! Persistent instrumentation cache
+//!
+//! Keeps an instrumented copy of a project under `target/borrowscope` and
+//! only re-instruments files whose contents changed since the previous run.
+//! The copy stays at the same path, so cargo's own incremental build state
+//! in `target/borrowscope/workspace/target` is reused as well.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::instrumentation::Instrumenter;
+use crate::source_map::{LineMap, SourceMap};
+
+/// Directory inside the cargo target directory holding the cache
+pub const CACHE_DIR: &str = "borrowscope";
+const WORKSPACE_DIR: &str = "workspace";
+const STATE_FILE: &str = "state.json";
+
+/// What was recorded about the copy after the last sync
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheState {
+    /// Fingerprint of the instrumentation settings the copy was made with
+    key: String,
+    /// Every source file, by path relative to the project root
+    files: BTreeMap<PathBuf, FileState>,
+}
+
+/// What was recorded about a single source file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FileState {
+    /// Content hash of the original file
+    hash: String,
+    /// Line map of the instrumented copy; empty for copied files
+    #[serde(default, skip_serializing_if = "LineMap::is_empty")]
+    lines: LineMap,
+}
+
+/// Counts from a single [`InstrumentationCache::sync`]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SyncStats {
+    /// Rust files instrumented again
+    pub instrumented: usize,
+    /// Other files copied again
+    pub copied: usize,
+    /// Files reused from the previous run
+    pub unchanged: usize,
+    /// Files deleted because they no longer exist in the project
+    pub removed: usize,
+}
+
+/// Instrumented copy of a project that is updated in place between runs
+pub struct InstrumentationCache {
+    source_root: PathBuf,
+    dir: PathBuf,
+    key: String,
+    skip: Vec<PathBuf>,
+}
+
+impl InstrumentationCache {
+    /// Cache for `source_root`, stored in `target_dir/borrowscope`
+    pub fn new(source_root: PathBuf, target_dir: &Path) -> Self {
+        Self {
+            dir: target_dir.join(CACHE_DIR),
+            skip: vec![source_root.join(".git"), target_dir.to_path_buf()],
+            source_root,
+            key: String::new(),
+        }
+    }
+
+    /// Fingerprint of the settings used to instrument; a different key re-instruments everything
+    pub fn key(mut self, settings: &str) -> Self {
+        self.key = content_hash(format!("{}\n{}", env!("CARGO_PKG_VERSION"), settings).as_bytes());
+        self
+    }
+
+    /// Root of the instrumented copy
+    pub fn workspace_dir(&self) -> PathBuf {
+        self.dir.join(WORKSPACE_DIR)
+    }
+
+    /// Bring the copy up to date with the project.
+    ///
+    /// `instrumenter_for` picks the instrumenter responsible for a Rust file,
+    /// or `None` to copy it unchanged. Files that fail to instrument are
+    /// copied as they are.
+    pub fn sync<'a>(
+        &self,
+        instrumenter_for: impl Fn(&Path) -> Option<&'a Instrumenter>,
+    ) -> Result<SyncStats> {
+        let previous = self.load_state();
+        let reuse = previous.key == self.key;
+        let workspace = self.workspace_dir();
+        fs::create_dir_all(&workspace)
+            .with_context(|| format!("Failed to create {}", workspace.display()))?;
+
+        let mut stats = SyncStats::default();
+        let mut state = CacheState {
+            key: self.key.clone(),
+            files: BTreeMap::new(),
+        };
+
+        let walker = WalkDir::new(&self.source_root)
+            .into_iter()
+            .filter_entry(|entry| !self.skip.iter().any(|skip| entry.path() == skip));
+
+        for entry in walker {
+            let entry = entry.context("Failed to read project directory")?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let source = entry.path();
+            let relative = source
+                .strip_prefix(&self.source_root)
+                .unwrap_or(source)
+                .to_path_buf();
+            let contents =
+                fs::read(source).with_context(|| format!("Failed to read {}", source.display()))?;
+            let hash = content_hash(&contents);
+            let dest = workspace.join(&relative);
+
+            if let Some(file) = previous.files.get(&relative) {
+                if reuse && file.hash == hash && dest.exists() {
+                    stats.unchanged += 1;
+                    state.files.insert(relative, file.clone());
+                    continue;
+                }
+            }
+
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let instrumenter = match source.extension() {
+                Some(ext) if ext == "rs" => instrumenter_for(&relative)
+                    .filter(|instrumenter| !instrumenter.should_ignore(source)),
+                _ => None,
+            };
+
+            let instrumented = match instrumenter {
+                Some(instrumenter) => match instrumenter.instrument_file_mapped(source) {
+                    Ok((_, lines)) => Some(lines),
+                    Err(e) => {
+                        log::warn!("Copying {} uninstrumented: {}", relative.display(), e);
+                        None
+                    }
+                },
+                None => None,
+            };
+
+            let lines = match instrumented {
+                Some(lines) => {
+                    stats.instrumented += 1;
+                    lines
+                }
+                None => {
+                    fs::write(&dest, &contents)
+                        .with_context(|| format!("Failed to write {}", dest.display()))?;
+                    stats.copied += 1;
+                    LineMap::default()
+                }
+            };
+            state.files.insert(relative, FileState { hash, lines });
+        }
+
+        for relative in previous.files.keys() {
+            if !state.files.contains_key(relative) {
+                let _ = fs::remove_file(workspace.join(relative));
+                stats.removed += 1;
+            }
+        }
+
+        self.save_state(&state)?;
+        Ok(stats)
+    }
+
+    /// Line maps of the instrumented files as of the last sync
+    pub fn source_map(&self) -> SourceMap {
+        let mut map = SourceMap::new(self.workspace_dir());
+        for (relative, file) in self.load_state().files {
+            if !file.lines.is_empty() {
+                map.insert(relative, file.lines);
+            }
+        }
+        map
+    }
+
+    fn load_state(&self) -> CacheState {
+        fs::read_to_string(self.dir.join(STATE_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_state(&self, state: &CacheState) -> Result<()> {
+        let path = self.dir.join(STATE_FILE);
+        fs::write(&path, serde_json::to_string_pretty(state)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Stable 64-bit FNV-1a hash, hex encoded
+fn content_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrumentation::InstrumentationConfig;
+    use tempfile::TempDir;
+
+    struct Fixture {
+        _temp: TempDir,
+        project: PathBuf,
+        target: PathBuf,
+        instrumenter: Instrumenter,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let temp = TempDir::new().unwrap();
+            let project = temp.path().to_path_buf();
+            let target = project.join("target");
+            fs::create_dir_all(project.join("src")).unwrap();
+            fs::write(project.join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+            fs::write(project.join("src/main.rs"), "fn main() { let x = 1; }").unwrap();
+            fs::write(project.join("src/util.rs"), "pub fn util() { let y = 2; }").unwrap();
+
+            let workspace = target.join(CACHE_DIR).join(WORKSPACE_DIR);
+            let instrumenter =
+                Instrumenter::new(project.clone(), workspace, InstrumentationConfig::default());
+            Self {
+                _temp: temp,
+                project,
+                target,
+                instrumenter,
+            }
+        }
+
+        fn cache(&self, key: &str) -> InstrumentationCache {
+            InstrumentationCache::new(self.project.clone(), &self.target).key(key)
+        }
+
+        fn sync(&self, key: &str) -> SyncStats {
+            self.cache(key).sync(|_| Some(&self.instrumenter)).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_first_sync_instruments_everything() {
+        let fixture = Fixture::new();
+        let stats = fixture.sync("settings");
+
+        assert_eq!(stats.instrumented, 2);
+        assert_eq!(stats.copied, 1);
+        assert_eq!(stats.unchanged, 0);
+
+        let main = fs::read_to_string(
+            fixture
+                .cache("settings")
+                .workspace_dir()
+                .join("src/main.rs"),
+        )
+        .unwrap();
+        assert!(main.contains("track_new"));
+    }
+
+    #[test]
+    fn test_only_changed_files_reinstrumented() {
+        let fixture = Fixture::new();
+        fixture.sync("settings");
+
+        fs::write(
+            fixture.project.join("src/util.rs"),
+            "pub fn util() { let z = 3; }",
+        )
+        .unwrap();
+        let stats = fixture.sync("settings");
+
+        assert_eq!(stats.instrumented, 1);
+        assert_eq!(stats.unchanged, 2);
+        let util = fs::read_to_string(
+            fixture
+                .cache("settings")
+                .workspace_dir()
+                .join("src/util.rs"),
+        )
+        .unwrap();
+        assert!(util.contains(r#"track_new_in_scope("z", 0usize, 3)"#));
+    }
+
+    #[test]
+    fn test_settings_change_invalidates_cache() {
+        let fixture = Fixture::new();
+        fixture.sync("settings");
+
+        let stats = fixture.sync("other settings");
+        assert_eq!(stats.instrumented, 2);
+        assert_eq!(stats.unchanged, 0);
+    }
+
+    #[test]
+    fn test_deleted_files_removed() {
+        let fixture = Fixture::new();
+        fixture.sync("settings");
+
+        fs::remove_file(fixture.project.join("src/util.rs")).unwrap();
+        let stats = fixture.sync("settings");
+
+        assert_eq!(stats.removed, 1);
+        assert!(!fixture
+            .cache("settings")
+            .workspace_dir()
+            .join("src/util.rs")
+            .exists());
+    }
+
+    #[test]
+    fn test_target_dir_not_copied() {
+        let fixture = Fixture::new();
+        fs::create_dir_all(fixture.target.join("debug")).unwrap();
+        fs::write(fixture.target.join("debug/app"), "binary").unwrap();
+
+        fixture.sync("settings");
+        let workspace = fixture.cache("settings").workspace_dir();
+        assert!(!workspace.join("target/debug/app").exists());
+    }
+
+    #[test]
+    fn test_source_map_survives_reuse() {
+        let fixture = Fixture::new();
+        fixture.sync("settings");
+        let stats = fixture.sync("settings");
+        assert_eq!(stats.unchanged, 3);
+
+        let mut error: crate::cargo::Diagnostic = serde_json::from_value(serde_json::json!({
+            "level": "error",
+            "message": "unused variable",
+            "spans": [{
+                "file_name": "src/main.rs",
+                "line_start": 6,
+                "line_end": 6,
+                "column_start": 5,
+                "column_end": 6,
+                "is_primary": true
+            }]
+        }))
+        .unwrap();
+        fixture.cache("settings").source_map().remap(&mut error);
+        assert_eq!(error.spans[0].line_start, 1);
+    }
+
+    #[test]
+    fn test_content_hash_is_stable() {
+        assert_eq!(content_hash(b""), "cbf29ce484222325");
+        assert_eq!(content_hash(b"a"), "af63dc4c8601ec8c");
+        assert_ne!(content_hash(b"fn a() {}"), content_hash(b"fn b() {}"));
+    }
+}
@@ -12,8 +12,10 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output};
+use std::process::{Command, Output, Stdio};
+use std::thread;
 
 /// Cargo project metadata
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -30,6 +32,26 @@ pub struct CargoMetadata {
     pub version: u32,
 }
 
+impl CargoMetadata {
+    /// Packages that are members of the workspace
+    pub fn members(&self) -> impl Iterator<Item = &Package> {
+        self.packages
+            .iter()
+            .filter(|p| self.workspace_members.contains(&p.id))
+    }
+
+    /// Find a workspace member by package name
+    pub fn member(&self, name: &str) -> Option<&Package> {
+        self.members().find(|p| p.name == name)
+    }
+
+    /// Find the workspace member whose manifest lives in `dir`
+    pub fn member_at(&self, dir: &Path) -> Option<&Package> {
+        let dir = dir.canonicalize().ok()?;
+        self.members().find(|p| p.manifest_dir() == dir)
+    }
+}
+
 /// Package information
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Package {
@@ -49,6 +71,16 @@ pub struct Package {
     pub features: HashMap<String, Vec<String>>,
 }
 
+impl Package {
+    /// Directory containing the package's `Cargo.toml`
+    pub fn manifest_dir(&self) -> PathBuf {
+        Path::new(&self.manifest_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+}
+
 /// Dependency information
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Dependency {
@@ -60,6 +92,9 @@ pub struct Dependency {
     pub kind: Option<String>,
     /// Whether dependency is optional
     pub optional: bool,
+    /// Local path for path dependencies
+    #[serde(default)]
+    pub path: Option<String>,
 }
 
 /// Build target information
@@ -78,6 +113,9 @@ pub struct Target {
 /// Builder for cargo build operations
 pub struct CargoBuilder {
     project_path: PathBuf,
+    packages: Vec<String>,
+    workspace: bool,
+    tests: bool,
     release: bool,
     features: Vec<String>,
     target: Option<String>,
@@ -91,6 +129,9 @@ impl CargoBuilder {
     pub fn new(project_path: PathBuf) -> Self {
         Self {
             project_path,
+            packages: Vec::new(),
+            workspace: false,
+            tests: false,
             release: false,
             features: Vec::new(),
             target: None,
@@ -100,6 +141,24 @@ impl CargoBuilder {
         }
     }
 
+    /// Build only these workspace members (`-p`)
+    pub fn packages(mut self, packages: Vec<String>) -> Self {
+        self.packages = packages;
+        self
+    }
+
+    /// Build every workspace member (`--workspace`)
+    pub fn workspace(mut self, workspace: bool) -> Self {
+        self.workspace = workspace;
+        self
+    }
+
+    /// Also build the test targets (`--tests`)
+    pub fn tests(mut self, tests: bool) -> Self {
+        self.tests = tests;
+        self
+    }
+
     /// Set release mode
     pub fn release(mut self, release: bool) -> Self {
         self.release = release;
@@ -138,10 +197,28 @@ impl CargoBuilder {
 
     /// Execute the build
     pub fn build(&self) -> Result<BuildResult> {
+        self.build_with(|_| {})
+    }
+
+    /// Execute the build, reporting each compiled crate and compiler message as it happens
+    pub fn build_with(&self, mut on_event: impl FnMut(BuildEvent)) -> Result<BuildResult> {
         let mut cmd = Command::new("cargo");
         cmd.arg("build");
         cmd.current_dir(&self.project_path);
 
+        if self.workspace {
+            cmd.arg("--workspace");
+        }
+
+        for package in &self.packages {
+            cmd.arg("--package");
+            cmd.arg(package);
+        }
+
+        if self.tests {
+            cmd.arg("--tests");
+        }
+
         if self.release {
             cmd.arg("--release");
         }
@@ -169,10 +246,58 @@ impl CargoBuilder {
         }
 
         cmd.arg("--message-format=json");
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        let mut child = cmd.spawn().context("Failed to execute cargo build")?;
+        // Drain stderr on the side so a chatty build never blocks on a full pipe
+        let stderr = child.stderr.take().map(|mut stderr| {
+            thread::spawn(move || {
+                let mut buf = String::new();
+                let _ = stderr.read_to_string(&mut buf);
+                buf
+            })
+        });
+
+        let mut result = BuildResult {
+            success: false,
+            artifacts: Vec::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        if let Some(stdout) = child.stdout.take() {
+            for line in BufReader::new(stdout).lines() {
+                let line = line.context("Failed to read cargo build output")?;
+                parse_build_message(&line, &mut result, &mut on_event);
+            }
+        }
 
-        let output = cmd.output().context("Failed to execute cargo build")?;
+        let status = child.wait().context("Failed to wait for cargo build")?;
+        let stderr = stderr
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default();
+        result.success = status.success();
+        if !result.success && result.errors.is_empty() && !stderr.is_empty() {
+            result.errors.push(stderr);
+        }
 
-        parse_build_output(output)
+        Ok(result)
+    }
+
+    /// Rough number of crates a build compiles, from the lock file; `None` before the first build
+    pub fn estimate_crates(&self) -> Option<u64> {
+        let lock = self
+            .project_path
+            .ancestors()
+            .map(|dir| dir.join("Cargo.lock"))
+            .find(|path| path.exists())?;
+        let contents = std::fs::read_to_string(lock).ok()?;
+        Some(
+            contents
+                .lines()
+                .filter(|line| line.trim() == "[[package]]")
+                .count() as u64,
+        )
     }
 
     /// Clean build artifacts
@@ -202,6 +327,81 @@ pub struct BuildResult {
     pub errors: Vec<String>,
     /// Compilation warnings
     pub warnings: Vec<String>,
+    /// Structured compiler messages, with their source locations
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Compiler message from cargo's JSON output
+#[derive(Debug, Clone, Deserialize)]
+pub struct Diagnostic {
+    /// Severity, such as `error` or `warning`
+    pub level: String,
+    /// Main message
+    pub message: String,
+    /// Source locations the message points at
+    #[serde(default)]
+    pub spans: Vec<DiagnosticSpan>,
+    /// Message as rustc would print it
+    #[serde(default)]
+    pub rendered: Option<String>,
+}
+
+impl Diagnostic {
+    /// The span the message is mainly about
+    pub fn primary_span(&self) -> Option<&DiagnosticSpan> {
+        self.spans.iter().find(|span| span.is_primary)
+    }
+}
+
+/// Source location of a compiler message
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiagnosticSpan {
+    /// File, relative to the workspace root
+    pub file_name: PathBuf,
+    /// 1-based first line
+    pub line_start: usize,
+    /// 1-based last line
+    pub line_end: usize,
+    /// 1-based first column
+    pub column_start: usize,
+    /// 1-based column after the span
+    pub column_end: usize,
+    /// Whether this is the main location of the message
+    pub is_primary: bool,
+    /// Source lines covered by the span
+    #[serde(default)]
+    pub text: Vec<DiagnosticText>,
+}
+
+impl DiagnosticSpan {
+    /// The highlighted part of each covered source line
+    pub fn highlighted(&self) -> impl Iterator<Item = &str> {
+        self.text.iter().filter_map(|line| {
+            let start = line.highlight_start.saturating_sub(1);
+            let end = line.highlight_end.saturating_sub(1);
+            line.text.get(start..end)
+        })
+    }
+}
+
+/// Source line covered by a diagnostic span
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiagnosticText {
+    /// The full line
+    pub text: String,
+    /// 1-based column where the highlight starts
+    pub highlight_start: usize,
+    /// 1-based column where the highlight ends
+    pub highlight_end: usize,
+}
+
+/// Something that happened during a streamed build
+#[derive(Debug, Clone, Copy)]
+pub enum BuildEvent<'a> {
+    /// A crate finished compiling, or was already up to date
+    Artifact(&'a Artifact),
+    /// The compiler reported a warning or error
+    Diagnostic(&'a Diagnostic),
 }
 
 /// Build artifact
@@ -215,54 +415,103 @@ pub struct Artifact {
     pub fresh: bool,
 }
 
-fn parse_build_output(output: Output) -> Result<BuildResult> {
-    let mut result = BuildResult {
-        success: output.status.success(),
-        artifacts: Vec::new(),
-        errors: Vec::new(),
-        warnings: Vec::new(),
+/// Record one line of `--message-format=json` output and report it to `on_event`
+fn parse_build_message(
+    line: &str,
+    result: &mut BuildResult,
+    on_event: &mut impl FnMut(BuildEvent),
+) {
+    let Ok(message) = serde_json::from_str::<serde_json::Value>(line) else {
+        return;
     };
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    match message.get("reason").and_then(|r| r.as_str()) {
+        Some("compiler-artifact") => {
+            if let Some(artifact) = parse_artifact(&message) {
+                result.artifacts.push(artifact);
+                if let Some(artifact) = result.artifacts.last() {
+                    on_event(BuildEvent::Artifact(artifact));
+                }
+            }
+        }
+        Some("compiler-message") => {
+            if let Some(msg) = message.get("message") {
+                if let Some(level) = msg.get("level").and_then(|l| l.as_str()) {
+                    let text = msg
+                        .get("message")
+                        .and_then(|m| m.as_str())
+                        .unwrap_or("")
+                        .to_string();
 
-    for line in stdout.lines() {
-        if let Ok(message) = serde_json::from_str::<serde_json::Value>(line) {
-            match message.get("reason").and_then(|r| r.as_str()) {
-                Some("compiler-artifact") => {
-                    if let Some(artifact) = parse_artifact(&message) {
-                        result.artifacts.push(artifact);
+                    match level {
+                        "error" => result.errors.push(text),
+                        "warning" => result.warnings.push(text),
+                        _ => {}
                     }
                 }
-                Some("compiler-message") => {
-                    if let Some(msg) = message.get("message") {
-                        if let Some(level) = msg.get("level").and_then(|l| l.as_str()) {
-                            let text = msg
-                                .get("message")
-                                .and_then(|m| m.as_str())
-                                .unwrap_or("")
-                                .to_string();
-
-                            match level {
-                                "error" => result.errors.push(text),
-                                "warning" => result.warnings.push(text),
-                                _ => {}
-                            }
-                        }
+                if let Ok(diagnostic) = serde_json::from_value(msg.clone()) {
+                    result.diagnostics.push(diagnostic);
+                    if let Some(diagnostic) = result.diagnostics.last() {
+                        on_event(BuildEvent::Diagnostic(diagnostic));
                     }
                 }
-                _ => {}
             }
         }
+        _ => {}
     }
+}
 
-    if !output.status.success() && result.errors.is_empty() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        if !stderr.is_empty() {
-            result.errors.push(stderr.to_string());
-        }
-    }
-
-    Ok(result)
+/// Run `cmd`, echoing its stdout and stderr line by line while also capturing them
+///
+/// The build is already done by then, so cargo runs with `--message-format=json`
+/// and its messages up to `build-finished` are dropped rather than replaying
+/// every compiler warning ahead of the program's output.
+fn output_passthrough(cmd: &mut Command) -> std::io::Result<Output> {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = cmd.spawn()?;
+
+    fn tee(
+        stream: impl Read + Send + 'static,
+        mut sink: impl Write + Send + 'static,
+        mut building: bool,
+    ) -> thread::JoinHandle<Vec<u8>> {
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stream);
+            let mut captured = Vec::new();
+            let mut line = Vec::new();
+            while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+                if building {
+                    if let Ok(message) = serde_json::from_slice::<serde_json::Value>(&line) {
+                        if let Some(reason) = message.get("reason").and_then(|r| r.as_str()) {
+                            building = reason != "build-finished";
+                            line.clear();
+                            continue;
+                        }
+                    }
+                }
+                let _ = sink.write_all(&line);
+                let _ = sink.flush();
+                captured.append(&mut line);
+            }
+            captured
+        })
+    }
+
+    let stdout = child
+        .stdout
+        .take()
+        .map(|out| tee(out, std::io::stdout(), true));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|err| tee(err, std::io::stderr(), false));
+    let status = child.wait()?;
+
+    Ok(Output {
+        status,
+        stdout: stdout.and_then(|h| h.join().ok()).unwrap_or_default(),
+        stderr: stderr.and_then(|h| h.join().ok()).unwrap_or_default(),
+    })
 }
 
 fn parse_artifact(message: &serde_json::Value) -> Option<Artifact> {
@@ -291,10 +540,12 @@ fn parse_artifact(message: &serde_json::Value) -> Option<Artifact> {
 /// Runner for cargo run operations
 pub struct CargoRunner {
     project_path: PathBuf,
+    package: Option<String>,
     release: bool,
     args: Vec<String>,
     env: HashMap<String, String>,
     example: Option<String>,
+    passthrough: bool,
 }
 
 impl CargoRunner {
@@ -302,10 +553,12 @@ impl CargoRunner {
     pub fn new(project_path: PathBuf) -> Self {
         Self {
             project_path,
+            package: None,
             release: false,
             args: Vec::new(),
             env: HashMap::new(),
             example: None,
+            passthrough: false,
         }
     }
 
@@ -315,6 +568,12 @@ impl CargoRunner {
         self
     }
 
+    /// Run a binary from this workspace member (`-p`)
+    pub fn package(mut self, package: String) -> Self {
+        self.package = Some(package);
+        self
+    }
+
     /// Set program arguments
     pub fn args(mut self, args: Vec<String>) -> Self {
         self.args = args;
@@ -333,12 +592,23 @@ impl CargoRunner {
         self
     }
 
+    /// Echo the program's stdout and stderr as they are produced, still capturing them
+    pub fn passthrough(mut self, passthrough: bool) -> Self {
+        self.passthrough = passthrough;
+        self
+    }
+
     /// Execute the program
     pub fn run(&self) -> Result<Output> {
         let mut cmd = Command::new("cargo");
         cmd.arg("run");
         cmd.current_dir(&self.project_path);
 
+        if let Some(ref package) = self.package {
+            cmd.arg("--package");
+            cmd.arg(package);
+        }
+
         if self.release {
             cmd.arg("--release");
         }
@@ -348,6 +618,12 @@ impl CargoRunner {
             cmd.arg(example);
         }
 
+        if self.passthrough {
+            // Only the program's own output is passed through, not cargo's
+            cmd.arg("--quiet");
+            cmd.arg("--message-format=json");
+        }
+
         if !self.args.is_empty() {
             cmd.arg("--");
             cmd.args(&self.args);
@@ -357,6 +633,10 @@ impl CargoRunner {
             cmd.env(key, value);
         }
 
+        if self.passthrough {
+            return output_passthrough(&mut cmd).context("Failed to execute cargo run");
+        }
+
         cmd.output().context("Failed to execute cargo run")
     }
 
@@ -373,6 +653,169 @@ impl CargoRunner {
     }
 }
 
+/// Runner for cargo test operations
+pub struct CargoTester {
+    project_path: PathBuf,
+    packages: Vec<String>,
+    workspace: bool,
+    release: bool,
+    features: Vec<String>,
+    filter: Option<String>,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    passthrough: bool,
+}
+
+impl CargoTester {
+    /// Create a new cargo tester
+    pub fn new(project_path: PathBuf) -> Self {
+        Self {
+            project_path,
+            packages: Vec::new(),
+            workspace: false,
+            release: false,
+            features: Vec::new(),
+            filter: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+            passthrough: false,
+        }
+    }
+
+    /// Test only these workspace members (`-p`)
+    pub fn packages(mut self, packages: Vec<String>) -> Self {
+        self.packages = packages;
+        self
+    }
+
+    /// Test every workspace member (`--workspace`)
+    pub fn workspace(mut self, workspace: bool) -> Self {
+        self.workspace = workspace;
+        self
+    }
+
+    /// Set release mode
+    pub fn release(mut self, release: bool) -> Self {
+        self.release = release;
+        self
+    }
+
+    /// Set features to enable
+    pub fn features(mut self, features: Vec<String>) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Only run tests whose name contains `filter`
+    pub fn filter(mut self, filter: String) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Set arguments for the test harness
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Add environment variable
+    pub fn env(mut self, key: String, value: String) -> Self {
+        self.env.insert(key, value);
+        self
+    }
+
+    /// Echo test output as it is produced, still capturing it
+    pub fn passthrough(mut self, passthrough: bool) -> Self {
+        self.passthrough = passthrough;
+        self
+    }
+
+    /// Run the tests; failing tests do not make this an error
+    pub fn run(&self) -> Result<Output> {
+        let mut cmd = Command::new("cargo");
+        cmd.arg("test");
+        cmd.current_dir(&self.project_path);
+
+        if self.workspace {
+            cmd.arg("--workspace");
+        }
+
+        for package in &self.packages {
+            cmd.arg("--package");
+            cmd.arg(package);
+        }
+
+        if self.release {
+            cmd.arg("--release");
+        }
+
+        if !self.features.is_empty() {
+            cmd.arg("--features");
+            cmd.arg(self.features.join(","));
+        }
+
+        // Keep going after a failing test binary so every test gets a trace
+        cmd.arg("--no-fail-fast");
+
+        if self.passthrough {
+            cmd.arg("--message-format=json");
+        }
+
+        if self.filter.is_some() || !self.args.is_empty() {
+            cmd.arg("--");
+            cmd.args(&self.filter);
+            cmd.args(&self.args);
+        }
+
+        for (key, value) in &self.env {
+            cmd.env(key, value);
+        }
+
+        if self.passthrough {
+            return output_passthrough(&mut cmd).context("Failed to execute cargo test");
+        }
+
+        cmd.output().context("Failed to execute cargo test")
+    }
+}
+
+/// Outcome of a single test case
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+/// A test case reported by the libtest harness
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestOutcome {
+    /// Test path relative to its crate, e.g. `tests::adds`
+    pub name: String,
+    pub status: TestStatus,
+}
+
+/// Parse `test <name> ... ok|FAILED|ignored` lines from `cargo test` output
+pub fn parse_test_results(stdout: &str) -> Vec<TestOutcome> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("test ")?;
+            let (name, status) = rest.rsplit_once(" ... ")?;
+            let status = match status.trim() {
+                "ok" => TestStatus::Passed,
+                "FAILED" => TestStatus::Failed,
+                s if s.starts_with("ignored") => TestStatus::Ignored,
+                _ => return None,
+            };
+            Some(TestOutcome {
+                name: name.trim().to_string(),
+                status,
+            })
+        })
+        .collect()
+}
+
 /// Get cargo metadata for a project
 pub fn get_metadata(path: &Path) -> Result<CargoMetadata> {
     let output = Command::new("cargo")
@@ -862,6 +1305,67 @@ edition = "2021"
         assert!(!result.errors.is_empty());
     }
 
+    #[test]
+    fn test_build_diagnostics_have_spans() {
+        let temp = TempDir::new().unwrap();
+        create_test_project(temp.path(), "type_error");
+        fs::write(
+            temp.path().join("src/main.rs"),
+            "fn main() {\n    let x: u32 = \"text\";\n}\n",
+        )
+        .unwrap();
+
+        let result = CargoBuilder::new(temp.path().to_path_buf())
+            .build()
+            .unwrap();
+        let error = result
+            .diagnostics
+            .iter()
+            .find(|d| d.level == "error")
+            .unwrap();
+        let span = error.primary_span().unwrap();
+        assert_eq!(span.file_name, PathBuf::from("src/main.rs"));
+        assert_eq!((span.line_start, span.column_start), (2, 18));
+        assert_eq!(span.highlighted().collect::<Vec<_>>(), vec!["\"text\""]);
+    }
+
+    #[test]
+    fn test_build_with_streams_events() {
+        let temp = TempDir::new().unwrap();
+        create_test_project(temp.path(), "streamed");
+        fs::write(
+            temp.path().join("src/main.rs"),
+            "fn main() {\n    let unused = 1;\n}\n",
+        )
+        .unwrap();
+
+        let mut compiled = Vec::new();
+        let mut warnings = 0;
+        let result = CargoBuilder::new(temp.path().to_path_buf())
+            .build_with(|event| match event {
+                BuildEvent::Artifact(artifact) => compiled.push(artifact.target.clone()),
+                BuildEvent::Diagnostic(d) if d.level == "warning" => warnings += 1,
+                BuildEvent::Diagnostic(_) => {}
+            })
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(compiled, vec!["streamed".to_string()]);
+        assert!(warnings >= 1);
+        assert_eq!(warnings, result.diagnostics.len());
+    }
+
+    #[test]
+    fn test_estimate_crates_from_lock_file() {
+        let temp = TempDir::new().unwrap();
+        create_test_project(temp.path(), "estimate");
+        let builder = CargoBuilder::new(temp.path().to_path_buf());
+        assert_eq!(builder.estimate_crates(), None);
+
+        builder.build().unwrap();
+        assert_eq!(builder.estimate_crates(), Some(1));
+    }
+
     #[test]
     fn test_build_release_mode() {
         let temp = TempDir::new().unwrap();
@@ -944,6 +1448,26 @@ fn main() {
         assert!(!output.status.success());
     }
 
+    #[test]
+    fn test_runner_passthrough_still_captures() {
+        let temp = TempDir::new().unwrap();
+        create_test_project(temp.path(), "passthrough");
+        fs::write(
+            temp.path().join("src/main.rs"),
+            "fn main() {\n    println!(\"out\");\n    eprintln!(\"err\");\n}\n",
+        )
+        .unwrap();
+
+        let output = CargoRunner::new(temp.path().to_path_buf())
+            .passthrough(true)
+            .run()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "out\n");
+        // `--quiet` keeps cargo's own progress out of the program's stderr
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "err\n");
+    }
+
     #[test]
     fn test_runner_output_with_failure() {
         let temp = TempDir::new().unwrap();
@@ -1270,4 +1794,50 @@ feat2 = ["feat1"]
         assert!(features.contains(&"feat1".to_string()));
         assert!(features.contains(&"feat2".to_string()));
     }
+
+    #[test]
+    fn test_parse_test_results() {
+        let stdout = "\nrunning 3 tests\ntest tests::adds ... ok\ntest tests::fails ... FAILED\ntest tests::slow ... ignored, too slow\n\ntest result: FAILED. 1 passed; 1 failed; 1 ignored\n";
+
+        let results = parse_test_results(stdout);
+        assert_eq!(
+            results,
+            vec![
+                TestOutcome {
+                    name: "tests::adds".into(),
+                    status: TestStatus::Passed
+                },
+                TestOutcome {
+                    name: "tests::fails".into(),
+                    status: TestStatus::Failed
+                },
+                TestOutcome {
+                    name: "tests::slow".into(),
+                    status: TestStatus::Ignored
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cargo_tester_runs_failing_tests() {
+        let temp = TempDir::new().unwrap();
+        create_test_project(temp.path(), "tester_test");
+        fs::write(
+            temp.path().join("src/lib.rs"),
+            "#[test]\nfn passes() {}\n#[test]\nfn fails() { panic!(); }\n",
+        )
+        .unwrap();
+
+        let output = CargoTester::new(temp.path().to_path_buf())
+            .filter("passes".into())
+            .run()
+            .unwrap();
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let results = parse_test_results(&stdout);
+        assert!(output.status.success());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "passes");
+    }
 }
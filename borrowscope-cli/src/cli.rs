@@ -49,6 +49,18 @@ pub enum Commands {
     /// Watch files and auto-rerun on changes
     Watch(WatchArgs),
 
+    /// Show the instrumented code of a file or function
+    Expand(ExpandArgs),
+
+    /// Summarize a trace for review
+    Report(ReportArgs),
+
+    /// Compare the ownership recorded in two traces
+    Diff(DiffArgs),
+
+    /// Find variables in a trace with an ownership query
+    Query(QueryArgs),
+
     /// Generate shell completion scripts
     Completion(CompletionArgs),
 }
@@ -59,7 +71,7 @@ pub struct RunArgs {
     #[arg(default_value = ".")]
     pub path: PathBuf,
 
-    /// Output file for tracking data
+    /// Output file for tracking data (a directory of traces with --target test)
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
@@ -90,6 +102,34 @@ pub struct RunArgs {
     /// Example name (when target=example)
     #[arg(long, requires = "target")]
     pub example: Option<String>,
+
+    /// Workspace member to instrument and run
+    #[arg(short, long)]
+    pub package: Option<String>,
+
+    /// Instrument every workspace member
+    #[arg(long)]
+    pub workspace: bool,
+
+    /// Also instrument path dependencies inside the workspace
+    #[arg(long)]
+    pub with_path_deps: bool,
+
+    /// Only trace tests whose name contains this (with --target test)
+    #[arg(long)]
+    pub filter: Option<String>,
+
+    /// Only instrument functions matching these path globs (e.g. 'crate::parser::*')
+    #[arg(long, value_delimiter = ',')]
+    pub only: Vec<String>,
+
+    /// Skip functions matching these path globs
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+
+    /// Splice tracking calls into the original text instead of reformatting files
+    #[arg(long)]
+    pub preserve_formatting: bool,
 }
 
 #[derive(ValueEnum, Clone, Copy, Debug)]
@@ -106,8 +146,9 @@ pub enum RunTarget {
 
 #[derive(Args)]
 pub struct VisualizeArgs {
-    /// Path to tracking data file
-    pub file: PathBuf,
+    /// Tracking data files, or directories of them, to serve
+    #[arg(required = true, num_args = 1..)]
+    pub files: Vec<PathBuf>,
 
     /// Port for web server
     #[arg(short, long)]
@@ -127,13 +168,21 @@ pub struct ExportArgs {
     /// Input tracking data file
     pub file: PathBuf,
 
-    /// Output file
+    /// Output file (`-` writes text output to stdout)
     #[arg(short, long)]
     pub output: PathBuf,
 
     /// Export format
     #[arg(short = 'f', long, value_enum)]
     pub format: ExportFormat,
+
+    /// What to draw: the ownership graph or a lifetime Gantt chart
+    #[arg(long, value_enum, default_value_t = ExportView::Graph)]
+    pub view: ExportView,
+
+    /// Renderer for SVG and PNG output
+    #[arg(long, value_enum, default_value_t = Renderer::Native)]
+    pub renderer: Renderer,
 }
 
 #[derive(Args)]
@@ -167,6 +216,26 @@ pub struct CheckArgs {
     /// Validate graph integrity
     #[arg(long)]
     pub validate: bool,
+
+    /// Exit with a non-zero code when these are found (conflicts: 2, cycles: 4, leaks: 8, pointers: 32, aliasing: 64, or-ed)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub fail_on: Vec<CheckCategory>,
+
+    /// Project the trace was recorded from, used to locate findings in its sources
+    #[arg(long, default_value = ".")]
+    pub source_root: PathBuf,
+
+    /// Print findings as a CI report instead of in the global output format
+    #[arg(long, value_enum)]
+    pub report_format: Option<ReportFormat>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// SARIF 2.1.0, for code scanning
+    Sarif,
+    /// JUnit XML, for CI test reports
+    Junit,
 }
 
 #[derive(ValueEnum, Clone, Copy, Debug)]
@@ -175,6 +244,29 @@ pub enum CheckMode {
     Conflicts,
     /// Detect reference cycles only
     Cycles,
+    /// Find Rc/Arc allocations that were never dropped only
+    Leaks,
+    /// Run the ownership lints configured under `[lints]` only
+    Lints,
+    /// Check raw pointer dereferences for use-after-free and writes through `*const` only
+    Pointers,
+    /// Model borrow stacks and report references and raw pointers used after being invalidated only
+    Aliasing,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckCategory {
+    /// Overlapping mutable and shared borrows
+    Conflicts,
+    /// Reference cycles between Rc/Arc values
+    Cycles,
+    /// Rc/Arc allocations never dropped
+    Leaks,
+    /// Raw pointers used after their pointee was dropped or moved, or written through `*const`
+    Pointers,
+    /// References and raw pointers used after an aliasing access invalidated them
+    Aliasing,
 }
 
 #[derive(Args)]
@@ -200,6 +292,28 @@ pub struct WatchArgs {
     pub clear: bool,
 }
 
+#[derive(Args)]
+pub struct ExpandArgs {
+    /// Rust file, or function path such as `parser::Parser::parse` (globs allowed)
+    pub target: String,
+
+    /// Project to search for the function
+    #[arg(long, default_value = ".")]
+    pub path: PathBuf,
+
+    /// Print a unified diff against the original instead
+    #[arg(long)]
+    pub diff: bool,
+
+    /// Splice tracking calls into the original text instead of reformatting
+    #[arg(long)]
+    pub preserve_formatting: bool,
+
+    /// Instrument test functions, as `run --target test` does
+    #[arg(long)]
+    pub tests: bool,
+}
+
 #[derive(Args)]
 pub struct CompletionArgs {
     /// Shell type
@@ -228,6 +342,69 @@ pub enum ExportFormat {
     Json,
     /// HTML report
     Html,
+    /// Plain text (lifetimes view only)
+    Text,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportView {
+    /// Ownership and borrow graph
+    Graph,
+    /// Gantt chart of variable lifetimes with nested borrow intervals
+    Lifetimes,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Renderer {
+    /// Built-in layout and rasterizer, no external tools required
+    Native,
+    /// Graphviz `dot` (must be installed)
+    Graphviz,
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    #[command(subcommand)]
+    pub report: ReportKind,
+}
+
+#[derive(Subcommand)]
+pub enum ReportKind {
+    /// Unsafe blocks, raw pointers, transmutes, FFI calls and union reads, per function
+    Unsafe(UnsafeReportArgs),
+}
+
+#[derive(Args)]
+pub struct UnsafeReportArgs {
+    /// Tracking data file
+    pub file: PathBuf,
+
+    /// Project the trace was recorded from, used to find the enclosing functions
+    #[arg(long, default_value = ".")]
+    pub source_root: PathBuf,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Trace recorded before the change
+    pub old: PathBuf,
+
+    /// Trace recorded after the change
+    pub new: PathBuf,
+
+    /// Also write a side-by-side HTML view to this file
+    #[arg(long)]
+    pub html: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct QueryArgs {
+    /// Tracking data file
+    pub file: PathBuf,
+
+    /// Query terms, e.g. `type:Vec* alive_at:120 'borrowed_by>2'`
+    #[arg(required = true, num_args = 1..)]
+    pub query: Vec<String>,
 }
 
 #[derive(ValueEnum, Clone, Copy, Debug)]
@@ -244,8 +421,12 @@ impl Cli {
             Commands::Visualize(args) => commands::visualize::execute(args, config),
             Commands::Export(args) => commands::export::execute(args),
             Commands::Init(args) => commands::init::execute(args),
-            Commands::Check(args) => commands::check::execute(args),
+            Commands::Check(args) => commands::check::execute(args, config, self.output_format),
             Commands::Watch(args) => commands::watch::execute(args, config),
+            Commands::Expand(args) => commands::expand::execute(args, config),
+            Commands::Report(args) => commands::report::execute(args, self.output_format),
+            Commands::Diff(args) => commands::diff::execute(args, self.output_format),
+            Commands::Query(args) => commands::query::execute(args, self.output_format),
             Commands::Completion(args) => commands::completion::execute(args),
         }
     }
@@ -1,11 +1,86 @@
 //! Check command implementation
+//!
+//! The trace is replayed into an [`OwnershipGraph`](borrowscope_graph::OwnershipGraph)
+//! and every check runs the graph library's own analyses, so `check` agrees
+//! with what the visualizer and exports report.
+//!
+//! With `--fail-on` the command doubles as a CI gate, and the SARIF and JUnit
+//! output formats let CI servers show the findings next to the code.
+//! Ownership lints configured under `[lints]` are reported alongside.
+
+use std::path::Path;
+
+use borrowscope_graph::GraphStatistics;
+use borrowscope_runtime::{Event, ExportData};
+use serde::Serialize;
+
+use crate::aliasing::{self, AliasingReport};
+use crate::cli::{CheckArgs, CheckCategory, CheckMode, ReportFormat};
+use crate::config::{Config, LintsConfig};
+use crate::error::{CliError, Result};
+use crate::findings::{self, Finding, Rule, Severity};
+use crate::lints::{self, LintResult};
+use crate::locations::{SourceIndex, SourceLocation};
+use crate::output::OutputFormat;
+use crate::pointers::{self, PointerReport};
+use crate::trace::{self, ConflictReport, Trace};
+
+/// Results of the checks requested on the command line
+#[derive(Debug, Serialize)]
+pub struct CheckReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub statistics: Option<TraceStatistics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflicts: Option<Vec<ConflictReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<Vec<CycleReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leaks: Option<Vec<LeakReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pointers: Option<Vec<PointerReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aliasing: Option<Vec<AliasingReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lints: Option<Vec<LintResult>>,
+    /// Integrity errors from [`OwnershipGraph::validate`](borrowscope_graph::OwnershipGraph::validate)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation: Option<Vec<String>>,
+}
 
-use std::fs;
+/// Event counts alongside the graph library's statistics
+#[derive(Debug, Serialize)]
+pub struct TraceStatistics {
+    pub total_events: usize,
+    pub new: usize,
+    pub borrows: usize,
+    pub moves: usize,
+    pub drops: usize,
+    pub graph: GraphStatistics,
+}
 
-use crate::cli::{CheckArgs, CheckMode};
-use crate::error::{CliError, Result};
+/// A reference cycle with the names of the variables involved
+#[derive(Debug, Clone, Serialize)]
+pub struct CycleReport {
+    pub variables: Vec<usize>,
+    pub message: String,
+}
+
+/// An Rc/Arc value still alive when the trace ended
+#[derive(Debug, Clone, Serialize)]
+pub struct LeakReport {
+    pub variable: usize,
+    pub message: String,
+}
 
-pub fn execute(args: CheckArgs) -> Result<()> {
+/// `--output-format json`: the report plus its located findings
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    #[serde(flatten)]
+    report: &'a CheckReport,
+    findings: &'a [Finding],
+}
+
+pub fn execute(args: CheckArgs, config: Config, format: OutputFormat) -> Result<()> {
     log::info!("Checking: {}", args.file.display());
 
     // Check if file exists
@@ -13,305 +88,482 @@ pub fn execute(args: CheckArgs) -> Result<()> {
         return Err(CliError::FileNotFound(args.file.clone()));
     }
 
-    // Load tracking data
-    let contents = fs::read_to_string(&args.file)?;
-    let data: serde_json::Value = serde_json::from_str(&contents)?;
+    // Load tracking data, upgrading older traces so every check sees the current layout
+    let data = trace::read_json(&args.file)?;
+    let export = validate_structure(data)?;
+    let trace = Trace::from_events(export.events);
 
-    // Validate structure
-    validate_structure(&data)?;
+    let checked = categories(args.mode, &args.fail_on);
+    let lints = matches!(args.mode, None | Some(CheckMode::Lints)).then_some(&config.lints);
+    let report = CheckReport::build(&trace, &checked, lints, args.stats, args.validate);
 
-    // Handle mode-specific checks
-    match args.mode {
-        Some(CheckMode::Conflicts) => {
-            check_conflicts(&data)?;
-            return Ok(());
-        }
-        Some(CheckMode::Cycles) => {
-            check_cycles(&data)?;
-            return Ok(());
-        }
-        None => {
-            // Run all checks if no mode specified
-        }
+    let mut rules: Vec<Rule> = checked.iter().map(|category| category.rule()).collect();
+    if let Some(lints) = lints {
+        rules.extend(lints::rules(lints));
     }
 
-    // Show statistics if requested
-    if args.stats {
-        show_statistics(&data)?;
+    // Test traces are named after their test, `app.tests.parses.json`
+    let suite = args
+        .file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    if let Some(report_format) = args.report_format {
+        let findings = report.findings(&trace, &args.source_root, &suite);
+        let rendered = match report_format {
+            ReportFormat::Sarif => {
+                serde_json::to_string_pretty(&findings::to_sarif(&findings, &rules))?
+            }
+            ReportFormat::Junit => findings::to_junit(&suite, &findings, &rules),
+        };
+        println!("{}", rendered.trim_end());
+        return report.gate(&args.fail_on);
     }
 
-    // Validate graph integrity if requested
-    if args.validate {
-        validate_graph(&data)?;
-    }
+    match format {
+        OutputFormat::Text => {
+            report.print();
 
-    // If no specific checks requested, do basic validation
-    if !args.stats && !args.validate && args.mode.is_none() {
-        println!("✓ Tracking data is valid");
+            // If no specific checks requested, do basic validation
+            if !args.stats && !args.validate && args.mode.is_none() {
+                println!("\n✓ Tracking data is valid");
+            }
+        }
+        OutputFormat::Json => {
+            let findings = report.findings(&trace, &args.source_root, &suite);
+            let rendered = serde_json::to_string_pretty(&JsonOutput {
+                report: &report,
+                findings: &findings,
+            })?;
+            println!("{}", rendered);
+        }
+        OutputFormat::None => {}
     }
 
-    Ok(())
+    report.gate(&args.fail_on)
 }
 
-fn validate_structure(data: &serde_json::Value) -> Result<()> {
-    // Check required fields
-    if data.get("version").is_none() {
-        return Err(CliError::InvalidFormat(
-            "Missing 'version' field".to_string(),
-        ));
-    }
+/// Checks to run: the selected mode (all of them without one) plus every `--fail-on` category
+fn categories(mode: Option<CheckMode>, fail_on: &[CheckCategory]) -> Vec<CheckCategory> {
+    let selected = match mode {
+        Some(CheckMode::Conflicts) => vec![CheckCategory::Conflicts],
+        Some(CheckMode::Cycles) => vec![CheckCategory::Cycles],
+        Some(CheckMode::Leaks) => vec![CheckCategory::Leaks],
+        Some(CheckMode::Lints) => Vec::new(),
+        Some(CheckMode::Pointers) => vec![CheckCategory::Pointers],
+        Some(CheckMode::Aliasing) => vec![CheckCategory::Aliasing],
+        None => vec![
+            CheckCategory::Conflicts,
+            CheckCategory::Cycles,
+            CheckCategory::Leaks,
+            CheckCategory::Pointers,
+            CheckCategory::Aliasing,
+        ],
+    };
+
+    [
+        CheckCategory::Conflicts,
+        CheckCategory::Cycles,
+        CheckCategory::Leaks,
+        CheckCategory::Pointers,
+        CheckCategory::Aliasing,
+    ]
+    .into_iter()
+    .filter(|category| selected.contains(category) || fail_on.contains(category))
+    .collect()
+}
 
-    if data.get("events").is_none() {
-        return Err(CliError::InvalidFormat(
-            "Missing 'events' field".to_string(),
-        ));
+fn validate_structure(data: serde_json::Value) -> Result<ExportData> {
+    if trace::is_current(&data) {
+        trace::validate_schema(&data)?;
+    } else {
+        // Older traces are only upgraded if they at least recorded events
+        if !data.get("events").is_some_and(|e| e.is_array()) {
+            let version = match data.get("version") {
+                Some(version) => format!("trace version {}", version),
+                None => "unversioned trace".to_string(),
+            };
+            return Err(CliError::InvalidFormat(format!(
+                "Missing 'events' field in {}",
+                version
+            )));
+        }
+        log::info!("Upgrading trace from an older format version");
     }
 
-    if data.get("graph").is_none() {
-        return Err(CliError::InvalidFormat("Missing 'graph' field".to_string()));
+    trace::migrate(data)
+}
+
+impl CheckReport {
+    /// Run the `checks` and `lints`, plus statistics and graph validation when asked for
+    pub fn build(
+        trace: &Trace,
+        checks: &[CheckCategory],
+        lints: Option<&LintsConfig>,
+        stats: bool,
+        validate: bool,
+    ) -> Self {
+        let run = |category| checks.contains(&category);
+        let lints = lints.filter(|config| !lints::rules(config).is_empty());
+
+        Self {
+            statistics: stats.then(|| TraceStatistics::from_trace(trace)),
+            conflicts: run(CheckCategory::Conflicts).then(|| trace.conflicts()),
+            cycles: run(CheckCategory::Cycles).then(|| find_cycles(trace)),
+            leaks: run(CheckCategory::Leaks).then(|| find_leaks(trace)),
+            pointers: run(CheckCategory::Pointers).then(|| pointers::check(&trace.events)),
+            aliasing: run(CheckCategory::Aliasing).then(|| aliasing::check(&trace.events)),
+            lints: lints.map(|config| {
+                let end = trace.events.iter().map(Event::timestamp).max().unwrap_or(0);
+                lints::run(&trace.graph, end, config)
+            }),
+            validation: validate.then(|| trace.graph.validate().err().unwrap_or_default()),
+        }
     }
 
-    Ok(())
-}
+    /// Number of problems found in `category`
+    pub fn count(&self, category: CheckCategory) -> usize {
+        match category {
+            CheckCategory::Conflicts => self.conflicts.as_ref().map_or(0, Vec::len),
+            CheckCategory::Cycles => self.cycles.as_ref().map_or(0, Vec::len),
+            CheckCategory::Leaks => self.leaks.as_ref().map_or(0, Vec::len),
+            CheckCategory::Pointers => self.pointers.as_ref().map_or(0, Vec::len),
+            CheckCategory::Aliasing => self.aliasing.as_ref().map_or(0, Vec::len),
+        }
+    }
 
-fn show_statistics(data: &serde_json::Value) -> Result<()> {
-    println!("\n📊 Statistics:");
-
-    // Count events
-    let empty_vec = vec![];
-    let events = data
-        .get("events")
-        .and_then(|e| e.as_array())
-        .unwrap_or(&empty_vec);
-    println!("  Total events: {}", events.len());
-
-    // Count event types
-    let mut new_count = 0;
-    let mut borrow_count = 0;
-    let mut move_count = 0;
-    let mut drop_count = 0;
-
-    for event in events {
-        if let Some(event_type) = event.get("type").and_then(|t| t.as_str()) {
-            match event_type {
-                "New" => new_count += 1,
-                "Borrow" => borrow_count += 1,
-                "Move" => move_count += 1,
-                "Drop" => drop_count += 1,
-                _ => {}
+    /// Fail with the or-ed exit codes of every `fail_on` category that has findings.
+    ///
+    /// Lints set to `deny` always fail the check.
+    pub fn gate(&self, fail_on: &[CheckCategory]) -> Result<()> {
+        let mut code = 0;
+        let mut found = Vec::new();
+        for &category in fail_on {
+            let count = self.count(category);
+            if count > 0 && code & category.exit_code() == 0 {
+                code |= category.exit_code();
+                found.push(format!("{} {}", count, category.noun()));
             }
         }
-    }
 
-    println!("  - New: {}", new_count);
-    println!("  - Borrow: {}", borrow_count);
-    println!("  - Move: {}", move_count);
-    println!("  - Drop: {}", drop_count);
-
-    // Count graph elements
-    if let Some(graph) = data.get("graph") {
-        let empty_nodes = vec![];
-        let empty_edges = vec![];
-        let nodes = graph
-            .get("nodes")
-            .and_then(|n| n.as_array())
-            .unwrap_or(&empty_nodes);
-        let edges = graph
-            .get("edges")
-            .and_then(|e| e.as_array())
-            .unwrap_or(&empty_edges);
+        let denied = self
+            .lints
+            .iter()
+            .flatten()
+            .filter(|lint| lint.severity == Severity::Error)
+            .count();
+        if denied > 0 {
+            code |= lints::DENY_EXIT_CODE;
+            found.push(format!("{} denied lint(s)", denied));
+        }
 
-        println!("\n  Graph:");
-        println!("  - Nodes: {}", nodes.len());
-        println!("  - Edges: {}", edges.len());
+        if code == 0 {
+            return Ok(());
+        }
+        Err(CliError::CheckFailed {
+            message: format!("found {}", found.join(", ")),
+            code,
+        })
     }
 
-    Ok(())
-}
+    /// Every problem found and lint matched, located in the sources under `root`.
+    ///
+    /// Pointer accesses point at the unsafe block they happened in, and aliasing
+    /// violations at the dereference when one was recorded. Other findings
+    /// point at the `let` binding of the variable involved, looked up in the test
+    /// the trace was recorded from when `trace_name` names one.
+    pub fn findings(&self, trace: &Trace, root: &Path, trace_name: &str) -> Vec<Finding> {
+        let mut findings: Vec<(Finding, Option<usize>)> = Vec::new();
+
+        for report in self.conflicts.iter().flatten() {
+            let finding = Finding {
+                rule: CheckCategory::Conflicts.rule_id().to_string(),
+                severity: CheckCategory::Conflicts.severity(),
+                message: report.message.clone(),
+                location: None,
+            };
+            findings.push((finding, Some(report.conflict.owner_id)));
+        }
+        for cycle in self.cycles.iter().flatten() {
+            let finding = Finding {
+                rule: CheckCategory::Cycles.rule_id().to_string(),
+                severity: CheckCategory::Cycles.severity(),
+                message: cycle.message.clone(),
+                location: None,
+            };
+            findings.push((finding, cycle.variables.first().copied()));
+        }
+        for leak in self.leaks.iter().flatten() {
+            let finding = Finding {
+                rule: CheckCategory::Leaks.rule_id().to_string(),
+                severity: CheckCategory::Leaks.severity(),
+                message: leak.message.clone(),
+                location: None,
+            };
+            findings.push((finding, Some(leak.variable)));
+        }
+        for report in self.pointers.iter().flatten() {
+            let recorded = report
+                .block
+                .as_ref()
+                .map_or(&report.location, |b| &b.location);
+            let finding = Finding {
+                rule: CheckCategory::Pointers.rule_id().to_string(),
+                severity: CheckCategory::Pointers.severity(),
+                message: report.message.clone(),
+                location: SourceLocation::parse(recorded),
+            };
+            findings.push((finding, trace.resolve(&report.pointer_id)));
+        }
+        for report in self.aliasing.iter().flatten() {
+            let finding = Finding {
+                rule: CheckCategory::Aliasing.rule_id().to_string(),
+                severity: CheckCategory::Aliasing.severity(),
+                message: report.message.clone(),
+                location: report.location.as_deref().and_then(SourceLocation::parse),
+            };
+            findings.push((finding, trace.resolve(&report.item_id)));
+        }
+        for lint in self.lints.iter().flatten() {
+            let finding = Finding {
+                rule: lint.rule.to_string(),
+                severity: lint.severity,
+                message: lint.message.clone(),
+                location: None,
+            };
+            findings.push((finding, Some(lint.variable)));
+        }
+
+        if findings.is_empty() {
+            return Vec::new();
+        }
+
+        let index = SourceIndex::scan(root);
+        let test = trace_name.replace('.', "::");
+        let function = index.function(&test).map(|_| test.as_str());
 
-fn check_conflicts(data: &serde_json::Value) -> Result<()> {
-    println!("\n🔍 Checking for borrow conflicts...");
-
-    let empty_vec = vec![];
-    let events = data
-        .get("events")
-        .and_then(|e| e.as_array())
-        .unwrap_or(&empty_vec);
-
-    // Simple conflict detection: check for multiple mutable borrows
-    let mut active_borrows: std::collections::HashMap<String, Vec<(usize, bool)>> =
-        std::collections::HashMap::new();
-    let mut conflicts = Vec::new();
-
-    for (idx, event) in events.iter().enumerate() {
-        if let Some(event_type) = event.get("type").and_then(|t| t.as_str()) {
-            match event_type {
-                "Borrow" => {
-                    if let (Some(owner_id), Some(is_mutable)) = (
-                        event.get("owner_id").and_then(|id| id.as_str()),
-                        event.get("mutable").and_then(|m| m.as_bool()),
-                    ) {
-                        let borrows = active_borrows.entry(owner_id.to_string()).or_default();
-
-                        // Check for conflicts
-                        if is_mutable && !borrows.is_empty() {
-                            conflicts.push(format!(
-                                "Mutable borrow conflict at event {}: variable {} already borrowed",
-                                idx, owner_id
-                            ));
-                        } else if !is_mutable && borrows.iter().any(|(_, m)| *m) {
-                            conflicts.push(format!(
-                                "Borrow conflict at event {}: variable {} has active mutable borrow",
-                                idx, owner_id
-                            ));
-                        }
-
-                        borrows.push((idx, is_mutable));
-                    }
+        findings
+            .into_iter()
+            .map(|(mut finding, variable)| {
+                if finding.location.is_some() {
+                    return finding;
                 }
-                "Drop" => {
-                    if let Some(var_id) = event.get("var_id").and_then(|id| id.as_str()) {
-                        active_borrows.remove(var_id);
-                    }
+                // Where the trace says the variable was declared, else its
+                // binding in the test or the only binding of its name
+                let name = variable
+                    .and_then(|id| trace.graph.get_variable(id))
+                    .map(|var| var.name.as_str());
+                finding.location = variable
+                    .and_then(|id| trace.location(id))
+                    .or_else(|| name.and_then(|name| index.binding(function, name)))
+                    .or_else(|| function.and_then(|f| index.function(f)))
+                    .cloned();
+                finding
+            })
+            .collect()
+    }
+
+    fn print(&self) {
+        if let Some(stats) = &self.statistics {
+            stats.print();
+        }
+
+        if let Some(conflicts) = &self.conflicts {
+            println!("\n🔍 Checking for borrow conflicts...");
+            if conflicts.is_empty() {
+                println!("  ✓ No conflicts detected");
+            } else {
+                println!("  ✗ Found {} conflict(s):", conflicts.len());
+                for report in conflicts {
+                    let (start, end) = report.conflict.time_range;
+                    println!("    - [{}..{}] {}", start, end, report.message);
                 }
-                _ => {}
             }
         }
-    }
 
-    if conflicts.is_empty() {
-        println!("  ✓ No conflicts detected");
-    } else {
-        println!("  ✗ Found {} conflict(s):", conflicts.len());
-        for conflict in conflicts {
-            println!("    - {}", conflict);
+        if let Some(cycles) = &self.cycles {
+            println!("\n🔄 Checking for reference cycles...");
+            if cycles.is_empty() {
+                println!("  ✓ No cycles detected");
+            } else {
+                println!("  ✗ Found {} cycle(s):", cycles.len());
+                for cycle in cycles {
+                    println!("    - {}", cycle.message);
+                }
+            }
         }
-    }
 
-    Ok(())
-}
-
-fn check_cycles(data: &serde_json::Value) -> Result<()> {
-    println!("\n🔄 Checking for reference cycles...");
-
-    if let Some(graph) = data.get("graph") {
-        let empty_edges = vec![];
-        let edges = graph
-            .get("edges")
-            .and_then(|e| e.as_array())
-            .unwrap_or(&empty_edges);
-
-        // Build adjacency list
-        let mut adj_list: std::collections::HashMap<String, Vec<String>> =
-            std::collections::HashMap::new();
-
-        for edge in edges {
-            if let (Some(from), Some(to)) = (
-                edge.get("from").and_then(|f| f.as_str()),
-                edge.get("to").and_then(|t| t.as_str()),
-            ) {
-                adj_list
-                    .entry(from.to_string())
-                    .or_default()
-                    .push(to.to_string());
+        if let Some(leaks) = &self.leaks {
+            println!("\n💧 Checking for leaked allocations...");
+            if leaks.is_empty() {
+                println!("  ✓ No leaks detected");
+            } else {
+                println!("  ✗ Found {} leak(s):", leaks.len());
+                for leak in leaks {
+                    println!("    - {}", leak.message);
+                }
             }
         }
 
-        // Detect cycles using DFS
-        let mut visited = std::collections::HashSet::new();
-        let mut rec_stack = std::collections::HashSet::new();
-        let mut cycles = Vec::new();
-
-        fn dfs(
-            node: &str,
-            adj_list: &std::collections::HashMap<String, Vec<String>>,
-            visited: &mut std::collections::HashSet<String>,
-            rec_stack: &mut std::collections::HashSet<String>,
-            cycles: &mut Vec<String>,
-        ) {
-            visited.insert(node.to_string());
-            rec_stack.insert(node.to_string());
-
-            if let Some(neighbors) = adj_list.get(node) {
-                for neighbor in neighbors {
-                    if !visited.contains(neighbor) {
-                        dfs(neighbor, adj_list, visited, rec_stack, cycles);
-                    } else if rec_stack.contains(neighbor) {
-                        cycles.push(format!("{} -> {}", node, neighbor));
-                    }
+        if let Some(reports) = &self.pointers {
+            println!("\n⚠️  Checking raw pointer accesses...");
+            if reports.is_empty() {
+                println!("  ✓ No invalid accesses detected");
+            } else {
+                println!("  ✗ Found {} invalid access(es):", reports.len());
+                for report in reports {
+                    println!("    - [{}] {}", report.location, report.message);
                 }
             }
+        }
 
-            rec_stack.remove(node);
+        if let Some(reports) = &self.aliasing {
+            println!("\n🧱 Checking raw pointer aliasing...");
+            if reports.is_empty() {
+                println!("  ✓ No aliasing violations detected");
+            } else {
+                println!("  ✗ Found {} aliasing violation(s):", reports.len());
+                for report in reports {
+                    println!(
+                        "    - [{}] {} (invalidated at {})",
+                        report.timestamp, report.message, report.invalidated_at
+                    );
+                }
+            }
         }
 
-        for node in adj_list.keys() {
-            if !visited.contains(node) {
-                dfs(node, &adj_list, &mut visited, &mut rec_stack, &mut cycles);
+        if let Some(lints) = &self.lints {
+            println!("\n📏 Checking lints...");
+            if lints.is_empty() {
+                println!("  ✓ No lints triggered");
+            } else {
+                println!("  ✗ Found {} lint(s):", lints.len());
+                for lint in lints {
+                    let level = match lint.severity {
+                        Severity::Warning => "warn",
+                        Severity::Error => "deny",
+                    };
+                    println!("    - [{}] {}: {}", level, lint.rule, lint.message);
+                }
             }
         }
 
-        if cycles.is_empty() {
-            println!("  ✓ No cycles detected");
-        } else {
-            println!("  ✗ Found {} cycle(s):", cycles.len());
-            for cycle in cycles {
-                println!("    - {}", cycle);
+        if let Some(errors) = &self.validation {
+            println!("\n✓ Validating graph integrity...");
+            if errors.is_empty() {
+                println!("  ✓ Graph is valid");
+            } else {
+                println!("  ✗ Found {} problem(s):", errors.len());
+                for error in errors {
+                    println!("    - {}", error);
+                }
             }
         }
-    } else {
-        println!("  ⚠ No graph data found");
     }
-
-    Ok(())
 }
 
-fn validate_graph(data: &serde_json::Value) -> Result<()> {
-    println!("\n✓ Validating graph integrity...");
-
-    if let Some(graph) = data.get("graph") {
-        let empty_nodes = vec![];
-        let empty_edges = vec![];
-        let nodes = graph
-            .get("nodes")
-            .and_then(|n| n.as_array())
-            .unwrap_or(&empty_nodes);
-        let edges = graph
-            .get("edges")
-            .and_then(|e| e.as_array())
-            .unwrap_or(&empty_edges);
-
-        // Collect node IDs
-        let mut node_ids = std::collections::HashSet::new();
-        for node in nodes {
-            if let Some(id) = node.get("id") {
-                node_ids.insert(id.to_string());
+impl TraceStatistics {
+    fn from_trace(trace: &Trace) -> Self {
+        let mut stats = Self {
+            total_events: trace.events.len(),
+            new: 0,
+            borrows: 0,
+            moves: 0,
+            drops: 0,
+            graph: trace.graph.statistics(),
+        };
+
+        for event in &trace.events {
+            match event {
+                Event::New { .. } => stats.new += 1,
+                Event::Borrow { .. } => stats.borrows += 1,
+                Event::Move { .. } => stats.moves += 1,
+                Event::Drop { .. } => stats.drops += 1,
+                _ => {}
             }
         }
 
-        // Validate edges reference existing nodes
-        let mut invalid_edges = 0;
-        for edge in edges {
-            if let (Some(from), Some(to)) = (edge.get("from"), edge.get("to")) {
-                if !node_ids.contains(&from.to_string()) || !node_ids.contains(&to.to_string()) {
-                    invalid_edges += 1;
-                }
+        stats
+    }
+
+    fn print(&self) {
+        println!("\n📊 Statistics:");
+        println!("  Total events: {}", self.total_events);
+        println!("  - New: {}", self.new);
+        println!("  - Borrow: {}", self.borrows);
+        println!("  - Move: {}", self.moves);
+        println!("  - Drop: {}", self.drops);
+
+        println!("\n  Graph:");
+        println!("  - Variables: {}", self.graph.total_variables);
+        println!("  - Alive: {}", self.graph.alive_variables);
+        println!("  - Edges: {}", self.graph.total_edges);
+        println!("  - Immutable borrows: {}", self.graph.immutable_borrows);
+        println!("  - Mutable borrows: {}", self.graph.mutable_borrows);
+        println!("  - Moves: {}", self.graph.moves);
+        println!(
+            "  - Rc/Arc clones: {}",
+            self.graph.rc_clones + self.graph.arc_clones
+        );
+        println!("  - RefCell borrows: {}", self.graph.refcell_borrows);
+    }
+}
+
+fn find_cycles(trace: &Trace) -> Vec<CycleReport> {
+    trace
+        .graph
+        .find_cycles()
+        .into_iter()
+        .map(|mut variables| {
+            variables.sort_unstable();
+            let names: Vec<_> = variables
+                .iter()
+                .filter_map(|&id| trace.graph.get_variable(id))
+                .map(|v| v.name.as_str())
+                .collect();
+            CycleReport {
+                message: format!("Reference cycle between: {}", names.join(", ")),
+                variables,
             }
-        }
+        })
+        .collect()
+}
 
-        if invalid_edges == 0 {
-            println!("  ✓ Graph is valid");
-        } else {
-            println!("  ✗ Found {} invalid edge(s)", invalid_edges);
-        }
+/// Rc/Arc values never dropped, when the trace recorded drops at all
+fn find_leaks(trace: &Trace) -> Vec<LeakReport> {
+    if !trace
+        .events
+        .iter()
+        .any(|event| matches!(event, Event::Drop { .. }))
+    {
+        log::warn!("The trace recorded no drops, so leaks cannot be detected");
+        return Vec::new();
     }
 
-    Ok(())
+    trace
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            Event::RcNew { var_id, .. }
+            | Event::RcClone { var_id, .. }
+            | Event::ArcNew { var_id, .. }
+            | Event::ArcClone { var_id, .. } => trace.resolve(var_id),
+            _ => None,
+        })
+        .filter_map(|id| trace.graph.get_variable(id))
+        .filter(|var| var.dropped_at.is_none())
+        .map(|var| LeakReport {
+            variable: var.id,
+            message: format!("'{}' ({}) was never dropped", var.name, var.type_name),
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
     use std::path::PathBuf;
     use tempfile::TempDir;
 
@@ -322,9 +574,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_err());
     }
 
@@ -349,9 +604,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -371,9 +629,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_err());
     }
 
@@ -398,9 +659,12 @@ mod tests {
             mode: Some(CheckMode::Conflicts),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -425,48 +689,12 @@ mod tests {
             mode: Some(CheckMode::Cycles),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
-        assert!(result.is_ok());
-    }
-
-    #[test]
-    fn test_show_statistics() {
-        let data = serde_json::json!({
-            "version": "0.1.0",
-            "events": [
-                {"type": "New"},
-                {"type": "Borrow"},
-                {"type": "Drop"}
-            ],
-            "graph": {
-                "nodes": [{"id": 1}],
-                "edges": []
-            }
-        });
-
-        let result = show_statistics(&data);
-        assert!(result.is_ok());
-    }
-
-    #[test]
-    fn test_validate_graph() {
-        let data = serde_json::json!({
-            "version": "0.1.0",
-            "events": [],
-            "graph": {
-                "nodes": [
-                    {"id": 1},
-                    {"id": 2}
-                ],
-                "edges": [
-                    {"from": 1, "to": 2}
-                ]
-            }
-        });
-
-        let result = validate_graph(&data);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -478,9 +706,9 @@ mod tests {
         let data = serde_json::json!({
             "version": "0.1.0",
             "events": [
-                {"type": "New"},
-                {"type": "Borrow"},
-                {"type": "Drop"}
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x", "type_name": "i32"},
+                {"type": "Borrow", "timestamp": 2, "borrower_name": "r", "borrower_id": "r", "owner_id": "x", "mutable": false},
+                {"type": "Drop", "timestamp": 3, "var_id": "r"}
             ],
             "graph": {
                 "nodes": [{"id": 1}],
@@ -495,9 +723,12 @@ mod tests {
             mode: None,
             stats: true,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -522,9 +753,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: true,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -549,20 +783,28 @@ mod tests {
             mode: None,
             stats: true,
             validate: true,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
     #[test]
-    fn test_check_missing_version() {
+    fn test_check_unversioned_trace_is_upgraded() {
         let temp_dir = TempDir::new().unwrap();
         let test_file = temp_dir.path().join("data.json");
 
+        // The layout the runtime wrote before traces were versioned
         let data = serde_json::json!({
-            "events": [],
-            "graph": {"nodes": [], "edges": []}
+            "nodes": [],
+            "edges": [],
+            "events": [
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x_0", "type_name": "i32"}
+            ],
+            "metadata": {}
         });
 
         fs::write(&test_file, serde_json::to_string(&data).unwrap()).unwrap();
@@ -572,10 +814,13 @@ mod tests {
             mode: None,
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
-        assert!(result.is_err());
+        let result = execute(args, Config::default(), OutputFormat::Text);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -595,20 +840,66 @@ mod tests {
             mode: None,
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_check_runtime_trace() {
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("data.json");
+
+        let events = vec![
+            borrowscope_runtime::Event::New {
+                timestamp: 1,
+                var_name: "x".into(),
+                var_id: "x_0".into(),
+                type_name: "i32".into(),
+                scope_depth: None,
+            },
+            borrowscope_runtime::Event::Drop {
+                timestamp: 2,
+                var_id: "x_0".into(),
+            },
+        ];
+        let graph = borrowscope_runtime::build_graph(&events);
+        borrowscope_runtime::ExportData::new(graph, events)
+            .to_file(&test_file)
+            .unwrap();
+
+        let args = CheckArgs {
+            file: test_file,
+            mode: None,
+            stats: true,
+            validate: true,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
+        };
+
+        assert!(execute(args, Config::default(), OutputFormat::Text).is_ok());
+    }
+
     #[test]
     fn test_check_missing_graph() {
         let temp_dir = TempDir::new().unwrap();
         let test_file = temp_dir.path().join("data.json");
 
         let data = serde_json::json!({
-            "version": "0.1.0",
-            "events": []
+            "version": 1,
+            "events": [],
+            "metadata": {
+                "total_variables": 0,
+                "total_relationships": 0,
+                "immutable_borrows": 0,
+                "mutable_borrows": 0,
+                "total_events": 0
+            }
         });
 
         fs::write(&test_file, serde_json::to_string(&data).unwrap()).unwrap();
@@ -618,9 +909,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_err());
     }
 
@@ -648,9 +942,12 @@ mod tests {
             mode: Some(CheckMode::Cycles),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -678,9 +975,12 @@ mod tests {
             mode: Some(CheckMode::Cycles),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -692,8 +992,9 @@ mod tests {
         let data = serde_json::json!({
             "version": "0.1.0",
             "events": [
-                {"type": "Borrow", "owner_id": "x", "mutable": true},
-                {"type": "Borrow", "owner_id": "x", "mutable": false}
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x", "type_name": "i32"},
+                {"type": "Borrow", "timestamp": 2, "borrower_name": "a", "borrower_id": "a", "owner_id": "x", "mutable": true},
+                {"type": "Borrow", "timestamp": 3, "borrower_name": "b", "borrower_id": "b", "owner_id": "x", "mutable": false}
             ],
             "graph": {"nodes": [], "edges": []}
         });
@@ -705,50 +1006,15 @@ mod tests {
             mode: Some(CheckMode::Conflicts),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
-    #[test]
-    fn test_show_statistics_with_events() {
-        let data = serde_json::json!({
-            "version": "0.1.0",
-            "events": [
-                {"type": "New"},
-                {"type": "New"},
-                {"type": "Borrow"},
-                {"type": "Move"},
-                {"type": "Drop"}
-            ],
-            "graph": {
-                "nodes": [{"id": 1}, {"id": 2}],
-                "edges": [{"from": 1, "to": 2}]
-            }
-        });
-
-        let result = show_statistics(&data);
-        assert!(result.is_ok());
-    }
-
-    #[test]
-    fn test_validate_graph_invalid_edges() {
-        let data = serde_json::json!({
-            "version": "0.1.0",
-            "events": [],
-            "graph": {
-                "nodes": [{"id": 1}],
-                "edges": [
-                    {"from": 1, "to": 999}  // Invalid: node 999 doesn't exist
-                ]
-            }
-        });
-
-        let result = validate_graph(&data);
-        assert!(result.is_ok()); // Should complete but report invalid edges
-    }
-
     #[test]
     fn test_check_empty_events() {
         let temp_dir = TempDir::new().unwrap();
@@ -767,9 +1033,12 @@ mod tests {
             mode: None,
             stats: true,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -791,10 +1060,13 @@ mod tests {
             mode: None,
             stats: false,
             validate: true,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
-        assert!(result.is_ok());
+        let result = execute(args, Config::default(), OutputFormat::Text);
+        assert!(matches!(result, Err(CliError::InvalidFormat(_))));
     }
 
     #[test]
@@ -824,9 +1096,12 @@ mod tests {
             mode: Some(CheckMode::Cycles),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -855,9 +1130,12 @@ mod tests {
             mode: Some(CheckMode::Cycles),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -869,10 +1147,12 @@ mod tests {
         let data = serde_json::json!({
             "version": "0.1.0",
             "events": [
-                {"type": "Borrow", "owner_id": "x", "mutable": true},
-                {"type": "Borrow", "owner_id": "x", "mutable": true},
-                {"type": "Borrow", "owner_id": "y", "mutable": true},
-                {"type": "Borrow", "owner_id": "y", "mutable": false}
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x", "type_name": "i32"},
+                {"type": "New", "timestamp": 2, "var_name": "y", "var_id": "y", "type_name": "i32"},
+                {"type": "Borrow", "timestamp": 3, "borrower_name": "a", "borrower_id": "a", "owner_id": "x", "mutable": true},
+                {"type": "Borrow", "timestamp": 4, "borrower_name": "b", "borrower_id": "b", "owner_id": "x", "mutable": true},
+                {"type": "Borrow", "timestamp": 5, "borrower_name": "c", "borrower_id": "c", "owner_id": "y", "mutable": true},
+                {"type": "Borrow", "timestamp": 6, "borrower_name": "d", "borrower_id": "d", "owner_id": "y", "mutable": false}
             ],
             "graph": {"nodes": [], "edges": []}
         });
@@ -884,9 +1164,12 @@ mod tests {
             mode: Some(CheckMode::Conflicts),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -902,9 +1185,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_err());
     }
 
@@ -929,10 +1215,13 @@ mod tests {
             mode: None,
             stats: true,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
-        assert!(result.is_ok());
+        let result = execute(args, Config::default(), OutputFormat::Text);
+        assert!(matches!(result, Err(CliError::InvalidFormat(_))));
     }
 
     #[test]
@@ -955,10 +1244,13 @@ mod tests {
             mode: Some(CheckMode::Conflicts),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
-        assert!(result.is_ok());
+        let result = execute(args, Config::default(), OutputFormat::Text);
+        assert!(matches!(result, Err(CliError::InvalidFormat(_))));
     }
 
     #[test]
@@ -982,9 +1274,12 @@ mod tests {
             mode: Some(CheckMode::Cycles),
             stats: false,
             validate: true,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -1009,9 +1304,12 @@ mod tests {
             mode: Some(CheckMode::Cycles),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -1036,9 +1334,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: true,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -1050,10 +1351,11 @@ mod tests {
         let data = serde_json::json!({
             "version": "0.1.0",
             "events": [
-                {"type": "New"},
-                {"type": "Borrow", "owner_id": "x", "mutable": false},
-                {"type": "Move"},
-                {"type": "Drop", "var_id": "x"}
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x", "type_name": "i32"},
+                {"type": "Borrow", "timestamp": 2, "borrower_name": "r", "borrower_id": "r", "owner_id": "x", "mutable": false},
+                {"type": "Drop", "timestamp": 3, "var_id": "r"},
+                {"type": "Move", "timestamp": 4, "from_id": "x", "to_name": "y", "to_id": "y"},
+                {"type": "Drop", "timestamp": 5, "var_id": "y"}
             ],
             "graph": {"nodes": [], "edges": []}
         });
@@ -1065,9 +1367,12 @@ mod tests {
             mode: None,
             stats: true,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -1077,7 +1382,7 @@ mod tests {
         let test_file = temp_dir.path().join("data.json");
 
         let data = serde_json::json!({
-            "version": "1.0.0",
+            "version": "0.1.0",
             "events": [],
             "graph": {"nodes": [], "edges": []}
         });
@@ -1089,9 +1394,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -1107,9 +1415,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_err());
     }
 
@@ -1131,9 +1442,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -1145,9 +1459,10 @@ mod tests {
         let data = serde_json::json!({
             "version": "0.1.0",
             "events": [
-                {"type": "Borrow", "owner_id": "x", "mutable": false},
-                {"type": "Borrow", "owner_id": "x", "mutable": false},
-                {"type": "Borrow", "owner_id": "x", "mutable": false}
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x", "type_name": "i32"},
+                {"type": "Borrow", "timestamp": 2, "borrower_name": "a", "borrower_id": "a", "owner_id": "x", "mutable": false},
+                {"type": "Borrow", "timestamp": 3, "borrower_name": "b", "borrower_id": "b", "owner_id": "x", "mutable": false},
+                {"type": "Borrow", "timestamp": 4, "borrower_name": "c", "borrower_id": "c", "owner_id": "x", "mutable": false}
             ],
             "graph": {"nodes": [], "edges": []}
         });
@@ -1159,9 +1474,12 @@ mod tests {
             mode: Some(CheckMode::Conflicts),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -1175,7 +1493,7 @@ mod tests {
             "metadata": {"key": "value"}
         });
 
-        let result = validate_structure(&data);
+        let result = validate_structure(data);
         assert!(result.is_ok());
     }
 
@@ -1200,9 +1518,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: true,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -1231,9 +1552,12 @@ mod tests {
             mode: None,
             stats: false,
             validate: true,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -1245,9 +1569,10 @@ mod tests {
         let data = serde_json::json!({
             "version": "0.1.0",
             "events": [
-                {"type": "Borrow", "owner_id": "x", "mutable": true},
-                {"type": "Drop", "var_id": "x"},
-                {"type": "Borrow", "owner_id": "x", "mutable": true}
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x", "type_name": "i32"},
+                {"type": "Borrow", "timestamp": 2, "borrower_name": "a", "borrower_id": "a", "owner_id": "x", "mutable": true},
+                {"type": "Drop", "timestamp": 3, "var_id": "a"},
+                {"type": "Borrow", "timestamp": 4, "borrower_name": "b", "borrower_id": "b", "owner_id": "x", "mutable": true}
             ],
             "graph": {"nodes": [], "edges": []}
         });
@@ -1259,9 +1584,12 @@ mod tests {
             mode: Some(CheckMode::Conflicts),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -1295,9 +1623,444 @@ mod tests {
             mode: Some(CheckMode::Cycles),
             stats: false,
             validate: false,
+            fail_on: vec![],
+            source_root: PathBuf::from("."),
+            report_format: None,
         };
 
-        let result = execute(args);
+        let result = execute(args, Config::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
+
+    fn borrow(timestamp: u64, name: &str, owner: &str, mutable: bool) -> Event {
+        Event::Borrow {
+            timestamp,
+            borrower_name: name.into(),
+            borrower_id: format!("{}_b", name),
+            owner_id: owner.into(),
+            mutable,
+        }
+    }
+
+    fn conflicting_trace() -> Trace {
+        Trace::from_events(vec![
+            Event::New {
+                timestamp: 1,
+                var_name: "x".into(),
+                var_id: "x_0".into(),
+                type_name: "Vec<i32>".into(),
+                scope_depth: None,
+            },
+            borrow(2, "a", "x_0", true),
+            borrow(3, "b", "x_0", false),
+            Event::Drop {
+                timestamp: 5,
+                var_id: "b_b".into(),
+            },
+            Event::Drop {
+                timestamp: 6,
+                var_id: "a_b".into(),
+            },
+        ])
+    }
+
+    #[test]
+    fn test_report_conflicts_match_graph_library() {
+        let trace = conflicting_trace();
+        let report = CheckReport::build(&trace, &[CheckCategory::Conflicts], None, false, false);
+
+        let expected = trace.graph.find_conflicts_optimized();
+        let conflicts = report.conflicts.unwrap();
+        assert!(!expected.is_empty());
+        assert_eq!(conflicts.len(), expected.len());
+        for (report, conflict) in conflicts.iter().zip(&expected) {
+            assert_eq!(report.conflict.time_range, conflict.time_range);
+            assert_eq!(report.conflict.borrowers, conflict.borrowers);
+            assert_eq!(report.message, conflict.format(&trace.graph));
+        }
+        assert!(report.cycles.is_none());
+    }
+
+    #[test]
+    fn test_report_immutable_borrows_no_conflict() {
+        let trace = Trace::from_events(vec![
+            Event::New {
+                timestamp: 1,
+                var_name: "x".into(),
+                var_id: "x_0".into(),
+                type_name: "i32".into(),
+                scope_depth: None,
+            },
+            borrow(2, "a", "x_0", false),
+            borrow(3, "b", "x_0", false),
+        ]);
+
+        let report = CheckReport::build(&trace, &categories(None, &[]), None, false, false);
+        assert!(report.conflicts.unwrap().is_empty());
+        assert!(report.cycles.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_report_cycles_match_graph_library() {
+        let mut trace = Trace::from_events(vec![
+            Event::New {
+                timestamp: 1,
+                var_name: "a".into(),
+                var_id: "a_0".into(),
+                type_name: "Rc<Node>".into(),
+                scope_depth: None,
+            },
+            Event::New {
+                timestamp: 2,
+                var_name: "b".into(),
+                var_id: "b_1".into(),
+                type_name: "Rc<Node>".into(),
+                scope_depth: None,
+            },
+        ]);
+        let a = trace.resolve("a_0").unwrap();
+        let b = trace.resolve("b_1").unwrap();
+        trace.graph.add_rc_clone(a, b, 2, 3);
+        trace.graph.add_rc_clone(b, a, 2, 4);
+
+        let report = CheckReport::build(&trace, &[CheckCategory::Cycles], None, false, true);
+
+        let cycles = report.cycles.unwrap();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].variables, vec![a, b]);
+        assert_eq!(cycles[0].message, "Reference cycle between: a, b");
+        assert!(report.conflicts.is_none());
+        assert_eq!(
+            report.validation.unwrap(),
+            trace.graph.validate().unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_report_statistics() {
+        let trace = conflicting_trace();
+        let report = CheckReport::build(&trace, &categories(None, &[]), None, true, true);
+
+        let stats = report.statistics.unwrap();
+        assert_eq!(stats.total_events, 5);
+        assert_eq!(stats.new, 1);
+        assert_eq!(stats.borrows, 2);
+        assert_eq!(stats.drops, 2);
+        assert_eq!(stats.graph, trace.graph.statistics());
+        assert!(report.validation.unwrap().is_empty());
+    }
+
+    fn rc_trace(dropped: bool) -> Trace {
+        let mut events = vec![
+            Event::RcNew {
+                timestamp: 1,
+                var_name: "node".into(),
+                var_id: "node_0".into(),
+                type_name: "Rc<Node>".into(),
+                strong_count: 1,
+                weak_count: 0,
+            },
+            Event::New {
+                timestamp: 2,
+                var_name: "n".into(),
+                var_id: "n_1".into(),
+                type_name: "i32".into(),
+                scope_depth: None,
+            },
+            Event::Drop {
+                timestamp: 3,
+                var_id: "n_1".into(),
+            },
+        ];
+        if dropped {
+            events.push(Event::Drop {
+                timestamp: 4,
+                var_id: "node_0".into(),
+            });
+        }
+        Trace::from_events(events)
+    }
+
+    #[test]
+    fn test_categories_include_fail_on() {
+        assert_eq!(
+            categories(Some(CheckMode::Conflicts), &[CheckCategory::Leaks]),
+            vec![CheckCategory::Conflicts, CheckCategory::Leaks]
+        );
+        assert_eq!(categories(None, &[]).len(), 5);
+        assert_eq!(
+            categories(Some(CheckMode::Aliasing), &[]),
+            vec![CheckCategory::Aliasing]
+        );
+        assert!(categories(Some(CheckMode::Lints), &[]).is_empty());
+    }
+
+    #[test]
+    fn test_report_leaks() {
+        let report = CheckReport::build(
+            &rc_trace(false),
+            &[CheckCategory::Leaks],
+            None,
+            false,
+            false,
+        );
+        let leaks = report.leaks.unwrap();
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].message, "'node' (Rc<Node>) was never dropped");
+
+        let report =
+            CheckReport::build(&rc_trace(true), &[CheckCategory::Leaks], None, false, false);
+        assert!(report.leaks.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_report_leaks_need_recorded_drops() {
+        let trace = Trace::from_events(vec![Event::RcNew {
+            timestamp: 1,
+            var_name: "node".into(),
+            var_id: "node_0".into(),
+            type_name: "Rc<Node>".into(),
+            strong_count: 1,
+            weak_count: 0,
+        }]);
+        let report = CheckReport::build(&trace, &[CheckCategory::Leaks], None, false, false);
+        assert!(report.leaks.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_gate_ors_exit_codes() {
+        let trace = conflicting_trace();
+        let report = CheckReport::build(&trace, &categories(None, &[]), None, false, false);
+
+        assert!(report.gate(&[]).is_ok());
+        assert!(report.gate(&[CheckCategory::Cycles]).is_ok());
+
+        let err = report
+            .gate(&[CheckCategory::Conflicts, CheckCategory::Cycles])
+            .unwrap_err();
+        assert_eq!(err.exit_code(), 2);
+        assert!(err.to_string().contains("borrow conflict(s)"));
+
+        let report =
+            CheckReport::build(&rc_trace(false), &categories(None, &[]), None, false, false);
+        let err = report
+            .gate(&[CheckCategory::Leaks, CheckCategory::Leaks])
+            .unwrap_err();
+        assert_eq!(err.exit_code(), 8);
+    }
+
+    #[test]
+    fn test_report_lints_and_deny_gate() {
+        use crate::config::{LimitLint, LintLevel};
+        use crate::lints::LintRule;
+
+        let trace = conflicting_trace();
+        let lints = |level| LintsConfig {
+            mutable_borrow_events: Some(LimitLint { max: 3, level }),
+            ..Default::default()
+        };
+
+        let report = CheckReport::build(&trace, &[], Some(&lints(LintLevel::Warn)), false, false);
+        let results = report.lints.as_ref().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].rule, LintRule::MutableBorrowEvents);
+        assert!(report.gate(&[]).is_ok());
+
+        let report = CheckReport::build(
+            &trace,
+            &[CheckCategory::Conflicts],
+            Some(&lints(LintLevel::Deny)),
+            false,
+            false,
+        );
+        assert_eq!(report.gate(&[]).unwrap_err().exit_code(), 16);
+        let err = report.gate(&[CheckCategory::Conflicts]).unwrap_err();
+        assert_eq!(err.exit_code(), 18);
+        assert!(err.to_string().contains("1 denied lint(s)"));
+
+        let findings = report.findings(&trace, Path::new("."), "data");
+        assert!(findings
+            .iter()
+            .any(|f| f.rule == "mutable_borrow_events" && f.severity == Severity::Error));
+
+        // Rules that are not configured are not run at all
+        let report = CheckReport::build(&trace, &[], Some(&LintsConfig::default()), false, false);
+        assert!(report.lints.is_none());
+    }
+
+    #[test]
+    fn test_pointer_findings_point_at_unsafe_block() {
+        let trace = Trace::from_events(vec![
+            Event::New {
+                timestamp: 1,
+                var_name: "x".into(),
+                var_id: "x_0".into(),
+                type_name: "i32".into(),
+                scope_depth: None,
+            },
+            Event::RawPtrCreated {
+                timestamp: 2,
+                var_name: "p".into(),
+                var_id: "1".into(),
+                ptr_type: "*const i32".into(),
+                address: 4096,
+                location: "src/lib.rs:3:5".into(),
+                source_id: Some("x_0".into()),
+            },
+            Event::Drop {
+                timestamp: 3,
+                var_id: "x_0".into(),
+            },
+            Event::UnsafeBlockEnter {
+                timestamp: 4,
+                block_id: "0".into(),
+                location: "src/lib.rs:6:5".into(),
+            },
+            Event::RawPtrDeref {
+                timestamp: 5,
+                ptr_id: "1".into(),
+                location: "src/lib.rs:7:9".into(),
+                is_write: true,
+            },
+            Event::UnsafeBlockExit {
+                timestamp: 6,
+                block_id: "0".into(),
+                location: "src/lib.rs:6:5".into(),
+            },
+        ]);
+        let report = CheckReport::build(&trace, &categories(None, &[]), None, false, false);
+
+        assert_eq!(report.count(CheckCategory::Pointers), 2);
+        let err = report.gate(&[CheckCategory::Pointers]).unwrap_err();
+        assert_eq!(err.exit_code(), 32);
+
+        let findings = report.findings(&trace, Path::new("."), "data");
+        let pointer: Vec<_> = findings
+            .iter()
+            .filter(|f| f.rule == "invalid-pointer-access")
+            .collect();
+        assert_eq!(pointer.len(), 2);
+        assert!(pointer
+            .iter()
+            .all(|f| f.location.as_ref().unwrap().to_string() == "src/lib.rs:6:5"));
+    }
+
+    #[test]
+    fn test_findings_point_at_test_bindings() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("src/lib.rs"),
+            "fn other() {\n    let x = 0;\n}\n\n#[test]\nfn borrows() {\n    let x = vec![1];\n}\n",
+        )
+        .unwrap();
+
+        let trace = conflicting_trace();
+        let report = CheckReport::build(&trace, &[CheckCategory::Conflicts], None, false, false);
+
+        let findings = report.findings(&trace, root, "app.borrows");
+        assert!(!findings.is_empty());
+        let location = findings[0].location.as_ref().unwrap();
+        assert_eq!(location.to_string(), "src/lib.rs:7:9");
+
+        // Outside a known test `x` could be either binding
+        let findings = report.findings(&trace, root, "data");
+        assert!(findings[0].location.is_none());
+
+        // A location recorded in the trace wins over the sources
+        let mut events = conflicting_trace().events;
+        if let Event::New { type_name, .. } = &mut events[0] {
+            *type_name = "Vec<i32> @ src/lib.rs:2:9".into();
+        }
+        let trace = Trace::from_events(events);
+        let findings = report.findings(&trace, root, "app.borrows");
+        assert_eq!(
+            findings[0].location.as_ref().unwrap().to_string(),
+            "src/lib.rs:2:9"
+        );
+    }
+
+    #[test]
+    fn test_check_mode_aliasing() {
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("data.json");
+
+        let events = vec![
+            Event::New {
+                timestamp: 1,
+                var_name: "x".into(),
+                var_id: "x_0".into(),
+                type_name: "i32".into(),
+                scope_depth: None,
+            },
+            Event::RawPtrCreated {
+                timestamp: 2,
+                var_name: "p".into(),
+                var_id: "1".into(),
+                ptr_type: "*mut i32".into(),
+                address: 4096,
+                location: "src/lib.rs:3:5".into(),
+                source_id: Some("x_0".into()),
+            },
+            borrow(3, "r", "x_0", true),
+            Event::RawPtrDeref {
+                timestamp: 4,
+                ptr_id: "1".into(),
+                location: "src/lib.rs:5:14".into(),
+                is_write: true,
+            },
+        ];
+        let graph = borrowscope_runtime::build_graph(&events);
+        borrowscope_runtime::ExportData::new(graph, events)
+            .to_file(&test_file)
+            .unwrap();
+
+        let args = |fail_on| CheckArgs {
+            file: test_file.clone(),
+            mode: Some(CheckMode::Aliasing),
+            stats: false,
+            validate: false,
+            fail_on,
+            source_root: temp_dir.path().to_path_buf(),
+            report_format: Some(ReportFormat::Sarif),
+        };
+        assert!(execute(args(vec![]), Config::default(), OutputFormat::Text).is_ok());
+
+        let err = execute(
+            args(vec![CheckCategory::Aliasing]),
+            Config::default(),
+            OutputFormat::Text,
+        )
+        .unwrap_err();
+        assert_eq!(err.exit_code(), 64);
+        assert!(err.to_string().contains("1 aliasing violation(s)"));
+    }
+
+    #[test]
+    fn test_check_fail_on_exits_with_category_code() {
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("data.json");
+
+        let trace = conflicting_trace();
+        let graph = borrowscope_runtime::build_graph(&trace.events);
+        borrowscope_runtime::ExportData::new(graph, trace.events)
+            .to_file(&test_file)
+            .unwrap();
+
+        for report_format in [Some(ReportFormat::Sarif), Some(ReportFormat::Junit), None] {
+            let args = CheckArgs {
+                file: test_file.clone(),
+                mode: None,
+                stats: false,
+                validate: false,
+                fail_on: vec![CheckCategory::Conflicts, CheckCategory::Leaks],
+                source_root: temp_dir.path().to_path_buf(),
+                report_format,
+            };
+            let err = execute(args, Config::default(), OutputFormat::Json).unwrap_err();
+            assert_eq!(err.exit_code(), 2);
+        }
+    }
 }
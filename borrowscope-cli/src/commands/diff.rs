@@ -0,0 +1,954 @@
+//! Diff command implementation
+//!
+//! Compares the ownership graphs of two traces, e.g. before and after a
+//! refactor. Runtime IDs differ between runs, so variables are matched on
+//! where they were declared and their name, aligned in creation order.
+//! Variables whose events carry no location are matched by name alone. The
+//! old graph is then renumbered onto the new one and
+//! [`OwnershipGraph::export_delta`](borrowscope_graph::OwnershipGraph::export_delta)
+//! finds the variables and borrows that were added or removed.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use borrowscope_graph::{EdgeExport, GraphExport, Relationship, Variable};
+use serde::Serialize;
+
+use super::export::ensure_parent_dir;
+use crate::cli::DiffArgs;
+use crate::error::Result;
+use crate::locations::SourceLocation;
+use crate::markup::escape;
+use crate::output::OutputFormat;
+use crate::trace::Trace;
+
+/// Above this many cells an alignment falls back to a greedy match, and
+/// above this many overlapping pairs lifetimes are not compared
+const MAX_ALIGNMENT_CELLS: usize = 4_000_000;
+
+/// Ownership changes between two traces
+#[derive(Debug, Serialize)]
+pub struct TraceDiff {
+    /// Variables found in both traces
+    pub matched: usize,
+    pub added_variables: Vec<VariableRef>,
+    pub removed_variables: Vec<VariableRef>,
+    pub added_borrows: Vec<BorrowChange>,
+    pub removed_borrows: Vec<BorrowChange>,
+    pub lifetime_changes: Vec<LifetimeChange>,
+    pub new_conflicts: Vec<String>,
+    pub resolved_conflicts: Vec<String>,
+    /// Both traces' variables side by side, for the HTML view
+    #[serde(skip)]
+    rows: Vec<Row>,
+}
+
+/// A variable as the diff shows it
+#[derive(Debug, Clone, Serialize)]
+pub struct VariableRef {
+    pub name: String,
+    pub type_name: String,
+    pub created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dropped_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
+}
+
+/// A borrow present in only one of the traces
+#[derive(Debug, Clone, Serialize)]
+pub struct BorrowChange {
+    pub borrower: String,
+    pub owner: String,
+    pub mutable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
+}
+
+/// A variable that now lives alongside different variables, or is no longer dropped
+#[derive(Debug, Clone, Serialize)]
+pub struct LifetimeChange {
+    pub variable: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
+    pub old: VariableRef,
+    pub new: VariableRef,
+    /// Variables of both traces whose lifetime it overlaps only in the new one
+    pub now_overlaps: Vec<String>,
+    /// Variables of both traces whose lifetime it overlaps only in the old one
+    pub no_longer_overlaps: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowStatus {
+    Same,
+    Changed,
+    Added,
+    Removed,
+}
+
+#[derive(Debug)]
+struct Row {
+    old: Option<VariableRef>,
+    new: Option<VariableRef>,
+    status: RowStatus,
+}
+
+pub fn execute(args: DiffArgs, format: OutputFormat) -> Result<()> {
+    log::info!(
+        "Comparing {} with {}",
+        args.old.display(),
+        args.new.display()
+    );
+
+    let old = Trace::load(&args.old)?;
+    let new = Trace::load(&args.new)?;
+    let diff = TraceDiff::compare(&Side::new(&old), &Side::new(&new));
+
+    if let Some(html) = &args.html {
+        ensure_parent_dir(html)?;
+        fs::write(html, diff.to_html(&args.old, &args.new))?;
+        log::info!("Wrote side-by-side view to {}", html.display());
+    }
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+        OutputFormat::None => {}
+        _ => diff.print(&args.old, &args.new),
+    }
+    Ok(())
+}
+
+/// One trace with its variables in creation order
+struct Side<'a> {
+    trace: &'a Trace,
+    /// Graph node IDs in creation order
+    ids: Vec<usize>,
+}
+
+impl<'a> Side<'a> {
+    fn new(trace: &'a Trace) -> Self {
+        let mut ids: Vec<usize> = trace.graph.export().nodes.iter().map(|v| v.id).collect();
+        ids.sort_unstable();
+        Self { trace, ids }
+    }
+
+    fn variable(&self, id: usize) -> &Variable {
+        self.trace
+            .graph
+            .get_variable(id)
+            .expect("node IDs come from the graph")
+    }
+
+    /// What variables are matched on: where they were declared, when the
+    /// trace records it, and their name
+    fn key(&self, id: usize) -> (Option<&SourceLocation>, &str) {
+        (self.trace.location(id), self.variable(id).name.as_str())
+    }
+
+    fn describe(&self, id: usize) -> VariableRef {
+        let variable = self.variable(id);
+        VariableRef {
+            name: variable.name.clone(),
+            type_name: variable.type_name.clone(),
+            created_at: variable.created_at,
+            dropped_at: variable.dropped_at,
+            location: self.trace.location(id).cloned(),
+        }
+    }
+
+    fn name(&self, id: usize) -> String {
+        self.variable(id).name.clone()
+    }
+}
+
+impl TraceDiff {
+    fn compare(old: &Side, new: &Side) -> Self {
+        let pairs = align(old, new);
+        let to_new: HashMap<usize, usize> = pairs
+            .iter()
+            .filter_map(|pair| match pair {
+                (Some(o), Some(n)) => Some((*o, *n)),
+                _ => None,
+            })
+            .collect();
+
+        // Renumber the old graph onto the new one; unmatched variables get
+        // IDs past the end so they cannot collide
+        let offset = new.ids.last().map_or(0, |last| last + 1);
+        let renumber = |id: usize| to_new.get(&id).copied().unwrap_or(offset + id);
+        let old_export = old.trace.graph.export();
+        let renumbered = GraphExport {
+            nodes: old_export
+                .nodes
+                .iter()
+                .map(|v| Variable {
+                    id: renumber(v.id),
+                    ..v.clone()
+                })
+                .collect(),
+            edges: old_export
+                .edges
+                .iter()
+                .map(|e| EdgeExport {
+                    from_id: renumber(e.from_id),
+                    to_id: renumber(e.to_id),
+                    relationship: e.relationship.clone(),
+                })
+                .collect(),
+        };
+        let from_new: HashMap<usize, usize> = old_export
+            .nodes
+            .iter()
+            .map(|v| (renumber(v.id), v.id))
+            .collect();
+
+        let delta = new.trace.graph.export_delta(&renumbered);
+
+        let mut added_variables: Vec<VariableRef> = delta
+            .added_nodes
+            .iter()
+            .map(|v| new.describe(v.id))
+            .collect();
+        added_variables.sort_by_key(|v| v.created_at);
+        let mut removed_variables: Vec<VariableRef> = delta
+            .removed_nodes
+            .iter()
+            .map(|id| old.describe(from_new[id]))
+            .collect();
+        removed_variables.sort_by_key(|v| v.created_at);
+
+        // The delta matches edges by endpoints only, so a borrow that turned
+        // mutable or shared is added as removed and added here
+        let old_edges: HashMap<(usize, usize), &Relationship> = renumbered
+            .edges
+            .iter()
+            .map(|e| ((e.from_id, e.to_id), &e.relationship))
+            .collect();
+        let new_export = new.trace.graph.export();
+        let flipped: Vec<(&EdgeExport, &Relationship)> = new_export
+            .edges
+            .iter()
+            .filter_map(|e| {
+                let old = *old_edges.get(&(e.from_id, e.to_id))?;
+                (borrow_kind(old) != borrow_kind(&e.relationship)).then_some((e, old))
+            })
+            .collect();
+
+        let mut added: Vec<(usize, usize, &Relationship)> = delta
+            .added_edges
+            .iter()
+            .map(|e| (e.from_id, e.to_id, &e.relationship))
+            .chain(
+                flipped
+                    .iter()
+                    .map(|(e, _)| (e.from_id, e.to_id, &e.relationship)),
+            )
+            .collect();
+        added.sort_by_key(|&(from, to, _)| (from, to));
+        let mut removed: Vec<(usize, usize, &Relationship)> = delta
+            .removed_edges
+            .iter()
+            .map(|&(from, to)| (from_new[&from], from_new[&to], old_edges[&(from, to)]))
+            .chain(
+                flipped
+                    .iter()
+                    .map(|(e, old)| (from_new[&e.from_id], from_new[&e.to_id], *old)),
+            )
+            .collect();
+        removed.sort_by_key(|&(from, to, _)| (from, to));
+
+        let borrows = |side: &Side, edges: Vec<(usize, usize, &Relationship)>| {
+            edges
+                .into_iter()
+                .filter_map(|(from, to, relationship)| {
+                    Some(BorrowChange {
+                        borrower: side.name(from),
+                        owner: side.name(to),
+                        mutable: borrow_kind(relationship)?,
+                        location: side.trace.location(from).cloned(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let added_borrows = borrows(new, added);
+        let removed_borrows = borrows(old, removed);
+
+        let (changed, lifetime_changes): (HashSet<usize>, Vec<LifetimeChange>) =
+            lifetime_changes(old, new, &pairs).into_iter().unzip();
+
+        let conflict_keys = |side: &Side, map: &dyn Fn(usize) -> usize| {
+            side.trace
+                .conflicts()
+                .into_iter()
+                .map(|report| {
+                    let mut key: Vec<usize> = report
+                        .conflict
+                        .borrowers
+                        .iter()
+                        .map(|&id| map(id))
+                        .collect();
+                    key.sort_unstable();
+                    key.insert(0, map(report.conflict.owner_id));
+                    (key, report.message)
+                })
+                .collect::<Vec<_>>()
+        };
+        let old_conflicts = conflict_keys(old, &renumber);
+        let new_conflicts = conflict_keys(new, &|id| id);
+        let old_keys: HashSet<&Vec<usize>> = old_conflicts.iter().map(|(key, _)| key).collect();
+        let new_keys: HashSet<&Vec<usize>> = new_conflicts.iter().map(|(key, _)| key).collect();
+
+        let rows = pairs
+            .iter()
+            .map(|pair| match *pair {
+                (Some(o), Some(n)) => Row {
+                    old: Some(old.describe(o)),
+                    new: Some(new.describe(n)),
+                    status: if changed.contains(&n) {
+                        RowStatus::Changed
+                    } else {
+                        RowStatus::Same
+                    },
+                },
+                (Some(o), None) => Row {
+                    old: Some(old.describe(o)),
+                    new: None,
+                    status: RowStatus::Removed,
+                },
+                (None, Some(n)) => Row {
+                    old: None,
+                    new: Some(new.describe(n)),
+                    status: RowStatus::Added,
+                },
+                (None, None) => unreachable!("alignment pairs at least one side"),
+            })
+            .collect();
+
+        Self {
+            matched: to_new.len(),
+            added_variables,
+            removed_variables,
+            added_borrows,
+            removed_borrows,
+            lifetime_changes,
+            new_conflicts: new_conflicts
+                .iter()
+                .filter(|(key, _)| !old_keys.contains(key))
+                .map(|(_, message)| message.clone())
+                .collect(),
+            resolved_conflicts: old_conflicts
+                .iter()
+                .filter(|(key, _)| !new_keys.contains(key))
+                .map(|(_, message)| message.clone())
+                .collect(),
+            rows,
+        }
+    }
+
+    /// Whether the traces show the same ownership
+    pub fn is_empty(&self) -> bool {
+        self.added_variables.is_empty()
+            && self.removed_variables.is_empty()
+            && self.added_borrows.is_empty()
+            && self.removed_borrows.is_empty()
+            && self.lifetime_changes.is_empty()
+            && self.new_conflicts.is_empty()
+            && self.resolved_conflicts.is_empty()
+    }
+
+    fn print(&self, old: &Path, new: &Path) {
+        println!("🔀 Comparing {} -> {}", old.display(), new.display());
+        println!(
+            "  {} variable(s) matched, {} added, {} removed",
+            self.matched,
+            self.added_variables.len(),
+            self.removed_variables.len()
+        );
+
+        if self.is_empty() {
+            println!("\n  ✓ No ownership changes");
+            return;
+        }
+
+        print_borrows("➕ Added borrows:", &self.added_borrows);
+        print_borrows("➖ Removed borrows:", &self.removed_borrows);
+
+        if !self.lifetime_changes.is_empty() {
+            println!("\n⏳ Changed lifetimes:");
+            for change in &self.lifetime_changes {
+                println!("    - {}", change.describe());
+            }
+        }
+
+        if !self.new_conflicts.is_empty() {
+            println!("\n⚠️  New conflicts:");
+            for message in &self.new_conflicts {
+                println!("    - {}", message);
+            }
+        }
+        if !self.resolved_conflicts.is_empty() {
+            println!("\n✅ Resolved conflicts:");
+            for message in &self.resolved_conflicts {
+                println!("    - {}", message);
+            }
+        }
+    }
+
+    /// Self-contained page with both traces' variables side by side
+    pub fn to_html(&self, old: &Path, new: &Path) -> String {
+        let cell = |variable: &Option<VariableRef>| match variable {
+            Some(v) => format!(
+                "<td><code>{}</code>: {}<br><small>{} [{}, {})</small></td>",
+                escape(&v.name),
+                escape(&v.type_name),
+                v.location
+                    .as_ref()
+                    .map_or_else(String::new, |l| escape(&l.to_string())),
+                v.created_at,
+                v.dropped_at
+                    .map_or_else(|| "…".to_string(), |d| d.to_string()),
+            ),
+            None => "<td></td>".to_string(),
+        };
+        let mut rows = String::new();
+        for row in &self.rows {
+            let class = match row.status {
+                RowStatus::Same => "same",
+                RowStatus::Changed => "changed",
+                RowStatus::Added => "added",
+                RowStatus::Removed => "removed",
+            };
+            let _ = writeln!(
+                rows,
+                "<tr class=\"{}\">{}{}</tr>",
+                class,
+                cell(&row.old),
+                cell(&row.new)
+            );
+        }
+
+        let list = |items: Vec<String>| {
+            if items.is_empty() {
+                return "<li>None</li>\n".to_string();
+            }
+            items
+                .iter()
+                .map(|item| format!("<li>{}</li>\n", escape(item)))
+                .collect()
+        };
+        let borrows =
+            |borrows: &[BorrowChange]| list(borrows.iter().map(|b| b.describe()).collect());
+
+        format!(
+            r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="UTF-8">
+<title>BorrowScope Diff</title>
+<style>
+body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif; margin: 2rem; color: #222; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ border: 1px solid #ddd; padding: 0.3rem 0.6rem; width: 50%; vertical-align: top; }}
+small {{ color: #666; }}
+tr.added td:last-child {{ background: #e6ffed; }}
+tr.removed td:first-child {{ background: #ffeef0; }}
+tr.changed td {{ background: #fff8c5; }}
+</style>
+</head>
+<body>
+<h1>Ownership diff</h1>
+<p>{matched} variable(s) matched, {added} added, {removed} removed</p>
+<h2>Added borrows</h2>
+<ul>
+{added_borrows}</ul>
+<h2>Removed borrows</h2>
+<ul>
+{removed_borrows}</ul>
+<h2>Changed lifetimes</h2>
+<ul>
+{lifetimes}</ul>
+<h2>New conflicts</h2>
+<ul>
+{new_conflicts}</ul>
+<h2>Resolved conflicts</h2>
+<ul>
+{resolved_conflicts}</ul>
+<h2>Variables</h2>
+<table>
+<tr><th>{old}</th><th>{new}</th></tr>
+{rows}</table>
+</body>
+</html>
+"##,
+            matched = self.matched,
+            added = self.added_variables.len(),
+            removed = self.removed_variables.len(),
+            added_borrows = borrows(&self.added_borrows),
+            removed_borrows = borrows(&self.removed_borrows),
+            lifetimes = list(self.lifetime_changes.iter().map(|c| c.describe()).collect()),
+            new_conflicts = list(self.new_conflicts.clone()),
+            resolved_conflicts = list(self.resolved_conflicts.clone()),
+            old = escape(&old.display().to_string()),
+            new = escape(&new.display().to_string()),
+        )
+    }
+}
+
+impl BorrowChange {
+    fn describe(&self) -> String {
+        let kind = if self.mutable { "&mut" } else { "&" };
+        let mut text = format!("{} '{}' of '{}'", kind, self.borrower, self.owner);
+        if let Some(location) = &self.location {
+            let _ = write!(text, " ({})", location);
+        }
+        text
+    }
+}
+
+impl LifetimeChange {
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        match (self.old.dropped_at, self.new.dropped_at) {
+            (Some(_), None) => parts.push("is no longer dropped".to_string()),
+            (None, Some(_)) => parts.push("is now dropped".to_string()),
+            _ => {}
+        }
+        if !self.now_overlaps.is_empty() {
+            parts.push(format!(
+                "now lives alongside {}",
+                quoted(&self.now_overlaps)
+            ));
+        }
+        if !self.no_longer_overlaps.is_empty() {
+            parts.push(format!(
+                "no longer lives alongside {}",
+                quoted(&self.no_longer_overlaps)
+            ));
+        }
+
+        let mut text = format!("'{}' {}", self.variable, parts.join("; "));
+        if let Some(location) = &self.location {
+            let _ = write!(text, " ({})", location);
+        }
+        text
+    }
+}
+
+fn print_borrows(title: &str, borrows: &[BorrowChange]) {
+    if borrows.is_empty() {
+        return;
+    }
+    println!("\n{}", title);
+    for borrow in borrows {
+        println!("    - {}", borrow.describe());
+    }
+}
+
+fn quoted(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("'{}'", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `Some(mutable)` for borrow relationships
+fn borrow_kind(relationship: &Relationship) -> Option<bool> {
+    match relationship {
+        Relationship::BorrowsImmut { .. } => Some(false),
+        Relationship::BorrowsMut { .. } => Some(true),
+        Relationship::RefCellBorrow { is_mut, .. } => Some(*is_mut),
+        _ => None,
+    }
+}
+
+/// Pair up the variables of both traces in creation order.
+///
+/// The longest common subsequence of keys keeps one added variable from
+/// shifting every later match.
+fn align(old: &Side, new: &Side) -> Vec<(Option<usize>, Option<usize>)> {
+    let (a, b) = (&old.ids, &new.ids);
+    let matched = common_subsequence(a.len(), b.len(), |i, j| old.key(a[i]) == new.key(b[j]));
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (mi, mj) in matched
+        .into_iter()
+        .chain(std::iter::once((a.len(), b.len())))
+    {
+        pairs.extend((i..mi).map(|i| (Some(a[i]), None)));
+        pairs.extend((j..mj).map(|j| (None, Some(b[j]))));
+        if mi < a.len() {
+            pairs.push((Some(a[mi]), Some(b[mj])));
+        }
+        (i, j) = (mi + 1, mj + 1);
+    }
+    pairs
+}
+
+/// Index pairs of a longest common subsequence of two sequences of lengths
+/// `n` and `m`, whose elements `same(i, j)` compares.
+///
+/// Sequences too large for the table are matched greedily.
+pub(super) fn common_subsequence(
+    n: usize,
+    m: usize,
+    same: impl Fn(usize, usize) -> bool,
+) -> Vec<(usize, usize)> {
+    // Common prefix and suffix need no table
+    let prefix = (0..n.min(m)).take_while(|&i| same(i, i)).count();
+    let suffix = (0..(n - prefix).min(m - prefix))
+        .take_while(|&k| same(n - 1 - k, m - 1 - k))
+        .count();
+    let (rows, cols) = (n - prefix - suffix, m - prefix - suffix);
+
+    let mut matched: Vec<(usize, usize)> = (0..prefix).map(|i| (i, i)).collect();
+    if rows * cols <= MAX_ALIGNMENT_CELLS {
+        // lengths[i][j]: LCS length of the middles from prefix + i and prefix + j
+        let mut lengths = vec![vec![0u32; cols + 1]; rows + 1];
+        for i in (0..rows).rev() {
+            for j in (0..cols).rev() {
+                lengths[i][j] = if same(prefix + i, prefix + j) {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < rows && j < cols {
+            if same(prefix + i, prefix + j) {
+                matched.push((prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    } else {
+        let mut next = 0;
+        for i in 0..rows {
+            if let Some(j) = (next..cols).find(|&j| same(prefix + i, prefix + j)) {
+                matched.push((prefix + i, prefix + j));
+                next = j + 1;
+            }
+        }
+    }
+    matched.extend((0..suffix).map(|k| (n - suffix + k, m - suffix + k)));
+    matched
+}
+
+/// Matched variables whose lifetime overlaps different matched variables in
+/// the two traces, or that are dropped in only one of them, by new node ID.
+///
+/// Lifetimes are compared by what they overlap, not by length: timestamps
+/// count events, so durations shift whenever unrelated code records more or
+/// fewer of them. A variable that lives longer without meeting anything new
+/// is not reported.
+fn lifetime_changes(
+    old: &Side,
+    new: &Side,
+    pairs: &[(Option<usize>, Option<usize>)],
+) -> Vec<(usize, LifetimeChange)> {
+    let matched: Vec<(usize, usize)> = pairs
+        .iter()
+        .filter_map(|pair| match pair {
+            (Some(o), Some(n)) => Some((*o, *n)),
+            _ => None,
+        })
+        .collect();
+
+    let old_ids: Vec<usize> = matched.iter().map(|&(o, _)| o).collect();
+    let new_ids: Vec<usize> = matched.iter().map(|&(_, n)| n).collect();
+    // Too many overlaps to compare only leaves the drops to report
+    let overlap_changes = match (overlaps(old, &old_ids), overlaps(new, &new_ids)) {
+        (Some(before), Some(after)) => Some((before, after)),
+        _ => None,
+    };
+
+    let mut now_overlaps = vec![Vec::new(); matched.len()];
+    let mut no_longer_overlaps = vec![Vec::new(); matched.len()];
+    if let Some((before, after)) = &overlap_changes {
+        for &(k, l) in after.difference(before) {
+            now_overlaps[k].push(l);
+            now_overlaps[l].push(k);
+        }
+        for &(k, l) in before.difference(after) {
+            no_longer_overlaps[k].push(l);
+            no_longer_overlaps[l].push(k);
+        }
+    }
+    let names = |mut others: Vec<usize>| -> Vec<String> {
+        others.sort_unstable();
+        others.into_iter().map(|l| new.name(new_ids[l])).collect()
+    };
+
+    let mut changes = Vec::new();
+    for (k, &(o, n)) in matched.iter().enumerate() {
+        let now_overlaps = names(std::mem::take(&mut now_overlaps[k]));
+        let no_longer_overlaps = names(std::mem::take(&mut no_longer_overlaps[k]));
+
+        let (old_var, new_var) = (old.describe(o), new.describe(n));
+        let drop_changed = old_var.dropped_at.is_some() != new_var.dropped_at.is_some();
+        if drop_changed || !now_overlaps.is_empty() || !no_longer_overlaps.is_empty() {
+            let change = LifetimeChange {
+                variable: new_var.name.clone(),
+                location: new_var.location.clone(),
+                old: old_var,
+                new: new_var,
+                now_overlaps,
+                no_longer_overlaps,
+            };
+            changes.push((n, change));
+        }
+    }
+    changes
+}
+
+/// Pairs `(k, l)`, `k < l`, of positions in `ids` whose variables are alive
+/// at the same time, or `None` past `MAX_ALIGNMENT_CELLS` pairs.
+///
+/// A sweep in creation order only meets the variables still alive, so
+/// traces of short-lived variables cost far less than comparing all pairs.
+fn overlaps(side: &Side, ids: &[usize]) -> Option<HashSet<(usize, usize)>> {
+    let mut order: Vec<usize> = (0..ids.len()).collect();
+    order.sort_by_key(|&k| side.variable(ids[k]).created_at);
+
+    let ends_after = |v: &Variable, at: u64| v.dropped_at.map_or(true, |d| d > at);
+    let mut alive: Vec<usize> = Vec::new();
+    let mut pairs = HashSet::new();
+    for k in order {
+        let variable = side.variable(ids[k]);
+        alive.retain(|&l| ends_after(side.variable(ids[l]), variable.created_at));
+        for &l in &alive {
+            if ends_after(variable, side.variable(ids[l]).created_at) {
+                pairs.insert((k.min(l), k.max(l)));
+            }
+        }
+        if pairs.len() > MAX_ALIGNMENT_CELLS {
+            return None;
+        }
+        alive.push(k);
+    }
+    Some(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borrowscope_runtime::Event;
+    use tempfile::TempDir;
+
+    fn new_var(timestamp: u64, name: &str) -> Event {
+        Event::New {
+            timestamp,
+            var_name: name.into(),
+            var_id: format!("{}_{}", name, timestamp),
+            type_name: "Vec<i32>".into(),
+            scope_depth: None,
+        }
+    }
+
+    fn borrow(timestamp: u64, name: &str, owner: &str, mutable: bool) -> Event {
+        Event::Borrow {
+            timestamp,
+            borrower_name: name.into(),
+            borrower_id: format!("{}_{}", name, timestamp),
+            owner_id: owner.into(),
+            mutable,
+        }
+    }
+
+    fn drop_var(timestamp: u64, var_id: &str) -> Event {
+        Event::Drop {
+            timestamp,
+            var_id: var_id.into(),
+        }
+    }
+
+    fn diff(old: Vec<Event>, new: Vec<Event>) -> TraceDiff {
+        let (old, new) = (Trace::from_events(old), Trace::from_events(new));
+        TraceDiff::compare(&Side::new(&old), &Side::new(&new))
+    }
+
+    /// `name` declared at `line` of `src/lib.rs`, as the macro records it
+    fn located_var(timestamp: u64, name: &str, line: usize) -> Event {
+        Event::New {
+            timestamp,
+            var_name: name.into(),
+            var_id: format!("{}_{}", name, timestamp),
+            type_name: format!("Vec<i32> @ src/lib.rs:{}:9", line),
+            scope_depth: None,
+        }
+    }
+
+    /// `data` and a shared borrow of it, both dropped
+    fn baseline() -> Vec<Event> {
+        vec![
+            new_var(1, "data"),
+            borrow(2, "view", "data_1", false),
+            drop_var(3, "view_2"),
+            drop_var(4, "data_1"),
+        ]
+    }
+
+    #[test]
+    fn test_identical_traces_have_no_changes() {
+        let diff = diff(baseline(), baseline());
+        assert!(diff.is_empty());
+        assert_eq!(diff.matched, 2);
+    }
+
+    #[test]
+    fn test_matches_variables_despite_shifted_ids() {
+        // An extra variable first shifts every timestamp and runtime ID
+        let mut new = vec![new_var(1, "config")];
+        new.extend(vec![
+            new_var(2, "data"),
+            borrow(3, "view", "data_2", false),
+            drop_var(4, "view_3"),
+            drop_var(5, "data_2"),
+        ]);
+
+        let diff = diff(baseline(), new);
+        assert_eq!(diff.matched, 2);
+        assert_eq!(diff.added_variables.len(), 1);
+        assert_eq!(diff.added_variables[0].name, "config");
+        assert!(diff.removed_variables.is_empty());
+        assert!(diff.added_borrows.is_empty() && diff.removed_borrows.is_empty());
+    }
+
+    #[test]
+    fn test_matches_variables_on_location_and_name() {
+        // The first of two `item` bindings is gone; by name alone the second
+        // would look removed
+        let old = vec![
+            located_var(1, "item", 3),
+            located_var(2, "item", 7),
+            located_var(3, "total", 8),
+        ];
+        let new = vec![located_var(1, "item", 7), located_var(2, "total", 8)];
+
+        let diff = diff(old, new);
+        assert_eq!(diff.matched, 2);
+        assert_eq!(diff.removed_variables.len(), 1);
+        let removed = &diff.removed_variables[0];
+        assert_eq!(removed.type_name, "Vec<i32>");
+        assert_eq!(
+            removed.location.as_ref().unwrap().to_string(),
+            "src/lib.rs:3:9"
+        );
+        assert!(diff.added_variables.is_empty());
+    }
+
+    #[test]
+    fn test_new_mutable_borrow_and_conflict() {
+        let new = vec![
+            new_var(1, "data"),
+            borrow(2, "view", "data_1", false),
+            borrow(3, "edit", "data_1", true),
+            drop_var(4, "edit_3"),
+            drop_var(5, "view_2"),
+            drop_var(6, "data_1"),
+        ];
+
+        let diff = diff(baseline(), new);
+        assert_eq!(diff.added_borrows.len(), 1);
+        let added = &diff.added_borrows[0];
+        assert_eq!(
+            (added.borrower.as_str(), added.owner.as_str()),
+            ("edit", "data")
+        );
+        assert!(added.mutable);
+        assert_eq!(diff.new_conflicts.len(), 1);
+        assert!(diff.resolved_conflicts.is_empty());
+        // Later drops are not a lifetime change while the same variables overlap
+        assert!(diff.lifetime_changes.is_empty());
+    }
+
+    #[test]
+    fn test_borrow_turned_mutable_is_removed_and_added() {
+        let new = vec![
+            new_var(1, "data"),
+            borrow(2, "view", "data_1", true),
+            drop_var(3, "view_2"),
+            drop_var(4, "data_1"),
+        ];
+
+        let diff = diff(baseline(), new);
+        assert_eq!(diff.removed_borrows.len(), 1);
+        assert!(!diff.removed_borrows[0].mutable);
+        assert_eq!(diff.added_borrows.len(), 1);
+        assert!(diff.added_borrows[0].mutable);
+    }
+
+    #[test]
+    fn test_lifetime_changes() {
+        let old = vec![
+            new_var(1, "a"),
+            drop_var(2, "a_1"),
+            new_var(3, "b"),
+            drop_var(4, "b_3"),
+        ];
+        // `a` now outlives `b` and is never dropped
+        let new = vec![new_var(1, "a"), new_var(2, "b"), drop_var(3, "b_2")];
+
+        let diff = diff(old, new);
+        assert_eq!(diff.lifetime_changes.len(), 2);
+        let a = &diff.lifetime_changes[0];
+        assert_eq!(a.variable, "a");
+        assert_eq!(a.now_overlaps, ["b"]);
+        assert_eq!(
+            a.describe(),
+            "'a' is no longer dropped; now lives alongside 'b'"
+        );
+    }
+
+    #[test]
+    fn test_longer_lifetime_alone_is_not_a_change() {
+        let old = vec![new_var(1, "a"), drop_var(2, "a_1"), new_var(3, "b")];
+        // `a` lives through more events but still ends before `b` starts
+        let new = vec![
+            new_var(1, "a"),
+            new_var(2, "tmp"),
+            drop_var(3, "tmp_2"),
+            drop_var(4, "a_1"),
+            new_var(5, "b"),
+        ];
+
+        let diff = diff(old, new);
+        assert!(diff
+            .lifetime_changes
+            .iter()
+            .all(|change| change.variable != "a" && change.variable != "b"));
+    }
+
+    #[test]
+    fn test_execute_writes_html() {
+        let dir = TempDir::new().unwrap();
+        let write = |name: &str, events: Vec<Event>| {
+            let path = dir.path().join(name);
+            let data = serde_json::json!({ "events": events });
+            fs::write(&path, data.to_string()).unwrap();
+            path
+        };
+        let mut new = baseline();
+        new.insert(2, borrow(3, "<edit>", "data_1", true));
+
+        let args = DiffArgs {
+            old: write("old.json", baseline()),
+            new: write("new.json", new),
+            html: Some(dir.path().join("out/diff.html")),
+        };
+        execute(args, OutputFormat::None).unwrap();
+
+        let html = fs::read_to_string(dir.path().join("out/diff.html")).unwrap();
+        assert!(html.contains("<tr class=\"added\"><td></td><td><code>&lt;edit&gt;</code>"));
+        assert!(html.contains("&amp;mut '&lt;edit&gt;' of 'data'"));
+    }
+}
@@ -0,0 +1,728 @@
+//! Expand command implementation
+//!
+//! Prints what the instrumenter makes of a file or a single function, or a
+//! unified diff against the original, and warns about constructs it left
+//! untracked. Functions carrying `#[trace_borrow]` are also shown as the macro
+//! expands them.
+
+use borrowscope_transform::self_borrow::known_self_borrow;
+use borrowscope_transform::OwnershipVisitor;
+use colored::Colorize;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
+use walkdir::WalkDir;
+
+use crate::cli::ExpandArgs;
+use crate::config::Config;
+use crate::error::{CliError, Result};
+use crate::instrumentation::{matches_fn_path, InstrumentationConfig, Instrumenter, Skipped};
+use crate::locations::{Declaration, SourceIndex};
+
+use super::diff::common_subsequence;
+
+/// Lines of context around each change in a diff
+const DIFF_CONTEXT: usize = 3;
+
+pub fn execute(args: ExpandArgs, config: Config) -> Result<()> {
+    let mut config_inst = InstrumentationConfig::from_config(&config);
+    if args.preserve_formatting {
+        config_inst.preserve_formatting = true;
+    }
+    if args.tests {
+        config_inst.trace_tests = true;
+    }
+
+    let target = Path::new(&args.target);
+    let expansions = if target.is_file() {
+        vec![expand_file(target, &config_inst)?]
+    } else {
+        expand_function(&args.target, &args.path, &config_inst)?
+    };
+
+    for (index, expansion) in expansions.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        if args.diff {
+            print!(
+                "{}",
+                unified_diff(
+                    &expansion.original,
+                    &expansion.instrumented,
+                    &format!("a/{}", expansion.label),
+                    &format!("b/{}", expansion.label),
+                )
+            );
+        } else {
+            if let Some(function) = &expansion.function {
+                println!("// {} ({})", function, expansion.label);
+            }
+            print!("{}", expansion.instrumented);
+        }
+
+        for traced in &expansion.traced {
+            if args.diff {
+                print!(
+                    "{}",
+                    unified_diff(
+                        &traced.original,
+                        &traced.expanded,
+                        &format!("a/{}", traced.label),
+                        &format!("b/{} (#[trace_borrow])", traced.label),
+                    )
+                );
+            } else {
+                println!();
+                println!(
+                    "// #[trace_borrow] expansion of {} ({})",
+                    traced.function, traced.label
+                );
+                print!("{}", traced.expanded);
+            }
+        }
+
+        for warning in &expansion.warnings {
+            eprintln!("{} {}", "⚠".yellow().bold(), warning);
+        }
+    }
+
+    Ok(())
+}
+
+/// Instrumented code of a file or function, next to the original
+#[derive(Debug)]
+struct Expansion {
+    /// Function path, when a single function was expanded
+    function: Option<String>,
+    /// File and line the code comes from
+    label: String,
+    original: String,
+    instrumented: String,
+    /// What `#[trace_borrow]` makes of the annotated functions
+    traced: Vec<TracedFunction>,
+    warnings: Vec<String>,
+}
+
+/// A `#[trace_borrow]` function as the macro expands it
+#[derive(Debug, Clone)]
+struct TracedFunction {
+    function: String,
+    /// File and line of the function
+    label: String,
+    original: String,
+    expanded: String,
+}
+
+fn expand_file(path: &Path, config: &InstrumentationConfig) -> Result<Expansion> {
+    let crate_dir = crate_dir(path);
+    let relative = path.strip_prefix(&crate_dir).unwrap_or(path);
+    let content = fs::read_to_string(path)?;
+
+    let instrumenter = Instrumenter::new(crate_dir.clone(), crate_dir.clone(), config.clone());
+    let instrumented = instrumenter
+        .instrument_source(path, &content)
+        .map_err(|e| CliError::InstrumentationError(e.to_string()))?;
+
+    let mut warnings = skipped_warnings(relative, &instrumented.skipped, None);
+    let mut traced = Vec::new();
+    if let Ok(file) = syn::parse_file(&content) {
+        let index = SourceIndex::file(relative, &instrumenter.module_of(path), &file);
+        let macro_expansion = MacroExpansion::of(relative, &index, &file, &content);
+        warnings.extend(macro_expansion.warnings(None));
+        traced = macro_expansion.traced;
+    }
+
+    Ok(Expansion {
+        function: None,
+        label: relative.display().to_string(),
+        original: content,
+        instrumented: instrumented.code,
+        traced,
+        warnings,
+    })
+}
+
+fn expand_function(
+    pattern: &str,
+    project: &Path,
+    config: &InstrumentationConfig,
+) -> Result<Vec<Expansion>> {
+    if !project.join("Cargo.toml").exists() {
+        return Err(CliError::Other(format!(
+            "No Cargo.toml found in {}",
+            project.display()
+        )));
+    }
+
+    let instrumenter =
+        Instrumenter::new(project.to_path_buf(), project.to_path_buf(), config.clone());
+    let mut expansions = Vec::new();
+
+    let sources = WalkDir::new(project)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name.starts_with('.') || name == "target")
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "rs"));
+
+    for entry in sources {
+        let path = entry.path();
+        let relative = path.strip_prefix(project).unwrap_or(path);
+        let Ok(content) = fs::read_to_string(path) else {
+            continue;
+        };
+        let Ok(file) = syn::parse_file(&content) else {
+            continue;
+        };
+
+        let module = instrumenter.module_of(path);
+        let index = SourceIndex::file(relative, &module, &file);
+        let found: Vec<&Declaration> = index
+            .functions()
+            .iter()
+            .filter(|function| matches_fn_path(pattern, &function.path))
+            .collect();
+        if found.is_empty() {
+            continue;
+        }
+
+        let instrumented = instrumenter
+            .instrument_source(path, &content)
+            .map_err(|e| CliError::InstrumentationError(e.to_string()))?;
+        let Ok(instrumented_file) = syn::parse_file(&instrumented.code) else {
+            continue;
+        };
+        let instrumented_index = SourceIndex::file(relative, &module, &instrumented_file);
+        let macro_expansion = MacroExpansion::of(relative, &index, &file, &content);
+
+        for function in found {
+            let Some(expanded) = instrumented_index
+                .functions()
+                .iter()
+                .find(|f| f.path == function.path)
+            else {
+                continue;
+            };
+            let mut warnings =
+                skipped_warnings(relative, &instrumented.skipped, Some(&function.path));
+            warnings.extend(macro_expansion.warnings(Some(&function.path)));
+
+            expansions.push(Expansion {
+                label: format!("{}:{}", relative.display(), function.start_line),
+                original: function_text(&content, &function.bytes),
+                instrumented: function_text(&instrumented.code, &expanded.bytes),
+                traced: macro_expansion.traced(&function.path),
+                function: Some(function.path.clone()),
+                warnings,
+            });
+        }
+    }
+
+    if expansions.is_empty() {
+        return Err(CliError::Other(format!(
+            "No function matching `{}` found in {}",
+            pattern,
+            project.display()
+        )));
+    }
+    Ok(expansions)
+}
+
+/// Nearest directory above `path` holding a Cargo.toml, or its parent directory
+fn crate_dir(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    parent
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").exists())
+        .unwrap_or(parent)
+        .to_path_buf()
+}
+
+fn skipped_warnings(file: &Path, skipped: &[Skipped], function: Option<&str>) -> Vec<String> {
+    skipped
+        .iter()
+        .filter(|skipped| function.map_or(true, |function| skipped.function == function))
+        .map(|skipped| {
+            format!(
+                "{}:{}: in {}: {}",
+                file.display(),
+                skipped.line,
+                skipped.function,
+                skipped.reason
+            )
+        })
+        .collect()
+}
+
+/// Source of a function, re-indented to the column it starts at
+fn function_text(source: &str, bytes: &Range<usize>) -> String {
+    let line_start = source[..bytes.start]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let indent = &source[line_start..bytes.start];
+    let mut text = String::new();
+    if indent.trim().is_empty() {
+        text.push_str(indent);
+    }
+    text.push_str(&source[bytes.clone()]);
+    text.push('\n');
+    text
+}
+
+/// What `#[trace_borrow]` makes of the annotated functions of a file
+struct MacroExpansion {
+    /// Guesses the macro will make, as `(function path, warning)`
+    warnings: Vec<(String, String)>,
+    traced: Vec<TracedFunction>,
+}
+
+impl MacroExpansion {
+    fn of(file: &Path, index: &SourceIndex, syntax: &syn::File, source: &str) -> Self {
+        let mut finder = TraceBorrowFinder {
+            file,
+            index,
+            function: None,
+            warnings: Vec::new(),
+            traced: Vec::new(),
+        };
+        finder.visit_file(syntax);
+
+        let traced = finder
+            .traced
+            .into_iter()
+            .map(|(declaration, item)| TracedFunction {
+                function: declaration.path.clone(),
+                label: format!("{}:{}", file.display(), declaration.start_line),
+                original: function_text(source, &declaration.bytes),
+                expanded: expand_trace_borrow(item),
+            })
+            .collect();
+        Self {
+            warnings: finder.warnings,
+            traced,
+        }
+    }
+
+    /// Warnings for `function`, or for the whole file
+    fn warnings(&self, function: Option<&str>) -> Vec<String> {
+        self.warnings
+            .iter()
+            .filter(|(path, _)| function.map_or(true, |function| path == function))
+            .map(|(_, warning)| warning.clone())
+            .collect()
+    }
+
+    /// The macro expansion of `function`, if it carries `#[trace_borrow]`
+    fn traced(&self, function: &str) -> Vec<TracedFunction> {
+        self.traced
+            .iter()
+            .filter(|traced| traced.function == function)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Apply the macro's transformation to a function carrying `#[trace_borrow]`
+fn expand_trace_borrow(mut item: syn::ItemFn) -> String {
+    // `#[trace_borrow(unsafe_code)]` also instruments unsafe code
+    let unsafe_code = item
+        .attrs
+        .iter()
+        .filter(|attr| is_trace_borrow(attr))
+        .any(|attr| {
+            attr.parse_args_with(
+                syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated,
+            )
+            .is_ok_and(|options| options.iter().any(|option| option == "unsafe_code"))
+        });
+    item.attrs.retain(|attr| !is_trace_borrow(attr));
+
+    let mut visitor = OwnershipVisitor::new();
+    if unsafe_code {
+        visitor = visitor.with_unsafe_tracking();
+    }
+    visitor.visit_item_fn_mut(&mut item);
+
+    prettyplease::unparse(&syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![syn::Item::Fn(item)],
+    })
+}
+
+fn is_trace_borrow(attr: &syn::Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|seg| seg.ident == "trace_borrow")
+}
+
+/// Finds `#[trace_borrow]` functions, and the method calls in them that the
+/// macro can only guess at
+struct TraceBorrowFinder<'a> {
+    file: &'a Path,
+    /// Functions of the file, to name the ones found
+    index: &'a SourceIndex,
+    /// Path of the enclosing `#[trace_borrow]` function
+    function: Option<String>,
+    warnings: Vec<(String, String)>,
+    traced: Vec<(&'a Declaration, syn::ItemFn)>,
+}
+
+impl TraceBorrowFinder<'_> {
+    fn enter(&mut self, item: syn::ItemFn, visit: impl FnOnce(&mut Self)) {
+        let saved = self.function.take();
+        let start = item.sig.ident.span().start();
+        let declaration = self.index.functions().iter().find(|declaration| {
+            declaration.location.line == start.line
+                && declaration.location.column == start.column + 1
+        });
+        if let Some(declaration) = declaration.filter(|_| item.attrs.iter().any(is_trace_borrow)) {
+            self.function = Some(declaration.path.clone());
+            self.traced.push((declaration, item));
+        }
+        visit(self);
+        self.function = saved;
+    }
+}
+
+impl<'ast> Visit<'ast> for TraceBorrowFinder<'_> {
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        self.enter(node.clone(), |visitor| {
+            syn::visit::visit_item_fn(visitor, node)
+        });
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        // The macro sees a method as a function item
+        let item = syn::ItemFn {
+            attrs: node.attrs.clone(),
+            vis: node.vis.clone(),
+            sig: node.sig.clone(),
+            block: Box::new(node.block.clone()),
+        };
+        self.enter(item, |visitor| {
+            syn::visit::visit_impl_item_fn(visitor, node)
+        });
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        if let (Some(function), syn::Expr::Path(receiver)) = (&self.function, &*node.receiver) {
+            let method = node.method.to_string();
+            if let Some(receiver) = receiver.path.get_ident() {
+                if known_self_borrow(&method).is_none() {
+                    let warning = format!(
+                        "{}:{}: in {}: #[trace_borrow] does not know `{}`; `{}.{}()` is recorded as an immutable borrow",
+                        self.file.display(),
+                        node.method.span().start().line,
+                        function,
+                        method,
+                        receiver,
+                        method
+                    );
+                    self.warnings.push((function.clone(), warning));
+                }
+            }
+        }
+        syn::visit::visit_expr_method_call(self, node);
+    }
+}
+
+/// Line-based unified diff of `old` and `new`
+fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old, &new);
+    if ops.iter().all(|op| matches!(op, DiffOp::Equal(..))) {
+        return String::new();
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal(..)))
+        .map(|(index, _)| index)
+        .collect();
+
+    // Group changes whose context would overlap into one hunk
+    let mut hunks: Vec<Range<usize>> = Vec::new();
+    for &change in &changes {
+        let start = change.saturating_sub(DIFF_CONTEXT);
+        let end = (change + DIFF_CONTEXT + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.end => hunk.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+
+    for hunk in hunks {
+        let ops = &ops[hunk];
+        let (old_start, new_start) = match ops[0] {
+            DiffOp::Equal(old, new) => (old, new),
+            DiffOp::Delete(old, new) | DiffOp::Insert(old, new) => (old, new),
+        };
+        let old_len = ops
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Insert(..)))
+            .count();
+        let new_len = ops
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Delete(..)))
+            .count();
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        ));
+
+        for op in ops {
+            match *op {
+                DiffOp::Equal(old_index, _) => output.push_str(&format!(" {}\n", old[old_index])),
+                DiffOp::Delete(old_index, _) => output.push_str(&format!("-{}\n", old[old_index])),
+                DiffOp::Insert(_, new_index) => output.push_str(&format!("+{}\n", new[new_index])),
+            }
+        }
+    }
+
+    output
+}
+
+/// `start,len` of a hunk side; an empty side names the line before it
+fn hunk_range(start: usize, len: usize) -> String {
+    if len == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, len)
+    }
+}
+
+/// One step of an edit script, with the positions in both inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOp {
+    Equal(usize, usize),
+    Delete(usize, usize),
+    Insert(usize, usize),
+}
+
+/// Edit script turning `old` into `new`, from their longest common subsequence
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let matched = common_subsequence(old.len(), new.len(), |i, j| old[i] == new[j]);
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (mi, mj) in matched
+        .into_iter()
+        .chain(std::iter::once((old.len(), new.len())))
+    {
+        ops.extend((i..mi).map(|i| DiffOp::Delete(i, j)));
+        ops.extend((j..mj).map(|j| DiffOp::Insert(mi, j)));
+        if mi < old.len() {
+            ops.push(DiffOp::Equal(mi, mj));
+        }
+        (i, j) = (mi + 1, mj + 1);
+    }
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn project() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        fs::write(
+            temp.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(temp.path().join("src")).unwrap();
+        fs::write(
+            temp.path().join("src/main.rs"),
+            "mod parser;\n\nfn main() {\n    let x = 1;\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            temp.path().join("src/parser.rs"),
+            r#"pub struct Parser;
+
+impl Parser {
+    pub fn parse(&self) {
+        let (a, _) = (1, 2);
+        let later;
+        later = 3;
+        println!("{}", &later);
+    }
+}
+
+#[trace_borrow]
+fn traced(v: Vec<u8>) {
+    let n = v.frobnicate();
+    let m = v.len();
+}
+"#,
+        )
+        .unwrap();
+        temp
+    }
+
+    #[test]
+    fn test_expand_function_by_path() {
+        let temp = project();
+        let config = InstrumentationConfig::default();
+
+        let expansions = expand_function("parser::Parser::parse", temp.path(), &config).unwrap();
+        assert_eq!(expansions.len(), 1);
+
+        let expansion = &expansions[0];
+        assert_eq!(
+            expansion.function.as_deref(),
+            Some("crate::parser::Parser::parse")
+        );
+        assert_eq!(expansion.label, "src/parser.rs:4");
+        assert!(expansion.original.starts_with("    pub fn parse(&self) {"));
+        assert!(expansion.instrumented.contains("track_new"));
+        assert!(!expansion.instrumented.contains("fn traced"));
+    }
+
+    #[test]
+    fn test_expand_function_warnings() {
+        let temp = project();
+        let config = InstrumentationConfig::default();
+
+        let expansion = &expand_function("*::parse", temp.path(), &config).unwrap()[0];
+        let warnings = expansion.warnings.join("\n");
+        assert!(warnings
+            .contains("src/parser.rs:5: in crate::parser::Parser::parse: pattern `(a , _)`"));
+        assert!(warnings.contains("`later` is declared without a value"));
+        assert!(warnings.contains("borrows inside `println!` are not tracked"));
+        assert!(!warnings.contains("frobnicate"));
+    }
+
+    #[test]
+    fn test_expand_trace_borrow_warnings() {
+        let temp = project();
+        let config = InstrumentationConfig::default();
+
+        let expansion = &expand_function("parser::traced", temp.path(), &config).unwrap()[0];
+        assert_eq!(expansion.warnings.len(), 1);
+        assert!(expansion.warnings[0].contains("#[trace_borrow] does not know `frobnicate`"));
+    }
+
+    #[test]
+    fn test_expand_trace_borrow_function() {
+        let temp = project();
+        let config = InstrumentationConfig::default();
+
+        let expansion = &expand_function("parser::traced", temp.path(), &config).unwrap()[0];
+        assert_eq!(expansion.traced.len(), 1);
+        let traced = &expansion.traced[0];
+        assert_eq!(traced.function, "crate::parser::traced");
+        assert_eq!(traced.label, "src/parser.rs:12");
+        assert!(traced.original.starts_with("#[trace_borrow]"));
+        assert!(!traced.expanded.contains("#[trace_borrow]"));
+        assert!(traced
+            .expanded
+            .contains("borrowscope_runtime::reserve_ids("));
+        assert!(traced.expanded.contains("__track_new_with_id_helper("));
+        assert!(traced
+            .expanded
+            .contains("track_borrow(\"method_borrow\", &v)"));
+
+        // Functions without the attribute have no macro expansion
+        let plain = &expand_function("parser::Parser::parse", temp.path(), &config).unwrap()[0];
+        assert!(plain.traced.is_empty());
+    }
+
+    #[test]
+    fn test_expand_trace_borrow_unsafe_code() {
+        let temp = project();
+        fs::write(
+            temp.path().join("src/main.rs"),
+            "#[trace_borrow(unsafe_code)]\nfn main() {\n    let x = 1;\n    let p = &x as *const i32;\n    let _y = unsafe { *p };\n}\n",
+        )
+        .unwrap();
+
+        let expansion = expand_file(
+            &temp.path().join("src/main.rs"),
+            &InstrumentationConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(expansion.traced.len(), 1);
+        let expanded = &expansion.traced[0].expanded;
+        assert!(expanded.contains("track_raw_ptr_from("));
+        assert!(expanded.contains("track_unsafe_block("));
+    }
+
+    #[test]
+    fn test_expand_function_not_found() {
+        let temp = project();
+        let result = expand_function("missing", temp.path(), &InstrumentationConfig::default());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("No function matching `missing`"));
+    }
+
+    #[test]
+    fn test_expand_file() {
+        let temp = project();
+        let expansion = expand_file(
+            &temp.path().join("src/main.rs"),
+            &InstrumentationConfig::default(),
+        )
+        .unwrap();
+
+        assert_eq!(expansion.label, "src/main.rs");
+        assert!(expansion.function.is_none());
+        assert!(expansion
+            .instrumented
+            .contains(r#"track_new_in_scope("x", 0usize, 1)"#));
+        assert!(expansion.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nb\nc\nd\nE\nf\ng\nh\ni\nj\nk\n";
+
+        let diff = unified_diff(old, new, "a/x.rs", "b/x.rs");
+        assert_eq!(
+            diff,
+            "--- a/x.rs\n+++ b/x.rs\n@@ -2,9 +2,10 @@\n b\n c\n d\n-e\n+E\n f\n g\n h\n i\n j\n+k\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_separate_hunks() {
+        let old: String = (1..=20).map(|i| format!("{}\n", i)).collect();
+        let new: String = (1..=20)
+            .map(|i| match i {
+                2 => "two\n".to_string(),
+                19 => "nineteen\n".to_string(),
+                i => format!("{}\n", i),
+            })
+            .collect();
+
+        let diff = unified_diff(&old, &new, "a", "b");
+        assert_eq!(diff.matches("@@ -").count(), 2);
+        assert!(diff.contains("@@ -1,5 +1,5 @@\n 1\n-2\n+two\n"));
+        assert!(diff.contains("@@ -16,5 +16,5 @@\n 16\n 17\n 18\n-19\n+nineteen\n 20\n"));
+    }
+
+    #[test]
+    fn test_unified_diff_identical() {
+        assert_eq!(unified_diff("a\n", "a\n", "a", "b"), "");
+    }
+
+    #[test]
+    fn test_diff_lines_insert_into_empty() {
+        assert_eq!(diff_lines(&[], &["x"]), vec![DiffOp::Insert(0, 0)]);
+    }
+}
@@ -2,10 +2,17 @@
 
 use std::fs;
 
-use crate::cli::{ExportArgs, ExportFormat};
+use borrowscope_graph::{GraphExport, GraphStatistics};
+use borrowscope_runtime::{Event, Timeline};
+use serde::Serialize;
+
+use crate::cli::{ExportArgs, ExportFormat, ExportView, Renderer};
 use crate::error::{CliError, Result};
+use crate::gantt::{LifetimeChart, DEFAULT_TEXT_WIDTH};
 use crate::graphviz;
 use crate::progress::spinner;
+use crate::raster;
+use crate::trace::{self, event_location, ConflictReport, Trace};
 
 pub fn execute(args: ExportArgs) -> Result<()> {
     log::info!(
@@ -19,24 +26,30 @@ pub fn execute(args: ExportArgs) -> Result<()> {
         return Err(CliError::FileNotFound(args.file.clone()));
     }
 
-    // Load tracking data
-    let contents = fs::read_to_string(&args.file)?;
-    let data: serde_json::Value = serde_json::from_str(&contents)?;
-
-    // Export based on format
-    match args.format {
-        ExportFormat::Dot => export_dot(&data, &args.output)?,
-        ExportFormat::Svg => export_svg(&data, &args.output)?,
-        ExportFormat::Png => export_png(&data, &args.output)?,
-        ExportFormat::Json => export_json(&data, &args.output)?,
-        ExportFormat::Html => export_html(&data, &args.output)?,
+    // Load tracking data, upgrading older traces to the current layout
+    let data = serde_json::to_value(trace::load_export(&args.file)?)?;
+
+    // Export based on view and format
+    match (args.view, args.format) {
+        (ExportView::Lifetimes, format) => export_lifetimes(&data, &args.output, format)?,
+        (ExportView::Graph, ExportFormat::Dot) => export_dot(&data, &args.output)?,
+        (ExportView::Graph, ExportFormat::Svg) => export_svg(&data, &args.output, args.renderer)?,
+        (ExportView::Graph, ExportFormat::Png) => export_png(&data, &args.output, args.renderer)?,
+        (ExportView::Graph, ExportFormat::Json) => export_json(&data, &args.output)?,
+        (ExportView::Graph, ExportFormat::Html) => export_html(&data, &args.output)?,
+        (ExportView::Graph, ExportFormat::Text) => {
+            return Err(CliError::Other(
+                "Text export is only available with --view lifetimes".to_string(),
+            ))
+        }
     }
 
     log::info!("Exported to: {}", args.output.display());
     Ok(())
 }
 
-fn ensure_parent_dir(path: &std::path::Path) -> Result<()> {
+/// Create the directories `path` will be written into
+pub fn ensure_parent_dir(path: &std::path::Path) -> Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
@@ -50,44 +63,56 @@ fn export_dot(data: &serde_json::Value, output: &std::path::Path) -> Result<()>
     Ok(())
 }
 
-fn export_svg(data: &serde_json::Value, output: &std::path::Path) -> Result<()> {
-    // Check if Graphviz is available
-    if !graphviz::is_available() {
-        return Err(CliError::Other(
-            "Graphviz not found. Please install Graphviz to export SVG.".to_string(),
-        ));
-    }
-
+fn export_svg(
+    data: &serde_json::Value,
+    output: &std::path::Path,
+    renderer: Renderer,
+) -> Result<()> {
     ensure_parent_dir(output)?;
     let sp = spinner("Generating SVG");
-    let dot = generate_dot(data);
 
-    graphviz::render_svg(&dot, output)
-        .map_err(|e| CliError::Other(format!("Failed to generate SVG: {}", e)))?;
+    match renderer {
+        Renderer::Native => fs::write(output, Trace::from_value(data).graph.to_svg())?,
+        Renderer::Graphviz => {
+            require_graphviz()?;
+            graphviz::render_svg(&generate_dot(data), output)
+                .map_err(|e| CliError::Other(format!("Failed to generate SVG: {}", e)))?;
+        }
+    }
 
     sp.finish_with_message("✓ SVG generated");
     Ok(())
 }
 
-fn export_png(data: &serde_json::Value, output: &std::path::Path) -> Result<()> {
-    // Check if Graphviz is available
-    if !graphviz::is_available() {
-        return Err(CliError::Other(
-            "Graphviz not found. Please install Graphviz to export PNG.".to_string(),
-        ));
-    }
-
+fn export_png(
+    data: &serde_json::Value,
+    output: &std::path::Path,
+    renderer: Renderer,
+) -> Result<()> {
     ensure_parent_dir(output)?;
     let sp = spinner("Generating PNG");
-    let dot = generate_dot(data);
 
-    graphviz::render_png(&dot, output)
-        .map_err(|e| CliError::Other(format!("Failed to generate PNG: {}", e)))?;
+    let rendered = match renderer {
+        Renderer::Native => raster::render_png(&Trace::from_value(data).graph.to_svg(), output),
+        Renderer::Graphviz => {
+            require_graphviz()?;
+            graphviz::render_png(&generate_dot(data), output)
+        }
+    };
+    rendered.map_err(|e| CliError::Other(format!("Failed to generate PNG: {}", e)))?;
 
     sp.finish_with_message("✓ PNG generated");
     Ok(())
 }
 
+fn require_graphviz() -> Result<()> {
+    if graphviz::is_available() {
+        Ok(())
+    } else {
+        Err(CliError::CommandNotFound("dot".to_string()))
+    }
+}
+
 fn generate_dot(data: &serde_json::Value) -> String {
     let mut dot = String::from("digraph BorrowScope {\n");
     dot.push_str("  rankdir=LR;\n");
@@ -135,33 +160,94 @@ fn export_json(data: &serde_json::Value, output: &std::path::Path) -> Result<()>
     Ok(())
 }
 
-fn export_html(data: &serde_json::Value, output: &std::path::Path) -> Result<()> {
+fn export_lifetimes(
+    data: &serde_json::Value,
+    output: &std::path::Path,
+    format: ExportFormat,
+) -> Result<()> {
+    let chart = LifetimeChart::from_trace(&Trace::from_value(data));
+
+    let contents = match format {
+        ExportFormat::Text => {
+            let text = chart.to_text(DEFAULT_TEXT_WIDTH);
+            if output.as_os_str() == "-" {
+                print!("{}", text);
+                return Ok(());
+            }
+            text
+        }
+        ExportFormat::Svg => chart.to_svg(),
+        ExportFormat::Html => chart.to_html(),
+        ExportFormat::Json => serde_json::to_string_pretty(&chart)?,
+        ExportFormat::Png => {
+            ensure_parent_dir(output)?;
+            return raster::render_png(&chart.to_svg(), output)
+                .map_err(|e| CliError::Other(format!("Failed to generate PNG: {}", e)));
+        }
+        ExportFormat::Dot => {
+            return Err(CliError::Other(
+                "DOT export is only available with --view graph".to_string(),
+            ))
+        }
+    };
+
     ensure_parent_dir(output)?;
-    let html = format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="UTF-8">
-    <title>BorrowScope Report</title>
-    <style>
-        body {{ font-family: Arial, sans-serif; margin: 20px; }}
-        h1 {{ color: #333; }}
-        pre {{ background: #f4f4f4; padding: 10px; border-radius: 5px; }}
-    </style>
-</head>
-<body>
-    <h1>BorrowScope Analysis Report</h1>
-    <h2>Tracking Data</h2>
-    <pre>{}</pre>
-</body>
-</html>"#,
-        serde_json::to_string_pretty(data)?
-    );
+    fs::write(output, contents)?;
+    Ok(())
+}
+
+/// Everything the HTML report renders, embedded as JSON
+#[derive(Serialize)]
+struct ReportData<'a> {
+    source: Option<&'a str>,
+    statistics: GraphStatistics,
+    graph: GraphExport,
+    trace_ids: Vec<(usize, &'a str)>,
+    conflicts: Vec<ConflictReport>,
+    timeline: Timeline,
+    events: &'a [Event],
+    /// Where each of `events` was recorded, parallel to it
+    locations: Vec<Option<String>>,
+}
 
-    fs::write(output, html)?;
+fn export_html(data: &serde_json::Value, output: &std::path::Path) -> Result<()> {
+    ensure_parent_dir(output)?;
+    fs::write(output, render_html_report(data)?)?;
     Ok(())
 }
 
+/// Render a self-contained, offline HTML report for a trace
+fn render_html_report(data: &serde_json::Value) -> Result<String> {
+    let trace = Trace::from_value(data);
+    let graph = trace.graph.export();
+    let trace_ids = graph
+        .nodes
+        .iter()
+        .filter_map(|v| trace.trace_id(v.id).map(|key| (v.id, key)))
+        .collect();
+
+    let report = ReportData {
+        source: data.get("source").and_then(|s| s.as_str()),
+        statistics: trace.graph.statistics(),
+        graph,
+        trace_ids,
+        conflicts: trace.conflicts(),
+        timeline: trace.timeline(),
+        events: &trace.events,
+        locations: trace
+            .events
+            .iter()
+            .map(|event| event_location(event).map(|l| l.to_string()))
+            .collect(),
+    };
+
+    // `<` only occurs inside JSON strings, so escaping it keeps `</script>`
+    // in variable names or locations from terminating the data block.
+    let json = serde_json::to_string(&report)?.replace('<', "\\u003c");
+
+    Ok(include_str!("../../static/report.html").replace("/*__BORROWSCOPE_DATA__*/", &json))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -174,6 +260,8 @@ mod tests {
             file: PathBuf::from("/nonexistent/file.json"),
             output: PathBuf::from("output.dot"),
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         let result = execute(args);
@@ -186,25 +274,14 @@ mod tests {
         let input_file = temp_dir.path().join("input.json");
         let output_file = temp_dir.path().join("output.dot");
 
-        let data = serde_json::json!({
-            "version": "0.1.0",
-            "graph": {
-                "nodes": [
-                    {"id": 1, "name": "x"},
-                    {"id": 2, "name": "y"}
-                ],
-                "edges": [
-                    {"from": 1, "to": 2, "relationship": "borrows"}
-                ]
-            }
-        });
-
-        fs::write(&input_file, serde_json::to_string(&data).unwrap()).unwrap();
+        fs::write(&input_file, variables_trace(&["x", "y"]).to_string()).unwrap();
 
         let args = ExportArgs {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         let result = execute(args);
@@ -230,6 +307,8 @@ mod tests {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Json,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         let result = execute(args);
@@ -250,6 +329,8 @@ mod tests {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Html,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         let result = execute(args);
@@ -281,6 +362,8 @@ mod tests {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         let result = execute(args);
@@ -294,28 +377,16 @@ mod tests {
         let input_file = temp_dir.path().join("input.json");
         let output_file = temp_dir.path().join("output.dot");
 
-        let nodes: Vec<_> = (0..10)
-            .map(|i| serde_json::json!({"id": i, "name": format!("node{}", i)}))
-            .collect();
-
-        let edges: Vec<_> = (0..9)
-            .map(|i| serde_json::json!({"from": i, "to": i + 1, "relationship": "borrows"}))
-            .collect();
-
-        let data = serde_json::json!({
-            "version": "0.1.0",
-            "graph": {
-                "nodes": nodes,
-                "edges": edges
-            }
-        });
-
-        fs::write(&input_file, serde_json::to_string(&data).unwrap()).unwrap();
+        let names: Vec<_> = (0..10).map(|i| format!("node{}", i)).collect();
+        let names: Vec<_> = names.iter().map(String::as_str).collect();
+        fs::write(&input_file, variables_trace(&names).to_string()).unwrap();
 
         let args = ExportArgs {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         let result = execute(args);
@@ -338,6 +409,8 @@ mod tests {
             file: input_file,
             output: output_file,
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         let result = execute(args);
@@ -358,6 +431,8 @@ mod tests {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         execute(args).unwrap();
@@ -377,13 +452,15 @@ mod tests {
 
         let args = ExportArgs {
             file: input_file,
-            output: output_file,
+            output: output_file.clone(),
             format: ExportFormat::Svg,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
-        let result = execute(args);
-        // Will fail if graphviz not installed, or succeed if it is
-        assert!(result.is_ok() || result.is_err());
+        execute(args).unwrap();
+        let contents = fs::read_to_string(&output_file).unwrap();
+        assert!(contents.starts_with("<svg"));
     }
 
     #[test]
@@ -397,13 +474,105 @@ mod tests {
 
         let args = ExportArgs {
             file: input_file,
-            output: output_file,
+            output: output_file.clone(),
             format: ExportFormat::Png,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
+        };
+
+        execute(args).unwrap();
+        let bytes = fs::read(&output_file).unwrap();
+        assert!(bytes.starts_with(b"\x89PNG"));
+    }
+
+    /// Trace that declares one `i32` variable per name
+    fn variables_trace(names: &[&str]) -> serde_json::Value {
+        let events: Vec<_> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                serde_json::json!({"type": "New", "timestamp": i + 1, "var_name": name, "var_id": format!("{}_{}", name, i), "type_name": "i32"})
+            })
+            .collect();
+        serde_json::json!({ "events": events })
+    }
+
+    fn write_sample_trace(path: &std::path::Path) {
+        let data = serde_json::json!({
+            "events": [
+                {"type": "New", "timestamp": 1, "var_name": "v", "var_id": "v_0", "type_name": "Vec<i32>"},
+                {"type": "Borrow", "timestamp": 2, "borrower_name": "r", "borrower_id": "r_1", "owner_id": "v_0", "mutable": false},
+                {"type": "Borrow", "timestamp": 3, "borrower_name": "m", "borrower_id": "m_2", "owner_id": "v_0", "mutable": true}
+            ]
+        });
+        fs::write(path, serde_json::to_string(&data).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_export_native_svg_from_trace() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_file = temp_dir.path().join("trace.json");
+        let output_file = temp_dir.path().join("graph.svg");
+        write_sample_trace(&input_file);
+
+        let args = ExportArgs {
+            file: input_file,
+            output: output_file.clone(),
+            format: ExportFormat::Svg,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
+        };
+
+        execute(args).unwrap();
+        let contents = fs::read_to_string(&output_file).unwrap();
+        assert!(contents.contains("Vec&lt;i32&gt;"));
+        assert!(contents.contains("class=\"edge mutable\""));
+        assert!(contents.contains("class=\"edge immutable\""));
+    }
+
+    #[test]
+    fn test_export_native_png_from_trace() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_file = temp_dir.path().join("trace.json");
+        let output_file = temp_dir.path().join("nested/graph.png");
+        write_sample_trace(&input_file);
+
+        let args = ExportArgs {
+            file: input_file,
+            output: output_file.clone(),
+            format: ExportFormat::Png,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
+        };
+
+        execute(args).unwrap();
+        let bytes = fs::read(&output_file).unwrap();
+        assert!(bytes.starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn test_export_graphviz_renderer_requires_dot() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_file = temp_dir.path().join("trace.json");
+        let output_file = temp_dir.path().join("graph.svg");
+        write_sample_trace(&input_file);
+
+        let args = ExportArgs {
+            file: input_file,
+            output: output_file.clone(),
+            format: ExportFormat::Svg,
+            view: ExportView::Graph,
+            renderer: Renderer::Graphviz,
         };
 
         let result = execute(args);
-        // Will fail if graphviz not installed, or succeed if it is
-        assert!(result.is_ok() || result.is_err());
+        if graphviz::is_available() {
+            assert!(result.is_ok());
+            assert!(fs::read_to_string(&output_file).unwrap().contains("<svg"));
+        } else {
+            assert!(matches!(result, Err(CliError::CommandNotFound(_))));
+            assert!(!output_file.exists());
+        }
     }
 
     #[test]
@@ -425,6 +594,8 @@ mod tests {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         execute(args).unwrap();
@@ -440,20 +611,24 @@ mod tests {
         let input_file = temp_dir.path().join("input.json");
         let output_file = temp_dir.path().join("output.json");
 
-        let data = serde_json::json!({"key": "value", "nested": {"a": 1}});
-        fs::write(&input_file, serde_json::to_string(&data).unwrap()).unwrap();
+        write_sample_trace(&input_file);
 
         let args = ExportArgs {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Json,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         execute(args).unwrap();
 
+        // Exported JSON is always in the current trace format
         let contents = fs::read_to_string(&output_file).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&contents).unwrap();
-        assert_eq!(parsed["key"], "value");
+        assert!(trace::is_current(&parsed));
+        assert_eq!(parsed["events"].as_array().unwrap().len(), 3);
+        trace::validate_schema(&parsed).unwrap();
     }
 
     #[test]
@@ -469,6 +644,8 @@ mod tests {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Html,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         execute(args).unwrap();
@@ -494,6 +671,8 @@ mod tests {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         let result = execute(args);
@@ -507,19 +686,14 @@ mod tests {
         let input_file = temp_dir.path().join("input.json");
         let output_file = temp_dir.path().join("output.dot");
 
-        let data = serde_json::json!({
-            "graph": {
-                "nodes": [{"id": 1, "name": "变量"}],
-                "edges": []
-            }
-        });
-
-        fs::write(&input_file, serde_json::to_string(&data).unwrap()).unwrap();
+        fs::write(&input_file, variables_trace(&["变量"]).to_string()).unwrap();
 
         let args = ExportArgs {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         execute(args).unwrap();
@@ -547,6 +721,8 @@ mod tests {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         execute(args).unwrap();
@@ -580,6 +756,8 @@ mod tests {
             file: input_file,
             output: output_file.clone(),
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         };
 
         let start = std::time::Instant::now();
@@ -610,6 +788,8 @@ mod tests {
             file: input_file.clone(),
             output: dot_output.clone(),
             format: ExportFormat::Dot,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         })
         .unwrap();
         assert!(dot_output.exists());
@@ -620,6 +800,8 @@ mod tests {
             file: input_file.clone(),
             output: json_output.clone(),
             format: ExportFormat::Json,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         })
         .unwrap();
         assert!(json_output.exists());
@@ -630,8 +812,142 @@ mod tests {
             file: input_file.clone(),
             output: html_output.clone(),
             format: ExportFormat::Html,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
         })
         .unwrap();
         assert!(html_output.exists());
     }
+
+    fn report_trace() -> serde_json::Value {
+        serde_json::json!({
+            "version": "0.1.0",
+            "source": "src/main.rs",
+            "events": [
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x_0", "type_name": "Vec<i32> @ src/main.rs:10:9"},
+                {"type": "Borrow", "timestamp": 2, "borrower_name": "r", "borrower_id": "r_1", "owner_id": "x_0", "mutable": false},
+                {"type": "Borrow", "timestamp": 3, "borrower_name": "m", "borrower_id": "m_2", "owner_id": "x_0", "mutable": true},
+                {"type": "RawPtrDeref", "timestamp": 4, "ptr_id": "p_3", "location": "src/main.rs:12:5", "is_write": false},
+                {"type": "Drop", "timestamp": 5, "var_id": "m_2"},
+                {"type": "Drop", "timestamp": 6, "var_id": "r_1"}
+            ]
+        })
+    }
+
+    fn embedded_data(html: &str) -> serde_json::Value {
+        let start = html.find(r#"id="borrowscope-data">"#).unwrap() + 22;
+        let end = start + html[start..].find("</script>").unwrap();
+        serde_json::from_str(&html[start..end]).unwrap()
+    }
+
+    #[test]
+    fn test_html_report_embeds_analysis() {
+        let html = render_html_report(&report_trace()).unwrap();
+        let data = embedded_data(&html);
+
+        assert_eq!(data["source"], "src/main.rs");
+        assert_eq!(data["statistics"]["total_variables"], 3);
+        assert_eq!(data["statistics"]["mutable_borrows"], 1);
+        assert_eq!(data["graph"]["nodes"].as_array().unwrap().len(), 3);
+        assert_eq!(data["conflicts"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            data["conflicts"][0]["time_range"],
+            serde_json::json!([3, 5])
+        );
+        assert_eq!(data["timeline"]["relations"].as_array().unwrap().len(), 2);
+        assert_eq!(data["events"][3]["location"], "src/main.rs:12:5");
+        assert_eq!(data["locations"][0], "src/main.rs:10:9");
+        assert_eq!(data["locations"][1], serde_json::Value::Null);
+        assert_eq!(data["locations"][3], "src/main.rs:12:5");
+    }
+
+    #[test]
+    fn test_html_report_is_self_contained() {
+        let html = render_html_report(&report_trace()).unwrap();
+
+        assert!(!html.contains("/*__BORROWSCOPE_DATA__*/"));
+        assert!(!html.contains("<script src"));
+        assert!(!html.contains("<link"));
+    }
+
+    #[test]
+    fn test_html_report_escapes_script_close() {
+        let data = serde_json::json!({
+            "events": [
+                {"type": "New", "timestamp": 1, "var_name": "</script><b>", "var_id": "x_0", "type_name": "i32"}
+            ]
+        });
+
+        let html = render_html_report(&data).unwrap();
+        assert!(!html.contains("</script><b>"));
+        assert_eq!(
+            embedded_data(&html)["graph"]["nodes"][0]["name"],
+            "</script><b>"
+        );
+    }
+
+    #[test]
+    fn test_export_lifetimes_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_file = temp_dir.path().join("trace.json");
+        let output_file = temp_dir.path().join("lifetimes.txt");
+        write_sample_trace(&input_file);
+
+        let args = ExportArgs {
+            file: input_file,
+            output: output_file.clone(),
+            format: ExportFormat::Text,
+            view: ExportView::Lifetimes,
+            renderer: Renderer::Native,
+        };
+
+        execute(args).unwrap();
+        let contents = fs::read_to_string(&output_file).unwrap();
+        assert!(contents.starts_with("time"));
+        assert!(contents.contains("  &mut m"));
+        assert!(contents.contains("!! conflicts with"));
+    }
+
+    #[test]
+    fn test_export_lifetimes_svg_and_html() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_file = temp_dir.path().join("trace.json");
+        write_sample_trace(&input_file);
+
+        for (format, name, marker) in [
+            (ExportFormat::Svg, "lifetimes.svg", "<svg"),
+            (ExportFormat::Html, "lifetimes.html", "<!DOCTYPE html>"),
+        ] {
+            let output_file = temp_dir.path().join(name);
+            let args = ExportArgs {
+                file: input_file.clone(),
+                output: output_file.clone(),
+                format,
+                view: ExportView::Lifetimes,
+                renderer: Renderer::Native,
+            };
+
+            execute(args).unwrap();
+            let contents = fs::read_to_string(&output_file).unwrap();
+            assert!(contents.starts_with(marker));
+            assert!(contents.contains("row mutable conflict"));
+        }
+    }
+
+    #[test]
+    fn test_export_text_requires_lifetimes_view() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_file = temp_dir.path().join("trace.json");
+        write_sample_trace(&input_file);
+
+        let args = ExportArgs {
+            file: input_file,
+            output: temp_dir.path().join("graph.txt"),
+            format: ExportFormat::Text,
+            view: ExportView::Graph,
+            renderer: Renderer::Native,
+        };
+
+        assert!(execute(args).is_err());
+    }
 }
@@ -123,11 +123,15 @@ fn create_minimal_config() -> Config {
             smart_pointers: false,
             async_code: false,
             unsafe_code: false,
+            only: vec![],
+            exclude: vec![],
+            preserve_formatting: false,
         },
         ignore: crate::config::IgnoreConfig {
             patterns: vec![],
             directories: vec!["target".to_string()],
         },
+        lints: crate::config::LintsConfig::default(),
     }
 }
 
@@ -150,6 +154,9 @@ fn create_advanced_config() -> Config {
             smart_pointers: true,
             async_code: true,
             unsafe_code: true,
+            only: vec![],
+            exclude: vec![],
+            preserve_formatting: false,
         },
         ignore: crate::config::IgnoreConfig {
             patterns: vec!["*.test.rs".to_string(), "*_test.rs".to_string()],
@@ -159,6 +166,27 @@ fn create_advanced_config() -> Config {
                 "benches".to_string(),
             ],
         },
+        lints: crate::config::LintsConfig {
+            borrow_depth: Some(crate::config::LimitLint {
+                max: 3,
+                level: crate::config::LintLevel::Warn,
+            }),
+            mutable_borrow_events: Some(crate::config::LimitLint {
+                max: 100,
+                level: crate::config::LintLevel::Warn,
+            }),
+            rc_clones: Some(crate::config::LimitLint {
+                max: 8,
+                level: crate::config::LintLevel::Warn,
+            }),
+            clone_without_borrow: Some(crate::config::Lint {
+                level: crate::config::LintLevel::Warn,
+            }),
+            refcell_borrows: Some(crate::config::LimitLint {
+                max: 1,
+                level: crate::config::LintLevel::Deny,
+            }),
+        },
     }
 }
 
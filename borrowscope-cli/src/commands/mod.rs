@@ -2,8 +2,12 @@
 
 pub mod check;
 pub mod completion;
+pub mod diff;
+pub mod expand;
 pub mod export;
 pub mod init;
+pub mod query;
+pub mod report;
 pub mod run;
 pub mod visualize;
 pub mod watch;
@@ -0,0 +1,244 @@
+//! Query command implementation
+//!
+//! Evaluates an ownership query such as `type:Vec* alive_at:120 borrowed_by>2`
+//! against the graph replayed from a trace. The query language itself lives
+//! in [`borrowscope_graph::Query`]. Only `New` events of `let` bindings in
+//! instrumented functions record scope depths, so `scope_depth` terms are
+//! rejected for traces without any rather than matching every variable at
+//! depth 0. In a trace that records some, the other variables never match a
+//! `scope_depth` term.
+
+use std::path::Path;
+
+use borrowscope_graph::Query;
+use serde::Serialize;
+
+use crate::cli::QueryArgs;
+use crate::error::{CliError, Result};
+use crate::output::OutputFormat;
+use crate::trace::Trace;
+
+/// A variable matched by the query
+#[derive(Debug, Serialize)]
+pub struct QueryMatch {
+    /// Graph node ID, as accepted by `borrows:#ID`
+    pub id: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub var_id: Option<String>,
+    pub name: String,
+    pub type_name: String,
+    pub created_at: u64,
+    pub dropped_at: Option<u64>,
+    pub borrowers: usize,
+}
+
+pub fn execute(args: QueryArgs, format: OutputFormat) -> Result<()> {
+    let source = args.query.join(" ");
+    let query = Query::parse(&source).map_err(|e| CliError::ValidationError(e.to_string()))?;
+    log::info!("Querying {}: {}", args.file.display(), source);
+
+    let trace = Trace::load(&args.file)?;
+    if query.uses_scope_depth() && !trace.records_scopes() {
+        return Err(CliError::ValidationError(format!(
+            "{} does not record scope depths, so `scope_depth` cannot be queried",
+            args.file.display()
+        )));
+    }
+    let matches = evaluate(&trace, &query);
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&matches)?),
+        OutputFormat::None => {}
+        _ => print(&matches, &source, &args.file),
+    }
+    Ok(())
+}
+
+fn evaluate(trace: &Trace, query: &Query) -> Vec<QueryMatch> {
+    // The graph holds depth 0 for variables whose depth is unknown
+    let scoped = |id: usize| !query.uses_scope_depth() || trace.scope_depth(id).is_some();
+    query
+        .evaluate(&trace.graph)
+        .into_iter()
+        .filter(|var| scoped(var.id))
+        .map(|var| QueryMatch {
+            id: var.id,
+            var_id: trace.trace_id(var.id).map(str::to_string),
+            name: var.name.clone(),
+            type_name: var.type_name.clone(),
+            created_at: var.created_at,
+            dropped_at: var.dropped_at,
+            borrowers: trace.graph.borrowers_of(var.id).len(),
+        })
+        .collect()
+}
+
+fn print(matches: &[QueryMatch], query: &str, file: &Path) {
+    println!("🔎 {} in {}", query, file.display());
+    if matches.is_empty() {
+        println!("\n  No variables match");
+        return;
+    }
+
+    let header = ["ID", "NAME", "TYPE", "CREATED", "DROPPED", "BORROWERS"];
+    let rows: Vec<[String; 6]> = matches
+        .iter()
+        .map(|m| {
+            [
+                m.id.to_string(),
+                m.name.clone(),
+                m.type_name.clone(),
+                m.created_at.to_string(),
+                m.dropped_at
+                    .map_or_else(|| "-".to_string(), |d| d.to_string()),
+                m.borrowers.to_string(),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    println!();
+    print_row(header.iter().copied(), &widths);
+    for row in &rows {
+        print_row(row.iter().map(String::as_str), &widths);
+    }
+    println!("\n  {} variable(s) matched", matches.len());
+}
+
+fn print_row<'a>(cells: impl Iterator<Item = &'a str>, widths: &[usize]) {
+    let line: Vec<String> = cells
+        .zip(widths)
+        .map(|(cell, &width)| format!("{:width$}", cell, width = width))
+        .collect();
+    println!("  {}", line.join("  ").trim_end());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borrowscope_runtime::Event;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn events() -> Vec<Event> {
+        vec![
+            Event::New {
+                timestamp: 1,
+                var_name: "data".into(),
+                var_id: "data_1".into(),
+                type_name: "Vec<i32>".into(),
+                scope_depth: None,
+            },
+            Event::Borrow {
+                timestamp: 2,
+                borrower_name: "first".into(),
+                borrower_id: "first_2".into(),
+                owner_id: "data_1".into(),
+                mutable: false,
+            },
+            Event::Borrow {
+                timestamp: 3,
+                borrower_name: "second".into(),
+                borrower_id: "second_3".into(),
+                owner_id: "data_1".into(),
+                mutable: false,
+            },
+            Event::Drop {
+                timestamp: 4,
+                var_id: "second_3".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_evaluate_reports_trace_ids_and_borrowers() {
+        let trace = Trace::from_events(events());
+        let matches = evaluate(&trace, &Query::parse("type:Vec* borrowed_by>=2").unwrap());
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].name, "data");
+        assert_eq!(matches[0].var_id.as_deref(), Some("data_1"));
+        assert_eq!(matches[0].borrowers, 2);
+    }
+
+    #[test]
+    fn test_evaluate_graph_predicates() {
+        let trace = Trace::from_events(events());
+        let names = |query: &str| -> Vec<String> {
+            evaluate(&trace, &Query::parse(query).unwrap())
+                .into_iter()
+                .map(|m| m.name)
+                .collect()
+        };
+
+        assert_eq!(names("borrows:data dropped:false"), ["first"]);
+        assert_eq!(names("overlaps:second"), ["data", "first"]);
+    }
+
+    #[test]
+    fn test_execute_rejects_invalid_query() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("trace.json");
+        fs::write(&file, serde_json::json!({ "events": events() }).to_string()).unwrap();
+
+        let args = |query: &str| QueryArgs {
+            file: file.clone(),
+            query: query.split(' ').map(String::from).collect(),
+        };
+        assert!(execute(args("type:Vec* alive_at:2"), OutputFormat::None).is_ok());
+
+        let error = execute(args("colour:red"), OutputFormat::None).unwrap_err();
+        assert!(matches!(error, CliError::ValidationError(message) if message.contains("colour")));
+
+        // This trace has no scope depths to compare
+        let error = execute(args("type:Vec* scope_depth:1"), OutputFormat::None).unwrap_err();
+        assert!(
+            matches!(error, CliError::ValidationError(message) if message.contains("scope_depth"))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_recorded_scope_depth() {
+        let mut events = events();
+        events.push(Event::New {
+            timestamp: 5,
+            var_name: "inner".into(),
+            var_id: "inner_5".into(),
+            type_name: "Vec<u8>".into(),
+            scope_depth: Some(1),
+        });
+        let trace = Trace::from_events(events);
+        assert!(trace.records_scopes());
+
+        let matches = evaluate(&trace, &Query::parse("type:Vec* scope_depth:1").unwrap());
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].name, "inner");
+    }
+
+    #[test]
+    fn test_evaluate_skips_unknown_scope_depth() {
+        // `outer` records its depth, the variables of `events()` do not
+        let mut events = events();
+        events.push(Event::New {
+            timestamp: 5,
+            var_name: "outer".into(),
+            var_id: "outer_5".into(),
+            type_name: "Vec<u8>".into(),
+            scope_depth: Some(0),
+        });
+        let trace = Trace::from_events(events);
+
+        let matches = evaluate(&trace, &Query::parse("scope_depth:0").unwrap());
+        let names: Vec<&str> = matches.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["outer"]);
+
+        // Without a scope_depth term they still match
+        assert!(evaluate(&trace, &Query::parse("type:Vec*").unwrap()).len() > 1);
+    }
+}
@@ -0,0 +1,608 @@
+//! Report command implementation
+//!
+//! `report unsafe` gives reviewers the unsafe surface a run actually touched:
+//! unsafe blocks with hit counts, raw pointers created and dereferenced,
+//! transmutes, FFI and unsafe function calls, and union fields read. Events
+//! are grouped by the function and module whose source contains them.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use borrowscope_runtime::Event;
+use serde::Serialize;
+
+use crate::cli::{ReportArgs, ReportKind, UnsafeReportArgs};
+use crate::error::Result;
+use crate::locations::{SourceIndex, SourceLocation};
+use crate::output::OutputFormat;
+use crate::trace;
+
+/// Group for events whose location is not in the indexed sources
+const UNKNOWN: &str = "<unknown>";
+
+/// Unsafe operations recorded in a trace
+#[derive(Debug, Default, Serialize)]
+pub struct UnsafeReport {
+    pub modules: Vec<ModuleReport>,
+    pub totals: UnsafeTotals,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct UnsafeTotals {
+    pub unsafe_blocks: usize,
+    pub block_entries: usize,
+    pub raw_pointers_created: usize,
+    pub raw_pointer_reads: usize,
+    pub raw_pointer_writes: usize,
+    pub transmutes: usize,
+    pub ffi_calls: usize,
+    pub unsafe_fn_calls: usize,
+    pub union_field_reads: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModuleReport {
+    pub module: String,
+    pub functions: Vec<FunctionReport>,
+}
+
+/// Everything unsafe recorded inside one function
+#[derive(Debug, Default, Serialize)]
+pub struct FunctionReport {
+    pub function: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
+    pub unsafe_blocks: Vec<BlockHits>,
+    pub raw_pointers: Vec<RawPointerUse>,
+    pub transmutes: Vec<TransmuteUse>,
+    pub ffi_calls: Vec<CallCount>,
+    pub unsafe_fn_calls: Vec<CallCount>,
+    pub union_fields: Vec<UnionFieldRead>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlockHits {
+    pub location: String,
+    pub hits: usize,
+}
+
+/// A raw pointer as seen from one function: created there, dereferenced there, or both
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RawPointerUse {
+    pub name: String,
+    pub ptr_type: String,
+    pub created: usize,
+    pub reads: usize,
+    pub writes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransmuteUse {
+    pub from_type: String,
+    pub to_type: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CallCount {
+    pub function: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnionFieldRead {
+    pub union_name: String,
+    pub field_name: String,
+    pub count: usize,
+}
+
+pub fn execute(args: ReportArgs, format: OutputFormat) -> Result<()> {
+    match args.report {
+        ReportKind::Unsafe(args) => execute_unsafe(args, format),
+    }
+}
+
+fn execute_unsafe(args: UnsafeReportArgs, format: OutputFormat) -> Result<()> {
+    log::info!("Reporting unsafe operations in: {}", args.file.display());
+
+    let export = trace::load_export(&args.file)?;
+    let index = SourceIndex::scan(&args.source_root);
+    let report = UnsafeReport::from_events(&export.events, &index);
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::None => {}
+        _ => report.print(&args.file),
+    }
+    Ok(())
+}
+
+/// Counts for one function while the trace is replayed
+#[derive(Default)]
+struct Tally {
+    blocks: BTreeMap<String, usize>,
+    pointers: BTreeMap<(String, String), (usize, usize, usize)>,
+    transmutes: BTreeMap<(String, String), usize>,
+    ffi_calls: BTreeMap<String, usize>,
+    unsafe_fn_calls: BTreeMap<String, usize>,
+    union_fields: BTreeMap<(String, String), usize>,
+}
+
+impl UnsafeReport {
+    /// Group the unsafe events by the function of `index` they were recorded in
+    pub fn from_events(events: &[Event], index: &SourceIndex) -> Self {
+        let mut tallies: BTreeMap<String, Tally> = BTreeMap::new();
+        // Pointer ID -> (name, type), so dereferences can be named
+        let mut pointers: BTreeMap<&str, (&str, &str)> = BTreeMap::new();
+        let mut totals = UnsafeTotals::default();
+
+        for event in events {
+            match event {
+                Event::UnsafeBlockEnter { location, .. } => {
+                    *tallies
+                        .entry(group(index, location))
+                        .or_default()
+                        .blocks
+                        .entry(location.clone())
+                        .or_default() += 1;
+                    totals.block_entries += 1;
+                }
+                Event::RawPtrCreated {
+                    var_name,
+                    var_id,
+                    ptr_type,
+                    location,
+                    ..
+                } => {
+                    pointers.insert(var_id, (var_name, ptr_type));
+                    let key = (var_name.clone(), ptr_type.clone());
+                    tallies
+                        .entry(group(index, location))
+                        .or_default()
+                        .pointers
+                        .entry(key)
+                        .or_default()
+                        .0 += 1;
+                    totals.raw_pointers_created += 1;
+                }
+                Event::RawPtrDeref {
+                    ptr_id,
+                    location,
+                    is_write,
+                    ..
+                } => {
+                    let (name, ptr_type) = pointers
+                        .get(ptr_id.as_str())
+                        .copied()
+                        .unwrap_or((ptr_id.as_str(), "_"));
+                    let key = (name.to_string(), ptr_type.to_string());
+                    let counts = tallies
+                        .entry(group(index, location))
+                        .or_default()
+                        .pointers
+                        .entry(key)
+                        .or_default();
+                    if *is_write {
+                        counts.2 += 1;
+                        totals.raw_pointer_writes += 1;
+                    } else {
+                        counts.1 += 1;
+                        totals.raw_pointer_reads += 1;
+                    }
+                }
+                Event::Transmute {
+                    from_type,
+                    to_type,
+                    location,
+                    ..
+                } => {
+                    let key = (from_type.clone(), to_type.clone());
+                    *tallies
+                        .entry(group(index, location))
+                        .or_default()
+                        .transmutes
+                        .entry(key)
+                        .or_default() += 1;
+                    totals.transmutes += 1;
+                }
+                Event::FfiCall {
+                    fn_name, location, ..
+                } => {
+                    *tallies
+                        .entry(group(index, location))
+                        .or_default()
+                        .ffi_calls
+                        .entry(fn_name.clone())
+                        .or_default() += 1;
+                    totals.ffi_calls += 1;
+                }
+                Event::UnsafeFnCall {
+                    fn_name, location, ..
+                } => {
+                    *tallies
+                        .entry(group(index, location))
+                        .or_default()
+                        .unsafe_fn_calls
+                        .entry(fn_name.clone())
+                        .or_default() += 1;
+                    totals.unsafe_fn_calls += 1;
+                }
+                Event::UnionFieldAccess {
+                    union_name,
+                    field_name,
+                    location,
+                    ..
+                } => {
+                    let key = (union_name.clone(), field_name.clone());
+                    *tallies
+                        .entry(group(index, location))
+                        .or_default()
+                        .union_fields
+                        .entry(key)
+                        .or_default() += 1;
+                    totals.union_field_reads += 1;
+                }
+                _ => {}
+            }
+        }
+
+        totals.unsafe_blocks = tallies.values().map(|t| t.blocks.len()).sum();
+
+        let mut modules: BTreeMap<String, Vec<FunctionReport>> = BTreeMap::new();
+        for (function, tally) in tallies {
+            // Files and unknown locations are their own group
+            let module = index
+                .module_of(&function)
+                .map_or_else(|| function.clone(), str::to_string);
+            let report = FunctionReport {
+                location: index.function(&function).cloned(),
+                function,
+                unsafe_blocks: tally
+                    .blocks
+                    .into_iter()
+                    .map(|(location, hits)| BlockHits { location, hits })
+                    .collect(),
+                raw_pointers: tally
+                    .pointers
+                    .into_iter()
+                    .map(
+                        |((name, ptr_type), (created, reads, writes))| RawPointerUse {
+                            name,
+                            ptr_type,
+                            created,
+                            reads,
+                            writes,
+                        },
+                    )
+                    .collect(),
+                transmutes: tally
+                    .transmutes
+                    .into_iter()
+                    .map(|((from_type, to_type), count)| TransmuteUse {
+                        from_type,
+                        to_type,
+                        count,
+                    })
+                    .collect(),
+                ffi_calls: counts(tally.ffi_calls),
+                unsafe_fn_calls: counts(tally.unsafe_fn_calls),
+                union_fields: tally
+                    .union_fields
+                    .into_iter()
+                    .map(|((union_name, field_name), count)| UnionFieldRead {
+                        union_name,
+                        field_name,
+                        count,
+                    })
+                    .collect(),
+            };
+            modules.entry(module).or_default().push(report);
+        }
+
+        Self {
+            modules: modules
+                .into_iter()
+                .map(|(module, functions)| ModuleReport { module, functions })
+                .collect(),
+            totals,
+        }
+    }
+
+    fn print(&self, file: &Path) {
+        println!("🔓 Unsafe operations in {}", file.display());
+
+        if self.modules.is_empty() {
+            println!("\n  ✓ No unsafe operations were recorded");
+            return;
+        }
+
+        for module in &self.modules {
+            println!("\n📦 {}", module.module);
+            for function in &module.functions {
+                match &function.location {
+                    Some(location) => println!("  fn {} ({})", function.function, location),
+                    None if function.function == module.module => {
+                        println!("  (outside any function found under the source root)")
+                    }
+                    None => println!("  fn {}", function.function),
+                }
+                function.print();
+            }
+        }
+
+        let totals = &self.totals;
+        println!("\n📊 Totals:");
+        println!(
+            "  - Unsafe blocks: {} (entered {} time(s))",
+            totals.unsafe_blocks, totals.block_entries
+        );
+        println!(
+            "  - Raw pointers: {} created, {} reads, {} writes",
+            totals.raw_pointers_created, totals.raw_pointer_reads, totals.raw_pointer_writes
+        );
+        println!("  - Transmutes: {}", totals.transmutes);
+        println!("  - FFI calls: {}", totals.ffi_calls);
+        println!("  - Unsafe fn calls: {}", totals.unsafe_fn_calls);
+        println!("  - Union field reads: {}", totals.union_field_reads);
+    }
+}
+
+impl FunctionReport {
+    fn print(&self) {
+        if !self.unsafe_blocks.is_empty() {
+            println!("    Unsafe blocks:");
+            for block in &self.unsafe_blocks {
+                println!("      - {} entered {} time(s)", block.location, block.hits);
+            }
+        }
+        if !self.raw_pointers.is_empty() {
+            println!("    Raw pointers:");
+            for pointer in &self.raw_pointers {
+                println!(
+                    "      - '{}' ({}): {} created, {} read(s), {} write(s)",
+                    pointer.name, pointer.ptr_type, pointer.created, pointer.reads, pointer.writes
+                );
+            }
+        }
+        if !self.transmutes.is_empty() {
+            println!("    Transmutes:");
+            for transmute in &self.transmutes {
+                println!(
+                    "      - {} -> {} ({}x)",
+                    transmute.from_type, transmute.to_type, transmute.count
+                );
+            }
+        }
+        if !self.ffi_calls.is_empty() {
+            println!("    FFI calls:");
+            for call in &self.ffi_calls {
+                println!("      - {} ({}x)", call.function, call.count);
+            }
+        }
+        if !self.unsafe_fn_calls.is_empty() {
+            println!("    Unsafe fn calls:");
+            for call in &self.unsafe_fn_calls {
+                println!("      - {} ({}x)", call.function, call.count);
+            }
+        }
+        if !self.union_fields.is_empty() {
+            println!("    Union fields read:");
+            for field in &self.union_fields {
+                println!(
+                    "      - {}.{} ({}x)",
+                    field.union_name, field.field_name, field.count
+                );
+            }
+        }
+    }
+}
+
+/// The function containing `location`, else its file, else [`UNKNOWN`]
+fn group(index: &SourceIndex, location: &str) -> String {
+    SourceLocation::parse(location)
+        .map(|loc| {
+            index
+                .function_at(&loc)
+                .map_or_else(|| loc.uri(), str::to_string)
+        })
+        .unwrap_or_else(|| UNKNOWN.to_string())
+}
+
+fn counts(calls: BTreeMap<String, usize>) -> Vec<CallCount> {
+    calls
+        .into_iter()
+        .map(|(function, count)| CallCount { function, count })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn project() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(
+            dir.path().join("src/main.rs"),
+            "mod ffi;\n\nfn main() {\n    let x = 1;\n    unsafe {\n        ffi::call();\n    }\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("src/ffi.rs"),
+            "pub fn call() {\n    let p = 0;\n    unsafe {\n        let v = *p;\n    }\n}\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    fn events() -> Vec<Event> {
+        let block = |timestamp, location: &str| Event::UnsafeBlockEnter {
+            timestamp,
+            block_id: "0".into(),
+            location: location.into(),
+        };
+        vec![
+            block(1, "src/main.rs:5:5"),
+            Event::FfiCall {
+                timestamp: 2,
+                fn_name: "strlen".into(),
+                location: "src/main.rs:6:9".into(),
+            },
+            Event::RawPtrCreated {
+                timestamp: 3,
+                var_name: "p".into(),
+                var_id: "7".into(),
+                ptr_type: "*const u8".into(),
+                address: 4096,
+                location: "src/ffi.rs:2:9".into(),
+                source_id: None,
+            },
+            block(4, "src/ffi.rs:3:5"),
+            Event::RawPtrDeref {
+                timestamp: 5,
+                ptr_id: "7".into(),
+                location: "src/ffi.rs:4:17".into(),
+                is_write: false,
+            },
+            block(6, "src/ffi.rs:3:5"),
+            Event::RawPtrDeref {
+                timestamp: 7,
+                ptr_id: "7".into(),
+                location: "src/ffi.rs:4:17".into(),
+                is_write: false,
+            },
+            Event::Transmute {
+                timestamp: 8,
+                from_type: "u32".into(),
+                to_type: "f32".into(),
+                location: "src/ffi.rs:4:17".into(),
+            },
+            Event::UnionFieldAccess {
+                timestamp: 9,
+                union_name: "IntOrFloat".into(),
+                field_name: "f".into(),
+                location: "unknown".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_groups_by_function_and_module() {
+        let dir = project();
+        let index = SourceIndex::scan(dir.path());
+        let report = UnsafeReport::from_events(&events(), &index);
+
+        let modules: Vec<_> = report.modules.iter().map(|m| m.module.as_str()).collect();
+        assert_eq!(modules, vec![UNKNOWN, "crate", "crate::ffi"]);
+
+        let main = &report.modules[1].functions[0];
+        assert_eq!(main.function, "crate::main");
+        assert_eq!(
+            main.location.as_ref().unwrap().to_string(),
+            "src/main.rs:3:4"
+        );
+        assert_eq!(main.ffi_calls[0].function, "strlen");
+
+        let call = &report.modules[2].functions[0];
+        assert_eq!(
+            call.unsafe_blocks,
+            vec![BlockHits {
+                location: "src/ffi.rs:3:5".into(),
+                hits: 2
+            }]
+        );
+        assert_eq!(
+            call.raw_pointers,
+            vec![RawPointerUse {
+                name: "p".into(),
+                ptr_type: "*const u8".into(),
+                created: 1,
+                reads: 2,
+                writes: 0,
+            }]
+        );
+        assert_eq!(call.transmutes[0].to_type, "f32");
+
+        let unknown = &report.modules[0].functions[0];
+        assert_eq!(unknown.union_fields[0].field_name, "f");
+    }
+
+    #[test]
+    fn test_methods_grouped_by_module() {
+        let dir = project();
+        fs::write(
+            dir.path().join("src/ffi.rs"),
+            "pub struct Buf;\n\nimpl Buf {\n    pub fn read(&self) {\n        unsafe {}\n    }\n}\n",
+        )
+        .unwrap();
+        let index = SourceIndex::scan(dir.path());
+        let events = [Event::UnsafeBlockEnter {
+            timestamp: 1,
+            block_id: "0".into(),
+            location: "src/ffi.rs:5:9".into(),
+        }];
+
+        let report = UnsafeReport::from_events(&events, &index);
+        assert_eq!(report.modules.len(), 1);
+        assert_eq!(report.modules[0].module, "crate::ffi");
+        assert_eq!(
+            report.modules[0].functions[0].function,
+            "crate::ffi::Buf::read"
+        );
+    }
+
+    #[test]
+    fn test_totals() {
+        let report = UnsafeReport::from_events(&events(), &SourceIndex::default());
+        assert_eq!(
+            report.totals,
+            UnsafeTotals {
+                unsafe_blocks: 2,
+                block_entries: 3,
+                raw_pointers_created: 1,
+                raw_pointer_reads: 2,
+                raw_pointer_writes: 0,
+                transmutes: 1,
+                ffi_calls: 1,
+                unsafe_fn_calls: 0,
+                union_field_reads: 1,
+            }
+        );
+
+        // Without sources, events are grouped by file
+        let files: Vec<_> = report.modules.iter().map(|m| m.module.as_str()).collect();
+        assert_eq!(files, vec![UNKNOWN, "src/ffi.rs", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_execute_unsafe_report() {
+        let dir = project();
+        let file = dir.path().join("trace.json");
+        let events = events();
+        let graph = borrowscope_runtime::build_graph(&events);
+        borrowscope_runtime::ExportData::new(graph, events)
+            .to_file(&file)
+            .unwrap();
+
+        for format in [OutputFormat::Text, OutputFormat::Json] {
+            let args = ReportArgs {
+                report: ReportKind::Unsafe(UnsafeReportArgs {
+                    file: file.clone(),
+                    source_root: dir.path().to_path_buf(),
+                }),
+            };
+            assert!(execute(args, format).is_ok());
+        }
+
+        let args = ReportArgs {
+            report: ReportKind::Unsafe(UnsafeReportArgs {
+                file: dir.path().join("missing.json"),
+                source_root: dir.path().to_path_buf(),
+            }),
+        };
+        assert!(execute(args, OutputFormat::Text).is_err());
+    }
+}
@@ -1,24 +1,32 @@
 //! Run command implementation
 
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::cargo::{CargoBuilder, CargoRunner};
+use borrowscope_runtime::{ExportData, OwnershipGraph};
+
+use crate::cache::InstrumentationCache;
+use crate::cargo::{
+    parse_test_results, BuildEvent, CargoBuilder, CargoMetadata, CargoRunner, CargoTester,
+    Diagnostic, Package, TestStatus,
+};
 use crate::cli::RunArgs;
 use crate::config::Config;
 use crate::error::{CliError, Result};
-use crate::instrumentation::Instrumenter;
-use crate::progress::{build_progress, spinner};
-use crate::utils::TempWorkspace;
+use crate::instrumentation::{InstrumentationConfig, Instrumenter};
+use crate::manifest::ManifestRewriter;
+use crate::progress::{build_progress, file_progress, spinner};
+use crate::source_map::{render_diagnostic, render_errors, Origin, SourceMap};
 
 pub fn execute(args: RunArgs, config: Config) -> Result<()> {
     log::info!("Running BorrowScope on: {}", args.path.display());
 
-    // Determine output file
-    let output_file = args
-        .output
-        .clone()
-        .unwrap_or_else(|| PathBuf::from(&config.run.output));
+    // Determine output file, or the directory of per-test traces
+    let output_file = match args.output.clone() {
+        Some(output) => output,
+        None if is_test_run(&args) && args.path.is_dir() => test_trace_dir(&config.run.output),
+        None => PathBuf::from(&config.run.output),
+    };
 
     // Check if path exists
     if !args.path.exists() {
@@ -29,7 +37,7 @@ pub fn execute(args: RunArgs, config: Config) -> Result<()> {
     if args.path.is_file() {
         run_single_file(&args, &output_file)?;
     } else if args.path.is_dir() {
-        run_project(&args, &output_file)?;
+        run_project(&args, &config, &output_file)?;
     } else {
         return Err(CliError::Other(format!(
             "Invalid path: {}",
@@ -43,7 +51,7 @@ pub fn execute(args: RunArgs, config: Config) -> Result<()> {
     if args.visualize || config.run.visualize {
         log::info!("Opening visualization...");
         let visualize_args = crate::cli::VisualizeArgs {
-            file: output_file,
+            files: vec![output_file],
             port: None,
             no_browser: false,
             host: None,
@@ -54,30 +62,23 @@ pub fn execute(args: RunArgs, config: Config) -> Result<()> {
     Ok(())
 }
 
-fn run_single_file(args: &RunArgs, output_file: &PathBuf) -> Result<()> {
+fn run_single_file(args: &RunArgs, output_file: &Path) -> Result<()> {
     log::debug!("Running single file: {}", args.path.display());
 
     // For now, create a placeholder output
     // TODO: Implement actual instrumentation and execution
-    let placeholder_data = serde_json::json!({
-        "version": "0.1.0",
-        "source": args.path.display().to_string(),
-        "events": [],
-        "graph": {
-            "nodes": [],
-            "edges": []
-        }
-    });
-
-    fs::write(
-        output_file,
-        serde_json::to_string_pretty(&placeholder_data)?,
-    )?;
+    write_placeholder_trace(output_file, &args.path)
+}
 
+/// Write an empty trace recording only where it came from
+fn write_placeholder_trace(output_file: &Path, source: &Path) -> Result<()> {
+    let mut placeholder = ExportData::new(OwnershipGraph::new(), Vec::new());
+    placeholder.source = Some(source.display().to_string());
+    fs::write(output_file, serde_json::to_string_pretty(&placeholder)?)?;
     Ok(())
 }
 
-fn run_project(args: &RunArgs, output_file: &PathBuf) -> Result<()> {
+fn run_project(args: &RunArgs, config: &Config, output_file: &Path) -> Result<()> {
     log::debug!("Running project: {}", args.path.display());
 
     // Check if Cargo.toml exists
@@ -89,47 +90,139 @@ fn run_project(args: &RunArgs, output_file: &PathBuf) -> Result<()> {
         )));
     }
 
-    // Step 1: Create temporary workspace
-    let sp = spinner("Creating temporary workspace");
-    let mut workspace = TempWorkspace::new().map_err(|e| CliError::Other(e.to_string()))?;
-    let project_copy = workspace
-        .copy_project(&args.path)
-        .map_err(|e| CliError::Other(e.to_string()))?;
-    sp.finish_with_message("✓ Workspace created");
+    let metadata = crate::cargo::get_metadata(&args.path)
+        .map_err(|e| CliError::Other(format!("Failed to read project metadata: {}", e)))?;
+    let plan = RunPlan::new(args, &metadata)?;
+
+    // Step 1: Prepare one instrumenter per selected crate
+    let mut config_inst = InstrumentationConfig::from_config(config);
+    // Globs given on the command line take precedence over the config file
+    if !args.only.is_empty() {
+        config_inst.only = args.only.clone();
+    }
+    if !args.exclude.is_empty() {
+        config_inst.exclude = args.exclude.clone();
+    }
+    if args.preserve_formatting {
+        config_inst.preserve_formatting = true;
+    }
+    if is_test_run(args) {
+        config_inst.trace_tests = true;
+        config_inst.ignore_directories.retain(|dir| dir != "tests");
+    }
 
-    // Step 2: Instrument the project
+    let target_dir = PathBuf::from(&metadata.target_directory);
+    let cache = InstrumentationCache::new(plan.root.clone(), &target_dir).key(&format!(
+        "{:?}\n{:?}\n{:?}",
+        config_inst, plan.instrument, plan.manifests
+    ));
+    let instrumented_dir = cache.workspace_dir();
+
+    let instrumenters: Vec<(&PathBuf, Instrumenter)> = plan
+        .instrument
+        .iter()
+        .map(|dir| {
+            let output = plan.copy_path(dir, &instrumented_dir);
+            (
+                dir,
+                Instrumenter::new(dir.clone(), output, config_inst.clone()),
+            )
+        })
+        .collect();
+
+    // Step 2: Update the persistent instrumented copy, re-instrumenting only changed files
     let pb = build_progress("Instrumenting project");
-    let instrumented_dir = project_copy.join("instrumented");
-    fs::create_dir_all(&instrumented_dir)?;
-
-    let config_inst = crate::instrumentation::InstrumentationConfig::default();
-    let instrumenter =
-        Instrumenter::new(project_copy.clone(), instrumented_dir.clone(), config_inst);
-    instrumenter
-        .instrument_project()
-        .map_err(|e| CliError::Other(e.to_string()))?;
+    let stats = cache
+        .sync(|relative| {
+            // Files belong to the innermost crate containing them
+            let owner = plan.owner(&plan.root.join(relative))?;
+            instrumenters
+                .iter()
+                .find(|(dir, _)| *dir == owner)
+                .map(|(_, instrumenter)| instrumenter)
+        })
+        .map_err(|e| CliError::InstrumentationError(e.to_string()))?;
+    log::debug!(
+        "Instrumented {} files, copied {}, reused {}, removed {}",
+        stats.instrumented,
+        stats.copied,
+        stats.unchanged,
+        stats.removed
+    );
+
+    for dir in &plan.manifests {
+        ManifestRewriter::new(dir.clone())
+            .root(plan.root.clone())
+            .inject_runtime(plan.instrument.contains(dir))
+            .write(&plan.copy_path(dir, &instrumented_dir))
+            .map_err(|e| CliError::InstrumentationError(e.to_string()))?;
+    }
     pb.finish_with_message("✓ Instrumentation complete");
 
     // Step 3: Build the instrumented project
-    let pb = build_progress("Building project");
-    let mut builder = CargoBuilder::new(instrumented_dir.clone()).release(args.release);
+    let mut builder = CargoBuilder::new(instrumented_dir.clone())
+        .release(args.release)
+        .workspace(args.workspace)
+        .tests(is_test_run(args));
+
+    if let Some(ref package) = plan.run_package {
+        builder = builder.packages(vec![package.clone()]);
+    }
 
     if !args.features.is_empty() {
         builder = builder.features(args.features.clone());
     }
 
+    // The lock file only approximates the crate count; the bar grows if it runs over
+    let pb = file_progress(builder.estimate_crates().unwrap_or(0), "Building");
+    // Point diagnostics at the user's files rather than the instrumented copy
+    let source_map = cache.source_map();
+    let mut reported_errors = 0;
     let build_result = builder
-        .build()
+        .build_with(|event| match event {
+            BuildEvent::Artifact(artifact) => {
+                if pb.position() >= pb.length().unwrap_or(0) {
+                    pb.inc_length(1);
+                }
+                pb.set_message(format!("Building {}", artifact.target));
+                pb.inc(1);
+            }
+            BuildEvent::Diagnostic(diagnostic) => {
+                if let Some(report) = live_diagnostic(diagnostic, &source_map, &plan.root) {
+                    reported_errors += usize::from(diagnostic.level == "error");
+                    pb.suspend(|| eprintln!("{}\n", report));
+                }
+            }
+        })
         .map_err(|e| CliError::Other(e.to_string()))?;
     if !build_result.success {
-        return Err(CliError::ExecutionFailed(build_result.errors.join("\n")));
+        pb.finish_and_clear();
+        if reported_errors > 0 {
+            return Err(CliError::ExecutionFailed(format!(
+                "could not build the instrumented project due to {} previous error{}",
+                reported_errors,
+                if reported_errors == 1 { "" } else { "s" }
+            )));
+        }
+        let report = render_errors(&build_result.diagnostics, &source_map, &plan.root);
+        if report.is_empty() {
+            return Err(CliError::ExecutionFailed(build_result.errors.join("\n")));
+        }
+        return Err(CliError::ExecutionFailed(report));
     }
+    pb.set_length(pb.position());
     pb.finish_with_message("✓ Build complete");
 
+    let passthrough = args.no_capture || !config.run.capture;
+    if is_test_run(args) {
+        return run_tests(args, &plan, &instrumented_dir, output_file, passthrough);
+    }
+
     // Step 4: Run the instrumented binary
     let pb = build_progress("Running instrumented binary");
     let mut runner = CargoRunner::new(instrumented_dir.clone())
         .release(args.release)
+        .passthrough(passthrough)
         .env(
             "BORROWSCOPE_OUTPUT".to_string(),
             output_file.display().to_string(),
@@ -139,6 +232,10 @@ fn run_project(args: &RunArgs, output_file: &PathBuf) -> Result<()> {
         runner = runner.args(args.args.clone());
     }
 
+    if let Some(package) = plan.run_package {
+        runner = runner.package(package);
+    }
+
     // Handle different targets
     if let Some(crate::cli::RunTarget::Example) = args.target {
         if let Some(ref example) = args.example {
@@ -150,6 +247,10 @@ fn run_project(args: &RunArgs, output_file: &PathBuf) -> Result<()> {
         }
     }
 
+    // A spinner redrawing over the program's own output would garble it
+    if passthrough {
+        pb.finish_and_clear();
+    }
     let run_output = runner.run().map_err(|e| CliError::Other(e.to_string()))?;
 
     if !run_output.status.success() {
@@ -157,8 +258,8 @@ fn run_project(args: &RunArgs, output_file: &PathBuf) -> Result<()> {
         return Err(CliError::ExecutionFailed(stderr.to_string()));
     }
 
-    // Capture output if requested
-    if !args.no_capture {
+    // Captured output is only shown once the program exits
+    if !passthrough {
         let stdout = String::from_utf8_lossy(&run_output.stdout);
         log::info!("Program output:\n{}", stdout);
     }
@@ -170,38 +271,275 @@ fn run_project(args: &RunArgs, output_file: &PathBuf) -> Result<()> {
     if output_file.exists() {
         sp.finish_with_message("✓ Tracking data collected");
     } else {
-        // Fallback to placeholder if runtime didn't generate data
-        let placeholder_data = serde_json::json!({
-            "version": "0.1.0",
-            "source": args.path.display().to_string(),
-            "events": [],
-            "graph": {
-                "nodes": [],
-                "edges": []
-            },
-            "note": "Runtime tracking data not available"
-        });
-
-        fs::write(
-            output_file,
-            serde_json::to_string_pretty(&placeholder_data)?,
-        )?;
+        // Fallback to an empty trace if runtime didn't generate data
+        write_placeholder_trace(output_file, &args.path)?;
         sp.finish_with_message("⚠ Using placeholder data");
     }
 
     Ok(())
 }
 
+/// A build diagnostic worth showing while the build is still running
+///
+/// Warnings about inserted tracking code, warnings under `--quiet` and rustc's
+/// closing summaries ("aborting due to…", "N warnings emitted") are left out.
+fn live_diagnostic(diagnostic: &Diagnostic, map: &SourceMap, source_root: &Path) -> Option<String> {
+    let summary = diagnostic.spans.is_empty()
+        && (diagnostic.level == "warning" || diagnostic.message.starts_with("aborting due to"));
+    if summary || !matches!(diagnostic.level.as_str(), "error" | "warning") {
+        return None;
+    }
+    let (report, origin) = render_diagnostic(diagnostic, map, source_root);
+    if diagnostic.level == "warning"
+        && (origin == Origin::Instrumentation || !log::log_enabled!(log::Level::Warn))
+    {
+        return None;
+    }
+    Some(report)
+}
+
+fn is_test_run(args: &RunArgs) -> bool {
+    matches!(args.target, Some(crate::cli::RunTarget::Test))
+}
+
+/// Default directory for per-test traces: `borrowscope.json` becomes `borrowscope-tests`
+fn test_trace_dir(output: &str) -> PathBuf {
+    let output = Path::new(output);
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "borrowscope".into());
+    output.with_file_name(format!("{}-tests", stem))
+}
+
+/// Run the instrumented tests, each writing its own trace into `output_dir`
+fn run_tests(
+    args: &RunArgs,
+    plan: &RunPlan,
+    instrumented_dir: &Path,
+    output_dir: &Path,
+    passthrough: bool,
+) -> Result<()> {
+    // Tests run from their package directory, so the runtime needs an absolute path
+    fs::create_dir_all(output_dir)?;
+    let output_dir = output_dir.canonicalize()?;
+    clear_test_traces(&output_dir)?;
+
+    let pb = build_progress("Running instrumented tests");
+    let mut tester = CargoTester::new(instrumented_dir.to_path_buf())
+        .release(args.release)
+        .passthrough(passthrough)
+        .workspace(args.workspace)
+        .env(
+            borrowscope_runtime::TEST_OUTPUT_ENV.to_string(),
+            output_dir.display().to_string(),
+        );
+
+    if let Some(ref package) = plan.run_package {
+        tester = tester.packages(vec![package.clone()]);
+    }
+
+    if !args.features.is_empty() {
+        tester = tester.features(args.features.clone());
+    }
+
+    if let Some(ref filter) = args.filter {
+        tester = tester.filter(filter.clone());
+    }
+
+    if !args.args.is_empty() {
+        tester = tester.args(args.args.clone());
+    }
+
+    if passthrough {
+        pb.finish_and_clear();
+    }
+    let test_output = tester.run().map_err(|e| CliError::Other(e.to_string()))?;
+    let stdout = String::from_utf8_lossy(&test_output.stdout);
+    let results = parse_test_results(&stdout);
+
+    if results.is_empty() && !test_output.status.success() {
+        let stderr = String::from_utf8_lossy(&test_output.stderr);
+        return Err(CliError::ExecutionFailed(stderr.to_string()));
+    }
+
+    if !passthrough {
+        log::info!("Test output:\n{}", stdout);
+    }
+    pb.finish_with_message("✓ Tests complete");
+
+    let traces: Vec<String> = fs::read_dir(&output_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+
+    for result in results.iter().filter(|r| r.status != TestStatus::Ignored) {
+        let trace = find_test_trace(&traces, &result.name)
+            .map(|file| output_dir.join(file).display().to_string())
+            .unwrap_or_else(|| "no trace recorded".into());
+
+        match result.status {
+            TestStatus::Failed => log::warn!("✗ {} (failed): {}", result.name, trace),
+            _ => log::info!("✓ {}: {}", result.name, trace),
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove the traces of an earlier run, so a test that no longer exists or
+/// no longer records anything is not matched with a stale trace
+fn clear_test_traces(output_dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(output_dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Trace file for a libtest name such as `tests::adds`; the runtime prefixes it with the crate name
+fn find_test_trace<'a>(traces: &'a [String], test_name: &str) -> Option<&'a str> {
+    let file = borrowscope_runtime::trace_file_name(test_name);
+    let suffix = format!(".{}", file);
+    traces
+        .iter()
+        .map(String::as_str)
+        .find(|trace| *trace == file || trace.ends_with(&suffix))
+}
+
+/// Which crates of a workspace to instrument, build and run
+#[derive(Debug)]
+struct RunPlan {
+    /// Workspace root; the whole tree is copied
+    root: PathBuf,
+    /// Directories of the crates to instrument
+    instrument: Vec<PathBuf>,
+    /// Directories of every manifest that needs rewriting
+    manifests: Vec<PathBuf>,
+    /// Package passed to `cargo build/run -p`
+    run_package: Option<String>,
+}
+
+impl RunPlan {
+    fn new(args: &RunArgs, metadata: &CargoMetadata) -> Result<Self> {
+        let root = PathBuf::from(&metadata.workspace_root);
+        let members: Vec<&Package> = metadata.members().collect();
+        let member_names = || {
+            members
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let requested = match args.package {
+            Some(ref name) => Some(metadata.member(name).ok_or_else(|| {
+                CliError::Other(format!(
+                    "Package `{}` is not a workspace member (members: {})",
+                    name,
+                    member_names()
+                ))
+            })?),
+            None => None,
+        };
+
+        let selected: Vec<&Package> = if args.workspace {
+            members.clone()
+        } else if let Some(package) = requested {
+            vec![package]
+        } else if let Some(package) = metadata.member_at(&args.path) {
+            vec![package]
+        } else if let [package] = members.as_slice() {
+            vec![*package]
+        } else {
+            return Err(CliError::Other(format!(
+                "{} is a workspace with several members; pass -p <member> or --workspace (members: {})",
+                args.path.display(),
+                member_names()
+            )));
+        };
+
+        let run_package = match requested {
+            Some(package) => Some(package.name.clone()),
+            None if args.workspace => None,
+            None => selected.first().map(|p| p.name.clone()),
+        };
+
+        let mut instrument: Vec<PathBuf> = selected.iter().map(|p| p.manifest_dir()).collect();
+        if args.with_path_deps {
+            let mut queue: Vec<&Package> = selected.clone();
+            while let Some(package) = queue.pop() {
+                for dep in &package.dependencies {
+                    // Dev and build dependencies never run as part of the program
+                    if dep.kind.is_some() {
+                        continue;
+                    }
+                    let Some(ref path) = dep.path else {
+                        continue;
+                    };
+                    let dir = PathBuf::from(path);
+                    if !dir.starts_with(&root) {
+                        log::warn!(
+                            "Not instrumenting `{}`: it is outside the workspace",
+                            dep.name
+                        );
+                        continue;
+                    }
+                    if instrument.contains(&dir) {
+                        continue;
+                    }
+                    if let Some(member) = members.iter().find(|m| m.manifest_dir() == dir) {
+                        queue.push(member);
+                    }
+                    instrument.push(dir);
+                }
+            }
+        }
+
+        let mut manifests: Vec<PathBuf> = vec![root.clone()];
+        for dir in members
+            .iter()
+            .map(|p| p.manifest_dir())
+            .chain(instrument.clone())
+        {
+            if !manifests.contains(&dir) {
+                manifests.push(dir);
+            }
+        }
+
+        Ok(Self {
+            root,
+            instrument,
+            manifests,
+            run_package,
+        })
+    }
+
+    /// Location of an original directory inside the copied workspace
+    fn copy_path(&self, dir: &Path, copy_root: &Path) -> PathBuf {
+        copy_root.join(dir.strip_prefix(&self.root).unwrap_or(Path::new("")))
+    }
+
+    /// Innermost crate directory containing `path`
+    fn owner(&self, path: &Path) -> Option<&PathBuf> {
+        self.manifests
+            .iter()
+            .filter(|dir| path.starts_with(dir))
+            .max_by_key(|dir| dir.components().count())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
     use tempfile::TempDir;
 
-    #[test]
-    fn test_run_nonexistent_path() {
-        let args = RunArgs {
-            path: PathBuf::from("/nonexistent/path"),
+    fn run_args(path: impl Into<PathBuf>) -> RunArgs {
+        RunArgs {
+            path: path.into(),
             output: None,
             visualize: false,
             args: vec![],
@@ -210,7 +548,19 @@ mod tests {
             no_capture: false,
             target: None,
             example: None,
-        };
+            package: None,
+            workspace: false,
+            with_path_deps: false,
+            filter: None,
+            only: vec![],
+            exclude: vec![],
+            preserve_formatting: false,
+        }
+    }
+
+    #[test]
+    fn test_run_nonexistent_path() {
+        let args = run_args("/nonexistent/path");
         let config = Config::default();
 
         let result = execute(args, config);
@@ -226,15 +576,8 @@ mod tests {
 
         let output_file = temp_dir.path().join("output.json");
         let args = RunArgs {
-            path: test_file,
             output: Some(output_file.clone()),
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -258,15 +601,8 @@ mod tests {
 
         let custom_output = temp_dir.path().join("custom.json");
         let args = RunArgs {
-            path: test_file,
             output: Some(custom_output.clone()),
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -281,15 +617,8 @@ mod tests {
         fs::write(&test_file, "fn main() {}").unwrap();
 
         let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
             target: Some(crate::cli::RunTarget::Test),
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -306,15 +635,8 @@ mod tests {
         fs::write(project_dir.join("Cargo.toml"), "[package]\nname = \"test\"").unwrap();
 
         let args = RunArgs {
-            path: project_dir,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
             target: Some(crate::cli::RunTarget::Example),
-            example: None,
+            ..run_args(project_dir)
         };
         let config = Config::default();
 
@@ -329,15 +651,8 @@ mod tests {
         fs::write(&test_file, "fn main() {}").unwrap();
 
         let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
             features: vec!["feature1".to_string(), "feature2".to_string()],
-            no_capture: false,
-            target: None,
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -352,15 +667,8 @@ mod tests {
         fs::write(&test_file, "fn main() {}").unwrap();
 
         let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
             release: true,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -375,15 +683,8 @@ mod tests {
         fs::write(&test_file, "fn main() {}").unwrap();
 
         let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
             args: vec!["--arg1".to_string(), "value1".to_string()],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -397,17 +698,7 @@ mod tests {
         let project_dir = temp_dir.path().join("project");
         fs::create_dir(&project_dir).unwrap();
 
-        let args = RunArgs {
-            path: project_dir,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
-        };
+        let args = run_args(project_dir);
         let config = Config::default();
 
         let result = execute(args, config);
@@ -420,17 +711,7 @@ mod tests {
         let test_file = temp_dir.path().join("test.rs");
         fs::write(&test_file, "fn main() {}").unwrap();
 
-        let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
-        };
+        let args = run_args(test_file);
         let config = Config::default();
 
         execute(args, config).unwrap();
@@ -443,15 +724,8 @@ mod tests {
         fs::write(&test_file, "fn main() { println!(\"test\"); }").unwrap();
 
         let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
             no_capture: true,
-            target: None,
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -466,19 +740,12 @@ mod tests {
         fs::write(&test_file, "fn main() {}").unwrap();
 
         let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
             features: vec![
                 "feat1".to_string(),
                 "feat2".to_string(),
                 "feat3".to_string(),
             ],
-            no_capture: false,
-            target: None,
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -493,15 +760,8 @@ mod tests {
         fs::write(&test_file, "fn main() {}").unwrap();
 
         let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
             args: vec!["arg1".to_string(), "arg2".to_string(), "arg3".to_string()],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -516,15 +776,9 @@ mod tests {
         fs::write(&test_file, "fn main() {}").unwrap();
 
         let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
             release: true,
             features: vec!["feature1".to_string()],
-            no_capture: false,
-            target: None,
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -540,15 +794,13 @@ mod tests {
 
         let output_file = temp_dir.path().join("output.json");
         let args = RunArgs {
-            path: test_file,
             output: Some(output_file.clone()),
-            visualize: false,
             args: vec!["--arg".to_string()],
             release: true,
             features: vec!["feat1".to_string()],
             no_capture: true,
             target: Some(crate::cli::RunTarget::Bin),
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -565,15 +817,8 @@ mod tests {
 
         let output_file = temp_dir.path().join("output.json");
         let args = RunArgs {
-            path: test_file,
             output: Some(output_file.clone()),
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -595,15 +840,8 @@ mod tests {
         fs::write(&test_file, "fn main() {}").unwrap();
 
         let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
             target: Some(crate::cli::RunTarget::Bin),
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -618,15 +856,8 @@ mod tests {
         fs::write(&test_file, "fn main() {}").unwrap();
 
         let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
             target: Some(crate::cli::RunTarget::Bench),
-            example: None,
+            ..run_args(test_file)
         };
         let config = Config::default();
 
@@ -644,29 +875,15 @@ mod tests {
 
         // First run
         let args1 = RunArgs {
-            path: test_file.clone(),
             output: Some(output_file.clone()),
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
+            ..run_args(test_file.clone())
         };
         execute(args1, Config::default()).unwrap();
 
         // Second run should overwrite
         let args2 = RunArgs {
-            path: test_file,
             output: Some(output_file.clone()),
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
+            ..run_args(test_file)
         };
         let result = execute(args2, Config::default());
         assert!(result.is_ok());
@@ -679,17 +896,7 @@ mod tests {
         let test_file = temp_dir.path().join("test file.rs");
         fs::write(&test_file, "fn main() {}").unwrap();
 
-        let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
-        };
+        let args = run_args(test_file);
         let config = Config::default();
 
         let result = execute(args, config);
@@ -702,17 +909,7 @@ mod tests {
         let test_file = temp_dir.path().join("测试.rs");
         fs::write(&test_file, "fn main() {}").unwrap();
 
-        let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
-        };
+        let args = run_args(test_file);
         let config = Config::default();
 
         let result = execute(args, config);
@@ -726,20 +923,188 @@ mod tests {
         let test_file = temp_dir.path().join(long_name);
         fs::write(&test_file, "fn main() {}").unwrap();
 
-        let args = RunArgs {
-            path: test_file,
-            output: None,
-            visualize: false,
-            args: vec![],
-            release: false,
-            features: vec![],
-            no_capture: false,
-            target: None,
-            example: None,
-        };
+        let args = run_args(test_file);
         let config = Config::default();
 
         let result = execute(args, config);
         assert!(result.is_ok());
     }
+
+    fn package(root: &Path, name: &str, deps: &[(&str, Option<&str>)]) -> Package {
+        Package {
+            name: name.into(),
+            version: "0.1.0".into(),
+            id: format!("{} 0.1.0", name),
+            manifest_path: root.join(name).join("Cargo.toml").display().to_string(),
+            dependencies: deps
+                .iter()
+                .map(|(dep, kind)| crate::cargo::Dependency {
+                    name: dep.to_string(),
+                    req: "*".into(),
+                    kind: kind.map(String::from),
+                    optional: false,
+                    path: Some(root.join(dep).display().to_string()),
+                })
+                .collect(),
+            targets: vec![],
+            features: Default::default(),
+        }
+    }
+
+    fn workspace_metadata(root: &Path) -> CargoMetadata {
+        let packages = vec![
+            package(root, "app", &[("core", None), ("testkit", Some("dev"))]),
+            package(root, "core", &[("util", None)]),
+            package(root, "util", &[]),
+            package(root, "testkit", &[]),
+        ];
+        CargoMetadata {
+            workspace_root: root.display().to_string(),
+            workspace_members: packages.iter().map(|p| p.id.clone()).collect(),
+            packages,
+            target_directory: root.join("target").display().to_string(),
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_plan_requires_member_selection() {
+        let root = PathBuf::from("/ws");
+        let metadata = workspace_metadata(&root);
+
+        let result = RunPlan::new(&run_args(root), &metadata);
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("-p <member>"));
+        assert!(message.contains("app, core, util, testkit"));
+    }
+
+    #[test]
+    fn test_plan_selected_package() {
+        let root = PathBuf::from("/ws");
+        let metadata = workspace_metadata(&root);
+        let mut args = run_args(root.clone());
+        args.package = Some("app".into());
+
+        let plan = RunPlan::new(&args, &metadata).unwrap();
+        assert_eq!(plan.run_package.as_deref(), Some("app"));
+        assert_eq!(plan.instrument, vec![root.join("app")]);
+        // Every member manifest is rewritten, plus the workspace root
+        assert_eq!(plan.manifests.len(), 5);
+        assert_eq!(plan.manifests[0], root);
+    }
+
+    #[test]
+    fn test_plan_unknown_package() {
+        let root = PathBuf::from("/ws");
+        let metadata = workspace_metadata(&root);
+        let mut args = run_args(root);
+        args.package = Some("missing".into());
+
+        let result = RunPlan::new(&args, &metadata);
+        assert!(result.unwrap_err().to_string().contains("`missing`"));
+    }
+
+    #[test]
+    fn test_plan_with_path_deps() {
+        let root = PathBuf::from("/ws");
+        let metadata = workspace_metadata(&root);
+        let mut args = run_args(root.clone());
+        args.package = Some("app".into());
+        args.with_path_deps = true;
+
+        let plan = RunPlan::new(&args, &metadata).unwrap();
+        // Transitive normal deps are followed; dev-dependencies are not
+        assert!(plan.instrument.contains(&root.join("core")));
+        assert!(plan.instrument.contains(&root.join("util")));
+        assert!(!plan.instrument.contains(&root.join("testkit")));
+    }
+
+    #[test]
+    fn test_plan_whole_workspace() {
+        let root = PathBuf::from("/ws");
+        let metadata = workspace_metadata(&root);
+        let mut args = run_args(root);
+        args.workspace = true;
+
+        let plan = RunPlan::new(&args, &metadata).unwrap();
+        assert_eq!(plan.instrument.len(), 4);
+        assert!(plan.run_package.is_none());
+    }
+
+    #[test]
+    fn test_plan_member_from_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("core")).unwrap();
+        let metadata = workspace_metadata(&root);
+
+        let plan = RunPlan::new(&run_args(root.join("core")), &metadata).unwrap();
+        assert_eq!(plan.run_package.as_deref(), Some("core"));
+        assert_eq!(plan.instrument, vec![root.join("core")]);
+    }
+
+    #[test]
+    fn test_plan_files_owned_by_innermost_crate() {
+        let root = PathBuf::from("/ws");
+        let mut metadata = workspace_metadata(&root);
+        // A root package whose directory contains every other member
+        let mut root_package = package(&root, "root", &[]);
+        root_package.manifest_path = root.join("Cargo.toml").display().to_string();
+        metadata.workspace_members.push(root_package.id.clone());
+        metadata.packages.push(root_package);
+        let mut args = run_args(root.clone());
+        args.package = Some("root".into());
+
+        let plan = RunPlan::new(&args, &metadata).unwrap();
+        // Only the root package is instrumented, not the members nested inside it
+        assert_eq!(plan.owner(&root.join("src/main.rs")), Some(&root));
+        assert_eq!(
+            plan.owner(&root.join("core/src/lib.rs")),
+            Some(&root.join("core"))
+        );
+        assert!(!plan.instrument.contains(&root.join("core")));
+    }
+
+    #[test]
+    fn test_test_trace_dir() {
+        assert_eq!(
+            test_trace_dir("borrowscope.json"),
+            PathBuf::from("borrowscope-tests")
+        );
+        assert_eq!(
+            test_trace_dir("out/trace.json"),
+            PathBuf::from("out/trace-tests")
+        );
+    }
+
+    #[test]
+    fn test_find_test_trace() {
+        let traces = vec![
+            "app.tests.adds.json".to_string(),
+            "app.tests.adds_more.json".to_string(),
+            "it.integration.json".to_string(),
+        ];
+
+        assert_eq!(
+            find_test_trace(&traces, "tests::adds"),
+            Some("app.tests.adds.json")
+        );
+        assert_eq!(
+            find_test_trace(&traces, "integration"),
+            Some("it.integration.json")
+        );
+        assert_eq!(find_test_trace(&traces, "missing"), None);
+    }
+
+    #[test]
+    fn test_clear_test_traces() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("app.tests.removed.json"), "{}").unwrap();
+        fs::write(dir.join("notes.txt"), "keep").unwrap();
+
+        clear_test_traces(dir).unwrap();
+        assert!(!dir.join("app.tests.removed.json").exists());
+        assert!(dir.join("notes.txt").exists());
+    }
 }
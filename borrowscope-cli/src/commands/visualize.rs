@@ -1,7 +1,7 @@
 //! Visualize command implementation
 
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::cli::VisualizeArgs;
 use crate::config::Config;
@@ -13,17 +13,15 @@ use crate::progress::spinner;
 use crate::server;
 
 pub fn execute(args: VisualizeArgs, config: Config) -> Result<()> {
-    log::info!("Visualizing: {}", args.file.display());
+    let files = collect_trace_files(&args.files)?;
+    for file in &files {
+        log::info!("Visualizing: {}", file.display());
 
-    // Check if file exists
-    if !args.file.exists() {
-        return Err(CliError::FileNotFound(args.file.clone()));
+        // Load and validate tracking data
+        let data = load_tracking_data(file)?;
+        log::debug!("Loaded tracking data with {} events", data.event_count());
     }
 
-    // Load and validate tracking data
-    let data = load_tracking_data(&args.file)?;
-    log::debug!("Loaded tracking data with {} events", data.event_count());
-
     // Determine port and host
     let port = args.port.unwrap_or(config.visualize.port);
     let host = args.host.clone().unwrap_or(config.visualize.host);
@@ -48,13 +46,16 @@ pub fn execute(args: VisualizeArgs, config: Config) -> Result<()> {
             .map_err(|e| CliError::Other(format!("Failed to create runtime: {}", e)))?;
 
         let (addr, mut shutdown_rx) = runtime
-            .block_on(async { server::start_server(host.clone(), port, args.file.clone()).await })
+            .block_on(async { server::start_server(host.clone(), port, files.clone()).await })
             .map_err(|e| CliError::Other(format!("Failed to start server: {}", e)))?;
 
         sp.finish_with_message("✓ Server started");
 
         let url = format!("http://{}", addr);
         println!("\n🌐 Visualization server running at: {}", url);
+        if files.len() > 1 {
+            println!("   Serving {} traces", files.len());
+        }
         println!("   Press Ctrl+C to stop or visit /api/shutdown\n");
 
         // Open browser if requested
@@ -72,6 +73,40 @@ pub fn execute(args: VisualizeArgs, config: Config) -> Result<()> {
     }
 }
 
+/// Expand the given paths into trace files.
+///
+/// Directories contribute their `*.json` files (non-recursively, sorted by
+/// name); plain files are taken as-is.
+fn collect_trace_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        if !path.exists() {
+            return Err(CliError::FileNotFound(path.clone()));
+        }
+
+        if path.is_dir() {
+            let mut entries: Vec<_> = fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "json"))
+                .collect();
+            entries.sort();
+
+            if entries.is_empty() {
+                return Err(CliError::Other(format!(
+                    "No trace files (*.json) found in {}",
+                    path.display()
+                )));
+            }
+            files.extend(entries);
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    Ok(files)
+}
+
 struct TrackingData {
     events: Vec<serde_json::Value>,
 }
@@ -82,7 +117,7 @@ impl TrackingData {
     }
 }
 
-fn load_tracking_data(path: &PathBuf) -> Result<TrackingData> {
+fn load_tracking_data(path: &Path) -> Result<TrackingData> {
     let contents = fs::read_to_string(path)?;
     let json: serde_json::Value = serde_json::from_str(&contents)?;
 
@@ -134,7 +169,7 @@ mod tests {
     #[test]
     fn test_visualize_nonexistent_file() {
         let args = VisualizeArgs {
-            file: PathBuf::from("/nonexistent/file.json"),
+            files: vec![PathBuf::from("/nonexistent/file.json")],
             port: None,
             no_browser: true,
             host: None,
@@ -222,7 +257,7 @@ mod tests {
         fs::write(&test_file, serde_json::to_string(&data).unwrap()).unwrap();
 
         let args = VisualizeArgs {
-            file: test_file,
+            files: vec![test_file],
             port: Some(8080),
             no_browser: true,
             host: None,
@@ -247,7 +282,7 @@ mod tests {
         fs::write(&test_file, serde_json::to_string(&data).unwrap()).unwrap();
 
         let args = VisualizeArgs {
-            file: test_file,
+            files: vec![test_file],
             port: None,
             no_browser: true,
             host: Some("0.0.0.0".to_string()),
@@ -303,7 +338,7 @@ mod tests {
         fs::write(&test_file, serde_json::to_string(&data).unwrap()).unwrap();
 
         let args = VisualizeArgs {
-            file: test_file,
+            files: vec![test_file],
             port: None,
             no_browser: true,
             host: None,
@@ -314,4 +349,79 @@ mod tests {
         let result = execute(args, config);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_collect_trace_files_from_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("b.json"), "{}").unwrap();
+        fs::write(temp_dir.path().join("a.json"), "{}").unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "").unwrap();
+
+        let files = collect_trace_files(&[temp_dir.path().to_path_buf()]).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                temp_dir.path().join("a.json"),
+                temp_dir.path().join("b.json")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_trace_files_mixed() {
+        let temp_dir = TempDir::new().unwrap();
+        let traces = temp_dir.path().join("traces");
+        fs::create_dir(&traces).unwrap();
+        fs::write(traces.join("after.json"), "{}").unwrap();
+        let before = temp_dir.path().join("before.json");
+        fs::write(&before, "{}").unwrap();
+
+        let files = collect_trace_files(&[before.clone(), traces.clone()]).unwrap();
+        assert_eq!(files, vec![before, traces.join("after.json")]);
+    }
+
+    #[test]
+    fn test_collect_trace_files_empty_directory() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let result = collect_trace_files(&[temp_dir.path().to_path_buf()]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_visualize_multiple_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = serde_json::json!({"version": "0.1.0", "events": []});
+        let first = temp_dir.path().join("first.json");
+        let second = temp_dir.path().join("second.json");
+        fs::write(&first, data.to_string()).unwrap();
+        fs::write(&second, data.to_string()).unwrap();
+
+        let args = VisualizeArgs {
+            files: vec![first, second],
+            port: None,
+            no_browser: true,
+            host: None,
+        };
+
+        assert!(execute(args, Config::default()).is_ok());
+    }
+
+    #[test]
+    fn test_visualize_rejects_invalid_trace_in_set() {
+        let temp_dir = TempDir::new().unwrap();
+        let good = temp_dir.path().join("good.json");
+        let bad = temp_dir.path().join("bad.json");
+        fs::write(&good, r#"{"events": []}"#).unwrap();
+        fs::write(&bad, "not json").unwrap();
+
+        let args = VisualizeArgs {
+            files: vec![good, bad],
+            port: None,
+            no_browser: true,
+            host: None,
+        };
+
+        assert!(execute(args, Config::default()).is_err());
+    }
 }
@@ -1,5 +1,6 @@
 //! Watch command implementation
 
+use std::path::Path;
 use std::sync::mpsc::channel;
 use std::time::Duration;
 
@@ -45,10 +46,8 @@ pub fn execute(args: WatchArgs, config: Config) -> Result<()> {
                         }
 
                         // Filter Rust files only
-                        let has_rust_file = event
-                            .paths
-                            .iter()
-                            .any(|p| p.extension().is_some_and(|ext| ext == "rs"));
+                        let has_rust_file =
+                            event.paths.iter().any(|p| is_source_change(p, &args.path));
 
                         if !has_rust_file {
                             continue;
@@ -78,6 +77,18 @@ pub fn execute(args: WatchArgs, config: Config) -> Result<()> {
     }
 }
 
+/// A changed Rust source, ignoring the instrumented copy kept under `target/`
+fn is_source_change(path: &Path, root: &Path) -> bool {
+    let relative = path
+        .strip_prefix(root)
+        .or_else(|_| path.strip_prefix(root.canonicalize().unwrap_or_default()))
+        .unwrap_or(path);
+    path.extension().is_some_and(|ext| ext == "rs")
+        && !relative
+            .components()
+            .any(|component| component.as_os_str() == "target")
+}
+
 fn run_once(args: &WatchArgs, config: &Config) -> Result<()> {
     let run_args = RunArgs {
         path: args.path.clone(),
@@ -89,6 +100,13 @@ fn run_once(args: &WatchArgs, config: &Config) -> Result<()> {
         no_capture: false,
         target: None,
         example: None,
+        package: None,
+        workspace: false,
+        with_path_deps: false,
+        filter: None,
+        only: vec![],
+        exclude: vec![],
+        preserve_formatting: false,
     };
 
     crate::commands::run::execute(run_args, config.clone())
@@ -215,4 +233,16 @@ mod tests {
         let result = run_once(&args, &config);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_is_source_change() {
+        let root = Path::new("/project");
+
+        assert!(is_source_change(Path::new("/project/src/main.rs"), root));
+        assert!(!is_source_change(Path::new("/project/README.md"), root));
+        assert!(!is_source_change(
+            Path::new("/project/target/borrowscope/workspace/src/main.rs"),
+            root
+        ));
+    }
 }
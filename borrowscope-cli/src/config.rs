@@ -25,6 +25,9 @@ pub struct Config {
 
     #[serde(default)]
     pub ignore: IgnoreConfig,
+
+    #[serde(default)]
+    pub lints: LintsConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +70,18 @@ pub struct TrackingConfig {
 
     #[serde(default)]
     pub unsafe_code: bool,
+
+    /// Only instrument functions whose path matches one of these globs
+    #[serde(default)]
+    pub only: Vec<String>,
+
+    /// Never instrument functions whose path matches one of these globs
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Keep comments and formatting of instrumented files
+    #[serde(default)]
+    pub preserve_formatting: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,6 +93,56 @@ pub struct IgnoreConfig {
     pub directories: Vec<String>,
 }
 
+/// Policy rules `borrowscope check` applies to traces; a rule runs once it is set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LintsConfig {
+    /// Deepest chain of references to references
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub borrow_depth: Option<LimitLint>,
+
+    /// Events a mutable borrow may stay alive for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mutable_borrow_events: Option<LimitLint>,
+
+    /// Clones of a single Rc/Arc allocation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rc_clones: Option<LimitLint>,
+
+    /// Rc/Arc values that are cloned but never borrowed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clone_without_borrow: Option<Lint>,
+
+    /// RefCell borrows of one cell alive at the same time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refcell_borrows: Option<LimitLint>,
+}
+
+/// A rule with a threshold, e.g. `borrow_depth = { max = 3, level = "deny" }`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LimitLint {
+    pub max: u64,
+
+    #[serde(default)]
+    pub level: LintLevel,
+}
+
+/// A rule without settings besides its level
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lint {
+    #[serde(default)]
+    pub level: LintLevel,
+}
+
+/// What a lint does when it matches; `deny` fails `check`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Allow,
+    #[default]
+    Warn,
+    Deny,
+}
+
 impl Default for Config {
     fn default() -> Self {
         toml::from_str(DEFAULT_CONFIG).unwrap_or_else(|_| Self {
@@ -86,6 +151,7 @@ impl Default for Config {
             export: ExportConfig::default(),
             tracking: TrackingConfig::default(),
             ignore: IgnoreConfig::default(),
+            lints: LintsConfig::default(),
         })
     }
 }
@@ -124,6 +190,9 @@ impl Default for TrackingConfig {
             smart_pointers: true,
             async_code: true,
             unsafe_code: false,
+            only: Vec::new(),
+            exclude: Vec::new(),
+            preserve_formatting: false,
         }
     }
 }
@@ -394,6 +463,22 @@ mod tests {
     use std::fs;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_tracking_function_filters() {
+        let config: Config = toml::from_str(
+            r#"
+[tracking]
+only = ["crate::parser::*"]
+exclude = ["*::tests::*"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.tracking.only, vec!["crate::parser::*"]);
+        assert_eq!(config.tracking.exclude, vec!["*::tests::*"]);
+        assert!(Config::default().tracking.only.is_empty());
+    }
+
     #[test]
     fn test_default_config() {
         let config = Config::default();
@@ -1499,6 +1584,41 @@ directories = ["target", "build", "dist"]
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_parse_lints() {
+        let config: Config = toml::from_str(
+            r#"
+            [lints]
+            borrow_depth = { max = 3 }
+            rc_clones = { max = 8, level = "deny" }
+            clone_without_borrow = { level = "allow" }
+            "#,
+        )
+        .unwrap();
+
+        let lints = config.lints;
+        assert_eq!(
+            lints.borrow_depth,
+            Some(LimitLint {
+                max: 3,
+                level: LintLevel::Warn
+            })
+        );
+        assert_eq!(lints.rc_clones.unwrap().level, LintLevel::Deny);
+        assert_eq!(lints.clone_without_borrow.unwrap().level, LintLevel::Allow);
+        assert!(lints.refcell_borrows.is_none());
+    }
+
+    #[test]
+    fn test_default_config_has_no_lints() {
+        let lints = Config::default().lints;
+        assert!(lints.borrow_depth.is_none());
+        assert!(lints.mutable_borrow_events.is_none());
+        assert!(lints.rc_clones.is_none());
+        assert!(lints.clone_without_borrow.is_none());
+        assert!(lints.refcell_borrows.is_none());
+    }
+
     #[test]
     fn test_validate_ignore_pattern_multiple_stars() {
         let mut config = Config::default();
@@ -49,6 +49,9 @@ pub enum CliError {
     #[error("Validation failed: {0}")]
     ValidationError(String),
 
+    #[error("Check failed: {message}")]
+    CheckFailed { message: String, code: i32 },
+
     #[error("{0}")]
     Other(String),
 }
@@ -89,6 +92,14 @@ impl CliError {
         }
     }
 
+    /// Process exit code for this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::CheckFailed { code, .. } => *code,
+            _ => 1,
+        }
+    }
+
     /// Get the error category for colored output
     pub fn category(&self) -> &str {
         match self {
@@ -105,6 +116,7 @@ impl CliError {
             CliError::InstrumentationError(_) => "Instrumentation Error",
             CliError::BuildError(_) => "Build Error",
             CliError::ValidationError(_) => "Validation Error",
+            CliError::CheckFailed { .. } => "Check Failed",
             CliError::Other(_) => "Error",
         }
     }
@@ -267,6 +279,17 @@ mod tests {
         assert_eq!(err.category(), "Validation Error");
     }
 
+    #[test]
+    fn test_error_exit_code() {
+        let err = CliError::CheckFailed {
+            message: "found 1 borrow conflict(s)".to_string(),
+            code: 2,
+        };
+        assert_eq!(err.exit_code(), 2);
+        assert_eq!(err.category(), "Check Failed");
+        assert_eq!(CliError::Other("test".to_string()).exit_code(), 1);
+    }
+
     #[test]
     fn test_error_from_io() {
         let io_err = io::Error::new(io::ErrorKind::NotFound, "file not found");
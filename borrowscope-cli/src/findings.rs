@@ -0,0 +1,319 @@
+//! CI report formats for `check` findings
+//!
+//! SARIF 2.1.0 is what GitHub code scanning uploads; JUnit XML is what GitLab
+//! and most other CI servers show as test reports.
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::cli::CheckCategory;
+use crate::locations::SourceLocation;
+use crate::markup::escape;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// How serious a finding is; SARIF uses the same names for its levels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// A kind of finding, declared as a SARIF rule and a JUnit test case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub id: String,
+    pub description: String,
+    pub severity: Severity,
+}
+
+/// A problem found by `check`, located in the program's sources when possible
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub rule: String,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
+}
+
+impl CheckCategory {
+    /// Stable rule ID used in SARIF results and JUnit failure types
+    pub fn rule_id(self) -> &'static str {
+        match self {
+            CheckCategory::Conflicts => "borrow-conflict",
+            CheckCategory::Cycles => "reference-cycle",
+            CheckCategory::Leaks => "leak",
+            CheckCategory::Pointers => "invalid-pointer-access",
+            CheckCategory::Aliasing => "aliasing-violation",
+        }
+    }
+
+    /// Exit code bit set when `--fail-on` includes this category
+    pub fn exit_code(self) -> i32 {
+        match self {
+            CheckCategory::Conflicts => 2,
+            CheckCategory::Cycles => 4,
+            CheckCategory::Leaks => 8,
+            CheckCategory::Pointers => 32,
+            CheckCategory::Aliasing => 64,
+        }
+    }
+
+    /// Plural noun for summaries, e.g. "3 borrow conflict(s)"
+    pub fn noun(self) -> &'static str {
+        match self {
+            CheckCategory::Conflicts => "borrow conflict(s)",
+            CheckCategory::Cycles => "reference cycle(s)",
+            CheckCategory::Leaks => "leaked allocation(s)",
+            CheckCategory::Pointers => "invalid pointer access(es)",
+            CheckCategory::Aliasing => "aliasing violation(s)",
+        }
+    }
+
+    /// Level findings in this category are reported at
+    pub fn severity(self) -> Severity {
+        match self {
+            CheckCategory::Conflicts | CheckCategory::Pointers | CheckCategory::Aliasing => {
+                Severity::Error
+            }
+            CheckCategory::Cycles | CheckCategory::Leaks => Severity::Warning,
+        }
+    }
+
+    /// The rule findings in this category are reported under
+    pub fn rule(self) -> Rule {
+        let description = match self {
+            CheckCategory::Conflicts => {
+                "A mutable borrow overlapped another borrow of the same value"
+            }
+            CheckCategory::Cycles => "Reference-counted values point at each other",
+            CheckCategory::Leaks => "An Rc or Arc allocation was never dropped",
+            CheckCategory::Pointers => {
+                "A raw pointer was used after its pointee was dropped or moved, or written through a *const pointer"
+            }
+            CheckCategory::Aliasing => {
+                "A reference or raw pointer was used after an aliasing access invalidated it"
+            }
+        };
+        Rule {
+            id: self.rule_id().to_string(),
+            description: description.to_string(),
+            severity: self.severity(),
+        }
+    }
+}
+
+/// SARIF log with one run, declaring every rule that was checked
+pub fn to_sarif(findings: &[Finding], rules: &[Rule]) -> serde_json::Value {
+    let declared: Vec<_> = rules
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id,
+                "shortDescription": { "text": rule.description },
+                "defaultConfiguration": { "level": rule.severity.as_str() },
+            })
+        })
+        .collect();
+
+    let results: Vec<_> = findings
+        .iter()
+        .map(|finding| {
+            let mut result = json!({
+                "ruleId": finding.rule,
+                "level": finding.severity.as_str(),
+                "message": { "text": finding.message },
+            });
+            if let Some(index) = rules.iter().position(|rule| rule.id == finding.rule) {
+                result["ruleIndex"] = json!(index);
+            }
+            if let Some(location) = &finding.location {
+                result["locations"] = json!([{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": location.uri() },
+                        "region": {
+                            "startLine": location.line,
+                            "startColumn": location.column,
+                        },
+                    },
+                }]);
+            }
+            result
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "borrowscope",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": declared,
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+/// JUnit XML with a test suite named `suite` and one test case per checked rule.
+///
+/// A rule with findings fails; its failure lists every finding with its location.
+pub fn to_junit(suite: &str, findings: &[Finding], rules: &[Rule]) -> String {
+    let failures = rules
+        .iter()
+        .filter(|rule| findings.iter().any(|f| f.rule == rule.id))
+        .count();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"borrowscope\" tests=\"{}\" failures=\"{}\">\n",
+        rules.len(),
+        failures
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+        escape(suite),
+        rules.len(),
+        failures
+    ));
+
+    for rule in rules {
+        let found: Vec<&Finding> = findings.iter().filter(|f| f.rule == rule.id).collect();
+
+        let mut testcase = format!(
+            "    <testcase name=\"{}\" classname=\"{}\"",
+            escape(&rule.id),
+            escape(suite)
+        );
+        if let Some(location) = found.iter().find_map(|f| f.location.as_ref()) {
+            testcase.push_str(&format!(
+                " file=\"{}\" line=\"{}\"",
+                escape(&location.uri()),
+                location.line
+            ));
+        }
+
+        if found.is_empty() {
+            xml.push_str(&testcase);
+            xml.push_str("/>\n");
+            continue;
+        }
+
+        xml.push_str(&testcase);
+        xml.push_str(">\n");
+        let details: Vec<String> = found
+            .iter()
+            .map(|finding| match &finding.location {
+                Some(location) => format!("{}: {}", location, finding.message),
+                None => finding.message.clone(),
+            })
+            .collect();
+        xml.push_str(&format!(
+            "      <failure type=\"{}\" message=\"{}: {} finding(s)\">{}</failure>\n",
+            escape(&rule.id),
+            escape(&rule.description),
+            found.len(),
+            escape(&details.join("\n"))
+        ));
+        xml.push_str("    </testcase>\n");
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn findings() -> Vec<Finding> {
+        vec![
+            Finding {
+                rule: "borrow-conflict".into(),
+                severity: Severity::Error,
+                message: "Multiple mutable borrows of 'v' by: a, b".into(),
+                location: Some(SourceLocation {
+                    file: PathBuf::from("src").join("lib.rs"),
+                    line: 4,
+                    column: 9,
+                }),
+            },
+            Finding {
+                rule: "leak".into(),
+                severity: Severity::Warning,
+                message: "'node' (Rc<Node>) was never dropped".into(),
+                location: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_sarif_results_carry_locations() {
+        let rules = [CheckCategory::Conflicts.rule(), CheckCategory::Leaks.rule()];
+        let sarif = to_sarif(&findings(), &rules);
+
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][1]["id"], "leak");
+
+        let conflict = &run["results"][0];
+        assert_eq!(conflict["ruleId"], "borrow-conflict");
+        assert_eq!(conflict["level"], "error");
+        let location = &conflict["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/lib.rs");
+        assert_eq!(location["region"]["startLine"], 4);
+        assert_eq!(location["region"]["startColumn"], 9);
+
+        let leak = &run["results"][1];
+        assert_eq!(leak["ruleIndex"], 1);
+        assert!(leak.get("locations").is_none());
+    }
+
+    #[test]
+    fn test_junit_fails_categories_with_findings() {
+        let rules = [
+            CheckCategory::Conflicts.rule(),
+            CheckCategory::Cycles.rule(),
+            CheckCategory::Leaks.rule(),
+        ];
+        let xml = to_junit("app.tests.parses", &findings(), &rules);
+
+        assert!(xml.contains("<testsuite name=\"app.tests.parses\" tests=\"3\" failures=\"2\">"));
+        assert!(xml.contains(
+            "<testcase name=\"borrow-conflict\" classname=\"app.tests.parses\" file=\"src/lib.rs\" line=\"4\">"
+        ));
+        assert!(xml.contains("<testcase name=\"reference-cycle\" classname=\"app.tests.parses\"/>"));
+        assert!(xml.contains("src/lib.rs:4:9: Multiple mutable borrows of 'v' by: a, b"));
+        assert!(xml.contains("message=\"An Rc or Arc allocation was never dropped: 1 finding(s)\""));
+        assert!(xml.contains("(Rc&lt;Node&gt;)"));
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct_bits() {
+        let codes = [
+            CheckCategory::Conflicts.exit_code(),
+            CheckCategory::Cycles.exit_code(),
+            CheckCategory::Leaks.exit_code(),
+            CheckCategory::Pointers.exit_code(),
+            CheckCategory::Aliasing.exit_code(),
+            crate::lints::DENY_EXIT_CODE,
+        ];
+        assert_eq!(codes.iter().fold(0, |acc, code| acc | code), 126);
+        assert!(codes.iter().all(|code| code.count_ones() == 1 && *code > 1));
+    }
+}
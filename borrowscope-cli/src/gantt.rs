@@ -0,0 +1,644 @@
+//! Lifetime Gantt charts
+//!
+//! Lays out each variable's lifetime as a bar, with the `[start, end)`
+//! interval of every borrow nested under the variable it borrows from.
+//! Borrows are flagged as conflicts by the same detector `check` uses.
+//! Charts render as plain text, SVG or a standalone HTML page.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use borrowscope_runtime::{LifetimeRelation, Timeline};
+use serde::Serialize;
+
+use crate::markup::escape;
+use crate::trace::Trace;
+
+/// Width of the bar area in plain-text charts
+pub const DEFAULT_TEXT_WIDTH: usize = 60;
+
+const ROW_HEIGHT: f64 = 24.0;
+const BAR_HEIGHT: f64 = 14.0;
+const CHART_WIDTH: f64 = 640.0;
+const AXIS_HEIGHT: f64 = 30.0;
+const MARGIN: f64 = 16.0;
+const CHAR_WIDTH: f64 = 7.0;
+const TICKS: u64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowKind {
+    Variable,
+    SharedBorrow,
+    MutableBorrow,
+}
+
+impl RowKind {
+    fn glyph(self) -> char {
+        match self {
+            RowKind::Variable => '=',
+            RowKind::SharedBorrow => '-',
+            RowKind::MutableBorrow => '#',
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            RowKind::Variable => "#3498db",
+            RowKind::SharedBorrow => "#2ecc71",
+            RowKind::MutableBorrow => "#e74c3c",
+        }
+    }
+}
+
+/// One bar of the chart
+#[derive(Debug, Clone, Serialize)]
+pub struct ChartRow {
+    /// Runtime variable ID (`x_0`)
+    pub id: String,
+    pub label: String,
+    /// Nesting level: 0 for variables, 1 for their borrows, 2 for reborrows...
+    pub depth: usize,
+    pub kind: RowKind,
+    pub start: u64,
+    /// `None` while still alive at the end of the trace
+    pub end: Option<u64>,
+    /// Labels of the borrows this one conflicts with
+    pub conflicts_with: Vec<String>,
+}
+
+impl ChartRow {
+    pub fn is_conflict(&self) -> bool {
+        !self.conflicts_with.is_empty()
+    }
+}
+
+/// A lifetime Gantt chart for one trace
+#[derive(Debug, Clone, Serialize)]
+pub struct LifetimeChart {
+    pub rows: Vec<ChartRow>,
+    pub min_time: u64,
+    pub max_time: u64,
+}
+
+impl LifetimeChart {
+    /// Build the chart from a trace's variables and borrow timeline
+    pub fn from_trace(trace: &Trace) -> Self {
+        let timeline = trace.timeline();
+
+        let name_of = |trace_id: &str| -> String {
+            trace
+                .resolve(trace_id)
+                .and_then(|id| trace.graph.get_variable(id))
+                .map(|v| v.name.clone())
+                .unwrap_or_else(|| trace_id.to_string())
+        };
+        let borrow_label = |r: &LifetimeRelation| -> String {
+            let prefix = if r.is_mutable { "&mut " } else { "&" };
+            format!("{}{}", prefix, name_of(&r.borrower_id))
+        };
+
+        let conflicts = find_conflicts(trace, &timeline);
+
+        let mut by_owner: HashMap<&str, Vec<&LifetimeRelation>> = HashMap::new();
+        for relation in &timeline.relations {
+            by_owner
+                .entry(relation.borrowed_id.as_str())
+                .or_default()
+                .push(relation);
+        }
+        for relations in by_owner.values_mut() {
+            relations.sort_by(|a, b| {
+                (a.start_time, &a.borrower_id).cmp(&(b.start_time, &b.borrower_id))
+            });
+        }
+
+        let borrowers: HashSet<&str> = timeline
+            .relations
+            .iter()
+            .map(|r| r.borrower_id.as_str())
+            .collect();
+
+        let mut variables: Vec<_> = trace.graph.all_variables().collect();
+        variables.sort_by_key(|v| (v.created_at, v.id));
+
+        let borrow_row = |relation: &LifetimeRelation, depth: usize| -> ChartRow {
+            let conflicts_with = conflicts
+                .get(relation.borrower_id.as_str())
+                .map(|others| others.iter().map(|other| borrow_label(other)).collect())
+                .unwrap_or_default();
+
+            ChartRow {
+                id: relation.borrower_id.clone(),
+                label: borrow_label(relation),
+                depth,
+                kind: if relation.is_mutable {
+                    RowKind::MutableBorrow
+                } else {
+                    RowKind::SharedBorrow
+                },
+                start: relation.start_time,
+                end: relation.end_time,
+                conflicts_with,
+            }
+        };
+
+        let mut rows = Vec::new();
+        let mut emitted: HashSet<&str> = HashSet::new();
+
+        for var in &variables {
+            let Some(trace_id) = trace.trace_id(var.id) else {
+                continue;
+            };
+            if borrowers.contains(trace_id) {
+                continue;
+            }
+
+            rows.push(ChartRow {
+                id: trace_id.to_string(),
+                label: var.name.clone(),
+                depth: 0,
+                kind: RowKind::Variable,
+                start: var.created_at,
+                end: var.dropped_at,
+                conflicts_with: Vec::new(),
+            });
+            for (relation, depth) in nested_borrows(&by_owner, trace_id, 1, &mut emitted) {
+                rows.push(borrow_row(relation, depth));
+            }
+        }
+
+        // Borrows whose owner never appeared in the trace
+        let mut orphans: Vec<&LifetimeRelation> = timeline
+            .relations
+            .iter()
+            .filter(|r| !emitted.contains(r.borrower_id.as_str()))
+            .collect();
+        orphans.sort_by_key(|r| r.start_time);
+        for relation in orphans {
+            if emitted.insert(relation.borrower_id.as_str()) {
+                rows.push(borrow_row(relation, 0));
+                for (nested, depth) in
+                    nested_borrows(&by_owner, &relation.borrower_id, 1, &mut emitted)
+                {
+                    rows.push(borrow_row(nested, depth));
+                }
+            }
+        }
+
+        Self {
+            rows,
+            min_time: timeline.min_time,
+            max_time: timeline.max_time,
+        }
+    }
+
+    fn span(&self) -> u64 {
+        self.max_time.saturating_sub(self.min_time).max(1)
+    }
+
+    fn indented_label(row: &ChartRow) -> String {
+        format!("{}{}", "  ".repeat(row.depth), row.label)
+    }
+
+    /// Render as plain text with a bar area `width` characters wide.
+    ///
+    /// Variables are drawn with `=`, shared borrows with `-` and mutable
+    /// borrows with `#`. A trailing `>` marks a lifetime still open at the
+    /// end of the trace, and conflicting borrows are marked with `!!`.
+    pub fn to_text(&self, width: usize) -> String {
+        let width = width.max(10);
+        let label_width = self
+            .rows
+            .iter()
+            .map(|r| Self::indented_label(r).chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(8);
+        let column = |t: u64| -> usize {
+            let offset = t.saturating_sub(self.min_time) as f64 / self.span() as f64;
+            ((offset * (width - 1) as f64).round() as usize).min(width - 1)
+        };
+
+        let mut out = String::new();
+        let min = self.min_time.to_string();
+        let _ = writeln!(
+            out,
+            "{:label_width$} |{}{:>pad$}|",
+            "time",
+            min,
+            self.max_time,
+            pad = width.saturating_sub(min.len()),
+        );
+
+        for row in &self.rows {
+            let mut bar = vec![' '; width];
+            let start = column(row.start);
+            let end = row.end.map(column).unwrap_or(width);
+            for cell in bar.iter_mut().take(end.max(start + 1)).skip(start) {
+                *cell = row.kind.glyph();
+            }
+            if row.end.is_none() {
+                bar[width - 1] = '>';
+            }
+
+            let _ = write!(
+                out,
+                "{:label_width$} |{}|",
+                Self::indented_label(row),
+                bar.into_iter().collect::<String>(),
+            );
+            if row.is_conflict() {
+                let _ = write!(out, " !! conflicts with {}", row.conflicts_with.join(", "));
+            }
+            out.push('\n');
+        }
+
+        if self.rows.is_empty() {
+            out.push_str("(no lifetimes recorded)\n");
+        }
+        out
+    }
+
+    /// Render as a standalone SVG document
+    pub fn to_svg(&self) -> String {
+        let label_width = self
+            .rows
+            .iter()
+            .map(|r| r.label.chars().count() as f64 * CHAR_WIDTH + r.depth as f64 * 14.0)
+            .fold(60.0, f64::max)
+            + 16.0;
+        let chart_left = MARGIN + label_width;
+        let width = chart_left + CHART_WIDTH + MARGIN;
+        let height = MARGIN * 2.0 + AXIS_HEIGHT + ROW_HEIGHT * self.rows.len().max(1) as f64;
+        let top = MARGIN + AXIS_HEIGHT;
+        let x_of = |t: u64| -> f64 {
+            chart_left + t.saturating_sub(self.min_time) as f64 / self.span() as f64 * CHART_WIDTH
+        };
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.0}" height="{height:.0}" viewBox="0 0 {width:.0} {height:.0}" font-family="DejaVu Sans, Helvetica, Arial, sans-serif" font-size="12">"#
+        );
+        svg.push_str(r#"<rect width="100%" height="100%" fill="white"/>"#);
+        svg.push('\n');
+
+        // Time axis with evenly spaced ticks and grid lines
+        svg.push_str("<g class=\"axis\">\n");
+        for i in 0..=TICKS {
+            let t = self.min_time + self.span() * i / TICKS;
+            let x = x_of(t);
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{x:.1}\" y1=\"{:.1}\" x2=\"{x:.1}\" y2=\"{:.1}\" stroke=\"#e0e0e0\"/>\
+                 <text x=\"{x:.1}\" y=\"{:.1}\" text-anchor=\"middle\" fill=\"#555\">{t}</text>",
+                top - 6.0,
+                height - MARGIN,
+                top - 12.0,
+            );
+        }
+        svg.push_str("</g>\n");
+
+        svg.push_str("<g class=\"rows\">\n");
+        for (i, row) in self.rows.iter().enumerate() {
+            let y = top + i as f64 * ROW_HEIGHT;
+            let bar_y = y + (ROW_HEIGHT - BAR_HEIGHT) / 2.0;
+            let x1 = x_of(row.start);
+            let x2 = row.end.map(x_of).unwrap_or(chart_left + CHART_WIDTH);
+            let bar_width = (x2 - x1).max(3.0);
+            let class = match row.kind {
+                RowKind::Variable => "variable",
+                RowKind::SharedBorrow => "shared",
+                RowKind::MutableBorrow => "mutable",
+            };
+            let conflict = if row.is_conflict() { " conflict" } else { "" };
+            let stroke = if row.is_conflict() {
+                r##" stroke="#f1c40f" stroke-width="3""##
+            } else {
+                ""
+            };
+            let open = if row.end.is_none() {
+                r#" opacity="0.7""#
+            } else {
+                ""
+            };
+
+            let _ = writeln!(
+                svg,
+                r#"<g class="row {class}{conflict}" data-id="{}">"#,
+                escape(&row.id)
+            );
+            let _ = writeln!(
+                svg,
+                r#"<title>{}: [{}, {})</title>"#,
+                escape(&row.label),
+                row.start,
+                row.end.map_or_else(|| "…".to_string(), |e| e.to_string()),
+            );
+            let _ = writeln!(
+                svg,
+                r##"<text x="{:.1}" y="{:.1}" fill="#222">{}</text>"##,
+                MARGIN + row.depth as f64 * 14.0,
+                y + ROW_HEIGHT / 2.0 + 4.0,
+                escape(&row.label),
+            );
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{x1:.1}" y="{bar_y:.1}" width="{bar_width:.1}" height="{BAR_HEIGHT}" rx="3" fill="{}"{stroke}{open}/>"#,
+                row.kind.color(),
+            );
+            svg.push_str("</g>\n");
+        }
+        svg.push_str("</g>\n</svg>\n");
+        svg
+    }
+
+    /// Render as a standalone HTML page with a legend and a conflict list
+    pub fn to_html(&self) -> String {
+        let mut conflicts = String::new();
+        for row in self.rows.iter().filter(|r| r.is_conflict()) {
+            let _ = writeln!(
+                conflicts,
+                "<li><code>{}</code> [{}, {}) conflicts with <code>{}</code></li>",
+                escape(&row.label),
+                row.start,
+                row.end.map_or_else(|| "…".to_string(), |e| e.to_string()),
+                escape(&row.conflicts_with.join(", ")),
+            );
+        }
+        if conflicts.is_empty() {
+            conflicts.push_str("<li>No conflicting borrows</li>\n");
+        }
+
+        format!(
+            r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="UTF-8">
+<title>BorrowScope Lifetimes</title>
+<style>
+body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif; margin: 2rem; color: #222; }}
+.legend span {{ display: inline-block; margin-right: 1.5rem; }}
+.swatch {{ display: inline-block; width: 1.5rem; height: 0.8rem; margin-right: 0.4rem; vertical-align: middle; border-radius: 2px; }}
+.chart {{ overflow-x: auto; margin: 1rem 0; }}
+.row:hover rect {{ filter: brightness(1.15); }}
+</style>
+</head>
+<body>
+<h1>Lifetimes</h1>
+<p class="legend">
+<span><i class="swatch" style="background:#3498db"></i>variable</span>
+<span><i class="swatch" style="background:#2ecc71"></i>shared borrow</span>
+<span><i class="swatch" style="background:#e74c3c"></i>mutable borrow</span>
+<span><i class="swatch" style="background:#fff;border:3px solid #f1c40f"></i>conflict</span>
+</p>
+<div class="chart">
+{svg}</div>
+<h2>Conflicts</h2>
+<ul>
+{conflicts}</ul>
+</body>
+</html>
+"##,
+            svg = self.to_svg(),
+        )
+    }
+}
+
+/// Borrows of `owner` in start order, each followed by its own reborrows
+fn nested_borrows<'a>(
+    by_owner: &HashMap<&str, Vec<&'a LifetimeRelation>>,
+    owner: &str,
+    depth: usize,
+    emitted: &mut HashSet<&'a str>,
+) -> Vec<(&'a LifetimeRelation, usize)> {
+    let mut nested = Vec::new();
+    for &relation in by_owner.get(owner).into_iter().flatten() {
+        // A borrower seen twice would mean a cycle; draw it only once
+        if emitted.insert(relation.borrower_id.as_str()) {
+            nested.push((relation, depth));
+            nested.extend(nested_borrows(
+                by_owner,
+                &relation.borrower_id,
+                depth + 1,
+                emitted,
+            ));
+        }
+    }
+    nested
+}
+
+/// Borrower ID -> borrows of the same owner it conflicts with, as reported by
+/// [`Trace::conflicts`] so the chart agrees with `check`
+fn find_conflicts<'a>(
+    trace: &Trace,
+    timeline: &'a Timeline,
+) -> HashMap<&'a str, Vec<&'a LifetimeRelation>> {
+    let relations: HashMap<(&str, &str), &LifetimeRelation> = timeline
+        .relations
+        .iter()
+        .map(|r| ((r.borrowed_id.as_str(), r.borrower_id.as_str()), r))
+        .collect();
+
+    let mut conflicts: HashMap<&str, Vec<&LifetimeRelation>> = HashMap::new();
+    for report in trace.conflicts() {
+        let Some(owner) = trace.trace_id(report.conflict.owner_id) else {
+            continue;
+        };
+        let involved: Vec<&LifetimeRelation> = report
+            .conflict
+            .borrowers
+            .iter()
+            .filter_map(|&id| trace.trace_id(id))
+            .filter_map(|borrower| relations.get(&(owner, borrower)).copied())
+            .collect();
+
+        for a in &involved {
+            for b in &involved {
+                if a.borrower_id != b.borrower_id {
+                    conflicts.entry(a.borrower_id.as_str()).or_default().push(b);
+                }
+            }
+        }
+    }
+
+    for others in conflicts.values_mut() {
+        others.sort_by(|a, b| (a.start_time, &a.borrower_id).cmp(&(b.start_time, &b.borrower_id)));
+        others.dedup_by(|a, b| a.borrower_id == b.borrower_id);
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_trace() -> Trace {
+        Trace::from_value(&serde_json::json!({
+            "events": [
+                {"type": "New", "timestamp": 0, "var_name": "data", "var_id": "d_0", "type_name": "Vec<i32>"},
+                {"type": "Borrow", "timestamp": 10, "borrower_name": "r1", "borrower_id": "r_1", "owner_id": "d_0", "mutable": false},
+                {"type": "Borrow", "timestamp": 20, "borrower_name": "r2", "borrower_id": "r_2", "owner_id": "r_1", "mutable": false},
+                {"type": "Borrow", "timestamp": 30, "borrower_name": "w", "borrower_id": "w_3", "owner_id": "d_0", "mutable": true},
+                {"type": "Drop", "timestamp": 40, "var_id": "w_3"},
+                {"type": "Drop", "timestamp": 50, "var_id": "r_1"},
+                {"type": "New", "timestamp": 55, "var_name": "s", "var_id": "s_4", "type_name": "String"},
+                {"type": "Drop", "timestamp": 70, "var_id": "d_0"}
+            ]
+        }))
+    }
+
+    fn row<'a>(chart: &'a LifetimeChart, id: &str) -> &'a ChartRow {
+        chart.rows.iter().find(|r| r.id == id).unwrap()
+    }
+
+    #[test]
+    fn test_rows_nested_under_owner() {
+        let chart = LifetimeChart::from_trace(&sample_trace());
+
+        let order: Vec<(&str, usize)> = chart
+            .rows
+            .iter()
+            .map(|r| (r.id.as_str(), r.depth))
+            .collect();
+        assert_eq!(
+            order,
+            vec![("d_0", 0), ("r_1", 1), ("r_2", 2), ("w_3", 1), ("s_4", 0)]
+        );
+        assert_eq!(chart.min_time, 0);
+        assert_eq!(chart.max_time, 70);
+    }
+
+    #[test]
+    fn test_row_intervals() {
+        let chart = LifetimeChart::from_trace(&sample_trace());
+
+        let data = row(&chart, "d_0");
+        assert_eq!(data.kind, RowKind::Variable);
+        assert_eq!((data.start, data.end), (0, Some(70)));
+
+        let w = row(&chart, "w_3");
+        assert_eq!(w.kind, RowKind::MutableBorrow);
+        assert_eq!(w.label, "&mut w");
+        assert_eq!((w.start, w.end), (30, Some(40)));
+
+        // Never dropped, so still open at the end of the trace
+        assert_eq!(row(&chart, "r_2").end, None);
+    }
+
+    #[test]
+    fn test_conflicts_flagged_on_both_sides() {
+        let chart = LifetimeChart::from_trace(&sample_trace());
+
+        assert_eq!(row(&chart, "r_1").conflicts_with, vec!["&mut w"]);
+        assert_eq!(row(&chart, "w_3").conflicts_with, vec!["&r1"]);
+        // r2 borrows r1, not data, so it does not conflict with w
+        assert!(!row(&chart, "r_2").is_conflict());
+    }
+
+    #[test]
+    fn test_conflicts_agree_with_check() {
+        let trace = sample_trace();
+        let chart = LifetimeChart::from_trace(&trace);
+
+        let flagged: HashSet<&str> = chart
+            .rows
+            .iter()
+            .filter(|r| r.is_conflict())
+            .map(|r| r.id.as_str())
+            .collect();
+        let reported: HashSet<&str> = trace
+            .conflicts()
+            .iter()
+            .flat_map(|report| report.conflict.borrowers.clone())
+            .filter_map(|id| trace.trace_id(id))
+            .collect();
+        assert_eq!(flagged, reported);
+    }
+
+    #[test]
+    fn test_shared_borrows_do_not_conflict() {
+        let trace = Trace::from_value(&serde_json::json!({
+            "events": [
+                {"type": "New", "timestamp": 0, "var_name": "x", "var_id": "x_0", "type_name": "i32"},
+                {"type": "Borrow", "timestamp": 1, "borrower_name": "a", "borrower_id": "a_1", "owner_id": "x_0", "mutable": false},
+                {"type": "Borrow", "timestamp": 2, "borrower_name": "b", "borrower_id": "b_2", "owner_id": "x_0", "mutable": false},
+                {"type": "Drop", "timestamp": 3, "var_id": "b_2"},
+                {"type": "Drop", "timestamp": 4, "var_id": "a_1"}
+            ]
+        }));
+
+        let chart = LifetimeChart::from_trace(&trace);
+        assert!(chart.rows.iter().all(|r| !r.is_conflict()));
+    }
+
+    #[test]
+    fn test_orphan_borrow_still_shown() {
+        let trace = Trace::from_value(&serde_json::json!({
+            "events": [
+                {"type": "Borrow", "timestamp": 1, "borrower_name": "r", "borrower_id": "r_1", "owner_id": "gone_0", "mutable": false},
+                {"type": "Drop", "timestamp": 2, "var_id": "r_1"}
+            ]
+        }));
+
+        let chart = LifetimeChart::from_trace(&trace);
+        assert_eq!(chart.rows.len(), 1);
+        assert_eq!(chart.rows[0].depth, 0);
+        assert_eq!(chart.rows[0].label, "&r");
+    }
+
+    #[test]
+    fn test_text_rendering() {
+        let chart = LifetimeChart::from_trace(&sample_trace());
+        let text = chart.to_text(20);
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].starts_with("time"));
+        assert!(lines[0].contains("|0") && lines[0].ends_with("70|"));
+        assert!(lines[1].starts_with("data"));
+        assert!(lines[3].starts_with("    &r2"));
+        assert!(lines[3].contains("->|"));
+        assert!(lines[4].contains('#'));
+        assert!(lines[4].ends_with("!! conflicts with &r1"));
+
+        // Every bar area has the same width
+        let widths: Vec<usize> = lines
+            .iter()
+            .map(|l| l.split('|').nth(1).unwrap().chars().count())
+            .collect();
+        assert!(widths.iter().all(|&w| w == 20));
+    }
+
+    #[test]
+    fn test_text_empty_trace() {
+        let chart = LifetimeChart::from_trace(&Trace::from_events(Vec::new()));
+        assert!(chart
+            .to_text(DEFAULT_TEXT_WIDTH)
+            .contains("no lifetimes recorded"));
+    }
+
+    #[test]
+    fn test_svg_rendering() {
+        let chart = LifetimeChart::from_trace(&sample_trace());
+        let svg = chart.to_svg();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert_eq!(svg.matches("<g class=\"row ").count(), 5);
+        assert_eq!(svg.matches(" conflict\"").count(), 2);
+        assert!(svg.contains("&amp;mut w"));
+    }
+
+    #[test]
+    fn test_html_rendering() {
+        let chart = LifetimeChart::from_trace(&sample_trace());
+        let html = chart.to_html();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<svg"));
+        assert!(
+            html.contains("<code>&amp;r1</code> [10, 50) conflicts with <code>&amp;mut w</code>")
+        );
+    }
+}
@@ -5,24 +5,42 @@
 //! applies AST transformations, and generates instrumented code.
 
 use anyhow::{Context, Result};
+use borrowscope_transform::unsafe_code::{self, id_expr, RawPointer};
 use ignore::WalkBuilder;
+use proc_macro2::TokenStream;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
 use syn::visit_mut::VisitMut;
 
+use crate::config::Config;
+use crate::modules::ModuleTree;
+use crate::source_map::LineMap;
+use crate::splice::Splicer;
+
 /// Configuration for the instrumentation process
 #[derive(Clone, Debug)]
 pub struct InstrumentationConfig {
     /// Track smart pointers (Box, Rc, Arc, etc.)
-    #[allow(dead_code)]
     pub track_smart_pointers: bool,
     /// Track async code
-    #[allow(dead_code)]
     pub track_async: bool,
     /// Track unsafe code blocks
     pub track_unsafe: bool,
     /// Additional patterns to ignore
-    #[allow(dead_code)]
     pub ignore_patterns: Vec<String>,
+    /// Directory names whose contents are never instrumented
+    pub ignore_directories: Vec<String>,
+    /// Instrument `#[test]` functions and record each one into its own trace
+    pub trace_tests: bool,
+    /// Only instrument functions whose path matches one of these globs
+    pub only: Vec<String>,
+    /// Never instrument functions whose path matches one of these globs
+    pub exclude: Vec<String>,
+    /// Splice tracking calls into the original text instead of re-emitting the file
+    pub preserve_formatting: bool,
 }
 
 impl Default for InstrumentationConfig {
@@ -31,13 +49,35 @@ impl Default for InstrumentationConfig {
             track_smart_pointers: true,
             track_async: true,
             track_unsafe: false,
-            ignore_patterns: vec![
-                "target/**".into(),
-                "**/*_test.rs".into(),
-                "**/test_*.rs".into(),
-                "**/tests/**".into(),
-                "**/benches/**".into(),
-            ],
+            ignore_patterns: vec!["**/*_test.rs".into(), "**/test_*.rs".into()],
+            ignore_directories: vec!["target".into(), "tests".into(), "benches".into()],
+            trace_tests: false,
+            only: Vec::new(),
+            exclude: Vec::new(),
+            preserve_formatting: false,
+        }
+    }
+}
+
+impl InstrumentationConfig {
+    /// Build the instrumentation settings from the `[tracking]` and `[ignore]` config sections
+    pub fn from_config(config: &Config) -> Self {
+        let mut ignore_directories = config.ignore.directories.clone();
+        // Build output is never instrumented, whatever the config says
+        if !ignore_directories.iter().any(|dir| dir == "target") {
+            ignore_directories.push("target".into());
+        }
+
+        Self {
+            track_smart_pointers: config.tracking.smart_pointers,
+            track_async: config.tracking.async_code,
+            track_unsafe: config.tracking.unsafe_code,
+            ignore_patterns: config.ignore.patterns.clone(),
+            ignore_directories,
+            trace_tests: false,
+            only: config.tracking.only.clone(),
+            exclude: config.tracking.exclude.clone(),
+            preserve_formatting: config.tracking.preserve_formatting,
         }
     }
 }
@@ -48,6 +88,10 @@ pub struct Instrumenter {
     source_dir: PathBuf,
     output_dir: PathBuf,
     config: InstrumentationConfig,
+    /// Functions declared in `extern` blocks anywhere in the crate
+    extern_fns: OnceLock<HashSet<String>>,
+    /// Module of each file, found from the crate roots
+    modules: OnceLock<ModuleTree>,
 }
 
 #[allow(dead_code)]
@@ -58,6 +102,8 @@ impl Instrumenter {
             source_dir,
             output_dir,
             config,
+            extern_fns: OnceLock::new(),
+            modules: OnceLock::new(),
         }
     }
 
@@ -106,20 +152,16 @@ impl Instrumenter {
 
     /// Instrument a single file
     pub fn instrument_file(&self, path: &Path) -> Result<PathBuf> {
+        self.instrument_file_mapped(path)
+            .map(|(output_path, _)| output_path)
+    }
+
+    /// Instrument a single file, also returning where its lines came from
+    pub fn instrument_file_mapped(&self, path: &Path) -> Result<(PathBuf, LineMap)> {
         // Read source file
         let content = std::fs::read_to_string(path)
             .with_context(|| format!("Failed to read file: {}", path.display()))?;
-
-        // Parse with syn
-        let mut syntax = syn::parse_file(&content)
-            .with_context(|| format!("Failed to parse Rust file: {}", path.display()))?;
-
-        // Apply instrumentation
-        let mut visitor = InstrumentationVisitor::new(self.config.clone());
-        visitor.visit_file_mut(&mut syntax);
-
-        // Generate instrumented code
-        let instrumented = prettyplease::unparse(&syntax);
+        let instrumented = self.instrument_source(path, &content)?;
 
         // Determine output path
         let relative = path.strip_prefix(&self.source_dir).unwrap_or(path);
@@ -132,24 +174,114 @@ impl Instrumenter {
         }
 
         // Write instrumented code
-        std::fs::write(&output_path, instrumented)
+        std::fs::write(&output_path, &instrumented.code)
             .with_context(|| format!("Failed to write file: {}", output_path.display()))?;
 
-        Ok(output_path)
+        Ok((output_path, instrumented.lines))
     }
 
-    fn should_ignore(&self, path: &Path) -> bool {
-        let path_str = path.to_string_lossy();
+    /// Instrument the `content` of the file at `path` without writing anything
+    pub fn instrument_source(&self, path: &Path, content: &str) -> Result<InstrumentedSource> {
+        // Parse with syn
+        let mut syntax = syn::parse_file(content)
+            .with_context(|| format!("Failed to parse Rust file: {}", path.display()))?;
+
+        // Remember the original tokens before they are rewritten
+        let splicer = self
+            .config
+            .preserve_formatting
+            .then(|| Splicer::new(content, &syntax));
+
+        // Apply instrumentation
+        let relative = path.strip_prefix(&self.source_dir).unwrap_or(path);
+        let mut visitor = InstrumentationVisitor::new(self.config.clone())
+            .in_module(self.module_of(path))
+            .in_file(relative);
+        if self.config.track_unsafe {
+            visitor.extern_fns = self.extern_fns().clone();
+        }
+        visitor.visit_file_mut(&mut syntax);
 
-        // Check if path contains test-related directories
-        if path_str.contains("/tests/")
-            || path_str.contains("/benches/")
-            || path_str.contains("/target/")
-        {
+        // Generate instrumented code
+        let spliced = splicer.and_then(|splicer| {
+            let spliced = splicer.splice(&syntax);
+            if spliced.is_none() {
+                log::debug!("Could not splice {}, reformatting it", path.display());
+            }
+            spliced
+        });
+        let code = spliced.unwrap_or_else(|| prettyplease::unparse(&syntax));
+        let lines = LineMap::build(&syntax, &code);
+
+        Ok(InstrumentedSource {
+            code,
+            lines,
+            skipped: visitor.skipped,
+        })
+    }
+
+    /// Functions declared in `extern` blocks in any file of the crate, so that
+    /// calls to them are recorded as FFI calls wherever they are made
+    fn extern_fns(&self) -> &HashSet<String> {
+        self.extern_fns.get_or_init(|| {
+            let mut collector = ExternFns::default();
+            let walker = WalkBuilder::new(self.crate_dir())
+                .hidden(false)
+                .git_ignore(true)
+                .build();
+            for entry in walker.filter_map(|e| e.ok()) {
+                let path = entry.path();
+                if path.extension().and_then(|s| s.to_str()) != Some("rs")
+                    || self.should_ignore(path)
+                {
+                    continue;
+                }
+                let file = std::fs::read_to_string(path)
+                    .ok()
+                    .and_then(|content| syn::parse_file(&content).ok());
+                if let Some(file) = file {
+                    collector.visit_file(&file);
+                }
+            }
+            collector.0
+        })
+    }
+
+    /// Module path the file at `path` defines in the crate, e.g. `["parser", "lexer"]`
+    pub fn module_of(&self, path: &Path) -> Vec<String> {
+        let relative = path.strip_prefix(&self.source_dir).unwrap_or(path);
+        self.modules
+            .get_or_init(|| ModuleTree::scan(self.crate_dir()))
+            .module_of(relative)
+    }
+
+    /// The crate directory; one given as the current directory may be an empty path
+    fn crate_dir(&self) -> &Path {
+        if self.source_dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            &self.source_dir
+        }
+    }
+
+    /// Whether `path` is excluded by the ignore directories or patterns
+    pub fn should_ignore(&self, path: &Path) -> bool {
+        // Match against the project-relative path so the checkout location never matters
+        let relative = path.strip_prefix(&self.source_dir).unwrap_or(path);
+
+        // Check ignored directories, which may span several components (e.g. `build/debug`)
+        let in_ignored_dir = relative.ancestors().skip(1).any(|dir| {
+            self.config
+                .ignore_directories
+                .iter()
+                .any(|ignored| !ignored.is_empty() && dir.ends_with(ignored))
+        });
+        if in_ignored_dir {
             return true;
         }
 
         // Check custom ignore patterns
+        let path_str = relative.to_string_lossy();
         for pattern in &self.config.ignore_patterns {
             if self.matches_pattern(&path_str, pattern) {
                 return true;
@@ -235,41 +367,384 @@ impl InstrumentationResult {
     }
 }
 
+/// Output of instrumenting a single file
+pub struct InstrumentedSource {
+    /// The instrumented code
+    pub code: String,
+    /// Where the lines of `code` came from in the original
+    pub lines: LineMap,
+    /// Constructs the instrumenter could not track
+    pub skipped: Vec<Skipped>,
+}
+
+/// A construct left untracked, with the reason why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skipped {
+    /// Line in the original file
+    pub line: usize,
+    /// Path of the enclosing function
+    pub function: String,
+    /// What was skipped and why
+    pub reason: String,
+}
+
+/// Collects the functions declared in `extern` blocks
+#[derive(Default)]
+struct ExternFns(HashSet<String>);
+
+impl<'ast> Visit<'ast> for ExternFns {
+    fn visit_foreign_item_fn(&mut self, func: &'ast syn::ForeignItemFn) {
+        self.0.insert(func.sig.ident.to_string());
+    }
+}
+
+/// Runtime IDs handed out in the function being instrumented. They are
+/// numbered from 1 and offset by `reserve_ids` at runtime, as the macro does.
+#[derive(Default)]
+struct FnIds {
+    /// IDs handed out so far
+    used: usize,
+    /// Runtime IDs of the variables and pointers in scope
+    tracking_ids: HashMap<String, TokenStream>,
+    /// IDs of the raw pointers in scope
+    raw_ptrs: HashMap<String, usize>,
+    /// Blocks entered, the function body included
+    depth: usize,
+}
+
+impl FnIds {
+    fn next(&mut self) -> usize {
+        self.used += 1;
+        self.used
+    }
+}
+
 /// AST visitor for instrumentation
 struct InstrumentationVisitor {
     config: InstrumentationConfig,
-    next_id: usize,
+    /// IDs of the enclosing function, `None` outside functions
+    ids: Option<FnIds>,
+    /// Functions known to be declared in `extern` blocks
+    extern_fns: HashSet<String>,
+    /// File being instrumented, relative to the crate, for event locations
+    file: String,
     in_test: bool,
     in_unsafe: bool,
+    in_async: bool,
+    /// Whether the enclosing function passes the `only`/`exclude` filters
+    selected: bool,
+    /// Path of the current item, starting with `crate`
+    path: Vec<String>,
+    /// Constructs left untracked so far
+    skipped: Vec<Skipped>,
+}
+
+/// Visitor state saved on entering a function, restored on leaving it
+struct FnContext {
+    in_test: bool,
+    in_unsafe: bool,
+    in_async: bool,
+    selected: bool,
+    ids: Option<FnIds>,
 }
 
 impl InstrumentationVisitor {
     fn new(config: InstrumentationConfig) -> Self {
+        let selected = config.only.is_empty();
         Self {
             config,
-            next_id: 1,
+            ids: None,
+            extern_fns: HashSet::new(),
+            file: String::new(),
             in_test: false,
             in_unsafe: false,
+            in_async: false,
+            selected,
+            path: vec!["crate".into()],
+            skipped: Vec::new(),
         }
     }
 
-    #[allow(dead_code)]
-    fn next_id(&mut self) -> usize {
-        let id = self.next_id;
-        self.next_id += 1;
-        id
+    /// Start from the module a file defines, e.g. `["parser", "lexer"]`
+    fn in_module(mut self, module: Vec<String>) -> Self {
+        self.path.extend(module);
+        self
+    }
+
+    /// Record event locations in `file`
+    fn in_file(mut self, file: &Path) -> Self {
+        self.file = file.to_string_lossy().replace('\\', "/");
+        self
+    }
+
+    /// Original `file:line:column` of `span`, as a string literal
+    fn location(&self, span: proc_macro2::Span) -> TokenStream {
+        let start = span.start();
+        let location = format!("{}:{}:{}", self.file, start.line, start.column + 1);
+        quote::quote!(#location)
+    }
+
+    /// Whether unsafe constructs are rewritten here: `tracking.unsafe_code` is
+    /// on and the code inside a function that is instrumented
+    fn tracks_unsafe_code(&self) -> bool {
+        self.config.track_unsafe && self.ids.is_some() && self.should_instrument()
+    }
+
+    fn gen_id(&mut self) -> usize {
+        self.ids.get_or_insert_with(FnIds::default).next()
     }
 
-    #[allow(dead_code)]
     fn should_instrument(&self) -> bool {
-        if self.in_test {
+        if !self.selected {
+            return false;
+        }
+        if self.in_test && !self.config.trace_tests {
             return false;
         }
         if self.in_unsafe && !self.config.track_unsafe {
             return false;
         }
+        if self.in_async && !self.config.track_async {
+            return false;
+        }
+        true
+    }
+
+    /// Check a fully qualified function path against the `only`/`exclude` globs
+    fn is_selected(&self, fn_path: &str) -> bool {
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|pattern| matches_fn_path(pattern, fn_path))
+        };
+        (self.config.only.is_empty() || matches(&self.config.only))
+            && !matches(&self.config.exclude)
+    }
+
+    fn enter_fn(&mut self, attrs: &[syn::Attribute], sig: &syn::Signature) -> FnContext {
+        let saved = FnContext {
+            in_test: self.in_test,
+            in_unsafe: self.in_unsafe,
+            in_async: self.in_async,
+            selected: self.selected,
+            ids: self.ids.replace(FnIds::default()),
+        };
+        self.in_test |= has_test_attribute(attrs);
+        self.path.push(sig.ident.to_string());
+        self.selected = self.is_selected(&self.path.join("::"));
+        if sig.unsafety.is_some() {
+            self.enter_unsafe(sig.span(), "unsafe function");
+        }
+        if sig.asyncness.is_some() {
+            self.enter_async(sig.span(), "async function");
+        }
+        saved
+    }
+
+    /// Mark code as unsafe, noting when that turns tracking off
+    fn enter_unsafe(&mut self, span: proc_macro2::Span, what: &str) {
+        if !self.config.track_unsafe && self.should_instrument() {
+            self.skip(
+                span,
+                format!("{} not tracked (tracking.unsafe_code is off)", what),
+            );
+        }
+        self.in_unsafe = true;
+    }
+
+    /// Mark code as async, noting when that turns tracking off
+    fn enter_async(&mut self, span: proc_macro2::Span, what: &str) {
+        if !self.config.track_async && self.should_instrument() {
+            self.skip(
+                span,
+                format!("{} not tracked (tracking.async_code is off)", what),
+            );
+        }
+        self.in_async = true;
+    }
+
+    fn skip(&mut self, span: proc_macro2::Span, reason: String) {
+        self.skipped.push(Skipped {
+            line: span.start().line,
+            function: self.path.join("::"),
+            reason,
+        });
+    }
+
+    /// Restore the state saved by `enter_fn`, reserving the IDs the function
+    /// used at the top of its `body`
+    fn leave_fn(&mut self, saved: FnContext, body: Option<&mut syn::Block>) {
+        let ids = std::mem::replace(&mut self.ids, saved.ids);
+        let reserve = ids.and_then(|ids| unsafe_code::reserve_ids(ids.used));
+        if let (Some(body), Some(reserve)) = (body, reserve) {
+            body.stmts.insert(0, reserve);
+        }
+        self.in_test = saved.in_test;
+        self.in_unsafe = saved.in_unsafe;
+        self.in_async = saved.in_async;
+        self.selected = saved.selected;
+        self.path.pop();
+    }
+
+    /// Rewrite a cast to a raw pointer or a `&raw` expression into a
+    /// `track_raw_ptr` call, returning it with the pointer's ID
+    fn track_raw_pointer(
+        &mut self,
+        name: &str,
+        expr: &mut syn::Expr,
+    ) -> Option<(syn::Expr, usize)> {
+        let ids = self.ids.as_ref()?;
+        let source = unsafe_code::raw_pointer_source(expr, |name| ids.raw_ptrs.contains_key(name))
+            .and_then(|name| ids.tracking_ids.get(&name).cloned());
+        let pointer = RawPointer::of(expr)?;
+        let (mutable, ptr_type) = (pointer.mutable, pointer.ptr_type);
+
+        // A borrow taken only to make the pointer is not tracked on its own
+        if source.is_none() {
+            self.visit_expr_mut(pointer.operand);
+        }
+
+        let id = self.gen_id();
+        let location = self.location(expr.span());
+        let tracked = unsafe_code::track_raw_pointer(
+            name,
+            id_expr(id),
+            source,
+            mutable,
+            &ptr_type,
+            location,
+            expr,
+        );
+        Some((tracked, id))
+    }
+
+    /// Record a dereference of a tracked raw pointer
+    fn track_deref(&mut self, unary: &mut syn::ExprUnary, is_write: bool) -> bool {
+        let ptr_id = unsafe_code::deref_target(unary).and_then(|name| {
+            let ids = self.ids.as_ref()?;
+            ids.raw_ptrs.get(&name).copied()
+        });
+        let Some(ptr_id) = ptr_id else {
+            return false;
+        };
+        let location = self.location(unary.span());
+        unsafe_code::track_deref(unary, id_expr(ptr_id), location, is_write);
         true
     }
+
+    /// Record `mem::transmute` and calls of `extern` functions before they run
+    fn track_unsafe_call(&mut self, call: &mut syn::ExprCall) -> Option<syn::Expr> {
+        let syn::Expr::Path(func) = &*call.func else {
+            return None;
+        };
+        let location = self.location(call.span());
+
+        if unsafe_code::is_transmute(&func.path) {
+            syn::visit_mut::visit_expr_call_mut(self, call);
+            return unsafe_code::track_transmute(call, location);
+        }
+
+        let name = unsafe_code::call_name(&func.path);
+        let is_extern = func
+            .path
+            .segments
+            .last()
+            .is_some_and(|last| self.extern_fns.contains(&last.ident.to_string()))
+            || func.path.segments.iter().any(|seg| seg.ident == "libc");
+        if !is_extern {
+            return None;
+        }
+
+        syn::visit_mut::visit_expr_call_mut(self, call);
+        Some(unsafe_code::track_ffi_call(&name, call, location))
+    }
+
+    /// Rewrite the raw pointers, dereferences and unsafe calls in `expr`,
+    /// returning whether it was fully handled here
+    fn track_unsafe_expr(&mut self, expr: &mut syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Cast(_) | syn::Expr::RawAddr(_) => {
+                match self.track_raw_pointer("raw_ptr", expr) {
+                    Some((tracked, _)) => {
+                        *expr = tracked;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            syn::Expr::Unary(unary) => self.track_deref(unary, false),
+            syn::Expr::Assign(assign) => {
+                if let Some(unary) = unsafe_code::assigned_deref(&mut assign.left) {
+                    self.track_deref(unary, true);
+                }
+                false
+            }
+            syn::Expr::Binary(binary) if unsafe_code::is_compound_assign(&binary.op) => {
+                if let Some(unary) = unsafe_code::assigned_deref(&mut binary.left) {
+                    self.track_deref(unary, true);
+                }
+                false
+            }
+            syn::Expr::Call(call) => match self.track_unsafe_call(call) {
+                Some(tracked) => {
+                    *expr = tracked;
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn with_scope(&mut self, name: String, visit: impl FnOnce(&mut Self)) {
+        self.path.push(name);
+        visit(self);
+        self.path.pop();
+    }
+
+    /// Wrap `Rc`/`Arc` constructors and clones with their dedicated tracking calls
+    fn track_smart_pointer(&self, name: &str, expr: &syn::Expr) -> Option<syn::Expr> {
+        if !self.config.track_smart_pointers {
+            return None;
+        }
+
+        let syn::Expr::Call(call) = expr else {
+            return None;
+        };
+        let syn::Expr::Path(path) = &*call.func else {
+            return None;
+        };
+
+        let source = || {
+            let arg = call.args.first()?;
+            let arg = match arg {
+                syn::Expr::Reference(reference) => &*reference.expr,
+                other => other,
+            };
+            Some(quote::quote!(#arg).to_string())
+        };
+
+        match rc_call(&path.path)? {
+            ("Arc", "new") => {
+                Some(syn::parse_quote! { borrowscope_runtime::track_arc_new(#name, #expr) })
+            }
+            ("Rc", "new") => {
+                Some(syn::parse_quote! { borrowscope_runtime::track_rc_new(#name, #expr) })
+            }
+            ("Arc", _) => {
+                let source = source()?;
+                Some(
+                    syn::parse_quote! { borrowscope_runtime::track_arc_clone(#name, #source, #expr) },
+                )
+            }
+            _ => {
+                let source = source()?;
+                Some(
+                    syn::parse_quote! { borrowscope_runtime::track_rc_clone(#name, #source, #expr) },
+                )
+            }
+        }
+    }
 }
 
 impl VisitMut for InstrumentationVisitor {
@@ -288,69 +763,248 @@ impl VisitMut for InstrumentationVisitor {
     }
 
     fn visit_item_fn_mut(&mut self, func: &mut syn::ItemFn) {
-        // Check if this is a test function
-        let was_in_test = self.in_test;
-        if has_test_attribute(&func.attrs) {
-            self.in_test = true;
+        let saved = self.enter_fn(&func.attrs, &func.sig);
+        if self.tracks_unsafe_code() {
+            unsafe_code::rebind_pointer_params(&mut func.sig.inputs, &mut func.block);
         }
-
-        // Visit function body
         syn::visit_mut::visit_item_fn_mut(self, func);
+        if self.config.trace_tests && has_test_attribute(&func.attrs) {
+            insert_test_trace(&func.sig.ident, &mut func.block);
+        }
+        self.leave_fn(saved, Some(&mut func.block));
+    }
+
+    fn visit_impl_item_fn_mut(&mut self, func: &mut syn::ImplItemFn) {
+        let saved = self.enter_fn(&func.attrs, &func.sig);
+        if self.tracks_unsafe_code() {
+            unsafe_code::rebind_pointer_params(&mut func.sig.inputs, &mut func.block);
+        }
+        syn::visit_mut::visit_impl_item_fn_mut(self, func);
+        self.leave_fn(saved, Some(&mut func.block));
+    }
+
+    fn visit_trait_item_fn_mut(&mut self, func: &mut syn::TraitItemFn) {
+        let saved = self.enter_fn(&func.attrs, &func.sig);
+        syn::visit_mut::visit_trait_item_fn_mut(self, func);
+        self.leave_fn(saved, func.default.as_mut());
+    }
+
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        // Names bound in a block go out of scope with it
+        let outer = self.ids.as_mut().map(|ids| {
+            ids.depth += 1;
+            (ids.tracking_ids.clone(), ids.raw_ptrs.clone())
+        });
+        syn::visit_mut::visit_block_mut(self, block);
+        if let (Some(ids), Some((tracking_ids, raw_ptrs))) = (&mut self.ids, outer) {
+            ids.tracking_ids = tracking_ids;
+            ids.raw_ptrs = raw_ptrs;
+            ids.depth -= 1;
+        }
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        self.with_scope(module.ident.to_string(), |visitor| {
+            syn::visit_mut::visit_item_mod_mut(visitor, module)
+        });
+    }
+
+    fn visit_item_impl_mut(&mut self, item: &mut syn::ItemImpl) {
+        // Methods are addressed through their type: `crate::parser::Parser::parse`
+        let name = match &*item.self_ty {
+            syn::Type::Path(ty) => ty
+                .path
+                .segments
+                .last()
+                .map(|seg| seg.ident.to_string())
+                .unwrap_or_default(),
+            other => quote::quote!(#other).to_string(),
+        };
+        self.with_scope(name, |visitor| {
+            syn::visit_mut::visit_item_impl_mut(visitor, item)
+        });
+    }
 
-        self.in_test = was_in_test;
+    fn visit_item_trait_mut(&mut self, item: &mut syn::ItemTrait) {
+        self.with_scope(item.ident.to_string(), |visitor| {
+            syn::visit_mut::visit_item_trait_mut(visitor, item)
+        });
+    }
+
+    /// A name bound by a pattern is no longer a tracked variable or pointer
+    fn visit_pat_mut(&mut self, pat: &mut syn::Pat) {
+        if let (syn::Pat::Ident(binding), Some(ids)) = (&*pat, &mut self.ids) {
+            let name = binding.ident.to_string();
+            ids.raw_ptrs.remove(&name);
+            ids.tracking_ids.remove(&name);
+        }
+        syn::visit_mut::visit_pat_mut(self, pat);
     }
 
     fn visit_local_mut(&mut self, local: &mut syn::Local) {
-        if self.in_test {
+        if !self.should_instrument() {
             syn::visit_mut::visit_local_mut(self, local);
             return;
         }
 
         // Transform: let x = value;
-        // Into: let x = track_new(ID, "x", value);
+        // Into: let x = track_new("x", value);
+        let mut tracking_id = None;
         if let Some(init) = &mut local.init {
             let name = extract_pattern_name(&local.pat);
+            if name.contains("<pattern>") {
+                let pat = &local.pat;
+                self.skip(
+                    local.pat.span(),
+                    format!(
+                        "pattern `{}` is too complex to name, tracked as `{}`",
+                        quote::quote!(#pat),
+                        name
+                    ),
+                );
+            }
+
+            if self.tracks_unsafe_code() {
+                let mut pointer = unsafe_code::explicit_pointer_init(&local.pat, &init.expr)
+                    .unwrap_or_else(|| (*init.expr).clone());
+                if let Some((tracked, id)) = self.track_raw_pointer(&name, &mut pointer) {
+                    *init.expr = tracked;
+                    if let Some((_, diverge)) = &mut init.diverge {
+                        self.visit_expr_mut(diverge);
+                    }
+                    if let Some(ids) = &mut self.ids {
+                        ids.raw_ptrs.insert(name.clone(), id);
+                        ids.tracking_ids.insert(name, id_expr(id));
+                    }
+                    return;
+                }
+            }
 
             let expr = &init.expr;
-            let new_init: syn::Expr = syn::parse_quote! {
-                borrowscope_runtime::track_new(0, #name, #expr)
+            // Blocks around the variable inside the function body
+            let depth = self
+                .ids
+                .as_ref()
+                .map_or(0, |ids| ids.depth.saturating_sub(1));
+            let new_init: syn::Expr = match self.track_smart_pointer(&name, expr) {
+                Some(tracked) => tracked,
+                // Pointers and borrows made from the variable refer to it by
+                // its runtime ID. That costs a `reserve_ids` call in every
+                // instrumented function, so without `tracking.unsafe_code`,
+                // when the aliasing checks have no pointers to follow,
+                // variables keep the name-based `track_new`.
+                None if self.tracks_unsafe_code() && is_ident_name(&name) => {
+                    let id = id_expr(self.gen_id());
+                    let location = self.location(local.pat.span());
+                    tracking_id = Some((
+                        name.clone(),
+                        quote::quote! { borrowscope_runtime::VarId(#name, #id) },
+                    ));
+                    syn::parse_quote! {
+                        borrowscope_runtime::__track_new_with_id_helper(#id, #name, #location, #depth, #expr)
+                    }
+                }
+                None => syn::parse_quote! {
+                    borrowscope_runtime::track_new_in_scope(#name, #depth, #expr)
+                },
             };
 
-            init.expr = Box::new(new_init);
+            *init.expr = new_init;
+        } else {
+            self.skip(
+                local.span(),
+                format!(
+                    "`{}` is declared without a value and not tracked",
+                    extract_pattern_name(&local.pat)
+                ),
+            );
         }
 
         syn::visit_mut::visit_local_mut(self, local);
+        // Bound after visiting, so the initializer still sees an earlier binding
+        if let (Some((name, tracking_id)), Some(ids)) = (tracking_id, &mut self.ids) {
+            ids.tracking_ids.insert(name, tracking_id);
+        }
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        // Macro arguments are opaque tokens, so borrows inside them stay untracked
+        if self.should_instrument() && has_borrow(mac.tokens.clone()) {
+            let name = mac
+                .path
+                .segments
+                .last()
+                .map(|seg| seg.ident.to_string())
+                .unwrap_or_default();
+            self.skip(
+                mac.span(),
+                format!("borrows inside `{}!` are not tracked", name),
+            );
+        }
+        syn::visit_mut::visit_macro_mut(self, mac);
     }
 
     fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
-        if self.in_test {
-            syn::visit_mut::visit_expr_mut(self, expr);
+        if self.tracks_unsafe_code() && self.track_unsafe_expr(expr) {
             return;
         }
 
         match expr {
-            syn::Expr::Reference(ref_expr) => {
+            syn::Expr::Reference(ref_expr) if self.should_instrument() => {
                 // Transform: &x or &mut x
                 let is_mut = ref_expr.mutability.is_some();
 
                 let inner = &ref_expr.expr;
-
-                if is_mut {
+                let name = quote::quote!(#inner).to_string();
+                let owner = match &**inner {
+                    syn::Expr::Path(path) => path.path.get_ident().and_then(|ident| {
+                        let ids = self.ids.as_ref()?;
+                        ids.tracking_ids.get(&ident.to_string()).cloned()
+                    }),
+                    _ => None,
+                };
+
+                if let Some(owner) = owner {
+                    // The owner has a runtime ID, so the borrow can name it as the macro does
+                    let borrower_id = id_expr(self.gen_id());
+                    let location = self.location(ref_expr.span());
+                    *expr = if is_mut {
+                        syn::parse_quote! {
+                            borrowscope_runtime::track_borrow_mut_with_id(#borrower_id, #owner, #name, #location, &mut #inner)
+                        }
+                    } else {
+                        syn::parse_quote! {
+                            borrowscope_runtime::track_borrow_with_id(#borrower_id, #owner, #name, #location, false, &#inner)
+                        }
+                    };
+                } else if is_mut {
                     *expr = syn::parse_quote! {
-                        borrowscope_runtime::track_borrow_mut(0, &#inner)
+                        borrowscope_runtime::track_borrow_mut(#name, &mut #inner)
                     };
                 } else {
                     *expr = syn::parse_quote! {
-                        borrowscope_runtime::track_borrow(0, &#inner)
+                        borrowscope_runtime::track_borrow(#name, &#inner)
                     };
                 }
             }
-            syn::Expr::Unsafe(_) => {
+            syn::Expr::Unsafe(unsafe_expr) => {
                 let was_in_unsafe = self.in_unsafe;
-                self.in_unsafe = true;
-                syn::visit_mut::visit_expr_mut(self, expr);
+                self.enter_unsafe(unsafe_expr.span(), "unsafe block");
+                syn::visit_mut::visit_expr_unsafe_mut(self, unsafe_expr);
+                if self.tracks_unsafe_code() {
+                    let block_id = id_expr(self.gen_id());
+                    let location = self.location(unsafe_expr.span());
+                    let guard = unsafe_code::unsafe_block_guard(block_id, location);
+                    unsafe_expr.block.stmts.insert(0, guard);
+                }
                 self.in_unsafe = was_in_unsafe;
             }
+            syn::Expr::Async(_) => {
+                let was_in_async = self.in_async;
+                self.enter_async(expr.span(), "async block");
+                syn::visit_mut::visit_expr_mut(self, expr);
+                self.in_async = was_in_async;
+            }
             _ => {
                 syn::visit_mut::visit_expr_mut(self, expr);
             }
@@ -358,22 +1012,143 @@ impl VisitMut for InstrumentationVisitor {
     }
 }
 
-#[allow(dead_code)]
+/// `("Rc" | "Arc", "new" | "clone")` for a call of `Rc::new`, `Arc::clone`
+/// and the like, written bare or through `std::rc`, `std::sync`, `alloc::rc`
+/// or `alloc::sync`. Other types named `Rc` or `Arc` (`triomphe::Arc`) and
+/// lookalikes (`MyRc`) are not matched.
+fn rc_call(path: &syn::Path) -> Option<(&'static str, &'static str)> {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|seg| seg.ident.to_string())
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let (ty, method, module) = match segments.as_slice() {
+        [ty, method] => (*ty, *method, None),
+        [krate @ ("std" | "alloc"), module, ty, method] => (*ty, *method, Some((*krate, *module))),
+        _ => return None,
+    };
+    let ty = match (ty, module) {
+        ("Rc", None | Some((_, "rc"))) => "Rc",
+        ("Arc", None | Some((_, "sync"))) => "Arc",
+        _ => return None,
+    };
+    let method = match method {
+        "new" => "new",
+        "clone" => "clone",
+        _ => return None,
+    };
+    Some((ty, method))
+}
+
+/// Whether a `let` pattern name is a single variable
+fn is_ident_name(name: &str) -> bool {
+    name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Attributes that make a function a test run by `cargo test` under its own name
+const TEST_ATTRIBUTES: &[&str] = &[
+    "test",
+    "tokio::test",
+    "async_std::test",
+    "actix_rt::test",
+    "actix_web::test",
+    "test_log::test",
+    "sqlx::test",
+];
+
 fn has_test_attribute(attrs: &[syn::Attribute]) -> bool {
     attrs.iter().any(|attr| {
-        if let Some(ident) = attr.path().get_ident() {
-            ident == "test" || ident == "bench"
+        let path = unsafe_code::call_name(attr.path());
+        TEST_ATTRIBUTES.contains(&path.as_str())
+    })
+}
+
+/// Whether macro tokens contain a `&`, i.e. most likely a borrow
+fn has_borrow(tokens: proc_macro2::TokenStream) -> bool {
+    tokens.into_iter().any(|tree| match tree {
+        proc_macro2::TokenTree::Punct(punct) => punct.as_char() == '&',
+        proc_macro2::TokenTree::Group(group) => has_borrow(group.stream()),
+        _ => false,
+    })
+}
+
+/// Module path a file relative to its crate directory defines by its location
+/// alone, for files no `mod` declaration reaches (see [`ModuleTree`]).
+///
+/// `src/lib.rs` and `src/main.rs` are the crate root, `src/a/b.rs` and
+/// `src/a/b/mod.rs` are `a::b`. Files outside `src/` (tests, examples, build
+/// scripts) are crate roots of their own.
+pub fn module_path(relative: &Path) -> Vec<String> {
+    let Ok(in_src) = relative.strip_prefix("src") else {
+        return Vec::new();
+    };
+    // Every binary under src/bin is its own crate
+    if in_src.starts_with("bin") {
+        return Vec::new();
+    }
+
+    let mut segments: Vec<String> = in_src
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let is_root = matches!(segments.as_slice(), [file] if file == "lib" || file == "main");
+    if is_root || segments.last().is_some_and(|file| file == "mod") {
+        segments.pop();
+    }
+    segments
+}
+
+/// Match a function path against a glob where `*` spans any characters, `::` included.
+///
+/// Patterns are rooted at `crate`; a pattern naming neither `crate` nor starting
+/// with `*` is taken relative to the crate root (`parser::*` is `crate::parser::*`).
+pub fn matches_fn_path(pattern: &str, path: &str) -> bool {
+    let pattern =
+        if pattern == "crate" || pattern.starts_with("crate::") || pattern.starts_with('*') {
+            pattern.to_string()
         } else {
-            attr.path()
-                .segments
-                .iter()
-                .any(|seg| seg.ident == "test" || seg.ident == "tokio" || seg.ident == "async_std")
+            format!("crate::{}", pattern)
+        };
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+
+    // Iterative wildcard match with single-star backtracking
+    let (mut p, mut s) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while s < path.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == path[s]) {
+            p += 1;
+            s += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, s));
+            p += 1;
+        } else if let Some((star_p, star_s)) = star {
+            p = star_p + 1;
+            s = star_s + 1;
+            star = Some((star_p, star_s + 1));
+        } else {
+            return false;
         }
-    })
+    }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
-#[allow(dead_code)]
-fn extract_pattern_name(pat: &syn::Pat) -> String {
+/// Open a per-test trace guard as the first statement of a test body
+fn insert_test_trace(name: &syn::Ident, block: &mut syn::Block) {
+    let name = name.to_string();
+    let guard: syn::Stmt = syn::parse_quote! {
+        let _borrowscope_test = borrowscope_runtime::TestTrace::start(
+            concat!(module_path!(), "::", #name)
+        );
+    };
+    block.stmts.insert(0, guard);
+}
+
+/// Name a `let` pattern the way tracking calls record it
+pub fn extract_pattern_name(pat: &syn::Pat) -> String {
     match pat {
         syn::Pat::Ident(ident) => ident.ident.to_string(),
         syn::Pat::Type(pat_type) => extract_pattern_name(&pat_type.pat),
@@ -558,6 +1333,29 @@ fn main() {
         assert!(content.contains("track_borrow_mut"));
     }
 
+    #[test]
+    fn test_instrumented_calls_match_runtime_signatures() {
+        let temp_dir = TempDir::new().unwrap();
+        let source_file = temp_dir.path().join("main.rs");
+        fs::write(
+            &source_file,
+            "fn main() { let mut x = 42; let y = &x; let z = &mut x; }",
+        )
+        .unwrap();
+
+        let instrumenter = Instrumenter::new(
+            temp_dir.path().to_path_buf(),
+            temp_dir.path().join("output"),
+            InstrumentationConfig::default(),
+        );
+        let result = instrumenter.instrument_file(&source_file).unwrap();
+
+        let content = fs::read_to_string(&result).unwrap();
+        assert!(content.contains(r#"track_new_in_scope("x", 0usize, 42)"#));
+        assert!(content.contains(r#"track_borrow("x", &x)"#));
+        assert!(content.contains(r#"track_borrow_mut("x", &mut x)"#));
+    }
+
     #[test]
     fn test_instrument_preserves_structure() {
         let temp_dir = TempDir::new().unwrap();
@@ -1185,6 +1983,22 @@ fn main() {
     fn test_has_tokio_test_attribute() {
         let attrs: Vec<syn::Attribute> = vec![syn::parse_quote! { #[tokio::test] }];
         assert!(has_test_attribute(&attrs));
+
+        let attrs: Vec<syn::Attribute> = vec![syn::parse_quote! { #[tokio::main] }];
+        assert!(!has_test_attribute(&attrs));
+    }
+
+    #[test]
+    fn test_bench_and_unknown_test_attributes() {
+        let attrs: Vec<syn::Attribute> = vec![syn::parse_quote! { #[bench] }];
+        assert!(!has_test_attribute(&attrs));
+
+        // Not a test harness, just an attribute named `test`
+        let attrs: Vec<syn::Attribute> = vec![syn::parse_quote! { #[my_macros::test] }];
+        assert!(!has_test_attribute(&attrs));
+
+        let attrs: Vec<syn::Attribute> = vec![syn::parse_quote! { #[test_log::test] }];
+        assert!(has_test_attribute(&attrs));
     }
 
     #[test]
@@ -1365,6 +2179,11 @@ fn main() {
             track_async: true,
             track_unsafe: false,
             ignore_patterns: vec![],
+            ignore_directories: vec!["target".into(), "tests".into()],
+            trace_tests: false,
+            only: vec![],
+            exclude: vec![],
+            preserve_formatting: false,
         };
 
         let instrumenter = Instrumenter::new(PathBuf::from("/src"), PathBuf::from("/out"), config);
@@ -1372,4 +2191,490 @@ fn main() {
         assert!(instrumenter.should_ignore(Path::new("/src/tests/foo.rs")));
         assert!(!instrumenter.should_ignore(Path::new("/src/main.rs")));
     }
+
+    /// Instrument a snippet and return the emitted code without whitespace
+    fn instrument_source(source: &str, config: InstrumentationConfig) -> String {
+        let temp_dir = TempDir::new().unwrap();
+        let source_file = temp_dir.path().join("main.rs");
+        fs::write(&source_file, source).unwrap();
+
+        let instrumenter = Instrumenter::new(
+            temp_dir.path().to_path_buf(),
+            temp_dir.path().join("output"),
+            config,
+        );
+        let result = instrumenter.instrument_file(&source_file).unwrap();
+        // Drop whitespace so assertions do not depend on line wrapping
+        fs::read_to_string(result)
+            .unwrap()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect()
+    }
+
+    #[test]
+    fn test_from_config() {
+        let mut config = Config::default();
+        config.tracking.smart_pointers = false;
+        config.tracking.async_code = false;
+        config.tracking.unsafe_code = true;
+        config.tracking.only = vec!["crate::parser::*".into()];
+        config.tracking.exclude = vec!["*::tests::*".into()];
+        config.ignore.patterns = vec!["*.gen.rs".into()];
+        config.ignore.directories = vec!["generated".into()];
+
+        let inst = InstrumentationConfig::from_config(&config);
+        assert_eq!(inst.only, vec!["crate::parser::*".to_string()]);
+        assert_eq!(inst.exclude, vec!["*::tests::*".to_string()]);
+        assert!(!inst.track_smart_pointers);
+        assert!(!inst.track_async);
+        assert!(inst.track_unsafe);
+        assert_eq!(inst.ignore_patterns, vec!["*.gen.rs".to_string()]);
+        // target is always skipped
+        assert_eq!(
+            inst.ignore_directories,
+            vec!["generated".to_string(), "target".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_unsafe_code_flag() {
+        let source = "fn main() { let a = 1; unsafe { let b = 2; } }";
+
+        let skipped = instrument_source(source, InstrumentationConfig::default());
+        assert!(skipped.contains(r#"track_new_in_scope("a",0usize,1)"#));
+        assert!(!skipped.contains(r#"track_new_in_scope("b","#));
+
+        let config = InstrumentationConfig {
+            track_unsafe: true,
+            ..Default::default()
+        };
+        // With unsafe tracking on, variables are recorded with runtime IDs and
+        // the depth of the block they are declared in
+        let tracked = instrument_source(source, config);
+        assert!(tracked.contains(r#"(_borrowscope_ids+2usize,"b","main.rs:1:37",1usize,2"#));
+    }
+
+    #[test]
+    fn test_unsafe_code_borrows_checked_against_raw_pointers() {
+        // `main` as written, with its tokens without whitespace
+        macro_rules! instrumented {
+            ($($code:tt)*) => {{
+                $($code)*
+                let code: String = stringify!($($code)*).split_whitespace().collect();
+                (main as fn(), code)
+            }};
+        }
+
+        let source = "fn main() {\n    let mut x = 0;\n    let p = &mut x as *mut i32;\n    let r = &mut x;\n    unsafe { *p = 1 };\n    *r = 2;\n}\n";
+        let config = InstrumentationConfig {
+            track_unsafe: true,
+            ..Default::default()
+        };
+        let (main, expected) = instrumented! {
+            fn main() {
+                let _borrowscope_ids = borrowscope_runtime::reserve_ids(5usize);
+                let mut x = borrowscope_runtime::__track_new_with_id_helper(
+                    _borrowscope_ids + 1usize, "x", "main.rs:2:9", 0usize, 0,
+                );
+                let p = borrowscope_runtime::track_raw_ptr_mut_from(
+                    "p",
+                    _borrowscope_ids + 2usize,
+                    borrowscope_runtime::VarId("x", _borrowscope_ids + 1usize),
+                    "*mut i32",
+                    "main.rs:3:13",
+                    &mut x as *mut i32,
+                );
+                let r = borrowscope_runtime::__track_new_with_id_helper(
+                    _borrowscope_ids + 3usize, "r", "main.rs:4:9", 0usize,
+                    borrowscope_runtime::track_borrow_mut_with_id(
+                        _borrowscope_ids + 4usize,
+                        borrowscope_runtime::VarId("x", _borrowscope_ids + 1usize),
+                        "x",
+                        "main.rs:4:13",
+                        &mut x,
+                    ),
+                );
+                unsafe {
+                    let _borrowscope_unsafe_guard = borrowscope_runtime::track_unsafe_block(
+                        _borrowscope_ids + 5usize, "main.rs:5:5",
+                    );
+                    *{
+                        borrowscope_runtime::track_raw_ptr_deref(
+                            _borrowscope_ids + 2usize, "main.rs:5:14", true,
+                        );
+                        p
+                    } = 1;
+                };
+                *r = 2;
+            }
+        };
+        let output = instrument_source(source, config);
+        assert!(output.ends_with(&expected), "{}", output);
+
+        // The write through `p` comes after `&mut x` popped it
+        borrowscope_runtime::reset();
+        main();
+        let reports = crate::aliasing::check(&borrowscope_runtime::get_events());
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].item, "p");
+        assert_eq!(reports[0].location.as_deref(), Some("main.rs:5:14"));
+    }
+
+    #[test]
+    fn test_scope_depth_recorded() {
+        let source = "fn main() { let a = 1; { let b = 2; if a < b { let c = 3; } } }";
+
+        let output = instrument_source(source, InstrumentationConfig::default());
+        assert!(output.contains(r#"track_new_in_scope("a",0usize,1)"#));
+        assert!(output.contains(r#"track_new_in_scope("b",1usize,2)"#));
+        assert!(output.contains(r#"track_new_in_scope("c",2usize,3)"#));
+    }
+
+    #[test]
+    fn test_unsafe_fn_follows_unsafe_flag() {
+        let source = "unsafe fn raw() { let p = 1; }";
+
+        let skipped = instrument_source(source, InstrumentationConfig::default());
+        assert!(!skipped.contains(r#"track_new_in_scope("p","#));
+
+        let config = InstrumentationConfig {
+            track_unsafe: true,
+            ..Default::default()
+        };
+        assert!(instrument_source(source, config).contains(
+            r#"__track_new_with_id_helper(_borrowscope_ids+1usize,"p","main.rs:1:23",0usize,1"#
+        ));
+    }
+
+    #[test]
+    fn test_unsafe_code_rewrites() {
+        let source = r#"
+fn main() {
+    let mut x = 1;
+    let p = &mut x as *mut i32;
+    let id = unsafe {
+        *p = 2;
+        std::mem::transmute::<i32, Id>(*p)
+    };
+}
+"#;
+        let config = InstrumentationConfig {
+            track_unsafe: true,
+            ..Default::default()
+        };
+        let output = instrument_source(source, config);
+
+        assert!(output.contains("let_borrowscope_ids=borrowscope_runtime::reserve_ids(4usize);"));
+        // The pointer is made from `x`, named by its runtime ID
+        assert!(output.contains(
+            r#"track_raw_ptr_mut_from("p",_borrowscope_ids+2usize,borrowscope_runtime::VarId("x",_borrowscope_ids+1usize),"*muti32","main.rs:4:13",&mutxas*muti32"#
+        ));
+        assert!(output.contains(r#"track_unsafe_block(_borrowscope_ids+4usize,"main.rs:5:14""#));
+        assert!(output.contains(
+            r#"*{borrowscope_runtime::track_raw_ptr_deref(_borrowscope_ids+2usize,"main.rs:6:9",true"#
+        ));
+        assert!(output.contains(r#"track_transmute_call("main.rs:7:9",*{"#));
+        assert!(output.contains("|value|std::mem::transmute::<i32,Id>(value)"));
+    }
+
+    #[test]
+    fn test_extern_fns_declared_in_other_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let source_dir = temp_dir.path().to_path_buf();
+        fs::create_dir_all(source_dir.join("src")).unwrap();
+        fs::write(
+            source_dir.join("src/ffi.rs"),
+            "extern \"C\" { pub fn strlen(s: *const u8) -> usize; }",
+        )
+        .unwrap();
+        let main = source_dir.join("src/main.rs");
+        fs::write(
+            &main,
+            "mod ffi;\nfn main() { let n = unsafe { ffi::strlen(b\"a\\0\".as_ptr()) + len() }; }\nfn len() -> usize { 0 }",
+        )
+        .unwrap();
+
+        let config = InstrumentationConfig {
+            track_unsafe: true,
+            ..Default::default()
+        };
+        let instrumenter = Instrumenter::new(source_dir.clone(), source_dir.join("out"), config);
+        let output = instrumenter.instrument_file(&main).unwrap();
+        let content: String = fs::read_to_string(output)
+            .unwrap()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+
+        assert!(content.contains(r#"track_ffi_call("ffi::strlen","src/main.rs:2:30");"#));
+        assert_eq!(content.matches("track_ffi_call").count(), 1);
+    }
+
+    #[test]
+    fn test_async_code_flag() {
+        let source = r#"
+async fn fetch() { let a = 1; }
+fn main() { let fut = async { let b = 2; }; }
+"#;
+
+        let tracked = instrument_source(source, InstrumentationConfig::default());
+        assert!(tracked.contains(r#"track_new_in_scope("a",0usize,1)"#));
+        assert!(tracked.contains(r#"track_new_in_scope("b",1usize,2)"#));
+
+        let config = InstrumentationConfig {
+            track_async: false,
+            ..Default::default()
+        };
+        let skipped = instrument_source(source, config);
+        assert!(!skipped.contains(r#"track_new_in_scope("a","#));
+        assert!(!skipped.contains(r#"track_new_in_scope("b","#));
+        // The enclosing sync function is still instrumented
+        assert!(skipped.contains(r#"track_new_in_scope("fut""#));
+    }
+
+    #[test]
+    fn test_smart_pointers_flag() {
+        let source = r#"
+use std::rc::Rc;
+use std::sync::Arc;
+fn main() {
+    let a = Rc::new(1);
+    let b = Rc::clone(&a);
+    let c = Arc::new(2);
+    let d = Arc::clone(&c);
+}
+"#;
+
+        let tracked = instrument_source(source, InstrumentationConfig::default());
+        assert!(tracked.contains(r#"track_rc_new("a",Rc::new(1))"#));
+        assert!(tracked.contains(r#"track_rc_clone("b","a","#));
+        assert!(tracked.contains(r#"track_arc_new("c",Arc::new(2))"#));
+        assert!(tracked.contains(r#"track_arc_clone("d","c","#));
+
+        let config = InstrumentationConfig {
+            track_smart_pointers: false,
+            ..Default::default()
+        };
+        let plain = instrument_source(source, config);
+        assert!(plain.contains(r#"track_new_in_scope("a",0usize,Rc::new(1))"#));
+        assert!(!plain.contains("track_rc_"));
+        assert!(!plain.contains("track_arc_"));
+    }
+
+    #[test]
+    fn test_smart_pointer_paths_match_exactly() {
+        let source = r#"
+fn main() {
+    let a = std::rc::Rc::new(1);
+    let b = alloc::sync::Arc::clone(&a);
+    let c = triomphe::Arc::new(2);
+    let d = MyRc::new(3);
+    let e = Rc::new_cyclic(|_| 4);
+}
+"#;
+
+        let tracked = instrument_source(source, InstrumentationConfig::default());
+        assert!(tracked.contains(r#"track_rc_new("a",std::rc::Rc::new(1))"#));
+        assert!(tracked.contains(r#"track_arc_clone("b","a","#));
+        assert!(tracked.contains(r#"track_new_in_scope("c",0usize,triomphe::Arc::new(2))"#));
+        assert!(tracked.contains(r#"track_new_in_scope("d",0usize,MyRc::new(3))"#));
+        assert!(tracked.contains(r#"track_new_in_scope("e",0usize,Rc::new_cyclic("#));
+    }
+
+    #[test]
+    fn test_config_ignore_settings_applied() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("generated")).unwrap();
+        fs::create_dir_all(root.join("tests")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("src/schema.gen.rs"), "fn schema() {}").unwrap();
+        fs::write(root.join("generated/api.rs"), "fn api() {}").unwrap();
+        fs::write(root.join("tests/it.rs"), "fn it() {}").unwrap();
+
+        let mut config = Config::default();
+        config.ignore.patterns = vec!["*.gen.rs".into()];
+        config.ignore.directories = vec!["generated".into()];
+
+        let output_dir = TempDir::new().unwrap();
+        let instrumenter = Instrumenter::new(
+            root.to_path_buf(),
+            output_dir.path().to_path_buf(),
+            InstrumentationConfig::from_config(&config),
+        );
+        let result = instrumenter.instrument_project().unwrap();
+
+        let skipped: Vec<_> = result
+            .skipped_files
+            .iter()
+            .map(|p| p.strip_prefix(root).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped.contains(&PathBuf::from("src/schema.gen.rs")));
+        assert!(skipped.contains(&PathBuf::from("generated/api.rs")));
+        // `tests` is no longer in the ignored directories, so it is instrumented
+        assert_eq!(result.success_count, 2);
+    }
+
+    #[test]
+    fn test_ignore_directories_relative_to_source() {
+        let instrumenter = Instrumenter::new(
+            PathBuf::from("/home/user/tests/project"),
+            PathBuf::from("/out"),
+            InstrumentationConfig::default(),
+        );
+
+        // A `tests` directory above the project root does not count
+        assert!(!instrumenter.should_ignore(Path::new("/home/user/tests/project/src/main.rs")));
+        assert!(instrumenter.should_ignore(Path::new("/home/user/tests/project/tests/it.rs")));
+    }
+
+    #[test]
+    fn test_trace_tests_flag() {
+        let source = r#"
+fn add(a: i32) -> i32 { let b = a; b }
+#[test]
+fn adds() { let sum = add(1); assert_eq!(sum, 1); }
+"#;
+
+        let skipped = instrument_source(source, InstrumentationConfig::default());
+        assert!(!skipped.contains("TestTrace"));
+        assert!(!skipped.contains(r#"track_new_in_scope("sum""#));
+
+        let config = InstrumentationConfig {
+            trace_tests: true,
+            ..Default::default()
+        };
+        let traced = instrument_source(source, config);
+        // The guard opens the test body, ahead of the instrumented statements
+        assert!(traced.contains(
+            r#"fnadds(){let_borrowscope_test=borrowscope_runtime::TestTrace::start(concat!(module_path!(),"::","adds"),);letsum=borrowscope_runtime::track_new_in_scope("sum",0usize,"#
+        ));
+        assert!(traced.contains(r#"track_new_in_scope("b",0usize,a)"#));
+    }
+
+    #[test]
+    fn test_module_path() {
+        let path = |p: &str| module_path(Path::new(p));
+
+        assert!(path("src/lib.rs").is_empty());
+        assert!(path("src/main.rs").is_empty());
+        assert_eq!(path("src/parser.rs"), vec!["parser"]);
+        assert_eq!(path("src/parser/mod.rs"), vec!["parser"]);
+        assert_eq!(path("src/parser/lexer.rs"), vec!["parser", "lexer"]);
+        assert_eq!(path("src/parser/main.rs"), vec!["parser", "main"]);
+        assert!(path("src/bin/tool.rs").is_empty());
+        assert!(path("tests/it.rs").is_empty());
+    }
+
+    #[test]
+    fn test_matches_fn_path() {
+        assert!(matches_fn_path("crate::parser::*", "crate::parser::parse"));
+        assert!(matches_fn_path(
+            "crate::parser::*",
+            "crate::parser::Parser::new"
+        ));
+        assert!(!matches_fn_path("crate::parser::*", "crate::lexer::lex"));
+        assert!(matches_fn_path("parser::*", "crate::parser::parse"));
+        assert!(matches_fn_path("*::new", "crate::parser::Parser::new"));
+        assert!(matches_fn_path("crate::main", "crate::main"));
+        assert!(!matches_fn_path("crate::main", "crate::main_loop"));
+        assert!(matches_fn_path("crate::run_?", "crate::run_a"));
+    }
+
+    const SELECTION_SOURCE: &str = r#"
+fn main() { let a = 1; }
+mod parser {
+    pub fn parse() { let b = 2; }
+    pub struct Parser;
+    impl Parser {
+        pub fn new() -> Self { let c = 3; Parser }
+    }
+}
+"#;
+
+    #[test]
+    fn test_only_selects_functions() {
+        let config = InstrumentationConfig {
+            only: vec!["crate::parser::*".into()],
+            ..Default::default()
+        };
+        let output = instrument_source(SELECTION_SOURCE, config);
+
+        assert!(!output.contains(r#"track_new_in_scope("a","#));
+        assert!(output.contains(r#"track_new_in_scope("b",0usize,2)"#));
+        assert!(output.contains(r#"track_new_in_scope("c",0usize,3)"#));
+    }
+
+    #[test]
+    fn test_exclude_skips_functions() {
+        let config = InstrumentationConfig {
+            exclude: vec!["*::Parser::*".into()],
+            ..Default::default()
+        };
+        let output = instrument_source(SELECTION_SOURCE, config);
+
+        assert!(output.contains(r#"track_new_in_scope("a",0usize,1)"#));
+        assert!(output.contains(r#"track_new_in_scope("b",0usize,2)"#));
+        assert!(!output.contains(r#"track_new_in_scope("c","#));
+    }
+
+    #[test]
+    fn test_selection_uses_file_module_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let parser_dir = temp_dir.path().join("src/parser");
+        fs::create_dir_all(&parser_dir).unwrap();
+        fs::write(parser_dir.join("lexer.rs"), "fn lex() { let t = 1; }").unwrap();
+
+        let config = InstrumentationConfig {
+            only: vec!["crate::parser::lexer::lex".into()],
+            ..Default::default()
+        };
+        let instrumenter = Instrumenter::new(
+            temp_dir.path().to_path_buf(),
+            temp_dir.path().join("output"),
+            config,
+        );
+        let result = instrumenter
+            .instrument_file(&parser_dir.join("lexer.rs"))
+            .unwrap();
+
+        let content = fs::read_to_string(result).unwrap();
+        assert!(content.contains(r#"track_new_in_scope("t", 0usize, 1)"#));
+    }
+
+    #[test]
+    fn test_selection_follows_path_attributes() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        fs::create_dir_all(src.join("generated")).unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "#[path = \"generated/grammar.rs\"]\nmod parser;\n",
+        )
+        .unwrap();
+        fs::write(
+            src.join("generated/grammar.rs"),
+            "fn parse() { let t = 1; }",
+        )
+        .unwrap();
+
+        let config = InstrumentationConfig {
+            only: vec!["crate::parser::parse".into()],
+            ..Default::default()
+        };
+        let instrumenter = Instrumenter::new(
+            temp_dir.path().to_path_buf(),
+            temp_dir.path().join("output"),
+            config,
+        );
+        let result = instrumenter
+            .instrument_file(&src.join("generated/grammar.rs"))
+            .unwrap();
+
+        let content = fs::read_to_string(result).unwrap();
+        assert!(content.contains(r#"track_new_in_scope("t", 0usize, 1)"#));
+    }
 }
@@ -0,0 +1,533 @@
+//! Ownership lints configured under `[lints]`
+//!
+//! Lints are policy checks rather than errors: deep reference chains, long
+//! mutable borrows, heavily cloned `Rc`s. Each configured rule runs on the
+//! replayed [`OwnershipGraph`] through the `borrowscope-graph` query API.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use borrowscope_graph::{OwnershipGraph, Relationship};
+use serde::Serialize;
+
+use crate::config::{LintLevel, LintsConfig};
+use crate::findings::{Rule, Severity};
+
+/// Exit code bit set when a `deny` lint matches
+pub const DENY_EXIT_CODE: i32 = 16;
+
+/// A rule under `[lints]`, serialized as its key in `.borrowscope.toml`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintRule {
+    BorrowDepth,
+    MutableBorrowEvents,
+    RcClones,
+    CloneWithoutBorrow,
+    RefcellBorrows,
+}
+
+impl LintRule {
+    const ALL: [LintRule; 5] = [
+        LintRule::BorrowDepth,
+        LintRule::MutableBorrowEvents,
+        LintRule::RcClones,
+        LintRule::CloneWithoutBorrow,
+        LintRule::RefcellBorrows,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            LintRule::BorrowDepth => "borrow_depth",
+            LintRule::MutableBorrowEvents => "mutable_borrow_events",
+            LintRule::RcClones => "rc_clones",
+            LintRule::CloneWithoutBorrow => "clone_without_borrow",
+            LintRule::RefcellBorrows => "refcell_borrows",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            LintRule::BorrowDepth => "A reference chain is deeper than allowed",
+            LintRule::MutableBorrowEvents => "A mutable borrow stayed alive for too many events",
+            LintRule::RcClones => "An Rc or Arc allocation was cloned too often",
+            LintRule::CloneWithoutBorrow => "An Rc or Arc is cloned but never borrowed",
+            LintRule::RefcellBorrows => "Too many RefCell borrows were alive at once",
+        }
+    }
+
+    /// The configured level, `None` when the rule is not set
+    fn level(self, config: &LintsConfig) -> Option<LintLevel> {
+        match self {
+            LintRule::BorrowDepth => config.borrow_depth.map(|l| l.level),
+            LintRule::MutableBorrowEvents => config.mutable_borrow_events.map(|l| l.level),
+            LintRule::RcClones => config.rc_clones.map(|l| l.level),
+            LintRule::CloneWithoutBorrow => config.clone_without_borrow.map(|l| l.level),
+            LintRule::RefcellBorrows => config.refcell_borrows.map(|l| l.level),
+        }
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// A lint that matched a variable
+#[derive(Debug, Clone, Serialize)]
+pub struct LintResult {
+    pub rule: LintRule,
+    pub severity: Severity,
+    pub variable: usize,
+    pub message: String,
+}
+
+/// The enabled rules, i.e. those set to `warn` or `deny`
+pub fn rules(config: &LintsConfig) -> Vec<Rule> {
+    enabled(config)
+        .into_iter()
+        .map(|(rule, severity)| Rule {
+            id: rule.id().to_string(),
+            description: rule.description().to_string(),
+            severity,
+        })
+        .collect()
+}
+
+/// Run every enabled rule against `graph`, whose last event happened at `end`
+pub fn run(graph: &OwnershipGraph, end: u64, config: &LintsConfig) -> Vec<LintResult> {
+    let mut results = Vec::new();
+
+    for (rule, severity) in enabled(config) {
+        let matches = match rule {
+            LintRule::BorrowDepth => config
+                .borrow_depth
+                .map(|lint| borrow_depth(graph, lint.max)),
+            LintRule::MutableBorrowEvents => config
+                .mutable_borrow_events
+                .map(|lint| mutable_borrow_events(graph, end, lint.max)),
+            LintRule::RcClones => config.rc_clones.map(|lint| rc_clones(graph, lint.max)),
+            LintRule::CloneWithoutBorrow => Some(clone_without_borrow(graph)),
+            LintRule::RefcellBorrows => config
+                .refcell_borrows
+                .map(|lint| refcell_borrows(graph, lint.max)),
+        };
+
+        results.extend(
+            matches
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(variable, message)| LintResult {
+                    rule,
+                    severity,
+                    variable,
+                    message,
+                }),
+        );
+    }
+
+    results
+}
+
+fn enabled(config: &LintsConfig) -> Vec<(LintRule, Severity)> {
+    LintRule::ALL
+        .into_iter()
+        .filter_map(|rule| match rule.level(config)? {
+            LintLevel::Allow => None,
+            LintLevel::Warn => Some((rule, Severity::Warning)),
+            LintLevel::Deny => Some((rule, Severity::Error)),
+        })
+        .collect()
+}
+
+/// References more than `max` levels deep, reported at the end of each chain
+fn borrow_depth(graph: &OwnershipGraph, max: u64) -> Vec<(usize, String)> {
+    let references: HashSet<usize> = graph.find_references().iter().map(|v| v.id).collect();
+
+    fn depth(
+        graph: &OwnershipGraph,
+        id: usize,
+        references: &HashSet<usize>,
+        depths: &mut HashMap<usize, u64>,
+        visiting: &mut HashSet<usize>,
+    ) -> u64 {
+        if let Some(&known) = depths.get(&id) {
+            return known;
+        }
+        if !references.contains(&id) || !visiting.insert(id) {
+            return 0;
+        }
+        let deepest = graph
+            .borrows(id)
+            .iter()
+            .map(|owner| depth(graph, owner.id, references, depths, visiting))
+            .max()
+            .unwrap_or(0);
+        visiting.remove(&id);
+        depths.insert(id, deepest + 1);
+        deepest + 1
+    }
+
+    let mut depths = HashMap::new();
+    for &id in &references {
+        depth(graph, id, &references, &mut depths, &mut HashSet::new());
+    }
+
+    let mut results: Vec<(usize, String)> = depths
+        .iter()
+        .filter(|(_, &d)| d > max)
+        .filter(|(&id, &d)| {
+            !graph
+                .borrowers_of(id)
+                .iter()
+                .any(|b| depths.get(&b.id).is_some_and(|&bd| bd > d))
+        })
+        .filter_map(|(&id, &d)| {
+            let var = graph.find_by_id(id)?;
+            Some((
+                id,
+                format!(
+                    "'{}' is a reference {} levels deep (max {})",
+                    var.name, d, max
+                ),
+            ))
+        })
+        .collect();
+    results.sort();
+    results
+}
+
+/// Mutable references alive for more than `max` events; those never dropped
+/// are measured up to `end`
+fn mutable_borrow_events(graph: &OwnershipGraph, end: u64, max: u64) -> Vec<(usize, String)> {
+    let mut results: Vec<(usize, String)> = graph
+        .find_mutable_references()
+        .into_iter()
+        .filter_map(|var| {
+            let lived = var.dropped_at.unwrap_or(end).saturating_sub(var.created_at);
+            let state = if var.dropped_at.is_some() {
+                "was alive for"
+            } else {
+                "is still alive after"
+            };
+            (lived > max).then(|| {
+                (
+                    var.id,
+                    format!(
+                        "mutable borrow '{}' {} {} events (max {})",
+                        var.name, state, lived, max
+                    ),
+                )
+            })
+        })
+        .collect();
+    results.sort();
+    results
+}
+
+/// Clone edges, clone -> the value it was cloned from
+fn clone_edges(graph: &OwnershipGraph) -> Vec<(usize, usize)> {
+    graph
+        .export()
+        .edges
+        .into_iter()
+        .filter(|edge| {
+            matches!(
+                edge.relationship,
+                Relationship::RcClone { .. } | Relationship::ArcClone { .. }
+            )
+        })
+        .map(|edge| (edge.from_id, edge.to_id))
+        .collect()
+}
+
+/// Rc/Arc allocations with more than `max` clones, counted from the original value
+fn rc_clones(graph: &OwnershipGraph, max: u64) -> Vec<(usize, String)> {
+    let edges = clone_edges(graph);
+    let clones: HashSet<usize> = edges.iter().map(|&(clone, _)| clone).collect();
+    let originals: HashSet<usize> = edges
+        .iter()
+        .map(|&(_, original)| original)
+        .filter(|original| !clones.contains(original))
+        .collect();
+
+    let mut results: Vec<(usize, String)> = originals
+        .into_iter()
+        .filter_map(|id| {
+            let count = graph
+                .find_transitive_borrowers(id)
+                .iter()
+                .filter(|v| clones.contains(&v.id))
+                .count() as u64;
+            let var = graph.find_by_id(id)?;
+            (count > max).then(|| {
+                (
+                    id,
+                    format!("'{}' was cloned {} times (max {})", var.name, count, max),
+                )
+            })
+        })
+        .collect();
+    results.sort();
+    results
+}
+
+/// Values that were cloned without ever being borrowed
+fn clone_without_borrow(graph: &OwnershipGraph) -> Vec<(usize, String)> {
+    let edges = graph.export().edges;
+    let borrowed: HashSet<usize> = edges
+        .iter()
+        .filter(|edge| {
+            matches!(
+                edge.relationship,
+                Relationship::BorrowsImmut { .. }
+                    | Relationship::BorrowsMut { .. }
+                    | Relationship::RefCellBorrow { .. }
+            )
+        })
+        .map(|edge| edge.to_id)
+        .collect();
+
+    let mut cloned: BTreeMap<usize, usize> = BTreeMap::new();
+    for (_, original) in clone_edges(graph) {
+        *cloned.entry(original).or_default() += 1;
+    }
+
+    cloned
+        .into_iter()
+        .filter(|(id, _)| !borrowed.contains(id))
+        .filter_map(|(id, count)| {
+            let var = graph.find_by_id(id)?;
+            Some((
+                id,
+                format!(
+                    "'{}' is cloned {} time(s) but never borrowed; a borrow may be enough",
+                    var.name, count
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// RefCells with more than `max` borrows alive at the same time
+fn refcell_borrows(graph: &OwnershipGraph, max: u64) -> Vec<(usize, String)> {
+    let mut borrow_times: BTreeMap<usize, Vec<u64>> = BTreeMap::new();
+    for edge in graph.export().edges {
+        if let Relationship::RefCellBorrow { at, .. } = edge.relationship {
+            borrow_times.entry(edge.to_id).or_default().push(at);
+        }
+    }
+
+    borrow_times
+        .into_iter()
+        .filter_map(|(id, times)| {
+            let peak = times
+                .iter()
+                .map(|&at| {
+                    graph
+                        .active_borrows_at(id, at)
+                        .iter()
+                        .filter(|(_, rel)| matches!(rel, Relationship::RefCellBorrow { .. }))
+                        .count() as u64
+                })
+                .max()
+                .unwrap_or(0);
+            let var = graph.find_by_id(id)?;
+            (peak > max).then(|| {
+                (
+                    id,
+                    format!(
+                        "'{}' had {} RefCell borrows alive at once (max {})",
+                        var.name, peak, max
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LimitLint, Lint};
+    use borrowscope_graph::Variable;
+
+    fn var(graph: &mut OwnershipGraph, id: usize, name: &str, type_name: &str, life: (u64, u64)) {
+        graph.add_variable(Variable {
+            id,
+            name: name.into(),
+            type_name: type_name.into(),
+            created_at: life.0,
+            dropped_at: Some(life.1),
+            scope_depth: 0,
+        });
+    }
+
+    fn limit(max: u64, level: LintLevel) -> Option<LimitLint> {
+        Some(LimitLint { max, level })
+    }
+
+    #[test]
+    fn test_no_rules_configured() {
+        let config = LintsConfig::default();
+        assert!(rules(&config).is_empty());
+        assert!(run(&OwnershipGraph::new(), 0, &config).is_empty());
+    }
+
+    #[test]
+    fn test_allow_disables_rule() {
+        let config = LintsConfig {
+            borrow_depth: limit(0, LintLevel::Allow),
+            rc_clones: limit(1, LintLevel::Deny),
+            ..Default::default()
+        };
+        let rules = rules(&config);
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].id, "rc_clones");
+        assert_eq!(rules[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_borrow_depth_reports_chain_end() {
+        let mut graph = OwnershipGraph::new();
+        var(&mut graph, 1, "x", "i32", (0, 100));
+        var(&mut graph, 2, "r1", "&i32", (1, 90));
+        var(&mut graph, 3, "r2", "&&i32", (2, 80));
+        var(&mut graph, 4, "r3", "&&&i32", (3, 70));
+        graph.add_borrow(2, 1, false, 1);
+        graph.add_borrow(3, 2, false, 2);
+        graph.add_borrow(4, 3, false, 3);
+
+        let config = LintsConfig {
+            borrow_depth: limit(2, LintLevel::Warn),
+            ..Default::default()
+        };
+        let results = run(&graph, 100, &config);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].variable, 4);
+        assert_eq!(
+            results[0].message,
+            "'r3' is a reference 3 levels deep (max 2)"
+        );
+        assert_eq!(results[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_mutable_borrow_events() {
+        let mut graph = OwnershipGraph::new();
+        var(&mut graph, 1, "v", "Vec<i32>", (0, 100));
+        var(&mut graph, 2, "w", "&mut Vec<i32>", (10, 60));
+        var(&mut graph, 3, "short", "&mut Vec<i32>", (70, 75));
+        var(&mut graph, 4, "r", "&Vec<i32>", (0, 90));
+        graph.add_borrow(2, 1, true, 10);
+        graph.add_borrow(3, 1, true, 70);
+        graph.add_borrow(4, 1, false, 0);
+
+        let config = LintsConfig {
+            mutable_borrow_events: limit(20, LintLevel::Deny),
+            ..Default::default()
+        };
+        let results = run(&graph, 100, &config);
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].message,
+            "mutable borrow 'w' was alive for 50 events (max 20)"
+        );
+        assert_eq!(results[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_mutable_borrow_events_measures_undropped_borrows_to_end() {
+        let mut graph = OwnershipGraph::new();
+        var(&mut graph, 1, "v", "Vec<i32>", (0, 100));
+        graph.add_variable(Variable {
+            id: 2,
+            name: "w".into(),
+            type_name: "&mut Vec<i32>".into(),
+            created_at: 10,
+            dropped_at: None,
+            scope_depth: 0,
+        });
+        graph.add_borrow(2, 1, true, 10);
+
+        let config = |max| LintsConfig {
+            mutable_borrow_events: limit(max, LintLevel::Warn),
+            ..Default::default()
+        };
+        let results = run(&graph, 100, &config(20));
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].message,
+            "mutable borrow 'w' is still alive after 90 events (max 20)"
+        );
+        assert!(run(&graph, 100, &config(u64::MAX)).is_empty());
+    }
+
+    #[test]
+    fn test_rc_clones_counts_clones_of_clones() {
+        let mut graph = OwnershipGraph::new();
+        var(&mut graph, 1, "data", "Rc<i32>", (0, 100));
+        var(&mut graph, 2, "a", "Rc<i32>", (1, 100));
+        var(&mut graph, 3, "b", "Rc<i32>", (2, 100));
+        var(&mut graph, 4, "c", "Rc<i32>", (3, 100));
+        graph.add_rc_clone(2, 1, 2, 1);
+        graph.add_rc_clone(3, 2, 3, 2);
+        graph.add_rc_clone(4, 1, 4, 3);
+
+        let config = |max| LintsConfig {
+            rc_clones: limit(max, LintLevel::Warn),
+            ..Default::default()
+        };
+        let results = run(&graph, 100, &config(2));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].message, "'data' was cloned 3 times (max 2)");
+        assert!(run(&graph, 100, &config(3)).is_empty());
+    }
+
+    #[test]
+    fn test_clone_without_borrow() {
+        let mut graph = OwnershipGraph::new();
+        var(&mut graph, 1, "cloned", "Rc<i32>", (0, 100));
+        var(&mut graph, 2, "copy", "Rc<i32>", (1, 100));
+        var(&mut graph, 3, "shared", "Rc<i32>", (2, 100));
+        var(&mut graph, 4, "copy2", "Rc<i32>", (3, 100));
+        var(&mut graph, 5, "r", "&Rc<i32>", (4, 100));
+        graph.add_rc_clone(2, 1, 2, 1);
+        graph.add_rc_clone(4, 3, 2, 3);
+        graph.add_borrow(5, 3, false, 4);
+
+        let config = LintsConfig {
+            clone_without_borrow: Some(Lint::default()),
+            ..Default::default()
+        };
+        let results = run(&graph, 100, &config);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].variable, 1);
+        assert_eq!(results[0].rule, LintRule::CloneWithoutBorrow);
+    }
+
+    #[test]
+    fn test_refcell_borrows_peak() {
+        let mut graph = OwnershipGraph::new();
+        var(&mut graph, 1, "cell", "RefCell<i32>", (0, 100));
+        var(&mut graph, 2, "b1", "Ref<_>", (10, 50));
+        var(&mut graph, 3, "b2", "Ref<_>", (20, 40));
+        var(&mut graph, 4, "b3", "Ref<_>", (60, 70));
+        graph.add_refcell_borrow(2, 1, false, 10);
+        graph.add_refcell_borrow(3, 1, false, 20);
+        graph.add_refcell_borrow(4, 1, false, 60);
+
+        let config = |max| LintsConfig {
+            refcell_borrows: limit(max, LintLevel::Deny),
+            ..Default::default()
+        };
+        let results = run(&graph, 100, &config(1));
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].message,
+            "'cell' had 2 RefCell borrows alive at once (max 1)"
+        );
+        assert!(run(&graph, 100, &config(2)).is_empty());
+    }
+}
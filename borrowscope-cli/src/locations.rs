@@ -0,0 +1,420 @@
+//! Source locations for trace findings
+//!
+//! Not every runtime event records where its variable was declared.
+//! [`SourceIndex`] parses a project's sources and remembers the `let` binding
+//! of every name in every function, so reports can point at the line a
+//! variable came from.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+use walkdir::WalkDir;
+
+use crate::instrumentation::extract_pattern_name;
+use crate::modules::ModuleTree;
+
+/// A position in a project file, relative to the indexed root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceLocation {
+    pub file: PathBuf,
+    /// 1-based line
+    pub line: usize,
+    /// 1-based column
+    pub column: usize,
+}
+
+impl SourceLocation {
+    /// Parse a `file:line[:column]` location as the runtime records it
+    pub fn parse(location: &str) -> Option<Self> {
+        let mut parts = location.rsplitn(3, ':');
+        let last = parts.next()?.parse().ok()?;
+        let middle = parts.next()?;
+        let (file, line, column) = match (middle.parse(), parts.next()) {
+            (Ok(line), Some(file)) => (file, line, last),
+            _ => (location.rsplit_once(':')?.0, last, 1),
+        };
+        if file.is_empty() || line == 0 || column == 0 {
+            return None;
+        }
+        Some(Self {
+            file: PathBuf::from(file),
+            line,
+            column,
+        })
+    }
+
+    /// The file path with `/` separators, as SARIF and JUnit expect
+    pub fn uri(&self) -> String {
+        self.file
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.uri(), self.line, self.column)
+    }
+}
+
+/// Functions and `let` bindings of every Rust file under a directory
+#[derive(Debug, Default)]
+pub struct SourceIndex {
+    functions: Vec<Declaration>,
+    bindings: Vec<Binding>,
+}
+
+/// A function item, named like the instrumenter's `only` globs
+#[derive(Debug)]
+pub struct Declaration {
+    /// Full path starting with `crate`, e.g. `crate::tests::parses`
+    pub path: String,
+    /// Path of the module the function is in, e.g. `crate::tests`; unlike
+    /// `path` it leaves out impl and trait names
+    pub module: String,
+    /// Where the function's name is
+    pub location: SourceLocation,
+    /// First line of the item, including its attributes
+    pub start_line: usize,
+    /// Last line of the item
+    pub end_line: usize,
+    /// Byte range of the item in its file
+    pub bytes: Range<usize>,
+}
+
+#[derive(Debug)]
+struct Binding {
+    function: String,
+    name: String,
+    location: SourceLocation,
+}
+
+impl SourceIndex {
+    /// Index the sources under `root`; files that fail to read or parse are skipped
+    pub fn scan(root: &Path) -> Self {
+        let mut index = Self::default();
+        let mut trees: HashMap<PathBuf, ModuleTree> = HashMap::new();
+
+        let sources = WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|entry| {
+                let name = entry.file_name().to_string_lossy();
+                entry.depth() == 0 || !(name.starts_with('.') || name == "target")
+            })
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "rs"));
+
+        for entry in sources {
+            let path = entry.path();
+            let Ok(content) = fs::read_to_string(path) else {
+                continue;
+            };
+            let Ok(file) = syn::parse_file(&content) else {
+                continue;
+            };
+
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            let crate_dir = path
+                .ancestors()
+                .skip(1)
+                .find(|dir| dir.join("Cargo.toml").exists())
+                .unwrap_or(root);
+            let module = trees
+                .entry(crate_dir.to_path_buf())
+                .or_insert_with(|| ModuleTree::scan(crate_dir))
+                .module_of(path.strip_prefix(crate_dir).unwrap_or(relative));
+
+            index.add_file(relative, &module, &file);
+        }
+
+        log::debug!(
+            "Indexed {} functions and {} bindings under {}",
+            index.functions.len(),
+            index.bindings.len(),
+            root.display()
+        );
+        index
+    }
+
+    /// Index a single parsed file whose items live in `module`
+    pub fn file(relative: &Path, module: &[String], file: &syn::File) -> Self {
+        let mut index = Self::default();
+        index.add_file(relative, module, file);
+        index
+    }
+
+    fn add_file(&mut self, relative: &Path, module: &[String], file: &syn::File) {
+        let mut collector = BindingCollector {
+            file: relative.to_path_buf(),
+            offset: file.shebang.as_ref().map_or(0, String::len),
+            path: std::iter::once("crate".to_string())
+                .chain(module.iter().cloned())
+                .collect(),
+            modules: module.len() + 1,
+            function: None,
+            index: self,
+        };
+        collector.visit_file(file);
+    }
+
+    /// Every indexed function, in source order within each file
+    pub fn functions(&self) -> &[Declaration] {
+        &self.functions
+    }
+
+    /// Where the function named `path` is declared.
+    ///
+    /// The crate segment is ignored, so `app::tests::parses` (as `module_path!`
+    /// writes it) finds `crate::tests::parses`.
+    pub fn function(&self, path: &str) -> Option<&SourceLocation> {
+        self.functions
+            .iter()
+            .find(|declaration| same_item(&declaration.path, path))
+            .map(|declaration| &declaration.location)
+    }
+
+    /// Path of the module the function named `path` is declared in
+    pub fn module_of(&self, path: &str) -> Option<&str> {
+        self.functions
+            .iter()
+            .find(|declaration| same_item(&declaration.path, path))
+            .map(|declaration| declaration.module.as_str())
+    }
+
+    /// Path of the innermost function whose body contains `location`.
+    ///
+    /// Runtime locations are relative to the crate they were recorded in, so
+    /// files match when either path ends with the other.
+    pub fn function_at(&self, location: &SourceLocation) -> Option<&str> {
+        self.functions
+            .iter()
+            .filter(|declaration| {
+                let file = &declaration.location.file;
+                (file.ends_with(&location.file) || location.file.ends_with(file))
+                    && (declaration.location.line..=declaration.end_line).contains(&location.line)
+            })
+            .max_by_key(|declaration| declaration.location.line)
+            .map(|declaration| declaration.path.as_str())
+    }
+
+    /// The `let` binding of `name` inside `function`, or without a function
+    /// the only binding of that name; a name bound in several places could be
+    /// any of them
+    pub fn binding(&self, function: Option<&str>, name: &str) -> Option<&SourceLocation> {
+        let mut bindings = self.bindings.iter().filter(|binding| binding.name == name);
+        let binding = match function {
+            Some(function) => bindings.find(|binding| same_item(&binding.function, function)),
+            None => bindings.next().filter(|_| bindings.next().is_none()),
+        };
+        binding.map(|binding| &binding.location)
+    }
+}
+
+/// Compare two item paths, ignoring the crate they start with
+fn same_item(a: &str, b: &str) -> bool {
+    match (a.split_once("::"), b.split_once("::")) {
+        (Some((_, a)), Some((_, b))) => a == b,
+        _ => false,
+    }
+}
+
+struct BindingCollector<'a> {
+    file: PathBuf,
+    /// syn parses files after their shebang line
+    offset: usize,
+    path: Vec<String>,
+    /// How many leading segments of `path` name modules
+    modules: usize,
+    /// Path of the function being visited
+    function: Option<String>,
+    index: &'a mut SourceIndex,
+}
+
+impl BindingCollector<'_> {
+    fn location(&self, span: proc_macro2::Span) -> SourceLocation {
+        let start = span.start();
+        SourceLocation {
+            file: self.file.clone(),
+            line: start.line,
+            column: start.column + 1,
+        }
+    }
+
+    fn function(
+        &mut self,
+        name: &syn::Ident,
+        item: proc_macro2::Span,
+        visit: impl FnOnce(&mut Self),
+    ) {
+        let path = format!("{}::{}", self.path.join("::"), name);
+        let bytes = item.byte_range();
+        self.index.functions.push(Declaration {
+            path: path.clone(),
+            module: self.path[..self.modules].join("::"),
+            location: self.location(name.span()),
+            start_line: item.start().line,
+            end_line: item.end().line,
+            bytes: bytes.start + self.offset..bytes.end + self.offset,
+        });
+
+        let outer = self.function.replace(path);
+        visit(self);
+        self.function = outer;
+    }
+
+    fn scoped(&mut self, name: String, visit: impl FnOnce(&mut Self)) {
+        self.path.push(name);
+        visit(self);
+        self.path.pop();
+    }
+}
+
+impl<'ast> Visit<'ast> for BindingCollector<'_> {
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        self.function(&node.sig.ident, node.span(), |visitor| {
+            syn::visit::visit_item_fn(visitor, node)
+        });
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        self.function(&node.sig.ident, node.span(), |visitor| {
+            syn::visit::visit_impl_item_fn(visitor, node)
+        });
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast syn::TraitItemFn) {
+        self.function(&node.sig.ident, node.span(), |visitor| {
+            syn::visit::visit_trait_item_fn(visitor, node)
+        });
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast syn::ItemMod) {
+        let modules = std::mem::replace(&mut self.modules, self.path.len() + 1);
+        self.scoped(node.ident.to_string(), |visitor| {
+            syn::visit::visit_item_mod(visitor, node)
+        });
+        self.modules = modules;
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        let name = match &*node.self_ty {
+            syn::Type::Path(ty) => ty
+                .path
+                .segments
+                .last()
+                .map(|seg| seg.ident.to_string())
+                .unwrap_or_default(),
+            other => quote::quote!(#other).to_string(),
+        };
+        self.scoped(name, |visitor| syn::visit::visit_item_impl(visitor, node));
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast syn::ItemTrait) {
+        self.scoped(node.ident.to_string(), |visitor| {
+            syn::visit::visit_item_trait(visitor, node)
+        });
+    }
+
+    fn visit_local(&mut self, node: &'ast syn::Local) {
+        if let Some(function) = &self.function {
+            let binding = Binding {
+                function: function.clone(),
+                name: extract_pattern_name(&node.pat),
+                location: self.location(node.pat.span()),
+            };
+            self.index.bindings.push(binding);
+        }
+        syn::visit::visit_local(self, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn project() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(
+            dir.path().join("src/lib.rs"),
+            "pub fn build() {\n    let data = vec![1];\n}\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn parses() {\n        let data = 2;\n    }\n}\n\nfn helper() {\n    let total = 3;\n}\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_binding_in_function() {
+        let dir = project();
+        let index = SourceIndex::scan(dir.path());
+
+        let location = index.binding(Some("app::tests::parses"), "data").unwrap();
+        assert_eq!(location.uri(), "src/lib.rs");
+        assert_eq!((location.line, location.column), (9, 13));
+
+        // Bound in both functions
+        assert!(index.binding(None, "data").is_none());
+        let location = index.binding(None, "total").unwrap();
+        assert_eq!(location.line, 14);
+        assert!(index.binding(None, "missing").is_none());
+    }
+
+    #[test]
+    fn test_function_ignores_crate_name() {
+        let dir = project();
+        let index = SourceIndex::scan(dir.path());
+
+        let location = index.function("app::tests::parses").unwrap();
+        assert_eq!(location.to_string(), "src/lib.rs:8:8");
+        assert!(index.function("app::tests::other").is_none());
+    }
+
+    #[test]
+    fn test_function_at_location() {
+        let dir = project();
+        let index = SourceIndex::scan(dir.path());
+
+        let inside = SourceLocation::parse("src/lib.rs:9:9").unwrap();
+        assert_eq!(index.function_at(&inside), Some("crate::tests::parses"));
+
+        // Paths recorded relative to a workspace still match
+        let nested = SourceLocation::parse("app/src/lib.rs:2:5").unwrap();
+        assert_eq!(index.function_at(&nested), Some("crate::build"));
+
+        let between = SourceLocation::parse("src/lib.rs:4:1").unwrap();
+        assert!(index.function_at(&between).is_none());
+    }
+
+    #[test]
+    fn test_parse_runtime_locations() {
+        let location = SourceLocation::parse("src/main.rs:12:5").unwrap();
+        assert_eq!(location.to_string(), "src/main.rs:12:5");
+
+        let location = SourceLocation::parse("src/main.rs:3").unwrap();
+        assert_eq!((location.line, location.column), (3, 1));
+
+        assert!(SourceLocation::parse("unknown").is_none());
+        assert!(SourceLocation::parse(":3").is_none());
+    }
+
+    #[test]
+    fn test_skips_target_and_unparsable_files() {
+        let dir = project();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join("target/gen.rs"), "fn hidden() {}").unwrap();
+        fs::write(dir.path().join("src/broken.rs"), "fn broken( {").unwrap();
+
+        let index = SourceIndex::scan(dir.path());
+        assert!(index.function("app::hidden").is_none());
+        assert!(index.function("app::build").is_some());
+    }
+}
@@ -2,17 +2,31 @@
 //!
 //! Command-line interface for analyzing and visualizing Rust ownership and borrowing.
 
+mod aliasing;
 mod async_utils;
+mod cache;
 mod cargo;
 mod cli;
 mod commands;
 mod config;
 mod error;
+mod findings;
+mod gantt;
 mod graphviz;
 mod instrumentation;
+mod lints;
+mod locations;
+mod manifest;
+mod markup;
+mod modules;
 mod output;
+mod pointers;
 mod progress;
+mod raster;
 mod server;
+mod source_map;
+mod splice;
+mod trace;
 mod utils;
 
 use clap::Parser;
@@ -24,7 +38,7 @@ use crate::error::{print_error, Result};
 fn main() {
     if let Err(e) = run() {
         print_error(&e);
-        process::exit(1);
+        process::exit(e.exit_code());
     }
 }
 
@@ -0,0 +1,582 @@
+//! Cargo manifest rewriting for instrumented projects
+//!
+//! Instrumented sources call into `borrowscope-runtime`, so the copied crate
+//! needs a manifest that depends on it with the `track` feature enabled.
+//! Everything else in the original manifest is kept as-is, except that
+//! relative `path` dependencies leaving the copied tree are made absolute so
+//! they still resolve from the temporary build directory.
+
+use anyhow::{Context, Result};
+use std::path::{Component, Path, PathBuf};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, TableLike, Value};
+
+/// Name of the runtime crate injected into instrumented manifests
+pub const RUNTIME_CRATE: &str = "borrowscope-runtime";
+
+/// Feature that turns the runtime's tracking calls on
+pub const TRACK_FEATURE: &str = "track";
+
+/// Environment variable overriding the runtime location
+pub const RUNTIME_PATH_ENV: &str = "BORROWSCOPE_RUNTIME_PATH";
+
+/// Dependency tables that may contain `path` entries
+const DEPENDENCY_TABLES: &[&str] = &[
+    "dependencies",
+    "dev-dependencies",
+    "dev_dependencies",
+    "build-dependencies",
+    "build_dependencies",
+];
+
+/// Where the injected runtime dependency comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeDependency {
+    /// A local checkout of `borrowscope-runtime`
+    Path(PathBuf),
+    /// A published version
+    Version(String),
+}
+
+impl RuntimeDependency {
+    /// `$BORROWSCOPE_RUNTIME_PATH`, then the runtime next to this CLI's
+    /// sources, then the published version matching this CLI.
+    pub fn detect() -> Self {
+        if let Some(path) = std::env::var_os(RUNTIME_PATH_ENV) {
+            return Self::Path(PathBuf::from(path));
+        }
+
+        let sibling = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .map(|p| p.join(RUNTIME_CRATE));
+        match sibling {
+            Some(path) if path.join("Cargo.toml").exists() => Self::Path(path),
+            _ => Self::Version(env!("CARGO_PKG_VERSION").to_string()),
+        }
+    }
+
+    fn to_inline_table(&self) -> InlineTable {
+        let mut table = InlineTable::new();
+        match self {
+            Self::Path(path) => {
+                table.insert("path", Value::from(path.to_string_lossy().as_ref()));
+            }
+            Self::Version(version) => {
+                table.insert("version", Value::from(version.as_str()));
+            }
+        }
+        table.insert("features", Value::Array(Array::from_iter([TRACK_FEATURE])));
+        table
+    }
+}
+
+/// Produces the manifest of an instrumented copy of a crate
+pub struct ManifestRewriter {
+    /// Directory of the original manifest; relative paths resolve against it
+    source_dir: PathBuf,
+    /// Root of the copied tree; relative paths inside it are left alone
+    root: PathBuf,
+    runtime: RuntimeDependency,
+    inject_runtime: bool,
+}
+
+impl ManifestRewriter {
+    /// Create a rewriter for the crate in `source_dir`
+    pub fn new(source_dir: PathBuf) -> Self {
+        let source_dir = source_dir.canonicalize().unwrap_or(source_dir);
+        Self {
+            root: source_dir.clone(),
+            source_dir,
+            runtime: RuntimeDependency::detect(),
+            inject_runtime: true,
+        }
+    }
+
+    /// Root of the copied tree when the crate is a workspace member.
+    ///
+    /// The member keeps belonging to the copied workspace, and path
+    /// dependencies on other crates inside it keep pointing into the copy.
+    pub fn root(mut self, root: PathBuf) -> Self {
+        self.root = root.canonicalize().unwrap_or(root);
+        self
+    }
+
+    /// Whether to add the runtime dependency (off for uninstrumented members)
+    pub fn inject_runtime(mut self, inject: bool) -> Self {
+        self.inject_runtime = inject;
+        self
+    }
+
+    /// Use a specific runtime dependency instead of the detected one
+    #[cfg(test)]
+    pub fn runtime(mut self, runtime: RuntimeDependency) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
+    /// Read `Cargo.toml` from the source directory and write the patched
+    /// manifest into `output_dir`
+    pub fn write(&self, output_dir: &Path) -> Result<PathBuf> {
+        let source = self.source_dir.join("Cargo.toml");
+        let contents = std::fs::read_to_string(&source)
+            .with_context(|| format!("Failed to read manifest: {}", source.display()))?;
+
+        let rewritten = self.rewrite(&contents)?;
+
+        let output = output_dir.join("Cargo.toml");
+        // Leave an up-to-date manifest untouched so cargo keeps its fingerprints
+        if std::fs::read_to_string(&output).is_ok_and(|existing| existing == rewritten) {
+            return Ok(output);
+        }
+        std::fs::write(&output, rewritten)
+            .with_context(|| format!("Failed to write manifest: {}", output.display()))?;
+        Ok(output)
+    }
+
+    /// Patch manifest contents, preserving formatting and comments
+    pub fn rewrite(&self, manifest: &str) -> Result<String> {
+        let mut doc: DocumentMut = manifest.parse().context("Failed to parse Cargo.toml")?;
+        let has_build_script = self.has_build_script(&doc);
+
+        // Relative paths must keep pointing at the original locations
+        for name in DEPENDENCY_TABLES {
+            if let Some(deps) = doc.get_mut(name).and_then(Item::as_table_like_mut) {
+                self.absolutize_paths(deps);
+            }
+        }
+        if let Some(targets) = doc.get_mut("target").and_then(Item::as_table_like_mut) {
+            for (_, target) in targets.iter_mut() {
+                let Some(target) = target.as_table_like_mut() else {
+                    continue;
+                };
+                for name in DEPENDENCY_TABLES {
+                    if let Some(deps) = target.get_mut(name).and_then(Item::as_table_like_mut) {
+                        self.absolutize_paths(deps);
+                    }
+                }
+            }
+        }
+        if let Some(deps) = doc
+            .get_mut("workspace")
+            .and_then(|w| w.get_mut("dependencies"))
+            .and_then(Item::as_table_like_mut)
+        {
+            self.absolutize_paths(deps);
+        }
+        if let Some(patches) = doc.get_mut("patch").and_then(Item::as_table_like_mut) {
+            for (_, registry) in patches.iter_mut() {
+                if let Some(deps) = registry.as_table_like_mut() {
+                    self.absolutize_paths(deps);
+                }
+            }
+        }
+
+        if self.inject_runtime && doc.contains_key("package") {
+            self.add_runtime(&mut doc, "dependencies")?;
+            if has_build_script {
+                self.add_runtime(&mut doc, "build-dependencies")?;
+            }
+        }
+
+        // A copied standalone crate lives outside any original workspace,
+        // so make it its own root
+        if self.source_dir == self.root {
+            if let Some(package) = doc.get_mut("package").and_then(Item::as_table_like_mut) {
+                package.remove("workspace");
+            }
+            if !doc.contains_key("workspace") {
+                doc.insert("workspace", Item::Table(Table::new()));
+            }
+        }
+
+        Ok(doc.to_string())
+    }
+
+    fn has_build_script(&self, doc: &DocumentMut) -> bool {
+        match doc.get("package").and_then(|p| p.get("build")) {
+            Some(build) => build.as_bool() != Some(false),
+            None => self.source_dir.join("build.rs").exists(),
+        }
+    }
+
+    fn absolutize_paths(&self, deps: &mut dyn TableLike) {
+        for (_, dep) in deps.iter_mut() {
+            let Some(dep) = dep.as_table_like_mut() else {
+                continue;
+            };
+            let Some(path) = dep.get("path").and_then(Item::as_str) else {
+                continue;
+            };
+            if Path::new(path).is_absolute() {
+                continue;
+            }
+
+            let joined = self.source_dir.join(path);
+            let absolute = joined.canonicalize().unwrap_or_else(|_| normalize(&joined));
+            if absolute.starts_with(&self.root) {
+                continue;
+            }
+            dep.insert(
+                "path",
+                toml_edit::value(absolute.to_string_lossy().as_ref()),
+            );
+        }
+    }
+
+    /// Add the runtime to a dependency table, or enable `track` on an
+    /// existing entry for it
+    fn add_runtime(&self, doc: &mut DocumentMut, table: &str) -> Result<()> {
+        let deps = doc
+            .entry(table)
+            .or_insert_with(|| Item::Table(Table::new()))
+            .as_table_like_mut()
+            .with_context(|| format!("`{}` in Cargo.toml is not a table", table))?;
+
+        let existing = deps
+            .iter()
+            .find(|(key, item)| {
+                *key == RUNTIME_CRATE
+                    || item.get("package").and_then(Item::as_str) == Some(RUNTIME_CRATE)
+            })
+            .map(|(key, _)| key.to_string());
+
+        let Some(key) = existing else {
+            deps.insert(
+                RUNTIME_CRATE,
+                Item::Value(Value::InlineTable(self.runtime.to_inline_table())),
+            );
+            return Ok(());
+        };
+
+        let item = deps.get_mut(&key).expect("key was just found");
+        // `borrowscope-runtime = "0.1"` -> `{ version = "0.1", features = [...] }`
+        if let Some(version) = item.as_str().map(str::to_string) {
+            let mut table = InlineTable::new();
+            table.insert("version", Value::from(version));
+            *item = Item::Value(Value::InlineTable(table));
+        }
+
+        let dep = item
+            .as_table_like_mut()
+            .with_context(|| format!("Unsupported `{}` dependency entry", key))?;
+        let features = dep
+            .entry("features")
+            .or_insert(toml_edit::value(Array::new()))
+            .as_array_mut()
+            .with_context(|| format!("`{}.features` is not an array", key))?;
+        if !features.iter().any(|f| f.as_str() == Some(TRACK_FEATURE)) {
+            features.push(TRACK_FEATURE);
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolve `.` and `..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn rewriter(dir: &Path) -> ManifestRewriter {
+        ManifestRewriter::new(dir.to_path_buf()).runtime(RuntimeDependency::Path(PathBuf::from(
+            "/opt/borrowscope-runtime",
+        )))
+    }
+
+    fn parse(manifest: &str) -> toml::Table {
+        manifest.parse().unwrap()
+    }
+
+    const BASIC: &str = r#"[package]
+name = "demo"
+version = "0.1.0"
+edition = "2021"
+
+# Keep this comment
+[features]
+default = ["fast"]
+fast = []
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+"#;
+
+    #[test]
+    fn test_injects_runtime_with_track_feature() {
+        let temp_dir = TempDir::new().unwrap();
+        let out = parse(&rewriter(temp_dir.path()).rewrite(BASIC).unwrap());
+
+        let runtime = &out["dependencies"][RUNTIME_CRATE];
+        assert_eq!(runtime["path"].as_str(), Some("/opt/borrowscope-runtime"));
+        assert_eq!(runtime["features"][0].as_str(), Some("track"));
+    }
+
+    #[test]
+    fn test_preserves_features_dependencies_and_comments() {
+        let temp_dir = TempDir::new().unwrap();
+        let rewritten = rewriter(temp_dir.path()).rewrite(BASIC).unwrap();
+        let out = parse(&rewritten);
+
+        assert!(rewritten.contains("# Keep this comment"));
+        assert_eq!(out["features"]["default"][0].as_str(), Some("fast"));
+        assert_eq!(
+            out["dependencies"]["serde"]["version"].as_str(),
+            Some("1.0")
+        );
+        assert_eq!(out["package"]["name"].as_str(), Some("demo"));
+    }
+
+    #[test]
+    fn test_published_runtime_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let rewritten = ManifestRewriter::new(temp_dir.path().to_path_buf())
+            .runtime(RuntimeDependency::Version("0.1.0".into()))
+            .rewrite(BASIC)
+            .unwrap();
+
+        let runtime = &parse(&rewritten)["dependencies"][RUNTIME_CRATE];
+        assert_eq!(runtime["version"].as_str(), Some("0.1.0"));
+        assert!(runtime.get("path").is_none());
+    }
+
+    #[test]
+    fn test_relative_paths_made_absolute() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("app");
+        let sibling = temp_dir.path().join("helper");
+        fs::create_dir_all(&project).unwrap();
+        fs::create_dir_all(&sibling).unwrap();
+
+        let manifest = r#"[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+helper = { path = "../helper" }
+pinned = { path = "/abs/pinned" }
+
+[dev-dependencies.fixtures]
+path = "../fixtures"
+
+[target.'cfg(unix)'.dependencies]
+unix-helper = { path = "../helper" }
+
+[patch.crates-io]
+serde = { path = "../vendor/serde" }
+"#;
+        let out = parse(&rewriter(&project).rewrite(manifest).unwrap());
+
+        let helper = sibling.canonicalize().unwrap();
+        let base = project.canonicalize().unwrap();
+        assert_eq!(
+            out["dependencies"]["helper"]["path"].as_str(),
+            Some(helper.to_str().unwrap())
+        );
+        assert_eq!(
+            out["dependencies"]["pinned"]["path"].as_str(),
+            Some("/abs/pinned")
+        );
+        // Missing directories are resolved lexically
+        let fixtures = base.parent().unwrap().join("fixtures");
+        assert_eq!(
+            out["dev-dependencies"]["fixtures"]["path"].as_str(),
+            Some(fixtures.to_str().unwrap())
+        );
+        assert_eq!(
+            out["target"]["cfg(unix)"]["dependencies"]["unix-helper"]["path"].as_str(),
+            Some(helper.to_str().unwrap())
+        );
+        assert!(
+            Path::new(out["patch"]["crates-io"]["serde"]["path"].as_str().unwrap()).is_absolute()
+        );
+    }
+
+    #[test]
+    fn test_existing_runtime_dependency_gets_track() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = r#"[package]
+name = "demo"
+version = "0.1.0"
+
+[dependencies]
+borrowscope-runtime = "0.1"
+"#;
+        let out = parse(&rewriter(temp_dir.path()).rewrite(manifest).unwrap());
+
+        let runtime = &out["dependencies"][RUNTIME_CRATE];
+        assert_eq!(runtime["version"].as_str(), Some("0.1"));
+        assert_eq!(runtime["features"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_renamed_runtime_dependency_not_duplicated() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = r#"[package]
+name = "demo"
+version = "0.1.0"
+
+[dependencies]
+bs = { package = "borrowscope-runtime", version = "0.1", features = ["track"] }
+"#;
+        let out = parse(&rewriter(temp_dir.path()).rewrite(manifest).unwrap());
+
+        let deps = out["dependencies"].as_table().unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps["bs"]["features"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_standalone_workspace_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = r#"[package]
+name = "member"
+version = "0.1.0"
+workspace = "../.."
+"#;
+        let out = parse(&rewriter(temp_dir.path()).rewrite(manifest).unwrap());
+
+        assert!(out["workspace"].as_table().unwrap().is_empty());
+        assert!(out["package"].get("workspace").is_none());
+    }
+
+    #[test]
+    fn test_existing_workspace_kept() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = r#"[package]
+name = "root"
+version = "0.1.0"
+
+[workspace]
+members = ["crates/*"]
+"#;
+        let out = parse(&rewriter(temp_dir.path()).rewrite(manifest).unwrap());
+        assert_eq!(out["workspace"]["members"][0].as_str(), Some("crates/*"));
+    }
+
+    #[test]
+    fn test_member_paths_inside_workspace_kept() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("ws");
+        let member = root.join("crates/app");
+        fs::create_dir_all(&member).unwrap();
+        fs::create_dir_all(root.join("crates/core")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("outside")).unwrap();
+
+        let manifest = r#"[package]
+name = "app"
+version.workspace = true
+edition.workspace = true
+
+[dependencies]
+core = { path = "../core" }
+outside = { path = "../../../outside" }
+serde = { workspace = true }
+"#;
+        let rewritten = rewriter(&member)
+            .root(root.clone())
+            .rewrite(manifest)
+            .unwrap();
+        let out = parse(&rewritten);
+
+        assert_eq!(
+            out["dependencies"]["core"]["path"].as_str(),
+            Some("../core")
+        );
+        assert!(Path::new(out["dependencies"]["outside"]["path"].as_str().unwrap()).is_absolute());
+        // Still a member: inheritance stays and no nested workspace is created
+        assert!(rewritten.contains("version.workspace = true"));
+        assert!(out.get("workspace").is_none());
+        assert!(out["dependencies"].get(RUNTIME_CRATE).is_some());
+    }
+
+    #[test]
+    fn test_workspace_dependencies_absolutized() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("ws");
+        fs::create_dir_all(&root).unwrap();
+
+        let manifest = r#"[workspace]
+members = ["crates/*"]
+
+[workspace.dependencies]
+local = { path = "crates/local" }
+shared = { path = "../shared" }
+"#;
+        let out = parse(&rewriter(&root).rewrite(manifest).unwrap());
+
+        let deps = &out["workspace"]["dependencies"];
+        assert_eq!(deps["local"]["path"].as_str(), Some("crates/local"));
+        assert!(Path::new(deps["shared"]["path"].as_str().unwrap()).is_absolute());
+        // A virtual manifest has nowhere to put the runtime
+        assert!(out.get("dependencies").is_none());
+    }
+
+    #[test]
+    fn test_uninstrumented_member_has_no_runtime() {
+        let temp_dir = TempDir::new().unwrap();
+        let out = parse(
+            &rewriter(temp_dir.path())
+                .inject_runtime(false)
+                .rewrite(BASIC)
+                .unwrap(),
+        );
+        assert!(out["dependencies"].get(RUNTIME_CRATE).is_none());
+    }
+
+    #[test]
+    fn test_build_script_gets_runtime() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("build.rs"), "fn main() {}").unwrap();
+
+        let out = parse(&rewriter(temp_dir.path()).rewrite(BASIC).unwrap());
+        assert!(out["build-dependencies"].get(RUNTIME_CRATE).is_some());
+
+        let without = TempDir::new().unwrap();
+        let out = parse(&rewriter(without.path()).rewrite(BASIC).unwrap());
+        assert!(out.get("build-dependencies").is_none());
+    }
+
+    #[test]
+    fn test_invalid_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(rewriter(temp_dir.path()).rewrite("[package").is_err());
+    }
+
+    #[test]
+    fn test_write_reads_source_manifest() {
+        let source = TempDir::new().unwrap();
+        let output = TempDir::new().unwrap();
+        fs::write(source.path().join("Cargo.toml"), BASIC).unwrap();
+
+        let written = rewriter(source.path()).write(output.path()).unwrap();
+        assert_eq!(written, output.path().join("Cargo.toml"));
+
+        let out = parse(&fs::read_to_string(written).unwrap());
+        assert!(out["dependencies"].get(RUNTIME_CRATE).is_some());
+    }
+
+    #[test]
+    fn test_detect_sibling_checkout() {
+        if std::env::var_os(RUNTIME_PATH_ENV).is_none() {
+            let detected = RuntimeDependency::detect();
+            assert!(
+                matches!(detected, RuntimeDependency::Path(ref p) if p.ends_with(RUNTIME_CRATE))
+            );
+        }
+    }
+}
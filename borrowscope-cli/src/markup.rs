@@ -0,0 +1,9 @@
+//! Escaping for text embedded in HTML, SVG and XML output
+
+/// Escape text for use in XML and HTML content or attributes
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
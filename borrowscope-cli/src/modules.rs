@@ -0,0 +1,268 @@
+//! Module paths of a crate's files
+//!
+//! `--fn` filters, `expand` and [`SourceIndex`](crate::locations::SourceIndex)
+//! name functions by their full path, so every file needs the module it
+//! defines. [`ModuleTree`] finds it the way rustc does: from each crate root it
+//! follows `mod` declarations, honouring `#[path]` attributes and inline
+//! modules. Files no declaration reaches fall back to what their location
+//! suggests, see [`module_path`].
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::instrumentation::module_path;
+
+/// Module of every file reachable from a crate's roots
+#[derive(Debug, Default)]
+pub struct ModuleTree {
+    /// File relative to the crate directory -> module path below `crate`
+    modules: HashMap<PathBuf, Vec<String>>,
+}
+
+impl ModuleTree {
+    /// Follow the `mod` declarations of the crate in `crate_dir`; files that
+    /// fail to read or parse are skipped
+    pub fn scan(crate_dir: &Path) -> Self {
+        let mut tree = Self::default();
+        for root in crate_roots(crate_dir) {
+            tree.add_file(crate_dir, root, Vec::new(), true);
+        }
+        log::debug!(
+            "Found {} module files under {}",
+            tree.modules.len(),
+            crate_dir.display()
+        );
+        tree
+    }
+
+    /// Module defined by `relative`, a file relative to the crate directory
+    pub fn module_of(&self, relative: &Path) -> Vec<String> {
+        self.modules
+            .get(&normalize(relative))
+            .cloned()
+            .unwrap_or_else(|| module_path(relative))
+    }
+
+    /// Record the file `relative` as `module` and follow its declarations.
+    ///
+    /// `mod_rs` is set for crate roots, `mod.rs` files and files named by
+    /// `#[path]`, whose submodules live next to them rather than in a
+    /// directory named after them.
+    fn add_file(&mut self, crate_dir: &Path, relative: PathBuf, module: Vec<String>, mod_rs: bool) {
+        if self.modules.contains_key(&relative) {
+            return;
+        }
+        let Some(file) = fs::read_to_string(crate_dir.join(&relative))
+            .ok()
+            .and_then(|content| syn::parse_file(&content).ok())
+        else {
+            return;
+        };
+        self.modules.insert(relative.clone(), module.clone());
+
+        let parent = relative.parent().unwrap_or(Path::new("")).to_path_buf();
+        let dir = if mod_rs {
+            parent.clone()
+        } else {
+            let stem = relative.file_stem().unwrap_or_default();
+            parent.join(stem)
+        };
+        self.add_items(crate_dir, &file.items, &module, &dir, &parent);
+    }
+
+    /// Follow the `mod` declarations among `items`, whose submodule files are
+    /// looked up in `dir` and `#[path]` attributes resolved against `path_base`
+    fn add_items(
+        &mut self,
+        crate_dir: &Path,
+        items: &[syn::Item],
+        module: &[String],
+        dir: &Path,
+        path_base: &Path,
+    ) {
+        for item in items {
+            let syn::Item::Mod(item) = item else {
+                continue;
+            };
+            let name = item.ident.to_string();
+            let submodule: Vec<String> = module.iter().cloned().chain([name.clone()]).collect();
+            let path = path_attribute(&item.attrs);
+
+            match (&item.content, path) {
+                (Some((_, items)), path) => {
+                    let dir = dir.join(path.unwrap_or(name));
+                    self.add_items(crate_dir, items, &submodule, &dir, &dir);
+                }
+                (None, Some(path)) => {
+                    let file = normalize(&path_base.join(path));
+                    self.add_file(crate_dir, file, submodule, true);
+                }
+                (None, None) => {
+                    let file = dir.join(format!("{}.rs", name));
+                    if crate_dir.join(&file).is_file() {
+                        self.add_file(crate_dir, file, submodule, false);
+                    } else {
+                        let file = dir.join(&name).join("mod.rs");
+                        self.add_file(crate_dir, file, submodule, true);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Root files of the crate's targets, relative to the crate directory: the
+/// default library, binaries, tests, examples, benches and build script, and
+/// any `path` the manifest sets for its `[lib]` or `[[bin]]` targets
+fn crate_roots(crate_dir: &Path) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = ["src/lib.rs", "src/main.rs", "build.rs"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+
+    for dir in ["src/bin", "tests", "examples", "benches"] {
+        let Ok(entries) = fs::read_dir(crate_dir.join(dir)) else {
+            continue;
+        };
+        let mut found: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter_map(|path| {
+                let name = PathBuf::from(dir).join(path.file_name()?);
+                if path.is_dir() {
+                    Some(name.join("main.rs"))
+                } else {
+                    path.extension()
+                        .is_some_and(|ext| ext == "rs")
+                        .then_some(name)
+                }
+            })
+            .collect();
+        found.sort();
+        roots.extend(found);
+    }
+
+    let manifest = fs::read_to_string(crate_dir.join("Cargo.toml"))
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok());
+    if let Some(manifest) = manifest {
+        let lib = manifest.get("lib").into_iter();
+        let bins = manifest
+            .get("bin")
+            .and_then(|bins| bins.as_array())
+            .into_iter()
+            .flatten();
+        let paths = lib
+            .chain(bins)
+            .filter_map(|target| target.get("path")?.as_str());
+        roots.extend(paths.map(|path| normalize(Path::new(path))));
+    }
+    roots
+}
+
+/// The value of a `#[path = "..."]` attribute
+fn path_attribute(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("path"))
+        .find_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(path),
+                        ..
+                    }),
+                ..
+            }) => Some(path.value()),
+            _ => None,
+        })
+}
+
+/// Resolve `.` and `..` in a relative path without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, file: &str, content: &str) {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_follows_mod_declarations() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        write(root, "Cargo.toml", "[package]\nname = \"app\"\n");
+        write(
+            root,
+            "src/lib.rs",
+            "mod parser;\nmod net { pub mod http; }\n#[path = \"generated/tables.rs\"]\nmod tables;\n",
+        );
+        write(root, "src/parser/mod.rs", "mod lexer;\n");
+        write(root, "src/parser/lexer.rs", "mod token;\n");
+        write(root, "src/parser/lexer/token.rs", "");
+        write(root, "src/net/http.rs", "");
+        write(root, "src/generated/tables.rs", "mod rows;\n");
+        write(root, "src/generated/rows.rs", "");
+        write(root, "src/unused.rs", "");
+
+        let tree = ModuleTree::scan(root);
+        let module = |file: &str| tree.module_of(Path::new(file));
+
+        assert!(module("src/lib.rs").is_empty());
+        assert_eq!(module("src/parser/mod.rs"), ["parser"]);
+        assert_eq!(
+            module("src/parser/lexer/token.rs"),
+            ["parser", "lexer", "token"]
+        );
+        assert_eq!(module("src/net/http.rs"), ["net", "http"]);
+        assert_eq!(module("src/generated/tables.rs"), ["tables"]);
+        // A `#[path]` file's submodules sit next to it
+        assert_eq!(module("src/generated/rows.rs"), ["tables", "rows"]);
+        // Nothing declares it, so its location decides
+        assert_eq!(module("src/unused.rs"), ["unused"]);
+    }
+
+    #[test]
+    fn test_roots_from_manifest_and_targets() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        write(
+            root,
+            "Cargo.toml",
+            "[package]\nname = \"app\"\n\n[lib]\npath = \"lib/app.rs\"\n",
+        );
+        write(root, "lib/app.rs", "mod util;\n");
+        write(root, "lib/util.rs", "");
+        write(root, "src/bin/tool/main.rs", "mod cli;\n");
+        write(root, "src/bin/tool/cli.rs", "");
+        write(root, "tests/common/mod.rs", "");
+        write(root, "tests/it.rs", "mod common;\n");
+
+        let tree = ModuleTree::scan(root);
+        let module = |file: &str| tree.module_of(Path::new(file));
+
+        assert_eq!(module("lib/util.rs"), ["util"]);
+        assert_eq!(module("src/bin/tool/cli.rs"), ["cli"]);
+        assert_eq!(module("tests/common/mod.rs"), ["common"]);
+    }
+}
@@ -8,8 +8,11 @@ use serde::Serialize;
 
 #[derive(ValueEnum, Clone, Copy, Debug)]
 pub enum OutputFormat {
+    /// JSON
     Json,
+    /// Human-readable text
     Text,
+    /// No output
     None,
 }
 
@@ -0,0 +1,555 @@
+//! Raw pointer checks on the unsafe events of a trace
+//!
+//! Pointers recorded with the runtime ID of the variable they point into
+//! (`track_raw_ptr_from`) are followed through the trace. Variables are only
+//! ever matched by ID, and `#[trace_borrow]` gives every call of a function its
+//! own IDs, so dropping a shadowed variable of the same name, or the `x` of
+//! another function, leaves the pointer alone. Dereferencing one after that variable was
+//! dropped or moved away is a use-after-free, and writing through a `*const`
+//! pointer, or through a pointer derived from one, is undefined behaviour.
+//! Every report names the unsafe block the access happened in.
+//!
+//! `#[trace_borrow]` records `let m = n;` as a move whatever the type of `n`.
+//! Moving a `Copy` value leaves it in place, so moves only invalidate
+//! variables whose recorded type is not one of the standard `Copy` types:
+//! primitives, shared references, raw and function pointers, and tuples and
+//! arrays of them. A `Copy` type of the crate's own still counts as moved.
+
+use std::collections::{HashMap, HashSet};
+
+use borrowscope_runtime::Event;
+use serde::Serialize;
+
+/// What went wrong with a pointer access
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointerIssue {
+    UseAfterFree,
+    UseAfterMove,
+    WriteThroughConst,
+}
+
+/// An unsafe block open at the time of an access
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnsafeBlock {
+    pub id: String,
+    pub location: String,
+}
+
+/// An invalid access through a raw pointer
+#[derive(Debug, Clone, Serialize)]
+pub struct PointerReport {
+    /// Runtime ID of the pointer
+    pub pointer_id: String,
+    pub pointer: String,
+    pub issue: PointerIssue,
+    pub timestamp: u64,
+    /// Where the dereference was recorded
+    pub location: String,
+    /// Innermost unsafe block around the dereference
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<UnsafeBlock>,
+    pub message: String,
+}
+
+struct Pointer {
+    name: String,
+    /// The variable the pointer points into, if recorded
+    target: Option<String>,
+    /// The `*const` pointer this one was derived from, possibly itself
+    const_origin: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum Invalidated {
+    Dropped,
+    Moved,
+}
+
+/// Replay the `events` and report every invalid dereference
+pub fn check(events: &[Event]) -> Vec<PointerReport> {
+    // Runtime variable ID -> name, for messages
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut pointers: HashMap<String, Pointer> = HashMap::new();
+    let mut invalidated: HashMap<String, Invalidated> = HashMap::new();
+    // Runtime variable IDs of values that stay valid when moved
+    let mut copies: HashSet<String> = HashSet::new();
+    let mut blocks: Vec<UnsafeBlock> = Vec::new();
+    let mut reports = Vec::new();
+
+    for event in events {
+        match event {
+            Event::RawPtrCreated {
+                var_name,
+                var_id,
+                ptr_type,
+                source_id,
+                ..
+            } => {
+                // A pointer made from another pointer shares its target
+                let origin = source_id.as_deref().and_then(|source| pointers.get(source));
+                let pointer = Pointer {
+                    name: var_name.clone(),
+                    target: match origin {
+                        Some(origin) => origin.target.clone(),
+                        None => source_id.clone(),
+                    },
+                    const_origin: if ptr_type.trim_start().starts_with("*const") {
+                        Some(var_name.clone())
+                    } else {
+                        origin.and_then(|origin| origin.const_origin.clone())
+                    },
+                };
+                names.insert(var_id.clone(), var_name.clone());
+                pointers.insert(var_id.clone(), pointer);
+            }
+
+            Event::Drop { var_id, .. } => {
+                invalidated.insert(var_id.clone(), Invalidated::Dropped);
+            }
+
+            Event::Move {
+                from_id,
+                to_name,
+                to_id,
+                ..
+            } => {
+                if !copies.contains(from_id) {
+                    invalidated.insert(from_id.clone(), Invalidated::Moved);
+                }
+                names.insert(to_id.clone(), to_name.clone());
+            }
+
+            Event::UnsafeBlockEnter {
+                block_id, location, ..
+            } => blocks.push(UnsafeBlock {
+                id: block_id.clone(),
+                location: location.clone(),
+            }),
+
+            Event::UnsafeBlockExit { block_id, .. } => {
+                if let Some(index) = blocks.iter().rposition(|b| &b.id == block_id) {
+                    blocks.truncate(index);
+                }
+            }
+
+            Event::RawPtrDeref {
+                timestamp,
+                ptr_id,
+                location,
+                is_write,
+            } => {
+                let Some(pointer) = pointers.get(ptr_id) else {
+                    continue;
+                };
+                let block = blocks.last().cloned();
+                let report = |issue, message: String| PointerReport {
+                    pointer_id: ptr_id.clone(),
+                    pointer: pointer.name.clone(),
+                    issue,
+                    timestamp: *timestamp,
+                    location: location.clone(),
+                    message: match &block {
+                        Some(block) => format!("{} in unsafe block at {}", message, block.location),
+                        None => message,
+                    },
+                    block: block.clone(),
+                };
+
+                let target = pointer
+                    .target
+                    .as_deref()
+                    .and_then(|target| Some((target, *invalidated.get(target)?)));
+                if let Some((target, how)) = target {
+                    let (issue, what) = match how {
+                        Invalidated::Dropped => (PointerIssue::UseAfterFree, "dropped"),
+                        Invalidated::Moved => (PointerIssue::UseAfterMove, "moved"),
+                    };
+                    let target = names.get(target).map_or(target, String::as_str);
+                    reports.push(report(
+                        issue,
+                        format!(
+                            "'{}' was dereferenced after '{}' was {}",
+                            pointer.name, target, what
+                        ),
+                    ));
+                }
+
+                if *is_write {
+                    if let Some(origin) = &pointer.const_origin {
+                        let message = if origin == &pointer.name {
+                            format!("write through *const pointer '{}'", pointer.name)
+                        } else {
+                            format!(
+                                "write through '{}', derived from *const pointer '{}'",
+                                pointer.name, origin
+                            )
+                        };
+                        reports.push(report(PointerIssue::WriteThroughConst, message));
+                    }
+                }
+            }
+
+            _ => {
+                // A `let` that runs again, e.g. in a loop, makes its variable valid again
+                if let (Some(var_id), Some(name)) = (new_var_id(event), event.var_name()) {
+                    names.insert(var_id.to_string(), name.to_string());
+                    invalidated.remove(var_id);
+                }
+                if let Event::New {
+                    var_id, type_name, ..
+                } = event
+                {
+                    // The `_with_id` calls record the type as `T @ file:line:col`
+                    let type_name = type_name
+                        .rsplit_once(" @ ")
+                        .map_or(&**type_name, |(ty, _)| ty);
+                    if is_copy_type(type_name) {
+                        copies.insert(var_id.clone());
+                    } else {
+                        copies.remove(var_id);
+                    }
+                }
+            }
+        }
+    }
+
+    reports
+}
+
+/// Whether `type_name`, as `std::any::type_name` writes it, is a standard
+/// `Copy` type
+fn is_copy_type(type_name: &str) -> bool {
+    const PRIMITIVES: &[&str] = &[
+        "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+        "f32", "f64", "bool", "char", "()", "!",
+    ];
+    let ty = type_name.trim();
+    if PRIMITIVES.contains(&ty)
+        || ty.starts_with("*const ")
+        || ty.starts_with("*mut ")
+        || ty.starts_with("fn(")
+        || ty.starts_with("unsafe fn(")
+        || ty.starts_with("extern ")
+    {
+        return true;
+    }
+    if let Some(referent) = ty.strip_prefix('&') {
+        return !referent.trim_start().starts_with("mut ");
+    }
+    if let Some(inner) = ty.strip_prefix('[').and_then(|ty| ty.strip_suffix(']')) {
+        // Arrays are `Copy` when their elements are; slices are unsized
+        return match top_level(inner, ';').as_slice() {
+            [element, _] => is_copy_type(element),
+            _ => false,
+        };
+    }
+    if let Some(inner) = ty.strip_prefix('(').and_then(|ty| ty.strip_suffix(')')) {
+        return top_level(inner, ',')
+            .into_iter()
+            .filter(|element| !element.trim().is_empty())
+            .all(is_copy_type);
+    }
+    false
+}
+
+/// Split `text` at `separator`s that are not nested in brackets
+fn top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' | '[' | '<' => depth += 1,
+            ')' | ']' | '>' => depth = depth.saturating_sub(1),
+            c if c == separator && depth == 0 => {
+                parts.push(&text[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+fn new_var_id(event: &Event) -> Option<&str> {
+    match event {
+        Event::New { var_id, .. }
+        | Event::RcNew { var_id, .. }
+        | Event::ArcNew { var_id, .. }
+        | Event::RefCellNew { var_id, .. }
+        | Event::CellNew { var_id, .. }
+        | Event::StaticInit { var_id, .. } => Some(var_id),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new(timestamp: u64, name: &str, id: &str) -> Event {
+        Event::New {
+            timestamp,
+            var_name: name.into(),
+            var_id: id.into(),
+            type_name: "i32".into(),
+            scope_depth: None,
+        }
+    }
+
+    fn ptr(timestamp: u64, name: &str, id: &str, ptr_type: &str, source: &str) -> Event {
+        Event::RawPtrCreated {
+            timestamp,
+            var_name: name.into(),
+            var_id: id.into(),
+            ptr_type: ptr_type.into(),
+            address: 4096,
+            location: "src/lib.rs:3:5".into(),
+            source_id: Some(source.into()),
+        }
+    }
+
+    fn deref(timestamp: u64, id: &str, is_write: bool) -> Event {
+        Event::RawPtrDeref {
+            timestamp,
+            ptr_id: id.into(),
+            location: "src/lib.rs:9:9".into(),
+            is_write,
+        }
+    }
+
+    fn block(timestamp: u64, enter: bool) -> Event {
+        let (block_id, location) = ("0".to_string(), "src/lib.rs:8:5".to_string());
+        if enter {
+            Event::UnsafeBlockEnter {
+                timestamp,
+                block_id,
+                location,
+            }
+        } else {
+            Event::UnsafeBlockExit {
+                timestamp,
+                block_id,
+                location,
+            }
+        }
+    }
+
+    #[test]
+    fn test_deref_after_drop() {
+        let events = vec![
+            new(1, "x", "x_0"),
+            ptr(2, "p", "1", "*mut i32", "x_0"),
+            deref(3, "1", false),
+            Event::Drop {
+                timestamp: 4,
+                var_id: "x_0".into(),
+            },
+            block(5, true),
+            deref(6, "1", false),
+            block(7, false),
+        ];
+
+        let reports = check(&events);
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.issue, PointerIssue::UseAfterFree);
+        assert_eq!(report.timestamp, 6);
+        assert_eq!(report.block.as_ref().unwrap().location, "src/lib.rs:8:5");
+        assert_eq!(
+            report.message,
+            "'p' was dereferenced after 'x' was dropped in unsafe block at src/lib.rs:8:5"
+        );
+    }
+
+    #[test]
+    fn test_deref_after_move() {
+        let events = vec![
+            Event::New {
+                timestamp: 1,
+                var_name: "v".into(),
+                var_id: "v_0".into(),
+                type_name: "alloc::string::String @ src/lib.rs:2:9".into(),
+                scope_depth: Some(0),
+            },
+            ptr(2, "p", "1", "*const i32", "v_0"),
+            Event::Move {
+                timestamp: 3,
+                from_id: "v_0".into(),
+                to_name: "w".into(),
+                to_id: "w_1".into(),
+            },
+            deref(4, "1", false),
+        ];
+
+        let reports = check(&events);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].issue, PointerIssue::UseAfterMove);
+        assert!(reports[0].block.is_none());
+    }
+
+    #[test]
+    fn test_deref_after_copy() {
+        // let n = 1; let p = &n as *const i32; let m = n; unsafe { *p };
+        let events = vec![
+            new(1, "n", "n_1"),
+            ptr(2, "p", "2", "*const i32", "n_1"),
+            Event::Move {
+                timestamp: 3,
+                from_id: "n_1".into(),
+                to_name: "m".into(),
+                to_id: "m_3".into(),
+            },
+            deref(4, "2", false),
+        ];
+
+        assert!(check(&events).is_empty());
+    }
+
+    #[test]
+    fn test_copy_types() {
+        for ty in [
+            "i32",
+            "&str",
+            "*mut u8",
+            "(u8, [f64; 2])",
+            "()",
+            "fn(i32) -> i32",
+        ] {
+            assert!(is_copy_type(ty), "{}", ty);
+        }
+        for ty in [
+            "alloc::string::String",
+            "&mut i32",
+            "(i32, alloc::vec::Vec<u8>)",
+            "[u8]",
+        ] {
+            assert!(!is_copy_type(ty), "{}", ty);
+        }
+    }
+
+    #[test]
+    fn test_write_through_const_derived_pointer() {
+        let events = vec![
+            new(1, "x", "x_0"),
+            ptr(2, "c", "1", "*const i32", "x_0"),
+            ptr(3, "m", "2", "*mut i32", "1"),
+            deref(4, "2", true),
+            deref(5, "1", false),
+        ];
+
+        let reports = check(&events);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].issue, PointerIssue::WriteThroughConst);
+        assert_eq!(
+            reports[0].message,
+            "write through 'm', derived from *const pointer 'c'"
+        );
+
+        // The derived pointer shares the original's target
+        let mut events = events;
+        events.push(Event::Drop {
+            timestamp: 6,
+            var_id: "x_0".into(),
+        });
+        events.push(deref(7, "2", false));
+        assert_eq!(check(&events)[1].issue, PointerIssue::UseAfterFree);
+    }
+
+    #[test]
+    fn test_redeclared_variable_is_valid_again() {
+        let events = vec![
+            new(1, "x", "x_0"),
+            Event::Drop {
+                timestamp: 2,
+                var_id: "x_0".into(),
+            },
+            new(3, "x", "x_0"),
+            ptr(4, "p", "1", "*mut i32", "x_0"),
+            deref(5, "1", true),
+        ];
+
+        assert!(check(&events).is_empty());
+    }
+
+    #[test]
+    fn test_same_named_variables_are_told_apart() {
+        let events = vec![
+            new(1, "x", "x_0"),
+            ptr(2, "p", "1", "*mut i32", "x_0"),
+            // A shadowing `x` in an inner block
+            new(3, "x", "x_2"),
+            Event::Drop {
+                timestamp: 4,
+                var_id: "x_2".into(),
+            },
+            // A `buf` local to another function, moved away
+            new(5, "buf", "buf_3"),
+            ptr(6, "q", "4", "*const u8", "buf_3"),
+            new(7, "buf", "buf_5"),
+            Event::Move {
+                timestamp: 8,
+                from_id: "buf_5".into(),
+                to_name: "out".into(),
+                to_id: "out_6".into(),
+            },
+            deref(9, "1", true),
+            deref(10, "4", false),
+        ];
+
+        assert!(check(&events).is_empty());
+    }
+
+    #[test]
+    fn test_same_named_variables_in_two_functions() {
+        // `main` reserves IDs 1 to 4 and calls `helper`, which numbers from 5:
+        //
+        //     fn helper() { let x = 2; let q = &x as *const i32; unsafe { *q }; }
+        //     fn main() { let mut x = 1; let p = &raw mut x; helper(); unsafe { *p = 3 }; }
+        let events = vec![
+            new(1, "x", "x_1"),
+            ptr(2, "p", "2", "*mut i32", "x_1"),
+            new(3, "x", "x_5"),
+            ptr(4, "q", "6", "*const i32", "x_5"),
+            deref(5, "6", false),
+            Event::Drop {
+                timestamp: 6,
+                var_id: "6".into(),
+            },
+            Event::Drop {
+                timestamp: 7,
+                var_id: "x_5".into(),
+            },
+            deref(8, "2", true),
+            deref(9, "6", false),
+        ];
+
+        let reports = check(&events);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].pointer, "q");
+        assert_eq!(reports[0].issue, PointerIssue::UseAfterFree);
+    }
+
+    #[test]
+    fn test_pointers_without_source_are_not_tracked() {
+        let events = vec![
+            Event::RawPtrCreated {
+                timestamp: 1,
+                var_name: "p".into(),
+                var_id: "1".into(),
+                ptr_type: "*mut i32".into(),
+                address: 4096,
+                location: "src/lib.rs:3:5".into(),
+                source_id: None,
+            },
+            Event::Drop {
+                timestamp: 2,
+                var_id: "x".into(),
+            },
+            deref(3, "1", true),
+        ];
+
+        assert!(check(&events).is_empty());
+    }
+}
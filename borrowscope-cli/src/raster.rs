@@ -0,0 +1,73 @@
+//! Native SVG rasterization, used when Graphviz is not involved
+
+use anyhow::{Context, Result};
+use resvg::{tiny_skia, usvg};
+use std::path::Path;
+
+/// Render an SVG document to a PNG file
+pub fn render_png(svg: &str, output_path: &Path) -> Result<()> {
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+
+    let tree = usvg::Tree::from_str(svg, &options).context("Failed to parse SVG")?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .context("Image dimensions are invalid")?;
+
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+    pixmap
+        .save_png(output_path)
+        .context("Failed to write PNG")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    #[test]
+    fn test_render_png() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("test.png");
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20"><rect width="40" height="20" fill="red"/></svg>"#;
+        render_png(svg, &output).unwrap();
+
+        let bytes = fs::read(&output).unwrap();
+        assert!(bytes.starts_with(PNG_MAGIC));
+    }
+
+    #[test]
+    fn test_render_png_with_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("text.png");
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="80" height="20"><text x="4" y="14" font-size="12">x: i32</text></svg>"#;
+        assert!(render_png(svg, &output).is_ok());
+        assert!(output.exists());
+    }
+
+    #[test]
+    fn test_render_invalid_svg() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("bad.png");
+
+        assert!(render_png("not svg", &output).is_err());
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn test_render_zero_size_svg() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("empty.png");
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="0" height="0"></svg>"#;
+        assert!(render_png(svg, &output).is_err());
+    }
+}
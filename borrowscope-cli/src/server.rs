@@ -1,42 +1,155 @@
 //! Web server for visualization
 
 use axum::{
-    extract::State,
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{Html, IntoResponse, Response},
     routing::get,
     Json, Router,
 };
+use borrowscope_graph::Variable;
+use borrowscope_runtime::{ExportData, Timeline};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
 use tokio::sync::broadcast;
 
+use crate::error::CliError;
+use crate::trace::{self, ConflictReport, Trace};
+
+/// A trace file served under a short, unique name
+#[derive(Clone, Debug)]
+pub struct TraceFile {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl TraceFile {
+    /// Name each file after its stem, suffixing duplicates (`trace`, `trace-2`)
+    pub fn from_paths(paths: Vec<PathBuf>) -> Vec<Self> {
+        let mut used = HashSet::new();
+
+        paths
+            .into_iter()
+            .map(|path| {
+                let stem = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "trace".to_string());
+
+                let mut name = stem.clone();
+                let mut n = 2;
+                while !used.insert(name.clone()) {
+                    name = format!("{}-{}", stem, n);
+                    n += 1;
+                }
+
+                TraceFile { name, path }
+            })
+            .collect()
+    }
+}
+
+/// A trace file as last read from disk
+pub struct LoadedTrace {
+    modified: Option<SystemTime>,
+    pub export: ExportData,
+    trace: OnceLock<Trace>,
+}
+
+impl LoadedTrace {
+    /// The ownership graph of the trace, replayed on first use
+    pub fn trace(&self) -> &Trace {
+        self.trace
+            .get_or_init(|| Trace::from_events(self.export.events.clone()))
+    }
+}
+
 /// Server state
 #[derive(Clone)]
 pub struct ServerState {
-    pub data_file: PathBuf,
+    pub traces: Vec<TraceFile>,
     pub shutdown_tx: broadcast::Sender<()>,
+    /// Loaded traces by name, read again when their file changes
+    loaded: Arc<Mutex<HashMap<String, Arc<LoadedTrace>>>>,
+}
+
+impl ServerState {
+    /// Resolve `?trace=<name>`, defaulting to the first trace
+    fn trace(&self, selector: &TraceSelector) -> std::result::Result<&TraceFile, ApiError> {
+        let found = match &selector.trace {
+            Some(name) => self.traces.iter().find(|t| &t.name == name),
+            None => self.traces.first(),
+        };
+
+        found.ok_or_else(|| {
+            ApiError(
+                StatusCode::NOT_FOUND,
+                format!(
+                    "Trace not found: {}",
+                    selector.trace.as_deref().unwrap_or("<none>")
+                ),
+            )
+        })
+    }
+
+    fn load(&self, selector: &TraceSelector) -> std::result::Result<Arc<LoadedTrace>, ApiError> {
+        Ok(self.load_file(self.trace(selector)?)?)
+    }
+
+    /// The cached contents of `file`, unless it was modified since
+    fn load_file(&self, file: &TraceFile) -> crate::error::Result<Arc<LoadedTrace>> {
+        let modified = std::fs::metadata(&file.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if let Some(loaded) = self.loaded.lock().unwrap().get(&file.name) {
+            if modified.is_some() && loaded.modified == modified {
+                return Ok(Arc::clone(loaded));
+            }
+        }
+
+        let loaded = Arc::new(LoadedTrace {
+            modified,
+            export: trace::load_export(&file.path)?,
+            trace: OnceLock::new(),
+        });
+        self.loaded
+            .lock()
+            .unwrap()
+            .insert(file.name.clone(), Arc::clone(&loaded));
+        Ok(loaded)
+    }
 }
 
-/// Start the web server
+/// Start the web server for one or more trace files
 pub async fn start_server(
     host: String,
     port: u16,
-    data_file: PathBuf,
+    data_files: Vec<PathBuf>,
 ) -> anyhow::Result<(SocketAddr, broadcast::Receiver<()>)> {
     let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
 
     let state = ServerState {
-        data_file,
+        traces: TraceFile::from_paths(data_files),
         shutdown_tx: shutdown_tx.clone(),
+        loaded: Arc::default(),
     };
 
     let app = Router::new()
         .route("/", get(index_handler))
+        .route("/api/traces", get(traces_handler))
         .route("/api/data", get(data_handler))
         .route("/api/health", get(health_handler))
         .route("/api/shutdown", get(shutdown_handler))
+        .route("/api/variables", get(variables_handler))
+        .route("/api/variables/:id/borrowers", get(borrowers_handler))
+        .route("/api/conflicts", get(conflicts_handler))
+        .route("/api/timeline", get(timeline_handler))
+        .route("/api/stats", get(stats_handler))
+        .route("/api/path", get(path_handler))
         .with_state(Arc::new(state));
 
     let addr: SocketAddr = format!("{}:{}", host, port).parse()?;
@@ -56,15 +169,33 @@ async fn index_handler() -> Html<&'static str> {
     Html(include_str!("../static/index.html"))
 }
 
-/// Data API handler
-async fn data_handler(State(state): State<Arc<ServerState>>) -> Response {
-    match std::fs::read_to_string(&state.data_file) {
-        Ok(content) => match serde_json::from_str::<serde_json::Value>(&content) {
-            Ok(json) => Json(json).into_response(),
-            Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Invalid JSON").into_response(),
-        },
-        Err(_) => (StatusCode::NOT_FOUND, "Data file not found").into_response(),
-    }
+/// Available traces by the name `?trace=` selects them with, with their event
+/// counts; where the files live on the server's disk is not exposed
+async fn traces_handler(State(state): State<Arc<ServerState>>) -> Json<serde_json::Value> {
+    let traces: Vec<_> = state
+        .traces
+        .iter()
+        .map(|trace| {
+            let events = state
+                .load_file(trace)
+                .map(|loaded| loaded.export.events.len())
+                .ok();
+            serde_json::json!({
+                "name": trace.name,
+                "events": events,
+            })
+        })
+        .collect();
+
+    Json(serde_json::Value::Array(traces))
+}
+
+/// Data API handler: the whole trace, upgraded to the current format
+async fn data_handler(
+    State(state): State<Arc<ServerState>>,
+    Query(selector): Query<TraceSelector>,
+) -> ApiResult<serde_json::Value> {
+    Ok(Json(to_value(&state.load(&selector)?.export)?))
 }
 
 /// Health check handler
@@ -81,19 +212,237 @@ async fn shutdown_handler(State(state): State<Arc<ServerState>>) -> &'static str
     "Shutting down..."
 }
 
+/// A variable together with the runtime ID it was recorded under
+#[derive(Serialize)]
+struct VariableView<'a> {
+    #[serde(flatten)]
+    variable: &'a Variable,
+    trace_id: Option<&'a str>,
+}
+
+/// Selects one of the served traces by name
+#[derive(Deserialize)]
+struct TraceSelector {
+    trace: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct VariablesQuery {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    type_name: Option<String>,
+    alive_at: Option<u64>,
+    scope: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct BorrowersQuery {
+    #[serde(default)]
+    transitive: bool,
+}
+
+#[derive(Deserialize)]
+struct PathQuery {
+    from: String,
+    to: String,
+}
+
+/// Error response from the query endpoints
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, self.1).into_response()
+    }
+}
+
+impl From<CliError> for ApiError {
+    fn from(error: CliError) -> Self {
+        match error {
+            CliError::FileNotFound(_) => {
+                ApiError(StatusCode::NOT_FOUND, "Data file not found".to_string())
+            }
+            CliError::Json(_) => ApiError(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Invalid JSON".to_string(),
+            ),
+            e => ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        }
+    }
+}
+
+type ApiResult<T> = std::result::Result<Json<T>, ApiError>;
+
+fn variable_view<'a>(trace: &'a Trace, variable: &'a Variable) -> VariableView<'a> {
+    VariableView {
+        variable,
+        trace_id: trace.trace_id(variable.id),
+    }
+}
+
+fn resolve(trace: &Trace, key: &str) -> std::result::Result<usize, ApiError> {
+    trace.resolve(key).ok_or_else(|| {
+        ApiError(
+            StatusCode::NOT_FOUND,
+            format!("Variable not found: {}", key),
+        )
+    })
+}
+
+fn to_value<T: Serialize>(value: T) -> std::result::Result<serde_json::Value, ApiError> {
+    serde_json::to_value(value).map_err(|e| {
+        ApiError(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to serialize response: {}", e),
+        )
+    })
+}
+
+/// Variables matching `name`, `type`, `alive_at` and `scope` filters
+async fn variables_handler(
+    State(state): State<Arc<ServerState>>,
+    Query(selector): Query<TraceSelector>,
+    Query(params): Query<VariablesQuery>,
+) -> ApiResult<serde_json::Value> {
+    let loaded = state.load(&selector)?;
+    let trace = loaded.trace();
+
+    let mut query = trace.graph.query().all();
+    if let Some(name) = &params.name {
+        query = query.and_by_name(name);
+    }
+    if let Some(type_name) = &params.type_name {
+        query = query.and_by_type(type_name);
+    }
+    if let Some(time) = params.alive_at {
+        query = query.and_alive_at(time);
+    }
+    if let Some(depth) = params.scope {
+        query = query.and_in_scope(depth);
+    }
+
+    let mut variables = query.collect();
+    variables.sort_by_key(|v| v.id);
+    let views: Vec<_> = variables
+        .into_iter()
+        .map(|v| variable_view(trace, v))
+        .collect();
+
+    Ok(Json(to_value(views)?))
+}
+
+/// Direct (or, with `transitive=true`, transitive) borrowers of a variable
+async fn borrowers_handler(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+    Query(selector): Query<TraceSelector>,
+    Query(params): Query<BorrowersQuery>,
+) -> ApiResult<serde_json::Value> {
+    let loaded = state.load(&selector)?;
+    let trace = loaded.trace();
+    let id = resolve(trace, &id)?;
+
+    let mut borrowers = if params.transitive {
+        trace.graph.find_transitive_borrowers(id)
+    } else {
+        trace.graph.borrowers_of(id)
+    };
+    borrowers.sort_by_key(|v| v.id);
+    let views: Vec<_> = borrowers
+        .into_iter()
+        .map(|v| variable_view(trace, v))
+        .collect();
+
+    Ok(Json(to_value(views)?))
+}
+
+/// Borrow conflicts found by the interval-based detector
+async fn conflicts_handler(
+    State(state): State<Arc<ServerState>>,
+    Query(selector): Query<TraceSelector>,
+) -> ApiResult<Vec<ConflictReport>> {
+    let loaded = state.load(&selector)?;
+    let trace = loaded.trace();
+
+    let conflicts = trace.conflicts();
+
+    Ok(Json(conflicts))
+}
+
+/// Borrow lifetimes over the whole trace
+async fn timeline_handler(
+    State(state): State<Arc<ServerState>>,
+    Query(selector): Query<TraceSelector>,
+) -> ApiResult<Timeline> {
+    let loaded = state.load(&selector)?;
+    let trace = loaded.trace();
+    Ok(Json(trace.timeline()))
+}
+
+/// Graph statistics plus the raw event count
+async fn stats_handler(
+    State(state): State<Arc<ServerState>>,
+    Query(selector): Query<TraceSelector>,
+) -> ApiResult<serde_json::Value> {
+    let loaded = state.load(&selector)?;
+    let trace = loaded.trace();
+
+    let mut stats = to_value(trace.graph.statistics())?;
+    stats["total_events"] = serde_json::json!(trace.events.len());
+    stats["conflicts"] = serde_json::json!(trace.graph.find_conflicts_optimized().len());
+
+    Ok(Json(stats))
+}
+
+/// Shortest ownership path between two variables
+async fn path_handler(
+    State(state): State<Arc<ServerState>>,
+    Query(selector): Query<TraceSelector>,
+    Query(params): Query<PathQuery>,
+) -> ApiResult<serde_json::Value> {
+    let loaded = state.load(&selector)?;
+    let trace = loaded.trace();
+    let from = resolve(trace, &params.from)?;
+    let to = resolve(trace, &params.to)?;
+
+    let path = trace.graph.borrow_chain(from, to).ok_or_else(|| {
+        ApiError(
+            StatusCode::NOT_FOUND,
+            "No path between variables".to_string(),
+        )
+    })?;
+    let views: Vec<_> = path
+        .iter()
+        .filter_map(|&id| trace.graph.get_variable(id))
+        .map(|v| variable_view(trace, v))
+        .collect();
+
+    Ok(Json(serde_json::json!({ "path": views })))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_to_value_reports_serialization_failure() {
+        // JSON objects need string keys
+        let value: std::collections::HashMap<(u8, u8), u8> = [((1, 2), 3)].into_iter().collect();
+
+        let error = to_value(value).unwrap_err();
+        assert_eq!(error.0, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(error.1.starts_with("Failed to serialize response"));
+    }
+
     #[tokio::test]
     async fn test_start_server() {
         let temp_dir = TempDir::new().unwrap();
         let data_file = temp_dir.path().join("data.json");
         fs::write(&data_file, r#"{"test": "data"}"#).unwrap();
 
-        let result = start_server("127.0.0.1".to_string(), 0, data_file).await;
+        let result = start_server("127.0.0.1".to_string(), 0, vec![data_file]).await;
         assert!(result.is_ok());
 
         let (addr, _rx) = result.unwrap();
@@ -106,7 +455,7 @@ mod tests {
         let data_file = temp_dir.path().join("data.json");
         fs::write(&data_file, r#"{"test": "data"}"#).unwrap();
 
-        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, data_file)
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
             .await
             .unwrap();
 
@@ -125,7 +474,7 @@ mod tests {
         let data_file = temp_dir.path().join("data.json");
         fs::write(&data_file, r#"{"test": "data"}"#).unwrap();
 
-        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, data_file)
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
             .await
             .unwrap();
 
@@ -135,7 +484,8 @@ mod tests {
 
         assert_eq!(response.status(), 200);
         let json: serde_json::Value = response.json().await.unwrap();
-        assert_eq!(json["test"], "data");
+        assert_eq!(json["version"], borrowscope_runtime::TRACE_FORMAT_VERSION);
+        assert!(json["graph"]["nodes"].is_array());
     }
 
     #[tokio::test]
@@ -143,7 +493,7 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let data_file = temp_dir.path().join("nonexistent.json");
 
-        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, data_file)
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
             .await
             .unwrap();
 
@@ -160,7 +510,7 @@ mod tests {
         let data_file = temp_dir.path().join("data.json");
         fs::write(&data_file, r#"{"test": "data"}"#).unwrap();
 
-        let (addr, mut rx) = start_server("127.0.0.1".to_string(), 0, data_file)
+        let (addr, mut rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
             .await
             .unwrap();
 
@@ -185,7 +535,7 @@ mod tests {
         let data_file = temp_dir.path().join("data.json");
         fs::write(&data_file, r#"{"test": "data"}"#).unwrap();
 
-        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, data_file)
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
             .await
             .unwrap();
 
@@ -204,7 +554,7 @@ mod tests {
         let data_file = temp_dir.path().join("data.json");
         fs::write(&data_file, r#"{}"#).unwrap();
 
-        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, data_file)
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
             .await
             .unwrap();
         assert!(addr.port() > 0);
@@ -218,7 +568,7 @@ mod tests {
 
         // Use a high port to avoid conflicts
         let port = 50000 + (std::process::id() % 10000) as u16;
-        let result = start_server("127.0.0.1".to_string(), port, data_file).await;
+        let result = start_server("127.0.0.1".to_string(), port, vec![data_file]).await;
 
         if let Ok((addr, _rx)) = result {
             assert_eq!(addr.port(), port);
@@ -231,7 +581,7 @@ mod tests {
         let data_file = temp_dir.path().join("data.json");
         fs::write(&data_file, r#"{"test": "data"}"#).unwrap();
 
-        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, data_file)
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
             .await
             .unwrap();
 
@@ -259,7 +609,7 @@ mod tests {
         let data_file = temp_dir.path().join("data.json");
         fs::write(&data_file, "invalid json {{{").unwrap();
 
-        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, data_file)
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
             .await
             .unwrap();
 
@@ -275,14 +625,16 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let data_file = temp_dir.path().join("data.json");
 
-        let events: Vec<_> = (0..1000).map(|i| serde_json::json!({"id": i})).collect();
+        let events: Vec<_> = (0..1000)
+            .map(|i| serde_json::json!({"type": "New", "timestamp": i, "var_name": "x", "var_id": format!("x_{}", i), "type_name": "i32"}))
+            .collect();
 
         let large_data = serde_json::json!({
             "events": events
         });
         fs::write(&data_file, serde_json::to_string(&large_data).unwrap()).unwrap();
 
-        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, data_file)
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
             .await
             .unwrap();
 
@@ -301,7 +653,7 @@ mod tests {
         let data_file = temp_dir.path().join("data.json");
         fs::write(&data_file, r#"{}"#).unwrap();
 
-        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, data_file)
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
             .await
             .unwrap();
         assert!(addr.ip().is_loopback());
@@ -313,7 +665,7 @@ mod tests {
         let data_file = temp_dir.path().join("data.json");
         fs::write(&data_file, r#"{}"#).unwrap();
 
-        let (addr, mut rx) = start_server("127.0.0.1".to_string(), 0, data_file)
+        let (addr, mut rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
             .await
             .unwrap();
 
@@ -327,4 +679,278 @@ mod tests {
         // Should still receive signal
         let _ = rx.recv().await;
     }
+
+    fn sample_trace() -> serde_json::Value {
+        serde_json::json!({
+            "version": "0.1.0",
+            "events": [
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x_0", "type_name": "Vec<i32>"},
+                {"type": "Borrow", "timestamp": 2, "borrower_name": "r", "borrower_id": "r_1", "owner_id": "x_0", "mutable": false},
+                {"type": "Borrow", "timestamp": 3, "borrower_name": "m", "borrower_id": "m_2", "owner_id": "x_0", "mutable": true},
+                {"type": "Borrow", "timestamp": 4, "borrower_name": "rr", "borrower_id": "rr_3", "owner_id": "r_1", "mutable": false},
+                {"type": "Drop", "timestamp": 5, "var_id": "rr_3"},
+                {"type": "Drop", "timestamp": 6, "var_id": "m_2"},
+                {"type": "Drop", "timestamp": 7, "var_id": "r_1"},
+                {"type": "New", "timestamp": 8, "var_name": "y", "var_id": "y_4", "type_name": "i32"}
+            ]
+        })
+    }
+
+    async fn start_with_trace(temp_dir: &TempDir) -> SocketAddr {
+        let data_file = temp_dir.path().join("data.json");
+        fs::write(&data_file, serde_json::to_string(&sample_trace()).unwrap()).unwrap();
+
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
+            .await
+            .unwrap();
+        addr
+    }
+
+    async fn get_json(addr: SocketAddr, path: &str) -> (u16, serde_json::Value) {
+        let url = format!("http://{}{}", addr, path);
+        let response = reqwest::get(&url).await.unwrap();
+        let status = response.status().as_u16();
+        let json = response.json().await.unwrap_or(serde_json::Value::Null);
+        (status, json)
+    }
+
+    #[tokio::test]
+    async fn test_variables_endpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = start_with_trace(&temp_dir).await;
+
+        let (status, json) = get_json(addr, "/api/variables").await;
+        assert_eq!(status, 200);
+        assert_eq!(json.as_array().unwrap().len(), 5);
+        assert_eq!(json[0]["name"], "x");
+        assert_eq!(json[0]["trace_id"], "x_0");
+    }
+
+    #[tokio::test]
+    async fn test_variables_endpoint_filters() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = start_with_trace(&temp_dir).await;
+
+        let (_, json) = get_json(addr, "/api/variables?name=y").await;
+        assert_eq!(json.as_array().unwrap().len(), 1);
+
+        let (_, json) = get_json(addr, "/api/variables?type=%26mut%20Vec%3Ci32%3E").await;
+        assert_eq!(json.as_array().unwrap().len(), 1);
+        assert_eq!(json[0]["name"], "m");
+
+        let (_, json) = get_json(addr, "/api/variables?alive_at=4").await;
+        let names: Vec<_> = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["x", "r", "m", "rr"]);
+    }
+
+    #[tokio::test]
+    async fn test_variables_endpoint_bad_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = start_with_trace(&temp_dir).await;
+
+        let (status, _) = get_json(addr, "/api/variables?alive_at=soon").await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn test_borrowers_endpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = start_with_trace(&temp_dir).await;
+
+        let (status, json) = get_json(addr, "/api/variables/x_0/borrowers").await;
+        assert_eq!(status, 200);
+        assert_eq!(json.as_array().unwrap().len(), 2);
+
+        let (_, json) = get_json(addr, "/api/variables/0/borrowers?transitive=true").await;
+        assert_eq!(json.as_array().unwrap().len(), 3);
+
+        let (status, _) = get_json(addr, "/api/variables/missing/borrowers").await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_conflicts_endpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = start_with_trace(&temp_dir).await;
+
+        let (status, json) = get_json(addr, "/api/conflicts").await;
+        assert_eq!(status, 200);
+        let conflicts = json.as_array().unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0]["conflict_type"], "MutableWithImmutable");
+        assert_eq!(conflicts[0]["time_range"], serde_json::json!([3, 6]));
+        assert!(conflicts[0]["message"].as_str().unwrap().contains("'x'"));
+    }
+
+    #[tokio::test]
+    async fn test_timeline_endpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = start_with_trace(&temp_dir).await;
+
+        let (status, json) = get_json(addr, "/api/timeline").await;
+        assert_eq!(status, 200);
+        assert_eq!(json["relations"].as_array().unwrap().len(), 3);
+        assert_eq!(json["min_time"], 1);
+        assert_eq!(json["max_time"], 8);
+    }
+
+    #[tokio::test]
+    async fn test_stats_endpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = start_with_trace(&temp_dir).await;
+
+        let (status, json) = get_json(addr, "/api/stats").await;
+        assert_eq!(status, 200);
+        assert_eq!(json["total_variables"], 5);
+        assert_eq!(json["immutable_borrows"], 2);
+        assert_eq!(json["mutable_borrows"], 1);
+        assert_eq!(json["total_events"], 8);
+        assert_eq!(json["conflicts"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_path_endpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = start_with_trace(&temp_dir).await;
+
+        let (status, json) = get_json(addr, "/api/path?from=rr_3&to=x_0").await;
+        assert_eq!(status, 200);
+        let names: Vec<_> = json["path"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["rr", "r", "x"]);
+
+        let (status, _) = get_json(addr, "/api/path?from=x_0&to=y_4").await;
+        assert_eq!(status, 404);
+
+        let (status, _) = get_json(addr, "/api/path?from=x_0").await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn test_query_endpoints_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_file = temp_dir.path().join("nonexistent.json");
+
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![data_file])
+            .await
+            .unwrap();
+
+        for path in [
+            "/api/variables",
+            "/api/conflicts",
+            "/api/timeline",
+            "/api/stats",
+        ] {
+            let (status, _) = get_json(addr, path).await;
+            assert_eq!(status, 404);
+        }
+    }
+
+    #[test]
+    fn test_trace_file_names_are_unique() {
+        let traces = TraceFile::from_paths(vec![
+            PathBuf::from("before/trace.json"),
+            PathBuf::from("after/trace.json"),
+            PathBuf::from("other.json"),
+            PathBuf::from("again/trace.json"),
+        ]);
+
+        let names: Vec<_> = traces.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["trace", "trace-2", "other", "trace-3"]);
+    }
+
+    #[test]
+    fn test_loaded_traces_are_cached_until_modified() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("trace.json");
+        fs::write(&path, serde_json::to_string(&sample_trace()).unwrap()).unwrap();
+        let (shutdown_tx, _rx) = broadcast::channel(1);
+        let state = ServerState {
+            traces: TraceFile::from_paths(vec![path.clone()]),
+            shutdown_tx,
+            loaded: Arc::default(),
+        };
+        let file = &state.traces[0];
+
+        let first = state.load_file(file).unwrap();
+        assert!(Arc::ptr_eq(&first, &state.load_file(file).unwrap()));
+        assert_eq!(first.trace().events.len(), 8);
+
+        fs::write(&path, r#"{"version": "0.1.0", "events": []}"#).unwrap();
+        let file_times = fs::File::options().write(true).open(&path).unwrap();
+        file_times
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(10))
+            .unwrap();
+
+        let reloaded = state.load_file(file).unwrap();
+        assert!(reloaded.export.events.is_empty());
+        assert!(reloaded.trace().events.is_empty());
+    }
+
+    async fn start_with_two_traces(temp_dir: &TempDir) -> SocketAddr {
+        let before = temp_dir.path().join("before.json");
+        let after = temp_dir.path().join("after.json");
+        fs::write(&before, serde_json::to_string(&sample_trace()).unwrap()).unwrap();
+        fs::write(&after, r#"{"version": "0.1.0", "events": []}"#).unwrap();
+
+        let (addr, _rx) = start_server("127.0.0.1".to_string(), 0, vec![before, after])
+            .await
+            .unwrap();
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_traces_endpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = start_with_two_traces(&temp_dir).await;
+
+        let (status, json) = get_json(addr, "/api/traces").await;
+        assert_eq!(status, 200);
+        let traces = json.as_array().unwrap();
+        assert_eq!(traces.len(), 2);
+        assert_eq!(traces[0]["name"], "before");
+        assert_eq!(traces[0]["events"], 8);
+        assert!(traces[0].get("path").is_none());
+        assert_eq!(traces[1]["name"], "after");
+        assert_eq!(traces[1]["events"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_trace_selection() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = start_with_two_traces(&temp_dir).await;
+
+        // Defaults to the first trace
+        let (_, json) = get_json(addr, "/api/stats").await;
+        assert_eq!(json["total_events"], 8);
+
+        let (_, json) = get_json(addr, "/api/stats?trace=after").await;
+        assert_eq!(json["total_events"], 0);
+
+        let (_, json) = get_json(addr, "/api/data?trace=after").await;
+        assert_eq!(json["events"].as_array().unwrap().len(), 0);
+
+        let (_, json) = get_json(addr, "/api/variables?trace=before&name=x").await;
+        assert_eq!(json.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_trace() {
+        let temp_dir = TempDir::new().unwrap();
+        let addr = start_with_two_traces(&temp_dir).await;
+
+        for path in ["/api/data?trace=nope", "/api/conflicts?trace=nope"] {
+            let (status, _) = get_json(addr, path).await;
+            assert_eq!(status, 404);
+        }
+    }
 }
@@ -0,0 +1,457 @@
+//! Mapping from instrumented sources back to the user's code
+//!
+//! Instrumented files are re-emitted through `prettyplease`, so positions in
+//! compiler diagnostics for the instrumented copy do not match the original
+//! files. A [`LineMap`] pairs the start of every statement, expression and
+//! item in the emitted file with its position in the original file, and a
+//! [`SourceMap`] uses those maps to rewrite cargo diagnostics.
+
+use proc_macro2::Span;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+
+use crate::cargo::{Diagnostic, DiagnosticSpan};
+
+/// Identifiers that only appear in code inserted by the instrumenter
+const INSTRUMENTATION_MARKERS: &[&str] = &["borrowscope_runtime", "_borrowscope"];
+
+/// Position of a node in the emitted file and, unless the instrumenter
+/// synthesized it, in the original file: `(line, column, original line, original column)`.
+/// Synthesized nodes have original line 0, which no real position has.
+type Mapping = (usize, usize, usize, usize);
+
+/// Original position of a location in an instrumented file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    /// 1-based line in the original file
+    pub line: usize,
+    /// 1-based column in the original file
+    pub column: usize,
+    /// Whether the location falls inside code inserted by instrumentation
+    pub synthesized: bool,
+}
+
+/// Positions of syntax nodes in an instrumented file and in its original
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineMap {
+    entries: Vec<Mapping>,
+}
+
+impl LineMap {
+    /// Build the map for `instrumented`, which was parsed from the original
+    /// file and then transformed, and `output`, the code emitted for it
+    pub fn build(instrumented: &syn::File, output: &str) -> Self {
+        let Ok(emitted) = syn::parse_file(output) else {
+            return Self::default();
+        };
+
+        let original = NodePositions::collect(instrumented);
+        let emitted = NodePositions::collect(&emitted);
+        if original.len() != emitted.len() {
+            log::debug!(
+                "Instrumented syntax tree has {} nodes, emitted code {}; mapping the common prefix",
+                original.len(),
+                emitted.len()
+            );
+        }
+
+        let mut entries: Vec<Mapping> = emitted
+            .into_iter()
+            .zip(original)
+            .filter_map(|(emitted, original)| {
+                let (line, column) = emitted?;
+                let (original_line, original_column) = original.unwrap_or((0, 0));
+                Some((line, column, original_line, original_column))
+            })
+            .collect();
+        entries.sort_by_key(|&(line, column, _, _)| (line, column));
+        Self { entries }
+    }
+
+    /// Original position of the 1-based `line` and `column` of the emitted file
+    pub fn lookup(&self, line: usize, column: usize) -> Option<Location> {
+        // Columns in diagnostics are 1-based, syn columns 0-based
+        let position = (line, column.saturating_sub(1));
+        let end = self
+            .entries
+            .partition_point(|&(line, column, _, _)| (line, column) <= position);
+        let preceding = &self.entries[..end];
+
+        let synthesized = preceding
+            .last()
+            .is_some_and(|&(_, _, original_line, _)| original_line == 0);
+        let &(_, _, line, column) = preceding
+            .iter()
+            .rev()
+            .find(|&&(_, _, original_line, _)| original_line != 0)?;
+
+        Some(Location {
+            line,
+            column: column + 1,
+            synthesized,
+        })
+    }
+
+    /// Whether the map holds no positions
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Start positions of syntax nodes in visiting order, `None` for nodes the
+/// instrumenter created
+#[derive(Default)]
+struct NodePositions(Vec<Option<(usize, usize)>>);
+
+impl NodePositions {
+    fn collect(file: &syn::File) -> Vec<Option<(usize, usize)>> {
+        let mut positions = Self::default();
+        positions.visit_file(file);
+        positions.0
+    }
+
+    fn record(&mut self, span: Span) {
+        // Tokens built with `quote!` carry the call-site span, the only span
+        // without source text; a real token at 1:0 has the same start
+        let position = span.source_text().map(|_| {
+            let start = span.start();
+            (start.line, start.column)
+        });
+        self.0.push(position);
+    }
+}
+
+impl<'ast> Visit<'ast> for NodePositions {
+    fn visit_item(&mut self, node: &'ast syn::Item) {
+        self.record(node.span());
+        syn::visit::visit_item(self, node);
+    }
+
+    fn visit_impl_item(&mut self, node: &'ast syn::ImplItem) {
+        self.record(node.span());
+        syn::visit::visit_impl_item(self, node);
+    }
+
+    fn visit_trait_item(&mut self, node: &'ast syn::TraitItem) {
+        self.record(node.span());
+        syn::visit::visit_trait_item(self, node);
+    }
+
+    fn visit_stmt(&mut self, node: &'ast syn::Stmt) {
+        self.record(node.span());
+        syn::visit::visit_stmt(self, node);
+    }
+
+    fn visit_expr(&mut self, node: &'ast syn::Expr) {
+        // prettyplease adds parentheses where precedence needs them and drops
+        // invisible groups, so neither is recorded to keep both walks aligned
+        if !matches!(node, syn::Expr::Paren(_) | syn::Expr::Group(_)) {
+            self.record(node.span());
+        }
+        syn::visit::visit_expr(self, node);
+    }
+
+    fn visit_pat(&mut self, node: &'ast syn::Pat) {
+        if !matches!(node, syn::Pat::Paren(_)) {
+            self.record(node.span());
+        }
+        syn::visit::visit_pat(self, node);
+    }
+
+    fn visit_type(&mut self, node: &'ast syn::Type) {
+        if !matches!(node, syn::Type::Paren(_) | syn::Type::Group(_)) {
+            self.record(node.span());
+        }
+        syn::visit::visit_type(self, node);
+    }
+}
+
+/// Where a diagnostic came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// The user's own code
+    User,
+    /// Code inserted by instrumentation
+    Instrumentation,
+}
+
+/// Line maps of every instrumented file of a project
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    workspace: PathBuf,
+    files: BTreeMap<PathBuf, LineMap>,
+}
+
+impl SourceMap {
+    /// Empty map for the instrumented copy at `workspace`
+    pub fn new(workspace: PathBuf) -> Self {
+        Self {
+            workspace,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Add the map of a file, by path relative to the project root
+    pub fn insert(&mut self, relative: PathBuf, lines: LineMap) {
+        self.files.insert(relative, lines);
+    }
+
+    /// Point `diagnostic` at the original sources and tell where it came from
+    pub fn remap(&self, diagnostic: &mut Diagnostic) -> Origin {
+        let mut origin = if mentions_instrumentation(&diagnostic.message) {
+            Origin::Instrumentation
+        } else {
+            Origin::User
+        };
+
+        for span in &mut diagnostic.spans {
+            let synthesized = self.remap_span(span);
+            if span.is_primary && (synthesized || span.highlighted().any(mentions_instrumentation))
+            {
+                origin = Origin::Instrumentation;
+            }
+        }
+
+        origin
+    }
+
+    /// Rewrite a span in place, returning whether it points into inserted code
+    fn remap_span(&self, span: &mut DiagnosticSpan) -> bool {
+        let relative = span
+            .file_name
+            .strip_prefix(&self.workspace)
+            .unwrap_or(&span.file_name)
+            .to_path_buf();
+        let Some(lines) = self.files.get(&relative) else {
+            return false;
+        };
+        let Some(start) = lines.lookup(span.line_start, span.column_start) else {
+            return true;
+        };
+
+        // Multi-line spans are collapsed onto their start; the emitted layout
+        // says nothing about where they end in the original
+        span.file_name = relative;
+        span.line_start = start.line;
+        span.column_start = start.column;
+        span.line_end = start.line;
+        span.column_end = start.column;
+        start.synthesized
+    }
+}
+
+/// Human-readable report of build errors rewritten against the original sources
+pub fn render_errors(diagnostics: &[Diagnostic], map: &SourceMap, source_root: &Path) -> String {
+    diagnostics
+        .iter()
+        .filter(|d| d.level == "error")
+        .map(|d| render_diagnostic(d, map, source_root).0)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One diagnostic rewritten against the original sources, and where it came from
+pub fn render_diagnostic(
+    diagnostic: &Diagnostic,
+    map: &SourceMap,
+    source_root: &Path,
+) -> (String, Origin) {
+    let mut diagnostic = diagnostic.clone();
+    let origin = map.remap(&mut diagnostic);
+
+    let mut report = format!("{}: {}", diagnostic.level, diagnostic.message);
+    if let Some(span) = diagnostic.primary_span() {
+        let _ = write!(
+            report,
+            "\n  --> {}:{}:{}",
+            source_root.join(&span.file_name).display(),
+            span.line_start,
+            span.column_start
+        );
+    }
+    if origin == Origin::Instrumentation {
+        let _ = write!(
+            report,
+            "\n  = note: this {} is in code inserted by borrowscope instrumentation, not in your code",
+            diagnostic.level
+        );
+    }
+
+    (report, origin)
+}
+
+fn mentions_instrumentation(text: &str) -> bool {
+    INSTRUMENTATION_MARKERS
+        .iter()
+        .any(|marker| text.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrumentation::{InstrumentationConfig, Instrumenter};
+    use std::fs;
+    use tempfile::TempDir;
+
+    const SOURCE: &str = r#"// A comment that prettyplease drops
+
+fn main() {
+    let data = vec![1, 2, 3];
+
+
+    let r = &data;
+    println!("{:?}", r);
+}
+"#;
+
+    fn instrument(source: &str) -> (String, LineMap) {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("main.rs");
+        fs::write(&file, source).unwrap();
+
+        let instrumenter = Instrumenter::new(
+            temp.path().to_path_buf(),
+            temp.path().join("out"),
+            InstrumentationConfig::default(),
+        );
+        let (output, lines) = instrumenter.instrument_file_mapped(&file).unwrap();
+        (fs::read_to_string(output).unwrap(), lines)
+    }
+
+    /// 1-based line and column of the first occurrence of `needle`
+    fn position(text: &str, needle: &str) -> (usize, usize) {
+        let offset = text.find(needle).unwrap();
+        let line = text[..offset].matches('\n').count() + 1;
+        let column = offset - text[..offset].rfind('\n').map_or(0, |i| i + 1) + 1;
+        (line, column)
+    }
+
+    fn diagnostic(message: &str, line: usize, column: usize, text: &str) -> Diagnostic {
+        serde_json::from_value(serde_json::json!({
+            "level": "error",
+            "message": message,
+            "spans": [{
+                "file_name": "src/main.rs",
+                "line_start": line,
+                "line_end": line,
+                "column_start": column,
+                "column_end": column + 1,
+                "is_primary": true,
+                "text": [{ "text": text, "highlight_start": 1, "highlight_end": text.len() + 1 }]
+            }],
+            "rendered": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lookup_maps_statements_to_original_lines() {
+        let (output, lines) = instrument(SOURCE);
+
+        let (line, column) = position(&output, "println!");
+        let location = lines.lookup(line, column).unwrap();
+        assert_eq!((location.line, location.column), (8, 5));
+        assert!(!location.synthesized);
+
+        let (line, column) = position(&output, "vec!");
+        assert_eq!(lines.lookup(line, column).unwrap().line, 4);
+    }
+
+    #[test]
+    fn test_lookup_flags_inserted_code() {
+        let (output, lines) = instrument(SOURCE);
+
+        let (line, column) = position(&output, "borrowscope_runtime::track_borrow");
+        let location = lines.lookup(line, column).unwrap();
+        assert!(location.synthesized);
+        assert_eq!(location.line, 7);
+    }
+
+    #[test]
+    fn test_error_at_start_of_file_is_user_code() {
+        let source = "use std::fmt;\nfn main() {\n    let data = vec![1];\n    let r = &data;\n}\n";
+        let (output, lines) = instrument(source);
+        let mut map = SourceMap::new(PathBuf::from("/work"));
+        map.insert(PathBuf::from("src/main.rs"), lines);
+
+        let (line, column) = position(&output, "use std::fmt");
+        let mut error = diagnostic("unused import: `std::fmt`", line, column, "use std::fmt;");
+        assert_eq!(map.remap(&mut error), Origin::User);
+        assert_eq!(
+            (error.spans[0].line_start, error.spans[0].column_start),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn test_lookup_before_first_node() {
+        let (_, lines) = instrument(SOURCE);
+        assert!(lines.lookup(0, 0).is_none());
+    }
+
+    #[test]
+    fn test_remap_user_error() {
+        let (output, lines) = instrument(SOURCE);
+        let workspace = PathBuf::from("/work");
+        let mut map = SourceMap::new(workspace);
+        map.insert(PathBuf::from("src/main.rs"), lines);
+
+        let (line, column) = position(&output, "println!");
+        let mut error = diagnostic("mismatched types", line, column, "println!");
+        assert_eq!(map.remap(&mut error), Origin::User);
+        assert_eq!(error.spans[0].line_start, 8);
+        assert_eq!(error.spans[0].file_name, PathBuf::from("src/main.rs"));
+    }
+
+    #[test]
+    fn test_remap_instrumentation_error() {
+        let (output, lines) = instrument(SOURCE);
+        let mut map = SourceMap::new(PathBuf::from("/work"));
+        map.insert(PathBuf::from("src/main.rs"), lines);
+
+        let (line, column) = position(&output, "borrowscope_runtime::track_new");
+        let mut error = diagnostic(
+            "the trait bound `T: Serialize` is not satisfied",
+            line,
+            column,
+            "borrowscope_runtime::track_new",
+        );
+        assert_eq!(map.remap(&mut error), Origin::Instrumentation);
+        assert_eq!(error.spans[0].line_start, 4);
+    }
+
+    #[test]
+    fn test_remap_unmapped_file_left_alone() {
+        let map = SourceMap::new(PathBuf::from("/work"));
+        let mut error = diagnostic("cannot find value", 3, 5, "x");
+        error.spans[0].file_name = PathBuf::from("build.rs");
+
+        assert_eq!(map.remap(&mut error), Origin::User);
+        assert_eq!(error.spans[0].line_start, 3);
+        assert_eq!(error.spans[0].file_name, PathBuf::from("build.rs"));
+    }
+
+    #[test]
+    fn test_render_errors() {
+        let (output, lines) = instrument(SOURCE);
+        let mut map = SourceMap::new(PathBuf::from("/work"));
+        map.insert(PathBuf::from("src/main.rs"), lines);
+
+        let (line, column) = position(&output, "println!");
+        let user = diagnostic("mismatched types", line, column, "println!");
+        let (line, column) = position(&output, "borrowscope_runtime::track_new");
+        let inserted = diagnostic("trait bound not satisfied", line, column, "track_new");
+        let mut warning = user.clone();
+        warning.level = "warning".to_string();
+
+        let report = render_errors(&[user, inserted, warning], &map, Path::new("/project"));
+        assert!(report.contains("error: mismatched types\n  --> /project/src/main.rs:8:5"));
+        assert!(report.contains("error: trait bound not satisfied\n  --> /project/src/main.rs:4:"));
+        assert_eq!(report.matches("inserted by borrowscope").count(), 1);
+        assert_eq!(report.matches("error:").count(), 2);
+    }
+}
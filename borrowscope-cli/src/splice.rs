@@ -0,0 +1,412 @@
+//! Span-preserving output for instrumented files
+//!
+//! Instead of re-emitting a whole file through `prettyplease`, the tokens the
+//! instrumenter added are spliced into the original text at the byte offsets
+//! of the tokens around them. Comments, formatting and everything the
+//! instrumenter did not touch stay exactly as they were, so the instrumented
+//! copy can be diffed against the original.
+
+use proc_macro2::{Delimiter, Spacing, Span, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+/// A single token, with group delimiters as tokens of their own
+struct Atom {
+    text: String,
+    /// Byte range in the original file; `None` for tokens the instrumenter created
+    range: Option<Range<usize>>,
+    joint: bool,
+}
+
+/// Stretch of the instrumented token stream
+enum Segment {
+    /// Original token left where it was
+    Kept(Range<usize>),
+    /// Tokens created or moved by the instrumenter
+    Inserted(Vec<Atom>),
+}
+
+/// Splices instrumentation into the original text of a file
+pub struct Splicer<'a> {
+    source: &'a str,
+    /// Where the text syn parsed starts, after any BOM and shebang
+    offset: usize,
+    /// Byte ranges of every token of the original file
+    original: BTreeSet<(usize, usize)>,
+}
+
+impl<'a> Splicer<'a> {
+    /// Record the tokens of `file`, parsed from `source`, before it is instrumented
+    pub fn new(source: &'a str, file: &syn::File) -> Self {
+        let mut offset = source.len() - source.trim_start_matches('\u{feff}').len();
+        if let Some(shebang) = &file.shebang {
+            offset += shebang.len();
+        }
+
+        let mut atoms = Vec::new();
+        flatten(file.to_token_stream(), &mut atoms);
+        let mut original = BTreeSet::new();
+        let mut last: Option<Range<usize>> = None;
+        for range in atoms.into_iter().filter_map(|atom| atom.range) {
+            if !within(&range, last.as_ref()) {
+                original.insert((range.start + offset, range.end + offset));
+                last = Some(range);
+            }
+        }
+
+        Self {
+            source,
+            offset,
+            original,
+        }
+    }
+
+    /// Original text with the changes made to `instrumented` spliced in.
+    ///
+    /// Returns `None` when the result would not parse back into the same
+    /// syntax tree; callers then fall back to re-emitting the file.
+    pub fn splice(&self, instrumented: &syn::File) -> Option<String> {
+        let segments = self.segments(instrumented);
+        let kept: BTreeSet<(usize, usize)> = segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Kept(range) => Some((range.start, range.end)),
+                Segment::Inserted(_) => None,
+            })
+            .collect();
+        let deleted: Vec<Range<usize>> = self
+            .original
+            .difference(&kept)
+            .map(|&(start, end)| start..end)
+            .collect();
+
+        let mut insertions = Vec::new();
+        for (index, segment) in segments.iter().enumerate() {
+            let Segment::Inserted(atoms) = segment else {
+                continue;
+            };
+            let previous = segments[..index].iter().rev().find_map(kept_range);
+            let next = segments[index + 1..].iter().find_map(kept_range);
+            insertions.push(self.insertion(atoms, previous, next, &deleted));
+        }
+
+        let output = apply(self.source, insertions, &deleted);
+
+        // Only trust the splice if it means exactly what the instrumenter produced
+        let reparsed = syn::parse_file(&output).ok()?;
+        let expected = instrumented.to_token_stream().to_string();
+        (reparsed.to_token_stream().to_string() == expected).then_some(output)
+    }
+
+    /// Split the instrumented tokens into original ones still in order and inserted runs
+    fn segments(&self, instrumented: &syn::File) -> Vec<Segment> {
+        let mut atoms = Vec::new();
+        flatten(instrumented.to_token_stream(), &mut atoms);
+
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut last: Option<Range<usize>> = None;
+        for mut atom in atoms {
+            let range = atom
+                .range
+                .take()
+                .map(|range| range.start + self.offset..range.end + self.offset);
+
+            // Doc comments become several tokens that all lie inside the comment
+            if range
+                .as_ref()
+                .is_some_and(|range| within(range, last.as_ref()))
+            {
+                continue;
+            }
+
+            let in_order = match (&range, &last) {
+                (Some(range), Some(last)) => range.start >= last.end,
+                (Some(_), None) => true,
+                _ => false,
+            };
+            match range.filter(|range| in_order && self.is_original(range, &atom.text)) {
+                Some(range) => {
+                    last = Some(range.clone());
+                    segments.push(Segment::Kept(range));
+                }
+                None => match segments.last_mut() {
+                    Some(Segment::Inserted(run)) => run.push(atom),
+                    _ => segments.push(Segment::Inserted(vec![atom])),
+                },
+            }
+        }
+
+        segments
+    }
+
+    /// Whether `range` of the source still holds the token `text`
+    fn is_original(&self, range: &Range<usize>, text: &str) -> bool {
+        match self.source.get(range.clone()) {
+            Some(original) => {
+                original == text || original.starts_with("//") || original.starts_with("/*")
+            }
+            None => false,
+        }
+    }
+
+    /// Where and how an inserted run goes between its neighbouring original tokens
+    fn insertion(
+        &self,
+        atoms: &[Atom],
+        previous: Option<Range<usize>>,
+        next: Option<Range<usize>>,
+        deleted: &[Range<usize>],
+    ) -> (usize, String) {
+        let mut text = render(atoms);
+        let closes = matches!(atoms[0].text.as_str(), ")" | "]" | "}");
+
+        let position = match (previous, next) {
+            (Some(previous), _) if closes => previous.end,
+            (previous, Some(next)) => {
+                // Replace removed tokens in place rather than after them
+                let start = previous.map_or(0, |previous| previous.end);
+                deleted
+                    .iter()
+                    .find(|range| range.start >= start && range.end <= next.start)
+                    .map_or(next.start, |range| range.start)
+            }
+            (Some(previous), None) => previous.end,
+            (None, None) => 0,
+        };
+
+        let before = &self.source[..position];
+        let after = &self.source[position..];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        let indent = &before[line_start..];
+
+        if text.ends_with(';') && !after.is_empty() && indent.trim().is_empty() {
+            // Inserted statements get a line of their own
+            text.push('\n');
+            text.push_str(indent);
+        } else if joins(text.chars().last(), after.chars().next()) || text.ends_with(',') {
+            text.push(' ');
+        }
+        if joins(before.chars().last(), text.chars().next()) {
+            text.insert(0, ' ');
+        }
+
+        (position, text)
+    }
+}
+
+fn within(range: &Range<usize>, outer: Option<&Range<usize>>) -> bool {
+    outer.is_some_and(|outer| range.start >= outer.start && range.end <= outer.end)
+}
+
+fn kept_range(segment: &Segment) -> Option<Range<usize>> {
+    match segment {
+        Segment::Kept(range) => Some(range.clone()),
+        Segment::Inserted(_) => None,
+    }
+}
+
+/// Copy `source`, dropping `deleted` ranges and adding `insertions` at their offsets
+fn apply(source: &str, mut insertions: Vec<(usize, String)>, deleted: &[Range<usize>]) -> String {
+    insertions.sort_by_key(|(position, _)| *position);
+
+    let mut output = String::with_capacity(source.len() + insertions.len() * 64);
+    let mut cursor = 0;
+    let mut insertions = insertions.into_iter().peekable();
+    let ends = deleted
+        .iter()
+        .map(|range| (range.start, range.end))
+        .chain([(source.len(), source.len())]);
+
+    for (start, end) in ends {
+        while let Some((position, text)) = insertions.next_if(|(position, _)| *position <= start) {
+            output.push_str(&source[cursor..position]);
+            output.push_str(&text);
+            cursor = position;
+        }
+        output.push_str(&source[cursor..start]);
+        cursor = end;
+    }
+
+    output
+}
+
+fn flatten(stream: TokenStream, atoms: &mut Vec<Atom>) {
+    for tree in stream {
+        match tree {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                if !open.is_empty() {
+                    atoms.push(atom(open.to_string(), group.span_open(), false));
+                }
+                flatten(group.stream(), atoms);
+                if !close.is_empty() {
+                    atoms.push(atom(close.to_string(), group.span_close(), false));
+                }
+            }
+            TokenTree::Ident(ident) => atoms.push(atom(ident.to_string(), ident.span(), false)),
+            TokenTree::Punct(punct) => atoms.push(atom(
+                punct.as_char().to_string(),
+                punct.span(),
+                punct.spacing() == Spacing::Joint,
+            )),
+            TokenTree::Literal(literal) => {
+                atoms.push(atom(literal.to_string(), literal.span(), false))
+            }
+        }
+    }
+}
+
+fn atom(text: String, span: Span, joint: bool) -> Atom {
+    // Tokens created with `quote!` carry the call-site span, which has no bytes
+    let range = Some(span.byte_range()).filter(|range| !range.is_empty());
+    Atom { text, range, joint }
+}
+
+/// Print inserted tokens the way rustfmt would lay out the common cases
+fn render(atoms: &[Atom]) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&Atom> = None;
+    let mut path_separator = false;
+
+    for atom in atoms {
+        if let Some(previous) = previous {
+            if needs_space(previous, atom, path_separator) {
+                text.push(' ');
+            }
+            path_separator = previous.joint && previous.text == ":" && atom.text == ":";
+        }
+        text.push_str(&atom.text);
+        previous = Some(atom);
+    }
+
+    text
+}
+
+fn needs_space(previous: &Atom, next: &Atom, path_separator: bool) -> bool {
+    if previous.joint || path_separator {
+        return false;
+    }
+    if matches!(previous.text.as_str(), "(" | "[" | "&" | "#" | "." | "!") {
+        return false;
+    }
+    if matches!(next.text.as_str(), ")" | "]" | "," | ";" | "." | ":") {
+        return false;
+    }
+    let after_word = previous.text.chars().last().is_some_and(is_word);
+    !(after_word && matches!(next.text.as_str(), "(" | "!"))
+}
+
+/// Whether two characters would merge into one token without a space
+fn joins(before: Option<char>, after: Option<char>) -> bool {
+    matches!((before, after), (Some(before), Some(after)) if is_word(before) && is_word(after))
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instrumentation::{InstrumentationConfig, Instrumenter};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn instrument(source: &str, config: InstrumentationConfig) -> String {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("main.rs");
+        fs::write(&file, source).unwrap();
+
+        let config = InstrumentationConfig {
+            preserve_formatting: true,
+            ..config
+        };
+        let instrumenter =
+            Instrumenter::new(temp.path().to_path_buf(), temp.path().join("out"), config);
+        let output = instrumenter.instrument_file(&file).unwrap();
+        fs::read_to_string(output).unwrap()
+    }
+
+    #[test]
+    fn test_comments_and_layout_preserved() {
+        let source = r#"//! Crate docs
+
+/// Entry point
+fn main() {
+    // Build the data
+    let data   =   vec![1, 2, 3]; // trailing
+
+    /* block */
+    let r = &data;
+}
+"#;
+        let output = instrument(source, InstrumentationConfig::default());
+
+        assert!(output.contains("//! Crate docs\n"));
+        assert!(output.contains("/// Entry point\nfn main() {\n    // Build the data\n"));
+        assert!(output.contains(
+            r#"let data   =   borrowscope_runtime::track_new_in_scope("data", 0usize, vec![1, 2, 3]); // trailing"#
+        ));
+        assert!(output.contains("    /* block */\n"));
+        assert!(output.contains(
+            r#"let r = borrowscope_runtime::track_new_in_scope("r", 0usize, borrowscope_runtime::track_borrow("data", &data));"#
+        ));
+    }
+
+    #[test]
+    fn test_runtime_import_on_its_own_line() {
+        let output = instrument("use std::rc::Rc;\n", InstrumentationConfig::default());
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("#[allow(unused_imports)] use borrowscope_runtime::"));
+        assert_eq!(lines[1], "use std::rc::Rc;");
+    }
+
+    #[test]
+    fn test_mutable_borrow_replaces_original_tokens() {
+        let source = "fn main() {\n    let mut v = Vec::new();\n    push(&mut v);\n}\n";
+        let output = instrument(source, InstrumentationConfig::default());
+
+        assert!(output.contains(r#"    push(borrowscope_runtime::track_borrow_mut("v", &mut v));"#));
+    }
+
+    #[test]
+    fn test_test_guard_inserted_as_statement() {
+        let source = "#[test]\nfn adds() {\n    assert_eq!(1 + 1, 2);\n}\n";
+        let config = InstrumentationConfig {
+            trace_tests: true,
+            ..InstrumentationConfig::default()
+        };
+        let output = instrument(source, config);
+
+        assert!(output.contains(
+            "fn adds() {\n    let _borrowscope_test = borrowscope_runtime::TestTrace::start(concat!(module_path!(), \"::\", \"adds\"));\n    assert_eq!(1 + 1, 2);\n}"
+        ));
+    }
+
+    #[test]
+    fn test_shebang_kept() {
+        let source = "#!/usr/bin/env run-cargo-script\nfn main() {\n    let x = 1;\n}\n";
+        let output = instrument(source, InstrumentationConfig::default());
+
+        assert!(output.starts_with("#!/usr/bin/env run-cargo-script\n"));
+        assert!(output
+            .contains(r#"    let x = borrowscope_runtime::track_new_in_scope("x", 0usize, 1);"#));
+    }
+
+    #[test]
+    fn test_untouched_file_identical() {
+        let source = "// Nothing to track\nstruct Unit;\n\nimpl Unit {}\n";
+        let output = instrument(source, InstrumentationConfig::default());
+
+        // Only the runtime import is added, after the leading comment
+        let mut lines: Vec<&str> = output.lines().collect();
+        assert!(lines.remove(1).contains("use borrowscope_runtime::"));
+        assert_eq!(lines.join("\n") + "\n", source);
+    }
+}
@@ -0,0 +1,737 @@
+//! Loading tracking data into the analysis graph
+//!
+//! Trace files record runtime events keyed by string IDs (`x_0`, `r_1`, ...).
+//! This module replays those events into a [`borrowscope_graph::OwnershipGraph`]
+//! so that the library queries and conflict detection can run against a trace.
+//!
+//! Files are read as [`ExportData`], the versioned format the runtime writes.
+//! Traces from before the format was versioned are upgraded on load by
+//! [`migrate`].
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use borrowscope_graph::{BorrowConflict, OwnershipGraph, Variable};
+use borrowscope_runtime::{
+    build_graph, Event, ExportData, Timeline, TRACE_FORMAT_VERSION, TRACE_SCHEMA,
+};
+use jsonschema::{Draft, JSONSchema};
+use serde::Serialize;
+
+use crate::error::{CliError, Result};
+use crate::locations::SourceLocation;
+
+/// A conflict with its human-readable description
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictReport {
+    #[serde(flatten)]
+    pub conflict: BorrowConflict,
+    pub message: String,
+}
+
+/// A trace file replayed into an ownership graph
+pub struct Trace {
+    pub events: Vec<Event>,
+    pub graph: OwnershipGraph,
+    /// Runtime variable ID -> graph node ID
+    ids: HashMap<String, usize>,
+    /// Graph node ID -> runtime variable ID
+    keys: Vec<String>,
+    /// Graph node ID -> where the variable was declared, when recorded
+    locations: HashMap<usize, SourceLocation>,
+    /// Graph node IDs whose scope depth was not recorded; the graph holds 0
+    /// for them
+    unscoped: HashSet<usize>,
+}
+
+impl Trace {
+    /// Load and replay a trace file
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::from_events(load_export(path)?.events))
+    }
+
+    /// Replay the `events` array of a parsed trace.
+    ///
+    /// Entries that are not recognised runtime events are skipped.
+    pub fn from_value(data: &serde_json::Value) -> Self {
+        Self::from_events(parse_events(data).0)
+    }
+
+    /// Replay runtime events into an ownership graph
+    pub fn from_events(events: Vec<Event>) -> Self {
+        let mut trace = Self {
+            events: Vec::new(),
+            graph: OwnershipGraph::new(),
+            ids: HashMap::new(),
+            keys: Vec::new(),
+            locations: HashMap::new(),
+            unscoped: HashSet::new(),
+        };
+
+        for event in &events {
+            trace.apply(event);
+        }
+
+        trace.events = events;
+        trace
+    }
+
+    /// Resolve a graph node ID (`"3"`) or a runtime variable ID (`"x_0"`)
+    pub fn resolve(&self, key: &str) -> Option<usize> {
+        if let Some(&id) = self.ids.get(key) {
+            return Some(id);
+        }
+
+        key.parse::<usize>()
+            .ok()
+            .filter(|id| self.graph.get_variable(*id).is_some())
+    }
+
+    /// Runtime variable ID for a graph node ID
+    pub fn trace_id(&self, id: usize) -> Option<&str> {
+        self.keys.get(id).map(|k| k.as_str())
+    }
+
+    /// Where a variable was declared, for events that record it
+    pub fn location(&self, id: usize) -> Option<&SourceLocation> {
+        self.locations.get(&id)
+    }
+
+    /// How many blocks inside its function a variable was declared, for
+    /// events that record it
+    pub fn scope_depth(&self, id: usize) -> Option<usize> {
+        let variable = self.graph.get_variable(id)?;
+        (!self.unscoped.contains(&id)).then_some(variable.scope_depth)
+    }
+
+    /// Whether the trace records the scope depth of its variables
+    pub fn records_scopes(&self) -> bool {
+        self.events.iter().any(|event| {
+            matches!(
+                event,
+                Event::New {
+                    scope_depth: Some(_),
+                    ..
+                }
+            )
+        })
+    }
+
+    /// Borrow lifetimes computed from the raw events
+    pub fn timeline(&self) -> Timeline {
+        Timeline::from_events(&self.events)
+    }
+
+    /// Borrow conflicts found by the interval-based detector
+    pub fn conflicts(&self) -> Vec<ConflictReport> {
+        self.graph
+            .find_conflicts_optimized()
+            .into_iter()
+            .map(|conflict| ConflictReport {
+                message: conflict.format(&self.graph),
+                conflict,
+            })
+            .collect()
+    }
+
+    fn apply(&mut self, event: &Event) {
+        match event {
+            Event::New {
+                timestamp,
+                var_name,
+                var_id,
+                type_name,
+                scope_depth,
+            } => {
+                self.add_at_depth(
+                    var_id,
+                    var_name,
+                    type_name.clone(),
+                    *timestamp,
+                    *scope_depth,
+                );
+            }
+
+            Event::RcNew {
+                timestamp,
+                var_name,
+                var_id,
+                type_name,
+                ..
+            }
+            | Event::ArcNew {
+                timestamp,
+                var_name,
+                var_id,
+                type_name,
+                ..
+            }
+            | Event::RefCellNew {
+                timestamp,
+                var_name,
+                var_id,
+                type_name,
+            }
+            | Event::CellNew {
+                timestamp,
+                var_name,
+                var_id,
+                type_name,
+            }
+            | Event::StaticInit {
+                timestamp,
+                var_name,
+                var_id,
+                type_name,
+                ..
+            }
+            | Event::RawPtrCreated {
+                timestamp,
+                var_name,
+                var_id,
+                ptr_type: type_name,
+                ..
+            } => {
+                self.add(var_id, var_name, type_name.clone(), *timestamp);
+            }
+
+            Event::Borrow {
+                timestamp,
+                borrower_name,
+                borrower_id,
+                owner_id,
+                mutable,
+            } => {
+                let prefix = if *mutable { "&mut " } else { "&" };
+                let type_name = format!("{}{}", prefix, self.type_of(owner_id).unwrap_or("_"));
+                let borrower = self.add(borrower_id, borrower_name, type_name, *timestamp);
+                if let Some(owner) = self.resolve_trace_id(owner_id) {
+                    self.graph.add_borrow(borrower, owner, *mutable, *timestamp);
+                }
+            }
+
+            Event::Move {
+                timestamp,
+                from_id,
+                to_name,
+                to_id,
+            } => {
+                let type_name = self.type_of(from_id).unwrap_or("_").to_string();
+                let to = self.add(to_id, to_name, type_name, *timestamp);
+                if let Some(from) = self.resolve_trace_id(from_id) {
+                    self.graph.add_move(from, to, *timestamp);
+                }
+            }
+
+            Event::RcClone {
+                timestamp,
+                var_name,
+                var_id,
+                source_id,
+                strong_count,
+                ..
+            } => {
+                let type_name = self.type_of(source_id).unwrap_or("Rc<_>").to_string();
+                let clone = self.add(var_id, var_name, type_name, *timestamp);
+                if let Some(source) = self.resolve_trace_id(source_id) {
+                    self.graph
+                        .add_rc_clone(clone, source, *strong_count, *timestamp);
+                }
+            }
+
+            Event::ArcClone {
+                timestamp,
+                var_name,
+                var_id,
+                source_id,
+                strong_count,
+                ..
+            } => {
+                let type_name = self.type_of(source_id).unwrap_or("Arc<_>").to_string();
+                let clone = self.add(var_id, var_name, type_name, *timestamp);
+                if let Some(source) = self.resolve_trace_id(source_id) {
+                    self.graph
+                        .add_arc_clone(clone, source, *strong_count, *timestamp);
+                }
+            }
+
+            Event::RefCellBorrow {
+                timestamp,
+                borrow_id,
+                refcell_id,
+                is_mutable,
+                ..
+            } => {
+                let type_name = if *is_mutable { "RefMut<_>" } else { "Ref<_>" };
+                let borrow = self.add(borrow_id, borrow_id, type_name.to_string(), *timestamp);
+                if let Some(refcell) = self.resolve_trace_id(refcell_id) {
+                    self.graph
+                        .add_refcell_borrow(borrow, refcell, *is_mutable, *timestamp);
+                }
+            }
+
+            Event::Drop { timestamp, var_id }
+            | Event::RefCellDrop {
+                timestamp,
+                borrow_id: var_id,
+                ..
+            } => {
+                if let Some(id) = self.resolve_trace_id(var_id) {
+                    self.graph.mark_dropped(id, *timestamp);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    fn add(&mut self, trace_id: &str, name: &str, type_name: String, at: u64) -> usize {
+        self.add_at_depth(trace_id, name, type_name, at, None)
+    }
+
+    fn add_at_depth(
+        &mut self,
+        trace_id: &str,
+        name: &str,
+        mut type_name: String,
+        at: u64,
+        scope_depth: Option<usize>,
+    ) -> usize {
+        let id = self.keys.len();
+        if scope_depth.is_none() {
+            self.unscoped.insert(id);
+        }
+        self.keys.push(trace_id.to_string());
+        self.ids.insert(trace_id.to_string(), id);
+        if let Some((ty, location)) = split_location(&type_name) {
+            self.locations.insert(id, location);
+            type_name = ty.to_string();
+        }
+        self.graph.add_variable(Variable {
+            id,
+            name: name.to_string(),
+            type_name,
+            created_at: at,
+            dropped_at: None,
+            scope_depth: scope_depth.unwrap_or(0),
+        });
+        id
+    }
+
+    fn resolve_trace_id(&self, trace_id: &str) -> Option<usize> {
+        self.ids.get(trace_id).copied()
+    }
+
+    fn type_of(&self, trace_id: &str) -> Option<&str> {
+        self.resolve_trace_id(trace_id)
+            .and_then(|id| self.graph.get_variable(id))
+            .map(|v| v.type_name.as_str())
+    }
+}
+
+/// Where `event` was recorded, if the trace says
+pub fn event_location(event: &Event) -> Option<SourceLocation> {
+    let value = serde_json::to_value(event).ok()?;
+    let fields = value.as_object()?;
+    if let Some(location) = fields.get("location").and_then(|l| l.as_str()) {
+        return SourceLocation::parse(location);
+    }
+    fields
+        .values()
+        .filter_map(|field| field.as_str())
+        .find_map(|field| split_location(field).map(|(_, location)| location))
+}
+
+/// Split the location off a type or name recorded as `T @ file:line:col`,
+/// as the `_with_id` calls do
+fn split_location(text: &str) -> Option<(&str, SourceLocation)> {
+    let (text, location) = text.rsplit_once(" @ ")?;
+    Some((text, SourceLocation::parse(location)?))
+}
+
+/// Read a trace file, upgrading older format versions to the current one
+pub fn load_export(path: &Path) -> Result<ExportData> {
+    migrate(read_json(path)?)
+}
+
+/// Read a trace file as untyped JSON, without upgrading it
+pub fn read_json(path: &Path) -> Result<serde_json::Value> {
+    if !path.exists() {
+        return Err(CliError::FileNotFound(path.to_path_buf()));
+    }
+
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Whether a parsed trace is already in the current format version
+pub fn is_current(data: &serde_json::Value) -> bool {
+    data.get("version").and_then(|v| v.as_u64()) == Some(u64::from(TRACE_FORMAT_VERSION))
+}
+
+/// Crate versions the CLI tagged its own trace files with before the format
+/// was versioned
+const LEGACY_VERSIONS: &[&str] = &["0.1.0"];
+
+/// Upgrade a parsed trace of any known format version to the current one
+pub fn migrate(data: serde_json::Value) -> Result<ExportData> {
+    match data.get("version") {
+        // Before the format was versioned the runtime wrote no version at all
+        None => upgrade_unversioned(&data),
+        Some(serde_json::Value::String(version)) if LEGACY_VERSIONS.contains(&version.as_str()) => {
+            upgrade_unversioned(&data)
+        }
+        Some(version) => match version.as_u64() {
+            Some(v) if v == u64::from(TRACE_FORMAT_VERSION) => serde_json::from_value(data)
+                .map_err(|e| CliError::InvalidFormat(format!("Invalid trace: {}", e))),
+            Some(v) if v > u64::from(TRACE_FORMAT_VERSION) => {
+                Err(CliError::InvalidFormat(format!(
+                    "Trace format version {} is newer than this borrowscope supports ({})",
+                    v, TRACE_FORMAT_VERSION
+                )))
+            }
+            _ => Err(CliError::InvalidFormat(format!(
+                "Unknown trace format version: {}",
+                version
+            ))),
+        },
+    }
+}
+
+/// Unversioned traces kept their events but laid the graph out differently,
+/// so the graph is rebuilt from the events; unrecognised events are skipped,
+/// but a trace none of whose events can be read is rejected
+fn upgrade_unversioned(data: &serde_json::Value) -> Result<ExportData> {
+    let (events, skipped) = parse_events(data);
+    if events.is_empty() && skipped > 0 {
+        return Err(CliError::InvalidFormat(format!(
+            "None of the {} events in the trace are recognised runtime events",
+            skipped
+        )));
+    }
+
+    let mut export = ExportData::new(build_graph(&events), events);
+    export.source = data
+        .get("source")
+        .and_then(|s| s.as_str())
+        .map(String::from);
+    Ok(export)
+}
+
+/// Runtime events in the `events` array of a parsed trace, and how many
+/// entries were skipped because they are not recognised events
+fn parse_events(data: &serde_json::Value) -> (Vec<Event>, usize) {
+    let entries = data
+        .get("events")
+        .and_then(|e| e.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let events: Vec<Event> = entries
+        .iter()
+        .filter_map(|e| serde_json::from_value(e.clone()).ok())
+        .collect();
+
+    let skipped = entries.len() - events.len();
+    if skipped > 0 {
+        log::warn!(
+            "Skipped {} of {} trace events that are not recognised runtime events",
+            skipped,
+            entries.len()
+        );
+    }
+    (events, skipped)
+}
+
+/// Check a current-format trace against the published JSON Schema
+///
+/// Every violation is reported with the JSON pointer of the offending value.
+pub fn validate_schema(data: &serde_json::Value) -> Result<()> {
+    let schema: serde_json::Value = serde_json::from_str(TRACE_SCHEMA)?;
+    let schema = JSONSchema::options()
+        .with_draft(Draft::Draft7)
+        .compile(&schema)
+        .map_err(|e| CliError::Other(format!("Invalid trace schema: {}", e)))?;
+
+    if let Err(errors) = schema.validate(data) {
+        let errors: Vec<String> = errors
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                let path = if path.is_empty() {
+                    "/".to_string()
+                } else {
+                    path
+                };
+                format!("{}: {}", path, e)
+            })
+            .collect();
+        return Err(CliError::InvalidFormat(errors.join("\n")));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sample_events() -> serde_json::Value {
+        serde_json::json!({
+            "events": [
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x_0", "type_name": "i32"},
+                {"type": "Borrow", "timestamp": 2, "borrower_name": "r", "borrower_id": "r_1", "owner_id": "x_0", "mutable": false},
+                {"type": "Borrow", "timestamp": 3, "borrower_name": "m", "borrower_id": "m_2", "owner_id": "x_0", "mutable": true},
+                {"type": "Drop", "timestamp": 4, "var_id": "m_2"},
+                {"type": "Drop", "timestamp": 5, "var_id": "r_1"},
+                {"type": "Drop", "timestamp": 6, "var_id": "x_0"}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_replay_events() {
+        let trace = Trace::from_value(&sample_events());
+
+        assert_eq!(trace.events.len(), 6);
+        assert_eq!(trace.graph.node_count(), 3);
+        assert_eq!(trace.graph.edge_count(), 2);
+
+        let x = trace.resolve("x_0").unwrap();
+        assert_eq!(trace.graph.borrowers_of(x).len(), 2);
+        assert_eq!(trace.graph.get_variable(x).unwrap().dropped_at, Some(6));
+    }
+
+    #[test]
+    fn test_recorded_location_split_from_type() {
+        let trace = Trace::from_value(&serde_json::json!({
+            "events": [
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x_1", "type_name": "Vec<i32> @ src/main.rs:4:9"},
+                {"type": "New", "timestamp": 2, "var_name": "y", "var_id": "y_2", "type_name": "i32"}
+            ]
+        }));
+
+        let x = trace.resolve("x_1").unwrap();
+        assert_eq!(trace.graph.get_variable(x).unwrap().type_name, "Vec<i32>");
+        assert_eq!(trace.location(x).unwrap().to_string(), "src/main.rs:4:9");
+        assert!(trace.location(trace.resolve("y_2").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_event_location() {
+        let events: Vec<Event> = serde_json::from_value(serde_json::json!([
+            {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x_1", "type_name": "i32 @ src/main.rs:4:9"},
+            {"type": "Borrow", "timestamp": 2, "borrower_name": "r @ src/main.rs:5:13", "borrower_id": "r_2", "owner_id": "x_1", "mutable": false},
+            {"type": "UnsafeBlockEnter", "timestamp": 3, "block_id": "unsafe_0", "location": "src/main.rs:6:5"},
+            {"type": "Drop", "timestamp": 4, "var_id": "x_1"}
+        ]))
+        .unwrap();
+
+        let locations: Vec<_> = events
+            .iter()
+            .map(|event| event_location(event).map(|l| l.to_string()))
+            .collect();
+        assert_eq!(
+            locations,
+            [
+                Some("src/main.rs:4:9".to_string()),
+                Some("src/main.rs:5:13".to_string()),
+                Some("src/main.rs:6:5".to_string()),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_borrow_types_derived_from_owner() {
+        let trace = Trace::from_value(&sample_events());
+
+        let r = trace.resolve("r_1").unwrap();
+        let m = trace.resolve("m_2").unwrap();
+        assert_eq!(trace.graph.get_variable(r).unwrap().type_name, "&i32");
+        assert_eq!(trace.graph.get_variable(m).unwrap().type_name, "&mut i32");
+    }
+
+    #[test]
+    fn test_resolve_numeric_and_trace_ids() {
+        let trace = Trace::from_value(&sample_events());
+
+        assert_eq!(trace.resolve("x_0"), Some(0));
+        assert_eq!(trace.resolve("1"), Some(1));
+        assert_eq!(trace.resolve("99"), None);
+        assert_eq!(trace.resolve("nope"), None);
+        assert_eq!(trace.trace_id(2), Some("m_2"));
+    }
+
+    #[test]
+    fn test_conflicts_detected() {
+        let trace = Trace::from_value(&sample_events());
+
+        let conflicts = trace.graph.find_conflicts_optimized();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].time_range, (3, 4));
+    }
+
+    #[test]
+    fn test_unknown_events_skipped() {
+        let data = serde_json::json!({
+            "events": [
+                {"type": "New", "id": 1},
+                {"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x_0", "type_name": "i32"}
+            ]
+        });
+
+        let trace = Trace::from_value(&data);
+        assert_eq!(trace.events.len(), 1);
+        assert_eq!(trace.graph.node_count(), 1);
+    }
+
+    #[test]
+    fn test_missing_events() {
+        let trace = Trace::from_value(&serde_json::json!({"version": "0.1.0"}));
+        assert!(trace.events.is_empty());
+        assert_eq!(trace.graph.node_count(), 0);
+    }
+
+    #[test]
+    fn test_migrate_unversioned_runtime_trace() {
+        let mut data = sample_events();
+        data["nodes"] = serde_json::json!([]);
+        data["edges"] = serde_json::json!([]);
+
+        let export = migrate(data).unwrap();
+        assert_eq!(export.version, TRACE_FORMAT_VERSION);
+        assert_eq!(export.events.len(), 6);
+        assert_eq!(export.graph.nodes.len(), 1);
+        assert_eq!(export.metadata.total_events, 6);
+        validate_schema(&serde_json::to_value(&export).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_keeps_source() {
+        let mut data = sample_events();
+        data["version"] = serde_json::json!("0.1.0");
+        data["source"] = serde_json::json!("src/main.rs");
+
+        let export = migrate(data).unwrap();
+        assert_eq!(export.source.as_deref(), Some("src/main.rs"));
+    }
+
+    #[test]
+    fn test_migrate_current_version() {
+        let export = migrate(sample_events()).unwrap();
+        let current = serde_json::to_value(&export).unwrap();
+        assert!(is_current(&current));
+
+        let again = migrate(current).unwrap();
+        assert_eq!(again.events, export.events);
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_and_unknown_versions() {
+        let mut data = sample_events();
+        data["version"] = serde_json::json!(TRACE_FORMAT_VERSION + 1);
+        let err = migrate(data.clone()).unwrap_err();
+        assert!(err.to_string().contains("newer"));
+
+        data["version"] = serde_json::json!(true);
+        assert!(migrate(data.clone()).is_err());
+
+        data["version"] = serde_json::json!("2.0");
+        let err = migrate(data).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unknown trace format version: \"2.0\""));
+    }
+
+    #[test]
+    fn test_validate_schema_reports_paths() {
+        let mut data = serde_json::to_value(migrate(sample_events()).unwrap()).unwrap();
+        validate_schema(&data).unwrap();
+
+        data["events"][1]["mutable"] = serde_json::json!("yes");
+        data.as_object_mut().unwrap().remove("metadata");
+        let err = validate_schema(&data).unwrap_err().to_string();
+        assert!(err.contains("/events/1"));
+        assert!(err.contains("metadata"));
+    }
+
+    #[test]
+    fn test_runtime_export_matches_schema() {
+        let events = vec![
+            Event::New {
+                timestamp: 1,
+                var_name: "x".into(),
+                var_id: "x_0".into(),
+                type_name: "i32".into(),
+                scope_depth: None,
+            },
+            Event::RawPtrCreated {
+                timestamp: 2,
+                var_name: "p".into(),
+                var_id: "p_1".into(),
+                ptr_type: "*const i32".into(),
+                address: 4096,
+                location: "src/main.rs:3".into(),
+                source_id: Some("x_0".into()),
+            },
+            Event::Drop {
+                timestamp: 3,
+                var_id: "x_0".into(),
+            },
+        ];
+        let export = ExportData::new(build_graph(&events), events);
+        validate_schema(&serde_json::to_value(&export).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_without_events() {
+        let export = migrate(serde_json::json!({"version": "0.1.0"})).unwrap();
+        assert!(export.events.is_empty());
+        assert!(export.graph.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_skips_unrecognised_events() {
+        let mut data = sample_events();
+        data["events"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({"type": "Teleport", "timestamp": 7}));
+
+        let export = migrate(data).unwrap();
+        assert_eq!(export.events.len(), 6);
+    }
+
+    #[test]
+    fn test_migrate_rejects_trace_without_readable_events() {
+        let data = serde_json::json!({
+            "version": "0.1.0",
+            "events": [
+                {"kind": "new", "at": 1, "name": "x"},
+                {"kind": "drop", "at": 2, "name": "x"}
+            ]
+        });
+
+        let err = migrate(data).unwrap_err();
+        assert!(matches!(err, CliError::InvalidFormat(_)));
+        assert!(err.to_string().contains("None of the 2 events"));
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let result = Trace::load(&temp_dir.path().join("missing.json"));
+        assert!(matches!(result, Err(CliError::FileNotFound(_))));
+    }
+
+    #[test]
+    fn test_timeline() {
+        let trace = Trace::from_value(&sample_events());
+        let timeline = trace.timeline();
+
+        assert_eq!(timeline.relations.len(), 2);
+        assert_eq!(timeline.min_time, 1);
+        assert_eq!(timeline.max_time, 6);
+    }
+}
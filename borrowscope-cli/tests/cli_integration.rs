@@ -241,7 +241,7 @@ fn test_check_command_with_valid_json() {
     data_file
         .write_str(
             r#"{
-        "version": "1.0",
+        "version": "0.1.0",
         "nodes": [],
         "edges": [],
         "events": [],
@@ -276,7 +276,7 @@ fn test_check_command_with_stats_flag() {
     data_file
         .write_str(
             r#"{
-        "version": "1.0",
+        "version": "0.1.0",
         "nodes": [],
         "edges": [],
         "events": [],
@@ -612,7 +612,7 @@ fn test_check_with_validate_flag() {
     let data_file = temp.child("data.json");
 
     data_file.write_str(r#"{
-        "version": "1.0",
+        "version": "0.1.0",
         "nodes": [],
         "edges": [],
         "events": [],
@@ -658,7 +658,7 @@ fn test_export_with_complex_data() {
     let output = temp.child("output.dot");
 
     input.write_str(r#"{
-        "version": "1.0",
+        "version": "0.1.0",
         "nodes": [
             {"id": "x_0", "name": "x", "type_name": "String", "created_at": 1, "dropped_at": 5},
             {"id": "r_1", "name": "r", "type_name": "&String", "created_at": 2, "dropped_at": 4}
@@ -706,7 +706,7 @@ fn test_check_stats_with_data() {
     let data_file = temp.child("stats.json");
 
     data_file.write_str(r#"{
-        "version": "1.0",
+        "version": "0.1.0",
         "nodes": [
             {"id": "x_0", "name": "x", "type_name": "i32", "created_at": 1, "dropped_at": null}
         ],
@@ -794,7 +794,7 @@ fn test_export_creates_parent_directories() {
     let input = temp.child("input.json");
     let output = temp.child("nested/dir/output.dot");
 
-    input.write_str(r#"{"version": "1.0", "nodes": [], "edges": [], "events": [], "graph": {"nodes": [], "edges": []}, "metadata": {}}"#).unwrap();
+    input.write_str(r#"{"version": "0.1.0", "nodes": [], "edges": [], "events": [], "graph": {"nodes": [], "edges": []}, "metadata": {}}"#).unwrap();
 
     Command::cargo_bin("borrowscope")
         .unwrap()
@@ -880,7 +880,7 @@ fn test_pipeline_init_check_export() {
     data_file
         .write_str(
             r#"{
-        "version": "1.0",
+        "version": "0.1.0",
         "nodes": [{"id": "x_0", "name": "x", "type_name": "i32"}],
         "edges": [],
         "events": [{"type": "New", "timestamp": 1, "var_name": "x", "var_id": "x_0", "type_name": "i32"}],
@@ -925,7 +925,7 @@ fn test_check_with_borrow_conflicts() {
     data_file
         .write_str(
             r#"{
-        "version": "1.0",
+        "version": "0.1.0",
         "nodes": [
             {"id": "x_0", "name": "x", "type_name": "String"},
             {"id": "r1_1", "name": "r1", "type_name": "&String"},
@@ -976,29 +976,21 @@ fn test_export_with_graph_cycles() {
     input
         .write_str(
             r#"{
-        "version": "1.0",
-        "nodes": [
-            {"id": "a_0", "name": "a", "type_name": "Rc<RefCell<Node>>"},
-            {"id": "b_1", "name": "b", "type_name": "Rc<RefCell<Node>>"}
-        ],
-        "edges": [
-            {"from": "a_0", "to": "b_1", "relationship": "references"},
-            {"from": "b_1", "to": "a_0", "relationship": "references"}
-        ],
+        "version": 1,
         "events": [
-            {"type": "RcNew", "timestamp": 1, "var_name": "a", "var_id": "a_0", "type_name": "Rc<RefCell<Node>>", "strong_count": 1},
-            {"type": "RcNew", "timestamp": 2, "var_name": "b", "var_id": "b_1", "type_name": "Rc<RefCell<Node>>", "strong_count": 1},
-            {"type": "RcClone", "timestamp": 3, "var_name": "a_ref", "var_id": "a_0", "strong_count": 2},
-            {"type": "RcClone", "timestamp": 4, "var_name": "b_ref", "var_id": "b_1", "strong_count": 2}
+            {"type": "RcNew", "timestamp": 1, "var_name": "a", "var_id": "a_0", "type_name": "Rc<RefCell<Node>>", "strong_count": 1, "weak_count": 0},
+            {"type": "RcNew", "timestamp": 2, "var_name": "b", "var_id": "b_1", "type_name": "Rc<RefCell<Node>>", "strong_count": 1, "weak_count": 0},
+            {"type": "RcClone", "timestamp": 3, "var_name": "a_ref", "var_id": "a_2", "source_id": "a_0", "strong_count": 2, "weak_count": 0},
+            {"type": "RcClone", "timestamp": 4, "var_name": "b_ref", "var_id": "b_3", "source_id": "b_1", "strong_count": 2, "weak_count": 0}
         ],
         "graph": {
             "nodes": [
-                {"id": "a_0", "name": "a", "type_name": "Rc<RefCell<Node>>"},
-                {"id": "b_1", "name": "b", "type_name": "Rc<RefCell<Node>>"}
+                {"id": "a_0", "name": "a", "type_name": "Rc<RefCell<Node>>", "created_at": 1, "dropped_at": null},
+                {"id": "b_1", "name": "b", "type_name": "Rc<RefCell<Node>>", "created_at": 2, "dropped_at": null}
             ],
             "edges": [
-                {"from": "a_0", "to": "b_1", "relationship": "references"},
-                {"from": "b_1", "to": "a_0", "relationship": "references"}
+                {"from": "a_0", "to": "b_1", "relationship": "owns"},
+                {"from": "b_1", "to": "a_0", "relationship": "owns"}
             ]
         },
         "metadata": {"total_variables": 2, "total_relationships": 2, "immutable_borrows": 0, "mutable_borrows": 0, "total_events": 4}
@@ -1052,7 +1044,7 @@ fn test_check_with_large_dataset() {
 
     let json = format!(
         r#"{{
-        "version": "1.0",
+        "version": "0.1.0",
         "nodes": [{}],
         "edges": [],
         "events": [{}],
@@ -1118,7 +1110,7 @@ fn test_export_format_auto_detection() {
 
     input
         .write_str(
-            r#"{"version": "1.0", "nodes": [], "edges": [], "events": [], "graph": {"nodes": [], "edges": []}, "metadata": {}}"#,
+            r#"{"version": "0.1.0", "nodes": [], "edges": [], "events": [], "graph": {"nodes": [], "edges": []}, "metadata": {}}"#,
         )
         .unwrap();
 
@@ -1161,7 +1153,7 @@ fn test_concurrent_export_operations() {
 
     input
         .write_str(
-            r#"{"version": "1.0", "nodes": [], "edges": [], "events": [], "graph": {"nodes": [], "edges": []}, "metadata": {}}"#,
+            r#"{"version": "0.1.0", "nodes": [], "edges": [], "events": [], "graph": {"nodes": [], "edges": []}, "metadata": {}}"#,
         )
         .unwrap();
 
@@ -1237,7 +1229,7 @@ fn test_check_with_validate_detects_invalid_graph() {
     data_file
         .write_str(
             r#"{
-        "version": "1.0",
+        "version": "0.1.0",
         "nodes": [{"id": "x_0", "name": "x", "type_name": "i32"}],
         "edges": [{"from": "x_0", "to": "nonexistent", "relationship": "borrows"}],
         "events": [],
@@ -1268,23 +1260,21 @@ fn test_export_with_unicode_variable_names() {
     input
         .write_str(
             r#"{
-        "version": "1.0",
-        "nodes": [
-            {"id": "变量_0", "name": "变量", "type_name": "i32"},
-            {"id": "переменная_1", "name": "переменная", "type_name": "String"},
-            {"id": "🦀_2", "name": "🦀", "type_name": "Vec<u8>"}
+        "version": 1,
+        "events": [
+            {"type": "New", "timestamp": 1, "var_name": "变量", "var_id": "变量_0", "type_name": "i32"},
+            {"type": "New", "timestamp": 2, "var_name": "переменная", "var_id": "переменная_1", "type_name": "String"},
+            {"type": "New", "timestamp": 3, "var_name": "🦀", "var_id": "🦀_2", "type_name": "Vec<u8>"}
         ],
-        "edges": [],
-        "events": [],
         "graph": {
             "nodes": [
-                {"id": "变量_0", "name": "变量", "type_name": "i32"},
-                {"id": "переменная_1", "name": "переменная", "type_name": "String"},
-                {"id": "🦀_2", "name": "🦀", "type_name": "Vec<u8>"}
+                {"id": "变量_0", "name": "变量", "type_name": "i32", "created_at": 1, "dropped_at": null},
+                {"id": "переменная_1", "name": "переменная", "type_name": "String", "created_at": 2, "dropped_at": null},
+                {"id": "🦀_2", "name": "🦀", "type_name": "Vec<u8>", "created_at": 3, "dropped_at": null}
             ],
             "edges": []
         },
-        "metadata": {}
+        "metadata": {"total_variables": 3, "total_relationships": 0, "immutable_borrows": 0, "mutable_borrows": 0, "total_events": 3}
     }"#,
         )
         .unwrap();
@@ -0,0 +1,497 @@
+use crate::{LayoutConfig, OwnershipGraph, Position, Relationship, Variable};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const NODE_HEIGHT: f64 = 44.0;
+const MIN_NODE_WIDTH: f64 = 80.0;
+const CHAR_WIDTH: f64 = 7.0;
+const NODE_PADDING: f64 = 24.0;
+const MARGIN: f64 = 20.0;
+const ORDERING_SWEEPS: usize = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeLayout {
+    pub id: usize,
+    /// Centre of the node box
+    pub position: Position,
+    pub width: f64,
+    pub height: f64,
+    pub rank: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeLayout {
+    pub from_id: usize,
+    pub to_id: usize,
+    pub relationship: Relationship,
+    /// Polyline from the source box boundary, through any bends, to the target box boundary
+    pub points: Vec<Position>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphLayout {
+    pub nodes: Vec<NodeLayout>,
+    pub edges: Vec<EdgeLayout>,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl GraphLayout {
+    pub fn node(&self, id: usize) -> Option<&NodeLayout> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RankDir {
+    LeftRight,
+    RightLeft,
+    TopBottom,
+    BottomTop,
+}
+
+impl LayoutConfig {
+    fn option_f64(&self, key: &str, default: f64) -> f64 {
+        self.options
+            .as_ref()
+            .and_then(|o| o.get(key))
+            .and_then(|v| v.as_f64())
+            .unwrap_or(default)
+    }
+
+    fn rank_dir(&self) -> RankDir {
+        match self
+            .options
+            .as_ref()
+            .and_then(|o| o.get("rankDir"))
+            .and_then(|v| v.as_str())
+        {
+            Some("RL") => RankDir::RightLeft,
+            Some("TB") => RankDir::TopBottom,
+            Some("BT") => RankDir::BottomTop,
+            _ => RankDir::LeftRight,
+        }
+    }
+}
+
+/// A slot in a rank: either a real variable or a bend point of a long edge
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Node(usize),
+    Dummy,
+}
+
+/// Box size for a variable, wide enough for its name and type
+fn node_size(var: &Variable) -> (f64, f64) {
+    let chars = var.name.chars().count().max(var.type_name.chars().count()) as f64;
+    (
+        (chars * CHAR_WIDTH + NODE_PADDING).max(MIN_NODE_WIDTH),
+        NODE_HEIGHT,
+    )
+}
+
+/// Point where the segment from a box centre towards `toward` leaves the box
+fn clip_to_box(center: &Position, half_w: f64, half_h: f64, toward: &Position) -> Position {
+    let dx = toward.x - center.x;
+    let dy = toward.y - center.y;
+    if dx == 0.0 && dy == 0.0 {
+        return center.clone();
+    }
+
+    let scale_x = if dx == 0.0 {
+        f64::INFINITY
+    } else {
+        half_w / dx.abs()
+    };
+    let scale_y = if dy == 0.0 {
+        f64::INFINITY
+    } else {
+        half_h / dy.abs()
+    };
+    let scale = scale_x.min(scale_y).min(1.0);
+
+    Position {
+        x: center.x + dx * scale,
+        y: center.y + dy * scale,
+    }
+}
+
+impl OwnershipGraph {
+    /// Layered (Sugiyama-style) layout honouring the `rankDir`, `nodeSep` and
+    /// `rankSep` options of a [`LayoutConfig`] such as [`LayoutConfig::dagre`].
+    ///
+    /// Cycles are broken by reversing DFS back edges, ranks are assigned by
+    /// longest path, long edges are split with bend points, and crossings are
+    /// reduced with barycenter sweeps. The result is deterministic for a given
+    /// graph.
+    pub fn layered_layout(&self, config: &LayoutConfig) -> GraphLayout {
+        let node_sep = config.option_f64("nodeSep", 50.0);
+        let rank_sep = config.option_f64("rankSep", 100.0);
+        let rank_dir = config.rank_dir();
+        let horizontal = matches!(rank_dir, RankDir::LeftRight | RankDir::RightLeft);
+
+        let export = self.export();
+        let mut vars: Vec<&Variable> = export.nodes.iter().collect();
+        vars.sort_by_key(|v| v.id);
+
+        let index: HashMap<usize, usize> =
+            vars.iter().enumerate().map(|(i, v)| (v.id, i)).collect();
+        let n = vars.len();
+
+        // Edges as (source, target) indices; self loops do not affect ranking
+        let edges: Vec<(usize, usize)> = export
+            .edges
+            .iter()
+            .filter_map(|e| Some((*index.get(&e.from_id)?, *index.get(&e.to_id)?)))
+            .collect();
+
+        // 1. Break cycles by reversing back edges found by DFS
+        let mut successors = vec![vec![]; n];
+        for &(s, t) in &edges {
+            if s != t {
+                successors[s].push(t);
+            }
+        }
+        let reversed = back_edges(&successors);
+        let acyclic: Vec<(usize, usize)> = edges
+            .iter()
+            .filter(|(s, t)| s != t)
+            .map(|&(s, t)| {
+                if reversed.contains(&(s, t)) {
+                    (t, s)
+                } else {
+                    (s, t)
+                }
+            })
+            .collect();
+
+        // 2. Longest-path ranking, then pull sources towards their successors
+        let ranks = assign_ranks(n, &acyclic);
+        let max_rank = ranks.iter().copied().max().unwrap_or(0);
+
+        // 3. Split edges spanning several ranks into chains through dummy slots
+        let mut slots: Vec<Slot> = (0..n).map(Slot::Node).collect();
+        let mut slot_rank = ranks.clone();
+        let mut chains: Vec<Vec<usize>> = Vec::with_capacity(acyclic.len());
+        let mut slot_edges: Vec<(usize, usize)> = Vec::new();
+        for &(s, t) in &acyclic {
+            let mut chain = vec![s];
+            for r in (ranks[s] + 1)..ranks[t] {
+                slots.push(Slot::Dummy);
+                slot_rank.push(r);
+                chain.push(slots.len() - 1);
+            }
+            chain.push(t);
+            for pair in chain.windows(2) {
+                slot_edges.push((pair[0], pair[1]));
+            }
+            chains.push(chain);
+        }
+
+        // 4. Order slots within each rank, reducing crossings
+        let mut layers: Vec<Vec<usize>> = vec![vec![]; max_rank + 1];
+        for (slot, &r) in slot_rank.iter().enumerate() {
+            layers[r].push(slot);
+        }
+        order_layers(&mut layers, &slot_edges, slots.len());
+
+        // 5. Coordinates in rank-major space: `along` follows the ranks,
+        //    `across` separates slots of the same rank
+        let sizes: Vec<(f64, f64)> = slots
+            .iter()
+            .map(|slot| match slot {
+                Slot::Node(i) => {
+                    let (w, h) = node_size(vars[*i]);
+                    if horizontal {
+                        (w, h)
+                    } else {
+                        (h, w)
+                    }
+                }
+                Slot::Dummy => (0.0, 0.0),
+            })
+            .collect();
+
+        let rank_depths: Vec<f64> = layers
+            .iter()
+            .map(|layer| layer.iter().map(|&s| sizes[s].0).fold(0.0, f64::max))
+            .collect();
+        let rank_extents: Vec<f64> = layers
+            .iter()
+            .map(|layer| {
+                let total: f64 = layer.iter().map(|&s| sizes[s].1).sum();
+                total + node_sep * layer.len().saturating_sub(1) as f64
+            })
+            .collect();
+        let max_extent = rank_extents.iter().copied().fold(0.0, f64::max);
+
+        let mut along = vec![0.0; slots.len()];
+        let mut across = vec![0.0; slots.len()];
+        let mut offset = 0.0;
+        for (r, layer) in layers.iter().enumerate() {
+            let centre = offset + rank_depths[r] / 2.0;
+            let mut cursor = (max_extent - rank_extents[r]) / 2.0;
+            for &slot in layer {
+                along[slot] = centre;
+                across[slot] = cursor + sizes[slot].1 / 2.0;
+                cursor += sizes[slot].1 + node_sep;
+            }
+            offset += rank_depths[r] + rank_sep;
+        }
+        let total_along = (offset - rank_sep).max(0.0);
+
+        let place = |slot: usize| -> Position {
+            let (a, c) = (along[slot], across[slot]);
+            let (x, y) = match rank_dir {
+                RankDir::LeftRight => (a, c),
+                RankDir::RightLeft => (total_along - a, c),
+                RankDir::TopBottom => (c, a),
+                RankDir::BottomTop => (c, total_along - a),
+            };
+            Position {
+                x: x + MARGIN,
+                y: y + MARGIN,
+            }
+        };
+
+        let nodes: Vec<NodeLayout> = (0..n)
+            .map(|i| {
+                let (width, height) = node_size(vars[i]);
+                NodeLayout {
+                    id: vars[i].id,
+                    position: place(i),
+                    width,
+                    height,
+                    rank: ranks[i],
+                }
+            })
+            .collect();
+
+        // 6. Route each original edge through its chain and clip to the boxes
+        let mut chain_iter = chains.into_iter();
+        let edges: Vec<EdgeLayout> = export
+            .edges
+            .iter()
+            .filter_map(|e| {
+                let s = *index.get(&e.from_id)?;
+                let t = *index.get(&e.to_id)?;
+
+                let mut points: Vec<Position> = if s == t {
+                    self_loop(&nodes[s], horizontal)
+                } else {
+                    let chain = chain_iter.next()?;
+                    let mut points: Vec<Position> = chain.iter().map(|&slot| place(slot)).collect();
+                    if reversed.contains(&(s, t)) {
+                        points.reverse();
+                    }
+                    points
+                };
+
+                if s != t {
+                    let last = points.len() - 1;
+                    points[0] = clip_to_box(
+                        &nodes[s].position,
+                        nodes[s].width / 2.0,
+                        nodes[s].height / 2.0,
+                        &points[1],
+                    );
+                    points[last] = clip_to_box(
+                        &nodes[t].position,
+                        nodes[t].width / 2.0,
+                        nodes[t].height / 2.0,
+                        &points[last - 1],
+                    );
+                }
+
+                Some(EdgeLayout {
+                    from_id: e.from_id,
+                    to_id: e.to_id,
+                    relationship: e.relationship.clone(),
+                    points,
+                })
+            })
+            .collect();
+
+        let (width, height) = if horizontal {
+            (total_along, max_extent)
+        } else {
+            (max_extent, total_along)
+        };
+
+        GraphLayout {
+            nodes,
+            edges,
+            width: width + 2.0 * MARGIN,
+            height: height + 2.0 * MARGIN,
+        }
+    }
+}
+
+/// Edges that close a cycle during a DFS in index order
+fn back_edges(successors: &[Vec<usize>]) -> Vec<(usize, usize)> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        New,
+        Active,
+        Done,
+    }
+
+    let mut marks = vec![Mark::New; successors.len()];
+    let mut back = Vec::new();
+
+    for root in 0..successors.len() {
+        if marks[root] != Mark::New {
+            continue;
+        }
+        let mut stack = vec![(root, 0usize)];
+        marks[root] = Mark::Active;
+
+        while let Some((node, next)) = stack.pop() {
+            if let Some(&succ) = successors[node].get(next) {
+                stack.push((node, next + 1));
+                match marks[succ] {
+                    Mark::New => {
+                        marks[succ] = Mark::Active;
+                        stack.push((succ, 0));
+                    }
+                    Mark::Active => back.push((node, succ)),
+                    Mark::Done => {}
+                }
+            } else {
+                marks[node] = Mark::Done;
+            }
+        }
+    }
+
+    back
+}
+
+/// Longest-path ranks over an acyclic edge list, with sources moved as close
+/// to their successors as possible to keep edges short
+fn assign_ranks(n: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut indegree = vec![0; n];
+    let mut successors = vec![vec![]; n];
+    let mut predecessors = vec![vec![]; n];
+    for &(s, t) in edges {
+        indegree[t] += 1;
+        successors[s].push(t);
+        predecessors[t].push(s);
+    }
+
+    let mut ranks = vec![0; n];
+    let mut queue: std::collections::VecDeque<usize> =
+        (0..n).filter(|&i| indegree[i] == 0).collect();
+    let mut order = Vec::with_capacity(n);
+    while let Some(node) = queue.pop_front() {
+        order.push(node);
+        for &succ in &successors[node] {
+            ranks[succ] = ranks[succ].max(ranks[node] + 1);
+            indegree[succ] -= 1;
+            if indegree[succ] == 0 {
+                queue.push_back(succ);
+            }
+        }
+    }
+
+    for &node in order.iter().rev() {
+        if predecessors[node].is_empty() {
+            if let Some(min_succ) = successors[node].iter().map(|&s| ranks[s]).min() {
+                ranks[node] = min_succ - 1;
+            }
+        }
+    }
+
+    ranks
+}
+
+/// Barycenter crossing reduction, alternating downward and upward sweeps
+fn order_layers(layers: &mut [Vec<usize>], edges: &[(usize, usize)], slot_count: usize) {
+    let mut up = vec![vec![]; slot_count];
+    let mut down = vec![vec![]; slot_count];
+    for &(s, t) in edges {
+        down[s].push(t);
+        up[t].push(s);
+    }
+
+    let mut position = vec![0.0; slot_count];
+    for layer in layers.iter() {
+        for (i, &slot) in layer.iter().enumerate() {
+            position[slot] = i as f64;
+        }
+    }
+
+    for sweep in 0..ORDERING_SWEEPS {
+        let downward = sweep % 2 == 0;
+        let ranks: Vec<usize> = if downward {
+            (1..layers.len()).collect()
+        } else {
+            (0..layers.len().saturating_sub(1)).rev().collect()
+        };
+
+        for r in ranks {
+            let neighbours = if downward { &up } else { &down };
+            let mut keyed: Vec<(f64, usize)> = layers[r]
+                .iter()
+                .map(|&slot| {
+                    let adj = &neighbours[slot];
+                    let key = if adj.is_empty() {
+                        position[slot]
+                    } else {
+                        adj.iter().map(|&a| position[a]).sum::<f64>() / adj.len() as f64
+                    };
+                    (key, slot)
+                })
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+            layers[r] = keyed.into_iter().map(|(_, slot)| slot).collect();
+            for (i, &slot) in layers[r].iter().enumerate() {
+                position[slot] = i as f64;
+            }
+        }
+    }
+}
+
+/// A small loop leaving and re-entering the box on the side facing away from the ranks
+fn self_loop(node: &NodeLayout, horizontal: bool) -> Vec<Position> {
+    let Position { x, y } = node.position;
+    let (hw, hh) = (node.width / 2.0, node.height / 2.0);
+    if horizontal {
+        vec![
+            Position {
+                x: x - hw / 2.0,
+                y: y - hh,
+            },
+            Position {
+                x: x - hw / 2.0,
+                y: y - hh - 20.0,
+            },
+            Position {
+                x: x + hw / 2.0,
+                y: y - hh - 20.0,
+            },
+            Position {
+                x: x + hw / 2.0,
+                y: y - hh,
+            },
+        ]
+    } else {
+        vec![
+            Position {
+                x: x + hw,
+                y: y - hh / 2.0,
+            },
+            Position {
+                x: x + hw + 20.0,
+                y: y - hh / 2.0,
+            },
+            Position {
+                x: x + hw + 20.0,
+                y: y + hh / 2.0,
+            },
+            Position {
+                x: x + hw,
+                y: y + hh / 2.0,
+            },
+        ]
+    }
+}
@@ -1,15 +1,20 @@
 mod conflicts;
+mod layout;
 mod performance;
 mod query;
+mod query_language;
 mod serialization;
+mod svg;
 mod visualization;
 
 pub use conflicts::{BorrowConflict, ConflictType};
+pub use layout::{EdgeLayout, GraphLayout, NodeLayout};
 pub use performance::{
     BatchGraph, CachedOwnershipGraph, ConcurrentGraph, GraphMetrics, GraphStats, LazyGraph,
     MemoryStats,
 };
 pub use query::{FilteredQuery, QueryBuilder};
+pub use query_language::{Query, QueryError};
 pub use serialization::{EnhancedGraphExport, GraphDelta, GraphMetadata};
 pub use visualization::{
     D3Export, D3Link, D3Node, EdgeData, EdgeElement, Elements, HighlightConfig, LayoutConfig,
@@ -650,7 +655,7 @@ impl OwnershipGraph {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GraphStatistics {
     pub total_variables: usize,
     pub alive_variables: usize,
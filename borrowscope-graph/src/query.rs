@@ -169,7 +169,7 @@ impl OwnershipGraph {
         use petgraph::algo::tarjan_scc;
         tarjan_scc(&self.graph)
             .into_iter()
-            .filter(|scc| scc.len() > 1)
+            .filter(|scc| scc.len() > 1 || self.graph.contains_edge(scc[0], scc[0]))
             .map(|scc| scc.into_iter().map(|idx| self.graph[idx].id).collect())
             .collect()
     }
@@ -496,6 +496,11 @@ impl<'a> FilteredQuery<'a> {
         self
     }
 
+    pub fn and_where(mut self, mut predicate: impl FnMut(&Variable) -> bool) -> Self {
+        self.variables.retain(|v| predicate(v));
+        self
+    }
+
     pub fn count(self) -> usize {
         self.variables.len()
     }
@@ -0,0 +1,334 @@
+use crate::{FilteredQuery, OwnershipGraph, Variable};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+const FIELDS: &str = "name, type, alive_at, created, dropped, lifetime, scope_depth, \
+                      borrowed_by, transitive_borrowers, borrows, overlaps";
+
+/// A textual ownership query, e.g. `type:Vec* alive_at:120 borrowed_by>2 scope_depth:1`
+///
+/// A query is a list of whitespace-separated `field<op>value` terms that must
+/// all hold. `name` and `type` take a pattern where `*` and `?` are wildcards;
+/// quote values that contain spaces, as in `type:"&mut Vec<i32>"`. Numeric
+/// fields compare with `:`, `=`, `!=`, `<`, `<=`, `>` or `>=`. `borrows` and
+/// `overlaps` name another variable, or a node ID as `#3`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    pub term: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Name(String),
+    Type(String),
+    AliveAt(u64),
+    Dropped(bool),
+    Compare(Field, Op, u64),
+    /// Has a borrow chain to the target
+    Borrows(Target),
+    /// Lives at the same time as the target
+    Overlaps(Target),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Created,
+    Dropped,
+    Lifetime,
+    ScopeDepth,
+    BorrowedBy,
+    TransitiveBorrowers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    Name(String),
+    Id(usize),
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let terms = tokenize(input)?
+            .into_iter()
+            .map(|(raw, value)| Term::parse(&raw, value))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { terms })
+    }
+
+    /// Variables matching every term, in graph order
+    pub fn evaluate<'a>(&self, graph: &'a OwnershipGraph) -> Vec<&'a Variable> {
+        self.terms
+            .iter()
+            .fold(graph.query().all(), |query, term| term.apply(graph, query))
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Whether any term filters on `scope_depth`, which only graphs built
+    /// with scope information can answer
+    pub fn uses_scope_depth(&self) -> bool {
+        self.terms
+            .iter()
+            .any(|term| matches!(term, Term::Compare(Field::ScopeDepth, ..)))
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl QueryError {
+    fn new(term: &str, message: impl Into<String>) -> Self {
+        Self {
+            term: term.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid term `{}`: {}", self.term, self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Split on whitespace outside quotes, returning each term as written and
+/// with the quotes removed
+fn tokenize(input: &str) -> Result<Vec<(String, String)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while chars.peek().is_some() {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut raw = String::new();
+        let mut unquoted = String::new();
+        let mut in_quotes = false;
+        while let Some(c) = chars.next_if(|&c| in_quotes || !c.is_whitespace()) {
+            raw.push(c);
+            if c == '"' {
+                in_quotes = !in_quotes;
+            } else {
+                unquoted.push(c);
+            }
+        }
+        if in_quotes {
+            return Err(QueryError::new(&raw, "unterminated quote"));
+        }
+        tokens.push((raw, unquoted));
+    }
+    Ok(tokens)
+}
+
+impl Term {
+    fn parse(raw: &str, term: String) -> Result<Self, QueryError> {
+        let key_len = term
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(term.len());
+        let (key, rest) = term.split_at(key_len);
+        let (op, value) = [
+            (">=", Op::Ge),
+            ("<=", Op::Le),
+            ("!=", Op::Ne),
+            (">", Op::Gt),
+            ("<", Op::Lt),
+            ("=", Op::Eq),
+            (":", Op::Eq),
+        ]
+        .into_iter()
+        .find_map(|(symbol, op)| rest.strip_prefix(symbol).map(|value| (op, value)))
+        .ok_or_else(|| QueryError::new(raw, "expected `field:value`"))?;
+
+        if value.is_empty() {
+            return Err(QueryError::new(raw, "missing value"));
+        }
+
+        let equality = |term: Term| {
+            if op == Op::Eq {
+                Ok(term)
+            } else {
+                Err(QueryError::new(raw, format!("`{}` only supports `:`", key)))
+            }
+        };
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| QueryError::new(raw, format!("expected a number, found `{}`", value)))
+        };
+
+        match key {
+            "name" => equality(Term::Name(value.to_string())),
+            "type" => equality(Term::Type(value.to_string())),
+            "alive_at" => equality(Term::AliveAt(number()?)),
+            "borrows" => equality(Term::Borrows(Target::parse(raw, value)?)),
+            "overlaps" => equality(Term::Overlaps(Target::parse(raw, value)?)),
+            "dropped" => match value {
+                "true" => equality(Term::Dropped(true)),
+                "false" => equality(Term::Dropped(false)),
+                _ => Ok(Term::Compare(Field::Dropped, op, number()?)),
+            },
+            "created" => Ok(Term::Compare(Field::Created, op, number()?)),
+            "lifetime" => Ok(Term::Compare(Field::Lifetime, op, number()?)),
+            "scope_depth" => Ok(Term::Compare(Field::ScopeDepth, op, number()?)),
+            "borrowed_by" => Ok(Term::Compare(Field::BorrowedBy, op, number()?)),
+            "transitive_borrowers" => Ok(Term::Compare(Field::TransitiveBorrowers, op, number()?)),
+            "" => Err(QueryError::new(raw, "missing field name")),
+            _ => Err(QueryError::new(
+                raw,
+                format!("unknown field `{}`, expected one of {}", key, FIELDS),
+            )),
+        }
+    }
+
+    /// Narrow the query, using the builder's own filters where one exists
+    fn apply<'a>(&self, graph: &'a OwnershipGraph, query: FilteredQuery<'a>) -> FilteredQuery<'a> {
+        match self {
+            Term::Name(pattern) if !is_glob(pattern) => query.and_by_name(pattern),
+            Term::Name(pattern) => query.and_where(|v| glob_match(pattern, &v.name)),
+            Term::Type(pattern) if !is_glob(pattern) => query.and_by_type(pattern),
+            Term::Type(pattern) => query.and_where(|v| glob_match(pattern, &v.type_name)),
+            Term::AliveAt(time) => query.and_alive_at(*time),
+            Term::Dropped(true) => query.and_dropped(),
+            Term::Dropped(false) => query.and_not_dropped(),
+            Term::Compare(Field::Created, Op::Gt, time) => query.and_created_after(*time),
+            Term::Compare(Field::Created, Op::Lt, time) => query.and_created_before(*time),
+            Term::Compare(Field::ScopeDepth, Op::Eq, depth) => match usize::try_from(*depth) {
+                Ok(depth) => query.and_in_scope(depth),
+                Err(_) => query.and_where(|_| false),
+            },
+            Term::Compare(field, op, expected) => query.and_where(|v| {
+                field
+                    .value(graph, v)
+                    .is_some_and(|actual| op.holds(actual, *expected))
+            }),
+            Term::Borrows(target) => {
+                let targets = target.resolve(graph);
+                query.and_where(|v| {
+                    targets
+                        .iter()
+                        .any(|&id| id != v.id && graph.borrow_chain(v.id, id).is_some())
+                })
+            }
+            Term::Overlaps(target) => {
+                let overlapping: HashSet<usize> = target
+                    .resolve(graph)
+                    .into_iter()
+                    .flat_map(|id| graph.find_overlapping_lifetimes(id))
+                    .map(|v| v.id)
+                    .collect();
+                query.and_where(|v| overlapping.contains(&v.id))
+            }
+        }
+    }
+}
+
+impl Field {
+    fn value(self, graph: &OwnershipGraph, var: &Variable) -> Option<u64> {
+        match self {
+            Field::Created => Some(var.created_at),
+            Field::Dropped => var.dropped_at,
+            Field::Lifetime => var.dropped_at.map(|d| d.saturating_sub(var.created_at)),
+            Field::ScopeDepth => Some(var.scope_depth as u64),
+            Field::BorrowedBy => Some(graph.borrowers_of(var.id).len() as u64),
+            Field::TransitiveBorrowers => {
+                Some(graph.find_transitive_borrowers(var.id).len() as u64)
+            }
+        }
+    }
+}
+
+impl Op {
+    fn holds(self, actual: u64, expected: u64) -> bool {
+        match self {
+            Op::Eq => actual == expected,
+            Op::Ne => actual != expected,
+            Op::Lt => actual < expected,
+            Op::Le => actual <= expected,
+            Op::Gt => actual > expected,
+            Op::Ge => actual >= expected,
+        }
+    }
+}
+
+impl Target {
+    fn parse(raw: &str, value: &str) -> Result<Self, QueryError> {
+        match value.strip_prefix('#') {
+            Some(id) => id
+                .parse()
+                .map(Target::Id)
+                .map_err(|_| QueryError::new(raw, format!("expected a node ID, found `{}`", id))),
+            None => Ok(Target::Name(value.to_string())),
+        }
+    }
+
+    /// Every variable the target may refer to; a shadowed name matches all its bindings
+    fn resolve(&self, graph: &OwnershipGraph) -> Vec<usize> {
+        match self {
+            Target::Name(name) => graph.find_all_by_name(name).iter().map(|v| v.id).collect(),
+            Target::Id(id) => graph.get_variable(*id).map(|v| v.id).into_iter().collect(),
+        }
+    }
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Match `text` against a pattern where `*` is any run of characters and `?` any one
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,205 @@
+use crate::{GraphLayout, LayoutConfig, OwnershipGraph, Position, Relationship, StyleRule};
+use std::fmt::Write;
+
+const FONT_FAMILY: &str = "DejaVu Sans, Helvetica, Arial, sans-serif";
+const BACKGROUND: &str = "#ffffff";
+
+/// Resolved drawing style for a node or edge
+struct Style {
+    fill: String,
+    stroke: String,
+    opacity: f64,
+    width: f64,
+    dashed: bool,
+}
+
+impl Style {
+    /// Apply matching rules in order, so later selectors win as they do in the viewer
+    fn cascade(rules: &[StyleRule], element: &str, classes: &[&str]) -> Self {
+        let mut style = Style {
+            fill: "#3498db".into(),
+            stroke: "#95a5a6".into(),
+            opacity: 1.0,
+            width: 2.0,
+            dashed: false,
+        };
+
+        for rule in rules {
+            let matches = match rule.selector.split_once('.') {
+                Some((kind, class)) => kind == element && classes.contains(&class),
+                None => rule.selector == element,
+            };
+            if !matches {
+                continue;
+            }
+
+            let get = |key: &str| rule.style.get(key);
+            if let Some(color) = get("background-color").and_then(|v| v.as_str()) {
+                style.fill = color.into();
+            }
+            if let Some(color) = get("border-color")
+                .or_else(|| get("line-color"))
+                .and_then(|v| v.as_str())
+            {
+                style.stroke = color.into();
+            }
+            if let Some(opacity) = get("opacity").and_then(|v| v.as_f64()) {
+                style.opacity = opacity;
+            }
+            if let Some(width) = get("width").and_then(|v| v.as_f64()) {
+                style.width = width;
+            }
+            if let Some(line) = get("line-style").and_then(|v| v.as_str()) {
+                style.dashed = line == "dashed";
+            }
+        }
+
+        style
+    }
+}
+
+fn edge_class(relationship: &Relationship) -> &'static str {
+    match relationship {
+        Relationship::BorrowsImmut { .. } => "immutable",
+        Relationship::BorrowsMut { .. } => "mutable",
+        Relationship::Moves { .. } => "move",
+        Relationship::RcClone { .. } => "rc",
+        Relationship::ArcClone { .. } => "arc",
+        Relationship::RefCellBorrow { .. } => "refcell",
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn points_attr(points: &[Position]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{:.1},{:.1}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl OwnershipGraph {
+    /// Render the graph as a standalone SVG document using the dagre layout
+    pub fn to_svg(&self) -> String {
+        self.to_svg_with_layout(&LayoutConfig::dagre())
+    }
+
+    /// Render the graph as a standalone SVG document.
+    ///
+    /// Colours follow the same style rules as [`OwnershipGraph::export_for_visualization`].
+    pub fn to_svg_with_layout(&self, config: &LayoutConfig) -> String {
+        let layout = self.layered_layout(config);
+        self.render_svg(&layout)
+    }
+
+    fn render_svg(&self, layout: &GraphLayout) -> String {
+        let rules = Self::default_styles();
+        let mut svg = String::new();
+
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.0} {h:.0}" font-family="{FONT_FAMILY}">"#,
+            w = layout.width,
+            h = layout.height,
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{BACKGROUND}"/>"#
+        );
+
+        // One arrowhead marker per edge class so heads match the line colour
+        svg.push_str("<defs>\n");
+        for class in ["immutable", "mutable", "move", "rc", "arc", "refcell"] {
+            let style = Style::cascade(&rules, "edge", &[class]);
+            let _ = writeln!(
+                svg,
+                r#"<marker id="arrow-{class}" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="8" markerHeight="8" orient="auto-start-reverse"><path d="M0,0 L10,5 L0,10 z" fill="{}"/></marker>"#,
+                style.stroke
+            );
+        }
+        svg.push_str("</defs>\n");
+
+        svg.push_str("<g class=\"edges\">\n");
+        for edge in &layout.edges {
+            let class = edge_class(&edge.relationship);
+            let style = Style::cascade(&rules, "edge", &[class]);
+            let dash = if style.dashed {
+                r#" stroke-dasharray="6,4""#
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                svg,
+                r#"<polyline class="edge {class}" data-from="{}" data-to="{}" points="{}" fill="none" stroke="{}" stroke-width="{}"{dash} marker-end="url(#arrow-{class})"/>"#,
+                edge.from_id,
+                edge.to_id,
+                points_attr(&edge.points),
+                style.stroke,
+                style.width,
+            );
+        }
+        svg.push_str("</g>\n");
+
+        svg.push_str("<g class=\"nodes\">\n");
+        for node in &layout.nodes {
+            let Some(var) = self.get_variable(node.id) else {
+                continue;
+            };
+            let classes = self.node_classes(var);
+            let class_list: Vec<&str> = classes.split(' ').collect();
+            let style = Style::cascade(&rules, "node", &class_list);
+
+            let x = node.position.x - node.width / 2.0;
+            let y = node.position.y - node.height / 2.0;
+            let _ = writeln!(
+                svg,
+                r#"<g class="node {classes}" data-id="{}" opacity="{}">"#,
+                node.id, style.opacity
+            );
+            let _ = writeln!(
+                svg,
+                r#"<title>{}: {}</title>"#,
+                escape(&var.name),
+                escape(&var.type_name)
+            );
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{x:.1}" y="{y:.1}" width="{:.1}" height="{:.1}" rx="6" fill="{}" stroke="{}" stroke-width="2"/>"#,
+                node.width, node.height, style.fill, style.stroke
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="middle" font-size="12" font-weight="bold" fill="white">{}</text>"#,
+                node.position.x,
+                node.position.y - 3.0,
+                escape(&var.name)
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="middle" font-size="10" fill="white">{}</text>"#,
+                node.position.x,
+                node.position.y + 12.0,
+                escape(&var.type_name)
+            );
+            svg.push_str("</g>\n");
+        }
+        svg.push_str("</g>\n");
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
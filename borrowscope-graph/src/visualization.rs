@@ -214,7 +214,7 @@ impl OwnershipGraph {
         }
     }
 
-    fn node_classes(&self, var: &Variable) -> String {
+    pub(crate) fn node_classes(&self, var: &Variable) -> String {
         let mut classes = vec![];
 
         if var.dropped_at.is_some() {
@@ -244,7 +244,7 @@ impl OwnershipGraph {
         classes.join(" ")
     }
 
-    fn default_styles() -> Vec<StyleRule> {
+    pub(crate) fn default_styles() -> Vec<StyleRule> {
         vec![
             StyleRule {
                 selector: "node".into(),
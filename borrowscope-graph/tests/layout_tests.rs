@@ -0,0 +1,208 @@
+use borrowscope_graph::{LayoutConfig, OwnershipGraph, Variable};
+
+fn var(id: usize, name: &str, type_name: &str) -> Variable {
+    Variable {
+        id,
+        name: name.into(),
+        type_name: type_name.into(),
+        created_at: id as u64 * 10,
+        dropped_at: None,
+        scope_depth: 0,
+    }
+}
+
+fn sample_graph() -> OwnershipGraph {
+    let mut graph = OwnershipGraph::new();
+    graph.add_variable(var(1, "data", "Vec<i32>"));
+    graph.add_variable(var(2, "r1", "&Vec<i32>"));
+    graph.add_variable(var(3, "r2", "&Vec<i32>"));
+    graph.add_variable(var(4, "m", "&mut Vec<i32>"));
+    graph.add_borrow(2, 1, false, 20);
+    graph.add_borrow(3, 1, false, 30);
+    graph.add_borrow(4, 3, true, 40);
+    graph
+}
+
+// ============================================================================
+// Layered Layout Tests
+// ============================================================================
+
+#[test]
+fn test_layout_empty_graph() {
+    let layout = OwnershipGraph::new().layered_layout(&LayoutConfig::dagre());
+
+    assert!(layout.nodes.is_empty());
+    assert!(layout.edges.is_empty());
+}
+
+#[test]
+fn test_layout_every_node_and_edge_placed() {
+    let graph = sample_graph();
+    let layout = graph.layered_layout(&LayoutConfig::dagre());
+
+    assert_eq!(layout.nodes.len(), 4);
+    assert_eq!(layout.edges.len(), 3);
+    for node in &layout.nodes {
+        assert!(node.position.x - node.width / 2.0 >= 0.0);
+        assert!(node.position.y - node.height / 2.0 >= 0.0);
+        assert!(node.position.x + node.width / 2.0 <= layout.width);
+        assert!(node.position.y + node.height / 2.0 <= layout.height);
+    }
+}
+
+#[test]
+fn test_layout_edge_sources_ranked_before_targets() {
+    let graph = sample_graph();
+    let layout = graph.layered_layout(&LayoutConfig::dagre());
+
+    for edge in &layout.edges {
+        let from = layout.node(edge.from_id).unwrap();
+        let to = layout.node(edge.to_id).unwrap();
+        assert!(from.rank < to.rank);
+    }
+}
+
+#[test]
+fn test_layout_left_to_right() {
+    let graph = sample_graph();
+    let layout = graph.layered_layout(&LayoutConfig::dagre());
+
+    // m -> r2 -> data spans three ranks from left to right
+    let m = layout.node(4).unwrap();
+    let r2 = layout.node(3).unwrap();
+    let data = layout.node(1).unwrap();
+    assert!(m.position.x < r2.position.x);
+    assert!(r2.position.x < data.position.x);
+}
+
+#[test]
+fn test_layout_top_to_bottom() {
+    let graph = sample_graph();
+    let config = LayoutConfig {
+        name: "dagre".into(),
+        options: Some(serde_json::json!({"rankDir": "TB"})),
+    };
+    let layout = graph.layered_layout(&config);
+
+    let m = layout.node(4).unwrap();
+    let data = layout.node(1).unwrap();
+    assert!(m.position.y < data.position.y);
+}
+
+#[test]
+fn test_layout_nodes_do_not_overlap() {
+    let graph = sample_graph();
+    let layout = graph.layered_layout(&LayoutConfig::dagre());
+
+    for (i, a) in layout.nodes.iter().enumerate() {
+        for b in &layout.nodes[i + 1..] {
+            let apart_x = (a.position.x - b.position.x).abs() >= (a.width + b.width) / 2.0;
+            let apart_y = (a.position.y - b.position.y).abs() >= (a.height + b.height) / 2.0;
+            assert!(apart_x || apart_y, "nodes {} and {} overlap", a.id, b.id);
+        }
+    }
+}
+
+#[test]
+fn test_layout_long_edge_bends() {
+    let mut graph = sample_graph();
+    // m -> data skips the rank of r2 and is routed through a bend point
+    graph.add_borrow(4, 1, false, 50);
+    let layout = graph.layered_layout(&LayoutConfig::dagre());
+
+    let edge = layout
+        .edges
+        .iter()
+        .find(|e| e.from_id == 4 && e.to_id == 1)
+        .unwrap();
+    assert_eq!(edge.points.len(), 3);
+}
+
+#[test]
+fn test_layout_handles_cycles() {
+    let mut graph = OwnershipGraph::new();
+    graph.add_variable(var(1, "a", "i32"));
+    graph.add_variable(var(2, "b", "i32"));
+    graph.add_move(1, 2, 10);
+    graph.add_move(2, 1, 20);
+
+    let layout = graph.layered_layout(&LayoutConfig::dagre());
+    assert_eq!(layout.nodes.len(), 2);
+    assert_eq!(layout.edges.len(), 2);
+
+    // The reversed edge still starts at its own source
+    let back = layout.edges.iter().find(|e| e.from_id == 2).unwrap();
+    let b = layout.node(2).unwrap();
+    let start = &back.points[0];
+    assert!((start.x - b.position.x).abs() <= b.width / 2.0 + 0.01);
+}
+
+#[test]
+fn test_layout_is_deterministic() {
+    let graph = sample_graph();
+    let first = graph.layered_layout(&LayoutConfig::dagre());
+    let second = graph.layered_layout(&LayoutConfig::dagre());
+
+    for (a, b) in first.nodes.iter().zip(&second.nodes) {
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.position.x, b.position.x);
+        assert_eq!(a.position.y, b.position.y);
+    }
+}
+
+// ============================================================================
+// SVG Rendering Tests
+// ============================================================================
+
+#[test]
+fn test_svg_document() {
+    let svg = sample_graph().to_svg();
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<g class=\"node ").count(), 4);
+    assert_eq!(svg.matches("<polyline class=\"edge ").count(), 3);
+}
+
+#[test]
+fn test_svg_uses_style_colors() {
+    let svg = sample_graph().to_svg();
+
+    // Owned, immutable-ref and mutable-ref nodes
+    assert!(svg.contains("fill=\"#3498db\""));
+    assert!(svg.contains("fill=\"#2ecc71\""));
+    assert!(svg.contains("fill=\"#e74c3c\""));
+    assert!(svg.contains("class=\"edge mutable\""));
+}
+
+#[test]
+fn test_svg_move_edges_dashed() {
+    let mut graph = OwnershipGraph::new();
+    graph.add_variable(var(1, "a", "String"));
+    graph.add_variable(var(2, "b", "String"));
+    graph.add_move(1, 2, 10);
+
+    let svg = graph.to_svg();
+    assert!(svg.contains("stroke-dasharray"));
+}
+
+#[test]
+fn test_svg_escapes_labels() {
+    let mut graph = OwnershipGraph::new();
+    graph.add_variable(var(1, "x", "HashMap<&str, Vec<u8>>"));
+
+    let svg = graph.to_svg();
+    assert!(svg.contains("HashMap&lt;&amp;str, Vec&lt;u8&gt;&gt;"));
+    assert!(!svg.contains("HashMap<&str"));
+}
+
+#[test]
+fn test_svg_dropped_nodes_faded() {
+    let mut graph = OwnershipGraph::new();
+    graph.add_variable(var(1, "x", "i32"));
+    graph.mark_dropped(1, 100);
+
+    let svg = graph.to_svg();
+    assert!(svg.contains("node dropped"));
+    assert!(svg.contains("opacity=\"0.6\""));
+}
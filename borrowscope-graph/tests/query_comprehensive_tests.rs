@@ -31,6 +31,36 @@ fn test_find_cycles_no_cycles() {
     assert!(cycles.is_empty());
 }
 
+#[test]
+fn test_find_cycles_includes_self_reference() {
+    let mut graph = OwnershipGraph::new();
+    for i in 1..=4 {
+        graph.add_variable(Variable {
+            id: i,
+            name: format!("v{}", i),
+            type_name: "Rc<Node>".into(),
+            created_at: i as u64 * 100,
+            dropped_at: None,
+            scope_depth: 0,
+        });
+    }
+    graph.add_rc_clone(1, 2, 2, 500);
+    graph.add_rc_clone(2, 1, 2, 600);
+    graph.add_rc_clone(4, 4, 2, 700);
+
+    let mut cycles: Vec<Vec<usize>> = graph
+        .find_cycles()
+        .into_iter()
+        .map(|mut cycle| {
+            cycle.sort();
+            cycle
+        })
+        .collect();
+    cycles.sort();
+    assert!(graph.has_cycles());
+    assert_eq!(cycles, vec![vec![1, 2], vec![4]]);
+}
+
 // ============================================================================
 // Root and Leaf Tests
 // ============================================================================
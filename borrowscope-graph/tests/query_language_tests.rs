@@ -0,0 +1,138 @@
+use borrowscope_graph::{OwnershipGraph, Query, Variable};
+
+fn var(
+    id: usize,
+    name: &str,
+    type_name: &str,
+    created_at: u64,
+    dropped_at: Option<u64>,
+) -> Variable {
+    Variable {
+        id,
+        name: name.into(),
+        type_name: type_name.into(),
+        created_at,
+        dropped_at,
+        scope_depth: if name.starts_with("inner") { 1 } else { 0 },
+    }
+}
+
+/// `data` is borrowed by `a`, `b` and `c`; `d` borrows `a`
+fn sample_graph() -> OwnershipGraph {
+    let mut graph = OwnershipGraph::new();
+    graph.add_variable(var(0, "data", "Vec<i32>", 100, Some(300)));
+    graph.add_variable(var(1, "a", "&Vec<i32>", 110, Some(150)));
+    graph.add_variable(var(2, "b", "&Vec<i32>", 120, Some(200)));
+    graph.add_variable(var(3, "c", "&mut Vec<i32>", 210, Some(220)));
+    graph.add_variable(var(4, "d", "&&Vec<i32>", 115, Some(130)));
+    graph.add_variable(var(5, "inner_buf", "Vec<u8>", 400, None));
+    graph.add_borrow(1, 0, false, 110);
+    graph.add_borrow(2, 0, false, 120);
+    graph.add_borrow(3, 0, true, 210);
+    graph.add_borrow(4, 1, false, 115);
+    graph
+}
+
+fn names(graph: &OwnershipGraph, query: &str) -> Vec<String> {
+    Query::parse(query)
+        .unwrap()
+        .evaluate(graph)
+        .into_iter()
+        .map(|v| v.name.clone())
+        .collect()
+}
+
+#[test]
+fn test_combined_filters() {
+    let graph = sample_graph();
+    assert_eq!(
+        names(&graph, "type:Vec* alive_at:120 borrowed_by>2"),
+        ["data"]
+    );
+    assert_eq!(names(&graph, "type:Vec* scope_depth:1"), ["inner_buf"]);
+}
+
+#[test]
+fn test_empty_query_matches_everything() {
+    let graph = sample_graph();
+    assert!(Query::parse("   ").unwrap().is_empty());
+    assert_eq!(names(&graph, "").len(), 6);
+}
+
+#[test]
+fn test_name_and_type_patterns() {
+    let graph = sample_graph();
+    assert_eq!(names(&graph, "type:&*"), ["a", "b", "c", "d"]);
+    assert_eq!(names(&graph, "type:\"&mut Vec<i32>\""), ["c"]);
+    assert_eq!(names(&graph, "name:?"), ["a", "b", "c", "d"]);
+    assert_eq!(names(&graph, "name:inner_*"), ["inner_buf"]);
+    assert_eq!(names(&graph, "name:dat"), Vec::<String>::new());
+}
+
+#[test]
+fn test_numeric_comparisons() {
+    let graph = sample_graph();
+    assert_eq!(names(&graph, "created>=210"), ["c", "inner_buf"]);
+    assert_eq!(names(&graph, "created<110"), ["data"]);
+    assert_eq!(names(&graph, "lifetime<=15"), ["c", "d"]);
+    assert_eq!(names(&graph, "dropped:false"), ["inner_buf"]);
+    assert_eq!(names(&graph, "dropped>250"), ["data"]);
+    assert_eq!(names(&graph, "scope_depth!=0"), ["inner_buf"]);
+    assert_eq!(names(&graph, "transitive_borrowers=4"), ["data"]);
+    assert_eq!(names(&graph, "borrowed_by:1"), ["a"]);
+}
+
+#[test]
+fn test_graph_predicates() {
+    let graph = sample_graph();
+    assert_eq!(names(&graph, "borrows:data"), ["a", "b", "c", "d"]);
+    assert_eq!(names(&graph, "borrows:#1"), ["d"]);
+    assert_eq!(names(&graph, "overlaps:c"), ["data"]);
+    assert_eq!(names(&graph, "overlaps:a type:&*"), ["b", "d"]);
+    assert_eq!(names(&graph, "borrows:missing"), Vec::<String>::new());
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = Query::parse("type:Vec* colour:red").unwrap_err();
+    assert_eq!(error.term, "colour:red");
+    assert!(error.message.starts_with("unknown field `colour`"));
+
+    assert!(Query::parse("alive_at>10")
+        .unwrap_err()
+        .message
+        .contains("only supports `:`"));
+    assert!(Query::parse("borrowed_by>many")
+        .unwrap_err()
+        .message
+        .contains("expected a number"));
+    assert_eq!(Query::parse("type:").unwrap_err().message, "missing value");
+    assert_eq!(
+        Query::parse("Vec").unwrap_err().message,
+        "expected `field:value`"
+    );
+    assert_eq!(
+        Query::parse("type:\"&mut i32").unwrap_err().message,
+        "unterminated quote"
+    );
+    assert_eq!(
+        Query::parse("borrows:#x").unwrap_err().to_string(),
+        "invalid term `borrows:#x`: expected a node ID, found `x`"
+    );
+}
+
+#[test]
+fn test_uses_scope_depth() {
+    assert!(Query::parse("type:Vec* scope_depth>=1")
+        .unwrap()
+        .uses_scope_depth());
+    assert!(!Query::parse("type:Vec* created>1")
+        .unwrap()
+        .uses_scope_depth());
+}
+
+#[test]
+fn test_from_str() {
+    let query: Query = "name:a".parse().unwrap();
+    assert_eq!(query, Query::parse("name:a").unwrap());
+}
@@ -13,17 +13,18 @@ mod hygiene;
 mod optimized_transform;
 mod parser;
 mod pattern;
-mod smart_pointer;
 mod span_utils;
-mod transform_visitor;
 mod validation;
 mod visitor;
 
+use borrowscope_transform::OwnershipVisitor;
 use proc_macro::TokenStream;
 use proc_macro_error::{abort, proc_macro_error};
 use quote::quote;
-use syn::{parse_macro_input, visit_mut::VisitMut, ItemFn};
-use transform_visitor::OwnershipVisitor;
+use syn::{
+    parse::Parser, parse_macro_input, punctuated::Punctuated, visit_mut::VisitMut, Ident, ItemFn,
+    Token,
+};
 
 /// Validate function before transformation
 fn validate_function(func: &ItemFn) {
@@ -57,8 +58,38 @@ fn validate_function(func: &ItemFn) {
     }
 }
 
+/// Build the visitor for the options given to `#[trace_borrow(...)]`
+fn visitor_for(attr: TokenStream) -> OwnershipVisitor {
+    let options = match Punctuated::<Ident, Token![,]>::parse_terminated.parse(attr) {
+        Ok(options) => options,
+        Err(err) => abort!(err.span(), "{}", err; help = "the only option is `unsafe_code`"),
+    };
+
+    let mut visitor = OwnershipVisitor::new();
+    for option in options {
+        match option.to_string().as_str() {
+            "unsafe_code" => visitor = visitor.with_unsafe_tracking(),
+            _ => abort!(
+                option,
+                "unknown trace_borrow option `{}`", option;
+                help = "the only option is `unsafe_code`"
+            ),
+        }
+    }
+    visitor
+}
+
 /// Attribute macro to trace ownership and borrowing in a function
 ///
+/// With `#[trace_borrow(unsafe_code)]`, unsafe blocks, raw pointers and their
+/// dereferences, `mem::transmute` and calls into `extern` functions are
+/// tracked too.
+///
+/// The macro only sees the annotated function, so an `extern` function counts
+/// as FFI when it is declared in an `extern` block in the body or called
+/// through `libc::`. Calls to `extern "C"` functions declared elsewhere, e.g.
+/// at module level, look like ordinary calls and are not recorded.
+///
 /// # Example
 /// ```ignore
 /// #[trace_borrow]
@@ -69,7 +100,7 @@ fn validate_function(func: &ItemFn) {
 /// ```
 #[proc_macro_attribute]
 #[proc_macro_error]
-pub fn trace_borrow(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn trace_borrow(attr: TokenStream, item: TokenStream) -> TokenStream {
     // Parse the input as a function
     let mut input_fn = parse_macro_input!(item as ItemFn);
 
@@ -77,7 +108,7 @@ pub fn trace_borrow(_attr: TokenStream, item: TokenStream) -> TokenStream {
     validate_function(&input_fn);
 
     // Transform the function body using OwnershipVisitor
-    let mut visitor = OwnershipVisitor::new();
+    let mut visitor = visitor_for(attr);
     visitor.visit_item_fn_mut(&mut input_fn);
 
     // Generate output
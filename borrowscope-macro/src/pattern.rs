@@ -34,10 +34,8 @@ impl PatternInfo {
 /// Extract all variable names from a pattern recursively
 fn extract_variables(pat: &Pat, variables: &mut Vec<Ident>) {
     match pat {
-        Pat::Ident(PatIdent { ident, .. }) => {
-            if !ident.to_string().starts_with('_') {
-                variables.push(ident.clone());
-            }
+        Pat::Ident(PatIdent { ident, .. }) if !ident.to_string().starts_with('_') => {
+            variables.push(ident.clone());
         }
         Pat::Tuple(PatTuple { elems, .. }) => {
             for elem in elems {
@@ -68,14 +68,20 @@ fn test_macro_tracks_borrow_with_owner_id() {
     let events = get_events();
     assert!(events.len() >= 2);
 
-    // Should have a Borrow event with owner_id
+    let x_id = events
+        .iter()
+        .find_map(|e| match e {
+            Event::New {
+                var_name, var_id, ..
+            } if var_name == "x" => Some(var_id.clone()),
+            _ => None,
+        })
+        .expect("Should track x");
+
+    // Should have a Borrow event whose owner is x
     match events.iter().find(|e| e.is_borrow()) {
         Some(Event::Borrow { owner_id, .. }) => {
-            assert!(
-                owner_id.contains("owner_"),
-                "Should have owner ID: {}",
-                owner_id
-            );
+            assert_eq!(owner_id, &x_id, "Should have x's ID as owner ID");
         }
         _ => panic!("Should have Borrow event with owner ID"),
     }
@@ -0,0 +1,8 @@
+use borrowscope_macro::trace_borrow;
+
+#[trace_borrow(unsafe_blocks)]
+fn example() {
+    let x = 42;
+}
+
+fn main() {}
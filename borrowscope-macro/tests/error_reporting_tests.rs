@@ -12,6 +12,12 @@ fn test_extern_fn_error() {
     t.compile_fail("tests/compile/fail/extern_fn.rs");
 }
 
+#[test]
+fn test_unknown_option_error() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/compile/fail/unknown_option.rs");
+}
+
 #[test]
 fn test_async_fn_works() {
     let t = trybuild::TestCases::new();
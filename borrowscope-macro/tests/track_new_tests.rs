@@ -42,6 +42,69 @@ fn test_typed_variable() {
     assert!(events[0].is_new());
 }
 
+#[test]
+fn test_declaration_location_recorded() {
+    let _lock = TEST_LOCK.lock();
+    reset();
+
+    let line = line!() + 3;
+    #[trace_borrow]
+    fn example() {
+        let x = 42;
+        assert_eq!(x, 42);
+    }
+
+    example();
+
+    let events = get_events();
+    match &events[0] {
+        Event::New { type_name, .. } => {
+            assert_eq!(type_name, &format!("i32 @ {}:{}:13", file!(), line));
+        }
+        other => panic!("expected New, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_scope_depth_recorded() {
+    let _lock = TEST_LOCK.lock();
+    reset();
+
+    #[trace_borrow]
+    fn example() {
+        let outer = 1;
+        {
+            let inner = 2;
+            if outer < inner {
+                let nested = 3;
+                assert_eq!(nested, 3);
+            }
+        }
+    }
+
+    example();
+
+    let depths: Vec<(String, Option<usize>)> = get_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::New {
+                var_name,
+                scope_depth,
+                ..
+            } => Some((var_name, scope_depth)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        depths,
+        [
+            ("outer".to_string(), Some(0)),
+            ("inner".to_string(), Some(1)),
+            ("nested".to_string(), Some(2)),
+        ]
+    );
+}
+
 #[test]
 fn test_string_variable() {
     let _lock = TEST_LOCK.lock();
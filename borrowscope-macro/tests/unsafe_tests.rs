@@ -0,0 +1,340 @@
+//! Macro transformation tests for unsafe code
+//!
+//! These tests verify that `#[trace_borrow(unsafe_code)]` records unsafe
+//! blocks, raw pointers and their dereferences, transmutes and FFI calls.
+
+use borrowscope_macro::trace_borrow;
+use borrowscope_runtime::*;
+use serial_test::serial;
+
+#[test]
+#[serial]
+fn test_unsafe_block_and_raw_pointer() {
+    reset();
+
+    #[trace_borrow(unsafe_code)]
+    fn example() -> i32 {
+        let mut x = 1;
+        let p = &mut x as *mut i32;
+        unsafe {
+            *p = 5;
+            *p + 1
+        }
+    }
+
+    assert_eq!(example(), 6);
+
+    let events = get_events();
+    let created = events
+        .iter()
+        .find_map(|event| match event {
+            Event::RawPtrCreated {
+                var_name,
+                var_id,
+                ptr_type,
+                source_id,
+                ..
+            } => Some((var_name, var_id, ptr_type, source_id)),
+            _ => None,
+        })
+        .expect("pointer creation should be tracked");
+    assert_eq!(created.0, "p");
+    assert_eq!(created.2, "*mut i32");
+    // The source is the runtime ID `x` was created and dropped with
+    let x_id = events
+        .iter()
+        .find_map(|event| match event {
+            Event::New {
+                var_name, var_id, ..
+            } if var_name == "x" => Some(var_id),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(created.3.as_ref(), Some(x_id));
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, Event::Drop { var_id, .. } if var_id == x_id)));
+
+    let derefs: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::RawPtrDeref {
+                ptr_id,
+                is_write,
+                location,
+                ..
+            } => Some((ptr_id, *is_write, location)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(derefs.len(), 2);
+    assert!(derefs.iter().all(|(ptr_id, _, _)| *ptr_id == created.1));
+    assert!(derefs[0].1, "assignment through the pointer is a write");
+    assert!(!derefs[1].1);
+    assert!(
+        derefs[0]
+            .2
+            .starts_with("borrowscope-macro/tests/unsafe_tests.rs:")
+            || derefs[0].2.starts_with("tests/unsafe_tests.rs:"),
+        "location should point into this file: {}",
+        derefs[0].2
+    );
+
+    // The derefs happen inside the block
+    let enter = events
+        .iter()
+        .position(|e| matches!(e, Event::UnsafeBlockEnter { .. }))
+        .unwrap();
+    let exit = events
+        .iter()
+        .position(|e| matches!(e, Event::UnsafeBlockExit { .. }))
+        .unwrap();
+    let first_deref = events
+        .iter()
+        .position(|e| matches!(e, Event::RawPtrDeref { .. }))
+        .unwrap();
+    assert!(enter < first_deref && first_deref < exit);
+}
+
+#[test]
+#[serial]
+fn test_unsafe_block_exit_on_early_return() {
+    reset();
+
+    #[trace_borrow(unsafe_code)]
+    fn first(values: &[u8]) -> Option<u8> {
+        unsafe {
+            if values.is_empty() {
+                return None;
+            }
+            Some(*values.as_ptr())
+        }
+    }
+
+    assert_eq!(first(&[]), None);
+
+    let events = get_events();
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, Event::UnsafeBlockExit { .. })));
+}
+
+#[test]
+#[serial]
+fn test_pointer_parameter_and_field_write() {
+    reset();
+
+    struct Point {
+        x: i32,
+    }
+
+    #[trace_borrow(unsafe_code)]
+    fn shift(p: *mut Point) {
+        unsafe {
+            (*p).x += 1;
+        }
+    }
+
+    let mut point = Point { x: 1 };
+    shift(&mut point);
+    assert_eq!(point.x, 2);
+
+    let events = get_events();
+    assert!(events.iter().any(|e| matches!(
+        e,
+        Event::RawPtrCreated { var_name, ptr_type, .. } if var_name == "p" && ptr_type == "*mut Point"
+    )));
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, Event::RawPtrDeref { is_write: true, .. })));
+}
+
+#[test]
+#[serial]
+fn test_annotated_pointer_and_pointer_cast() {
+    reset();
+
+    #[trace_borrow(unsafe_code)]
+    fn example() -> i32 {
+        let x = 7;
+        let a: *const i32 = &x;
+        let b = a as *mut i32;
+        unsafe { *a + *b }
+    }
+
+    assert_eq!(example(), 14);
+
+    let events = get_events();
+    let created: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::RawPtrCreated {
+                var_name,
+                source_id,
+                ..
+            } => Some((var_name.as_str(), source_id.as_deref())),
+            _ => None,
+        })
+        .collect();
+    // `b` is made from `a`, whose runtime ID is the pointer's own
+    assert_eq!(created, vec![("a", Some("x_1")), ("b", Some("2"))]);
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| matches!(e, Event::RawPtrDeref { .. }))
+            .count(),
+        2
+    );
+}
+
+#[test]
+#[serial]
+fn test_ids_are_unique_across_functions() {
+    reset();
+
+    #[trace_borrow(unsafe_code)]
+    fn helper() -> i32 {
+        let x = 2;
+        let q = &x as *const i32;
+        unsafe { *q }
+    }
+
+    #[trace_borrow(unsafe_code)]
+    fn example() -> i32 {
+        let x = 1;
+        let p = &x as *const i32;
+        let y = helper();
+        unsafe { *p + y }
+    }
+
+    assert_eq!(example(), 3);
+
+    let events = get_events();
+    let xs: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::New {
+                var_name, var_id, ..
+            } if var_name == "x" => Some(var_id.as_str()),
+            _ => None,
+        })
+        .collect();
+    let created: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::RawPtrCreated {
+                var_name,
+                var_id,
+                source_id,
+                ..
+            } => Some((var_name.as_str(), var_id.as_str(), source_id.as_deref())),
+            _ => None,
+        })
+        .collect();
+    // `example` reserves IDs 1 to 4, so `helper` numbers its own from 5
+    assert_eq!(xs, vec!["x_1", "x_5"]);
+    assert_eq!(
+        created,
+        vec![("p", "2", Some("x_1")), ("q", "6", Some("x_5"))]
+    );
+
+    // `p` is dereferenced after helper's `x` was dropped, which is not its target
+    let p_deref = events
+        .iter()
+        .rposition(|e| matches!(e, Event::RawPtrDeref { ptr_id, .. } if ptr_id == "2"))
+        .unwrap();
+    let helper_x_drop = events
+        .iter()
+        .position(|e| matches!(e, Event::Drop { var_id, .. } if var_id == "x_5"))
+        .unwrap();
+    assert!(helper_x_drop < p_deref);
+}
+
+#[test]
+#[serial]
+fn test_transmute_and_extern_call() {
+    reset();
+
+    #[repr(transparent)]
+    struct Id(u32);
+
+    #[trace_borrow(unsafe_code)]
+    fn example() -> usize {
+        extern "C" {
+            fn strlen(s: *const std::ffi::c_char) -> usize;
+        }
+
+        let id = unsafe { std::mem::transmute::<u32, Id>(9) };
+        assert_eq!(id.0, 9);
+        unsafe { strlen(b"hello\0".as_ptr().cast()) }
+    }
+
+    assert_eq!(example(), 5);
+
+    let events = get_events();
+    // The runtime names the types in full
+    assert!(events.iter().any(|e| matches!(
+        e,
+        Event::Transmute { from_type, to_type, .. } if from_type == "u32" && to_type.ends_with("::Id")
+    )));
+    assert!(events.iter().any(|e| matches!(
+        e,
+        Event::FfiCall { fn_name, .. } if fn_name == "strlen"
+    )));
+}
+
+#[test]
+#[serial]
+fn test_transmute_types_come_from_the_values() {
+    reset();
+
+    #[trace_borrow(unsafe_code)]
+    fn example() -> u32 {
+        let value = 1.0f32;
+        unsafe { core::mem::transmute(value) }
+    }
+
+    assert_eq!(example(), 0x3f80_0000);
+
+    let events = get_events();
+    assert!(events.iter().any(|e| matches!(
+        e,
+        Event::Transmute { from_type, to_type, .. } if from_type == "f32" && to_type == "u32"
+    )));
+}
+
+#[test]
+#[serial]
+fn test_other_transmute_functions_are_not_recorded() {
+    reset();
+
+    unsafe fn transmute(value: u8) -> u8 {
+        value
+    }
+
+    #[trace_borrow(unsafe_code)]
+    fn example() -> u8 {
+        unsafe { transmute(4) }
+    }
+
+    assert_eq!(example(), 4);
+    assert!(!get_events()
+        .iter()
+        .any(|e| matches!(e, Event::Transmute { .. })));
+}
+
+#[test]
+#[serial]
+fn test_unsafe_code_not_tracked_by_default() {
+    reset();
+
+    #[trace_borrow]
+    fn example() -> i32 {
+        let x = 3;
+        let p = &x as *const i32;
+        unsafe { *p }
+    }
+
+    assert_eq!(example(), 3);
+    assert!(!get_events().iter().any(|e| e.is_unsafe()));
+}
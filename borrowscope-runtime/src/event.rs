@@ -12,6 +12,9 @@ pub enum Event {
         var_name: String,
         var_id: String,
         type_name: String,
+        /// Number of blocks around the declaration in its function, when known
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scope_depth: Option<usize>,
     },
 
     /// Variable borrowed
@@ -155,6 +158,9 @@ pub enum Event {
         ptr_type: String,
         address: usize,
         location: String,
+        /// Variable the pointer was taken from, when known
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source_id: Option<String>,
     },
 
     /// Raw pointer dereferenced
@@ -409,6 +415,7 @@ mod tests {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         };
 
         assert_eq!(event.timestamp(), 1);
@@ -467,6 +474,7 @@ mod tests {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         };
 
         let json = serde_json::to_string(&event).unwrap();
@@ -1,23 +1,45 @@
 //! Export functionality for graphs and events
+//!
+//! [`ExportData`] is the trace file format read by every BorrowScope tool.
+//! Its layout is versioned by [`TRACE_FORMAT_VERSION`] and described by the
+//! JSON Schema in [`TRACE_SCHEMA`]; bump the version and the schema together.
 
+use crate::error::Error;
 use crate::event::Event;
 use crate::graph::{OwnershipGraph, Relationship, Variable};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
+/// Version of the trace file layout written by [`ExportData`]
+pub const TRACE_FORMAT_VERSION: u32 = 1;
+
+/// JSON Schema (draft 7) for the current trace file layout
+pub const TRACE_SCHEMA: &str = include_str!("../schema/trace.schema.json");
+
 /// Complete export format optimized for visualization
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportData {
-    pub nodes: Vec<Variable>,
-    pub edges: Vec<ExportEdge>,
+    /// Trace format version, see [`TRACE_FORMAT_VERSION`]
+    pub version: u32,
+    /// Program or project the trace was recorded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
     pub events: Vec<Event>,
+    pub graph: ExportGraph,
     pub metadata: ExportMetadata,
 }
 
+/// Ownership graph built from the events
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportGraph {
+    pub nodes: Vec<Variable>,
+    pub edges: Vec<ExportEdge>,
+}
+
 /// Serializable edge for export
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportEdge {
     pub from: String,
     pub to: String,
@@ -29,7 +51,7 @@ pub struct ExportEdge {
 }
 
 /// Export metadata
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ExportMetadata {
     pub total_variables: usize,
     pub total_relationships: usize,
@@ -81,8 +103,12 @@ impl ExportData {
             .collect();
 
         ExportData {
-            nodes: graph.nodes,
-            edges,
+            version: TRACE_FORMAT_VERSION,
+            source: None,
+            graph: ExportGraph {
+                nodes: graph.nodes,
+                edges,
+            },
             events: events.clone(),
             metadata: ExportMetadata {
                 total_variables: stats.total_variables,
@@ -94,6 +120,26 @@ impl ExportData {
         }
     }
 
+    /// Parse a trace in the current format
+    ///
+    /// Traces written by other format versions are rejected; the CLI upgrades
+    /// older ones before handing them over.
+    pub fn from_json(json: &str) -> crate::error::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        match value.get("version").and_then(|v| v.as_u64()) {
+            Some(version) if version == u64::from(TRACE_FORMAT_VERSION) => {
+                Ok(serde_json::from_value(value)?)
+            }
+            Some(version) => Err(Error::ExportError(format!(
+                "unsupported trace format version {} (expected {})",
+                version, TRACE_FORMAT_VERSION
+            ))),
+            None => Err(Error::ExportError(
+                "trace has no format version".to_string(),
+            )),
+        }
+    }
+
     /// Serialize to JSON string
     pub fn to_json(&self) -> crate::error::Result<String> {
         Ok(serde_json::to_string_pretty(self)?)
@@ -119,8 +165,8 @@ mod tests {
         let events = vec![];
         let export = ExportData::new(graph, events);
 
-        assert_eq!(export.nodes.len(), 0);
-        assert_eq!(export.edges.len(), 0);
+        assert_eq!(export.graph.nodes.len(), 0);
+        assert_eq!(export.graph.edges.len(), 0);
         assert_eq!(export.metadata.total_events, 0);
     }
 
@@ -131,13 +177,14 @@ mod tests {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         }];
 
         let graph = build_graph(&events);
         let export = ExportData::new(graph, events);
 
-        assert_eq!(export.nodes.len(), 1);
-        assert_eq!(export.nodes[0].name, "x");
+        assert_eq!(export.graph.nodes.len(), 1);
+        assert_eq!(export.graph.nodes[0].name, "x");
         assert_eq!(export.metadata.total_events, 1);
     }
 
@@ -149,6 +196,7 @@ mod tests {
                 var_name: "x".to_string(),
                 var_id: "x_0".to_string(),
                 type_name: "i32".to_string(),
+                scope_depth: None,
             },
             Event::Borrow {
                 timestamp: 2,
@@ -166,8 +214,8 @@ mod tests {
         let graph = build_graph(&events);
         let export = ExportData::new(graph, events);
 
-        assert_eq!(export.edges.len(), 1);
-        assert_eq!(export.edges[0].relationship, "borrows_immut");
+        assert_eq!(export.graph.edges.len(), 1);
+        assert_eq!(export.graph.edges[0].relationship, "borrows_immut");
         assert_eq!(export.metadata.immutable_borrows, 1);
     }
 
@@ -178,6 +226,7 @@ mod tests {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         }];
 
         let graph = build_graph(&events);
@@ -195,6 +244,7 @@ mod tests {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         }];
 
         let graph = build_graph(&events);
@@ -203,12 +253,43 @@ mod tests {
 
         // Verify it's valid JSON
         let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
-        assert!(parsed["nodes"].is_array());
-        assert!(parsed["edges"].is_array());
+        assert_eq!(parsed["version"], TRACE_FORMAT_VERSION);
+        assert!(parsed["graph"]["nodes"].is_array());
+        assert!(parsed["graph"]["edges"].is_array());
         assert!(parsed["events"].is_array());
         assert!(parsed["metadata"].is_object());
     }
 
+    #[test]
+    fn test_from_json_round_trip() {
+        let events = vec![Event::New {
+            timestamp: 1,
+            var_name: "x".to_string(),
+            var_id: "x_0".to_string(),
+            type_name: "i32".to_string(),
+            scope_depth: None,
+        }];
+
+        let export = ExportData::new(build_graph(&events), events);
+        let parsed = ExportData::from_json(&export.to_json().unwrap()).unwrap();
+
+        assert_eq!(parsed.events, export.events);
+        assert_eq!(parsed.graph.nodes, export.graph.nodes);
+        assert_eq!(parsed.metadata.total_events, 1);
+    }
+
+    #[test]
+    fn test_from_json_rejects_other_versions() {
+        let export = ExportData::new(OwnershipGraph::new(), vec![]);
+        let mut value = serde_json::to_value(&export).unwrap();
+
+        value["version"] = serde_json::json!(TRACE_FORMAT_VERSION + 1);
+        assert!(ExportData::from_json(&value.to_string()).is_err());
+
+        value.as_object_mut().unwrap().remove("version");
+        assert!(ExportData::from_json(&value.to_string()).is_err());
+    }
+
     #[test]
     fn test_metadata() {
         let events = vec![
@@ -217,6 +298,7 @@ mod tests {
                 var_name: "x".to_string(),
                 var_id: "x_0".to_string(),
                 type_name: "i32".to_string(),
+                scope_depth: None,
             },
             Event::Borrow {
                 timestamp: 2,
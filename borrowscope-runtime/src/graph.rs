@@ -153,6 +153,7 @@ pub fn build_graph(events: &[Event]) -> OwnershipGraph {
                 var_id,
                 type_name,
                 timestamp,
+                ..
             } => {
                 let var = Variable {
                     id: var_id.clone(),
@@ -343,6 +344,7 @@ mod tests {
                 var_name: "x".to_string(),
                 var_id: "x_0".to_string(),
                 type_name: "i32".to_string(),
+                scope_depth: None,
             },
             Event::Drop {
                 timestamp: 2,
@@ -363,6 +365,7 @@ mod tests {
                 var_name: "x".to_string(),
                 var_id: "x_0".to_string(),
                 type_name: "i32".to_string(),
+                scope_depth: None,
             },
             Event::Borrow {
                 timestamp: 2,
@@ -409,6 +412,7 @@ mod tests {
                 var_name: "x".to_string(),
                 var_id: "x_0".to_string(),
                 type_name: "Vec<i32>".to_string(),
+                scope_depth: None,
             },
             Event::Borrow {
                 timestamp: 2,
@@ -41,6 +41,7 @@ mod event;
 mod export;
 mod graph;
 mod lifetime;
+mod test_trace;
 mod tracker;
 
 #[cfg(test)]
@@ -48,19 +49,24 @@ mod test_utils;
 
 pub use error::{Error, Result};
 pub use event::Event;
-pub use export::{ExportData, ExportEdge, ExportMetadata};
+pub use export::{
+    ExportData, ExportEdge, ExportGraph, ExportMetadata, TRACE_FORMAT_VERSION, TRACE_SCHEMA,
+};
 pub use graph::{build_graph, GraphStats, OwnershipGraph, Relationship, Variable};
 pub use lifetime::{ElisionRule, LifetimeRelation, Timeline};
+pub use test_trace::{trace_file_name, TestTrace, TEST_OUTPUT_ENV};
 pub use tracker::{
-    __track_new_with_id_helper, get_events, reset, track_arc_clone, track_arc_clone_with_id,
-    track_arc_new, track_arc_new_with_id, track_borrow, track_borrow_mut, track_borrow_mut_with_id,
-    track_borrow_with_id, track_cell_get, track_cell_new, track_cell_set, track_const_eval,
-    track_drop, track_drop_batch, track_drop_with_id, track_ffi_call, track_move,
-    track_move_with_id, track_new, track_new_with_id, track_raw_ptr, track_raw_ptr_deref,
-    track_raw_ptr_mut, track_rc_clone, track_rc_clone_with_id, track_rc_new, track_rc_new_with_id,
-    track_refcell_borrow, track_refcell_borrow_mut, track_refcell_drop, track_refcell_new,
-    track_static_access, track_static_init, track_transmute, track_union_field_access,
-    track_unsafe_block_enter, track_unsafe_block_exit, track_unsafe_fn_call,
+    __track_new_with_id_helper, get_events, reserve_ids, reset, track_arc_clone,
+    track_arc_clone_with_id, track_arc_new, track_arc_new_with_id, track_borrow, track_borrow_mut,
+    track_borrow_mut_with_id, track_borrow_with_id, track_cell_get, track_cell_new, track_cell_set,
+    track_const_eval, track_drop, track_drop_batch, track_drop_id, track_drop_with_id,
+    track_ffi_call, track_move, track_move_with_id, track_new, track_new_in_scope,
+    track_new_with_id, track_raw_ptr, track_raw_ptr_deref, track_raw_ptr_from, track_raw_ptr_mut,
+    track_raw_ptr_mut_from, track_rc_clone, track_rc_clone_with_id, track_rc_new,
+    track_rc_new_with_id, track_refcell_borrow, track_refcell_borrow_mut, track_refcell_drop,
+    track_refcell_new, track_static_access, track_static_init, track_transmute,
+    track_transmute_call, track_union_field_access, track_unsafe_block, track_unsafe_block_enter,
+    track_unsafe_block_exit, track_unsafe_fn_call, UnsafeBlockGuard, VarId,
 };
 
 /// Get the ownership graph built from current events
@@ -187,7 +193,7 @@ mod integration_tests {
         let contents = std::fs::read_to_string(&temp_path).unwrap();
         let parsed: serde_json::Value = serde_json::from_str(&contents).unwrap();
 
-        assert!(parsed["nodes"].is_array());
+        assert!(parsed["graph"]["nodes"].is_array());
         assert!(parsed["events"].is_array());
         assert!(parsed["metadata"].is_object());
 
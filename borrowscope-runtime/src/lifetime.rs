@@ -260,6 +260,7 @@ mod tests {
                 var_name: "x".to_string(),
                 var_id: "x_0".to_string(),
                 type_name: "i32".to_string(),
+                scope_depth: None,
             },
             Event::Borrow {
                 timestamp: 10,
@@ -327,6 +328,7 @@ mod tests {
                 var_name: "x".to_string(),
                 var_id: "x_0".to_string(),
                 type_name: "i32".to_string(),
+                scope_depth: None,
             },
             Event::Borrow {
                 timestamp: 10,
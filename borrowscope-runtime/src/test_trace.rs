@@ -0,0 +1,75 @@
+//! Per-test trace capture
+//!
+//! Instrumented `#[test]` functions open a [`TestTrace`] guard as their first
+//! statement. The guard clears the tracker, serializes traced tests so their
+//! events never interleave, and exports the events when the test ends, whether
+//! it passed or panicked.
+
+use crate::tracker::reset;
+use parking_lot::{Mutex, MutexGuard};
+use std::path::PathBuf;
+
+/// Directory the per-test traces are written to
+pub const TEST_OUTPUT_ENV: &str = "BORROWSCOPE_TEST_OUTPUT";
+
+/// Held by the running traced test; the tracker is global, so only one test records at a time
+static TEST_SERIAL: Mutex<()> = parking_lot::const_mutex(());
+
+/// Guard that records a single test case into its own trace file
+pub struct TestTrace {
+    name: String,
+    _serial: MutexGuard<'static, ()>,
+}
+
+impl TestTrace {
+    /// Start recording the test `name` (its full module path)
+    pub fn start(name: &str) -> Self {
+        let serial = TEST_SERIAL.lock();
+        reset();
+
+        Self {
+            name: name.to_string(),
+            _serial: serial,
+        }
+    }
+
+    /// Name of the test being recorded
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Where the trace will be written, if an output directory is configured
+    pub fn output_path(&self) -> Option<PathBuf> {
+        let dir = std::env::var_os(TEST_OUTPUT_ENV)?;
+        Some(PathBuf::from(dir).join(trace_file_name(&self.name)))
+    }
+}
+
+impl Drop for TestTrace {
+    fn drop(&mut self) {
+        // Tracking never breaks user code, so export failures are ignored
+        if let Some(path) = self.output_path() {
+            if let Some(parent) = path.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            let _ = crate::export_json(&path);
+        }
+        reset();
+    }
+}
+
+/// File name for a test's trace: `crate::tests::name` becomes `crate.tests.name.json`
+pub fn trace_file_name(test_name: &str) -> String {
+    let sanitized: String = test_name
+        .replace("::", ".")
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '.' || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.json", sanitized)
+}
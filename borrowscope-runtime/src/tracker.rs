@@ -3,7 +3,8 @@
 use crate::event::Event;
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 lazy_static! {
     /// Global tracker instance
@@ -13,6 +14,9 @@ lazy_static! {
 /// Global timestamp counter
 static TIMESTAMP: AtomicU64 = AtomicU64::new(0);
 
+/// Offset handed to the next call of an instrumented function, see [`reserve_ids`]
+static NEXT_ID_OFFSET: AtomicUsize = AtomicUsize::new(0);
+
 /// The main tracker that records events
 pub struct Tracker {
     /// All recorded events
@@ -46,6 +50,18 @@ impl Tracker {
     /// Record a New event
     #[cfg_attr(not(feature = "track"), allow(dead_code))]
     pub fn record_new(&mut self, var_name: &str, type_name: &str) -> String {
+        self.record_new_in_scope(var_name, type_name, None)
+    }
+
+    /// Record a New event for a variable declared `scope_depth` blocks inside
+    /// its function body
+    #[cfg_attr(not(feature = "track"), allow(dead_code))]
+    pub fn record_new_in_scope(
+        &mut self,
+        var_name: &str,
+        type_name: &str,
+        scope_depth: Option<usize>,
+    ) -> String {
         let timestamp = Self::next_timestamp();
         let var_id = self.next_var_id(var_name);
 
@@ -54,6 +70,7 @@ impl Tracker {
             var_name: var_name.to_string(),
             var_id: var_id.clone(),
             type_name: type_name.to_string(),
+            scope_depth,
         });
 
         var_id
@@ -197,7 +214,8 @@ impl Tracker {
         var_id
     }
 
-    /// Record a New event with explicit ID and location (advanced API)
+    /// Record a New event with explicit ID and location, and the scope depth
+    /// when it is known (advanced API)
     #[cfg_attr(not(feature = "track"), allow(dead_code))]
     pub fn record_new_with_id(
         &mut self,
@@ -205,6 +223,7 @@ impl Tracker {
         var_name: &str,
         type_name: &str,
         location: &str,
+        scope_depth: Option<usize>,
     ) -> String {
         let timestamp = Self::next_timestamp();
         let var_id = format!("{}_{}", var_name, id);
@@ -214,6 +233,7 @@ impl Tracker {
             var_name: var_name.to_string(),
             var_id: var_id.clone(),
             type_name: format!("{} @ {}", type_name, location),
+            scope_depth,
         });
 
         var_id
@@ -224,20 +244,19 @@ impl Tracker {
     pub fn record_borrow_with_id(
         &mut self,
         borrower_id: usize,
-        owner_id: usize,
+        owner_id: &str,
         borrower_name: &str,
         location: &str,
         mutable: bool,
     ) -> String {
         let timestamp = Self::next_timestamp();
         let borrower_var_id = format!("{}_{}", borrower_name, borrower_id);
-        let owner_var_id = format!("owner_{}", owner_id);
 
         self.events.push(Event::Borrow {
             timestamp,
             borrower_name: format!("{} @ {}", borrower_name, location),
             borrower_id: borrower_var_id.clone(),
-            owner_id: owner_var_id,
+            owner_id: owner_id.to_string(),
             mutable,
         });
 
@@ -248,18 +267,17 @@ impl Tracker {
     #[allow(dead_code)]
     pub fn record_move_with_id(
         &mut self,
-        from_id: usize,
+        from_id: &str,
         to_id: usize,
         to_name: &str,
         location: &str,
     ) -> String {
         let timestamp = Self::next_timestamp();
-        let from_var_id = format!("var_{}", from_id);
         let to_var_id = format!("{}_{}", to_name, to_id);
 
         self.events.push(Event::Move {
             timestamp,
-            from_id: from_var_id,
+            from_id: from_id.to_string(),
             to_name: format!("{} @ {}", to_name, location),
             to_id: to_var_id.clone(),
         });
@@ -529,6 +547,7 @@ impl Tracker {
         ptr_type: &str,
         address: usize,
         location: &str,
+        source_id: Option<&str>,
     ) {
         let timestamp = Self::next_timestamp();
 
@@ -539,6 +558,7 @@ impl Tracker {
             ptr_type: ptr_type.to_string(),
             address,
             location: location.to_string(),
+            source_id: source_id.map(str::to_string),
         });
     }
 
@@ -637,6 +657,7 @@ impl Tracker {
         self.events.clear();
         self.var_counter = 0;
         TIMESTAMP.store(0, Ordering::Relaxed);
+        NEXT_ID_OFFSET.store(0, Ordering::Relaxed);
     }
 }
 
@@ -661,6 +682,23 @@ pub fn track_new<T>(
     value
 }
 
+/// Track a new variable declared `scope_depth` blocks inside its function
+/// body, 0 being the body itself
+#[inline(always)]
+pub fn track_new_in_scope<T>(
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] name: &str,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] scope_depth: usize,
+    value: T,
+) -> T {
+    #[cfg(feature = "track")]
+    {
+        let type_name = std::any::type_name::<T>();
+        let mut tracker = TRACKER.lock();
+        tracker.record_new_in_scope(name, type_name, Some(scope_depth));
+    }
+    value
+}
+
 /// Track an immutable borrow
 #[inline(always)]
 pub fn track_borrow<'a, T: ?Sized>(
@@ -714,6 +752,20 @@ pub fn track_drop(#[cfg_attr(not(feature = "track"), allow(unused_variables))] n
     }
 }
 
+/// Track a drop by runtime ID, e.g. a [`VarId`] or the ID of a raw pointer
+///
+/// The ID is only formatted when tracking is enabled.
+#[inline(always)]
+pub fn track_drop_id(
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] id: impl fmt::Display,
+) {
+    #[cfg(feature = "track")]
+    {
+        let mut tracker = TRACKER.lock();
+        tracker.record_drop(&id.to_string());
+    }
+}
+
 /// Track multiple drops in batch (optimized)
 #[inline(always)]
 pub fn track_drop_batch(
@@ -739,20 +791,55 @@ pub fn get_events() -> Vec<Event> {
     TRACKER.lock().events().to_vec()
 }
 
-/// Helper function for track_new_with_id that extracts type at runtime
+/// Reserve `count` IDs for one call of an instrumented function.
+///
+/// `#[trace_borrow]` numbers the variables, borrows and pointers of a function
+/// from 1 when it expands, and adds the returned offset to each of them at
+/// runtime. Every call thus gets IDs that no other call in the trace uses, even
+/// when two functions both declare an `x`.
+#[inline(always)]
+pub fn reserve_ids(
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] count: usize,
+) -> usize {
+    #[cfg(feature = "track")]
+    {
+        NEXT_ID_OFFSET.fetch_add(count, Ordering::Relaxed)
+    }
+    #[cfg(not(feature = "track"))]
+    {
+        0
+    }
+}
+
+/// Runtime ID of a variable tracked with the `_with_id` API
+///
+/// Displays as `name_id`, the ID [`track_new_with_id`] records, so generated
+/// code only formats it when an event is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VarId<'a>(pub &'a str, pub usize);
+
+impl fmt::Display for VarId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.0, self.1)
+    }
+}
+
+/// Helper function for track_new_with_id that extracts type at runtime and
+/// records how many blocks inside the function body surround the variable
 #[inline(always)]
 #[doc(hidden)]
 pub fn __track_new_with_id_helper<T>(
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] id: usize,
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] name: &str,
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] location: &str,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] scope_depth: usize,
     value: T,
 ) -> T {
     #[cfg(feature = "track")]
     {
         let type_name = std::any::type_name::<T>();
         let mut tracker = TRACKER.lock();
-        tracker.record_new_with_id(id, name, type_name, location);
+        tracker.record_new_with_id(id, name, type_name, location, Some(scope_depth));
     }
     value
 }
@@ -769,16 +856,18 @@ pub fn track_new_with_id<T>(
     #[cfg(feature = "track")]
     {
         let mut tracker = TRACKER.lock();
-        tracker.record_new_with_id(id, name, type_name, location);
+        tracker.record_new_with_id(id, name, type_name, location, None);
     }
     value
 }
 
-/// Track an immutable borrow with full metadata (advanced API)
+/// Track an immutable borrow with full metadata (advanced API).
+///
+/// `owner_id` is the runtime ID of the borrowed variable, e.g. a [`VarId`].
 #[inline(always)]
 pub fn track_borrow_with_id<'a, T: ?Sized>(
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] borrower_id: usize,
-    #[cfg_attr(not(feature = "track"), allow(unused_variables))] owner_id: usize,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] owner_id: impl fmt::Display,
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] name: &str,
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] location: &str,
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] mutable: bool,
@@ -787,16 +876,18 @@ pub fn track_borrow_with_id<'a, T: ?Sized>(
     #[cfg(feature = "track")]
     {
         let mut tracker = TRACKER.lock();
-        tracker.record_borrow_with_id(borrower_id, owner_id, name, location, mutable);
+        tracker.record_borrow_with_id(borrower_id, &owner_id.to_string(), name, location, mutable);
     }
     value
 }
 
-/// Track a mutable borrow with full metadata (advanced API)
+/// Track a mutable borrow with full metadata (advanced API).
+///
+/// `owner_id` is the runtime ID of the borrowed variable, e.g. a [`VarId`].
 #[inline(always)]
 pub fn track_borrow_mut_with_id<'a, T: ?Sized>(
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] borrower_id: usize,
-    #[cfg_attr(not(feature = "track"), allow(unused_variables))] owner_id: usize,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] owner_id: impl fmt::Display,
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] name: &str,
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] location: &str,
     value: &'a mut T,
@@ -804,15 +895,17 @@ pub fn track_borrow_mut_with_id<'a, T: ?Sized>(
     #[cfg(feature = "track")]
     {
         let mut tracker = TRACKER.lock();
-        tracker.record_borrow_with_id(borrower_id, owner_id, name, location, true);
+        tracker.record_borrow_with_id(borrower_id, &owner_id.to_string(), name, location, true);
     }
     value
 }
 
-/// Track a move with explicit IDs and location (advanced API)
+/// Track a move with explicit IDs and location (advanced API).
+///
+/// `from_id` is the runtime ID of the variable moved from, e.g. a [`VarId`].
 #[inline(always)]
 pub fn track_move_with_id<T>(
-    #[cfg_attr(not(feature = "track"), allow(unused_variables))] from_id: usize,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] from_id: impl fmt::Display,
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] to_id: usize,
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] to_name: &str,
     #[cfg_attr(not(feature = "track"), allow(unused_variables))] location: &str,
@@ -821,7 +914,7 @@ pub fn track_move_with_id<T>(
     #[cfg(feature = "track")]
     {
         let mut tracker = TRACKER.lock();
-        tracker.record_move_with_id(from_id, to_id, to_name, location);
+        tracker.record_move_with_id(&from_id.to_string(), to_id, to_name, location);
     }
     value
 }
@@ -1163,6 +1256,7 @@ pub fn track_raw_ptr<T: ?Sized>(
             ptr_type,
             ptr as *const () as usize,
             location,
+            None,
         );
     }
     ptr
@@ -1186,6 +1280,61 @@ pub fn track_raw_ptr_mut<T: ?Sized>(
             ptr_type,
             ptr as *const () as usize,
             location,
+            None,
+        );
+    }
+    ptr
+}
+
+/// Track raw pointer creation from a tracked variable.
+///
+/// `source` is the runtime ID of the variable the pointer points into, e.g. a
+/// [`VarId`], or of the pointer it was made from, which lets `borrowscope check`
+/// tell when the pointer outlives it.
+#[inline(always)]
+pub fn track_raw_ptr_from<T: ?Sized>(
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] var_name: &str,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] var_id: usize,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] source: impl fmt::Display,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] ptr_type: &str,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] location: &str,
+    ptr: *const T,
+) -> *const T {
+    #[cfg(feature = "track")]
+    {
+        let mut tracker = TRACKER.lock();
+        tracker.record_raw_ptr_created(
+            var_name,
+            var_id,
+            ptr_type,
+            ptr as *const () as usize,
+            location,
+            Some(&source.to_string()),
+        );
+    }
+    ptr
+}
+
+/// Track mutable raw pointer creation from a tracked variable
+#[inline(always)]
+pub fn track_raw_ptr_mut_from<T: ?Sized>(
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] var_name: &str,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] var_id: usize,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] source: impl fmt::Display,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] ptr_type: &str,
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] location: &str,
+    ptr: *mut T,
+) -> *mut T {
+    #[cfg(feature = "track")]
+    {
+        let mut tracker = TRACKER.lock();
+        tracker.record_raw_ptr_created(
+            var_name,
+            var_id,
+            ptr_type,
+            ptr as *const () as usize,
+            location,
+            Some(&source.to_string()),
         );
     }
     ptr
@@ -1231,6 +1380,31 @@ pub fn track_unsafe_block_exit(
     }
 }
 
+/// Records the exit of an unsafe block when dropped
+///
+/// Returned by [`track_unsafe_block`], so the exit is recorded however the
+/// block is left, including early returns and `?`.
+#[must_use]
+pub struct UnsafeBlockGuard {
+    #[cfg_attr(not(feature = "track"), allow(dead_code))]
+    block_id: usize,
+    #[cfg_attr(not(feature = "track"), allow(dead_code))]
+    location: &'static str,
+}
+
+impl Drop for UnsafeBlockGuard {
+    fn drop(&mut self) {
+        track_unsafe_block_exit(self.block_id, self.location);
+    }
+}
+
+/// Track unsafe block entry, recording the exit when the guard is dropped
+#[inline(always)]
+pub fn track_unsafe_block(block_id: usize, location: &'static str) -> UnsafeBlockGuard {
+    track_unsafe_block_enter(block_id, location);
+    UnsafeBlockGuard { block_id, location }
+}
+
 /// Track unsafe function call
 #[inline(always)]
 pub fn track_unsafe_fn_call(
@@ -1271,6 +1445,21 @@ pub fn track_transmute(
     }
 }
 
+/// Run `transmute` on `value`, recording the types it actually converts between
+#[inline(always)]
+pub fn track_transmute_call<T, U>(
+    #[cfg_attr(not(feature = "track"), allow(unused_variables))] location: &str,
+    value: T,
+    transmute: impl FnOnce(T) -> U,
+) -> U {
+    track_transmute(
+        std::any::type_name::<T>(),
+        std::any::type_name::<U>(),
+        location,
+    );
+    transmute(value)
+}
+
 /// Track union field access
 #[inline(always)]
 pub fn track_union_field_access(
@@ -35,7 +35,7 @@ fn test_track_borrow_with_id() {
     reset();
 
     let x = track_new_with_id(1, "x", "i32", "test.rs:10:5", 42);
-    let _r = track_borrow_with_id(2, 1, "r", "test.rs:11:5", false, &x);
+    let _r = track_borrow_with_id(2, VarId("x", 1), "r", "test.rs:11:5", false, &x);
 
     let events = get_events();
     assert_eq!(events.len(), 2);
@@ -48,7 +48,7 @@ fn test_track_borrow_with_id() {
             ..
         } => {
             assert_eq!(borrower_id, "r_2");
-            assert_eq!(owner_id, "owner_1");
+            assert_eq!(owner_id, "x_1");
             assert!(!mutable);
         }
         _ => panic!("Expected Borrow event"),
@@ -61,7 +61,7 @@ fn test_track_borrow_mut_with_id() {
     reset();
 
     let mut x = track_new_with_id(1, "x", "Vec<i32>", "test.rs:10:5", vec![1, 2, 3]);
-    let _r = track_borrow_mut_with_id(2, 1, "r", "test.rs:11:5", &mut x);
+    let _r = track_borrow_mut_with_id(2, VarId("x", 1), "r", "test.rs:11:5", &mut x);
 
     let events = get_events();
     assert_eq!(events.len(), 2);
@@ -80,14 +80,14 @@ fn test_track_move_with_id() {
     reset();
 
     let x = track_new_with_id(1, "x", "String", "test.rs:10:5", String::from("hello"));
-    let _y = track_move_with_id(1, 2, "y", "test.rs:11:5", x);
+    let _y = track_move_with_id(VarId("x", 1), 2, "y", "test.rs:11:5", x);
 
     let events = get_events();
     assert_eq!(events.len(), 2);
 
     match &events[1] {
         Event::Move { from_id, to_id, .. } => {
-            assert_eq!(from_id, "var_1");
+            assert_eq!(from_id, "x_1");
             assert_eq!(to_id, "y_2");
         }
         _ => panic!("Expected Move event"),
@@ -269,22 +269,22 @@ fn test_borrow_chain_with_ids() {
     let x = track_new_with_id(1, "x", "String", "test.rs:10:5", String::from("hello"));
 
     // ID 2: r1 = &x (borrows from ID 1)
-    let r1 = track_borrow_with_id(2, 1, "r1", "test.rs:11:5", false, &x);
+    let r1 = track_borrow_with_id(2, VarId("x", 1), "r1", "test.rs:11:5", false, &x);
 
     // ID 3: r2 = &r1 (borrows from ID 2)
-    let _r2 = track_borrow_with_id(3, 2, "r2", "test.rs:12:5", false, &r1);
+    let _r2 = track_borrow_with_id(3, VarId("r1", 2), "r2", "test.rs:12:5", false, &r1);
 
     let events = get_events();
     assert_eq!(events.len(), 3);
 
     // Verify borrow chain through IDs
     match &events[1] {
-        Event::Borrow { owner_id, .. } => assert_eq!(owner_id, "owner_1"),
+        Event::Borrow { owner_id, .. } => assert_eq!(owner_id, "x_1"),
         _ => panic!("Expected Borrow"),
     }
 
     match &events[2] {
-        Event::Borrow { owner_id, .. } => assert_eq!(owner_id, "owner_2"),
+        Event::Borrow { owner_id, .. } => assert_eq!(owner_id, "r1_2"),
         _ => panic!("Expected Borrow"),
     }
 }
@@ -95,7 +95,7 @@ fn test_export_empty_data() {
 
     let json = result.unwrap();
     let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
-    assert_eq!(parsed["nodes"].as_array().unwrap().len(), 0);
+    assert_eq!(parsed["graph"]["nodes"].as_array().unwrap().len(), 0);
     assert_eq!(parsed["events"].as_array().unwrap().len(), 0);
 }
 
@@ -634,7 +634,7 @@ fn test_json_export() {
 
     // Verify JSON structure
     let data: serde_json::Value = serde_json::from_str(&json).unwrap();
-    assert!(data["nodes"].is_array());
+    assert!(data["graph"]["nodes"].is_array());
     assert!(data["events"].is_array());
     assert!(data["metadata"].is_object());
 }
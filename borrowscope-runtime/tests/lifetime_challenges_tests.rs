@@ -17,6 +17,7 @@ fn test_borrow_of_borrow() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
@@ -83,6 +84,7 @@ fn test_multiple_independent_borrows() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
@@ -135,6 +137,7 @@ fn test_graph_lifetime_integration() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
@@ -297,6 +300,7 @@ fn test_complex_nested_lifetimes() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
@@ -381,6 +385,7 @@ fn test_mutable_and_immutable_borrows() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
@@ -445,6 +450,7 @@ fn test_lifetime_with_early_drop() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "String".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
@@ -482,12 +488,14 @@ fn test_simultaneous_borrows_of_different_variables() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::New {
             timestamp: 5,
             var_name: "y".to_string(),
             var_id: "y_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
@@ -571,6 +579,7 @@ fn test_borrow_without_drop() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
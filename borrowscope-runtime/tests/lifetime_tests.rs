@@ -18,6 +18,7 @@ fn test_simple_lifetime_relation() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
@@ -62,6 +63,7 @@ fn test_multiple_immutable_borrows() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
@@ -124,6 +126,7 @@ fn test_nested_scopes() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
@@ -192,6 +195,7 @@ fn test_mutable_borrow() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
@@ -342,6 +346,7 @@ fn test_timeline_total_duration() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "i32".to_string(),
+            scope_depth: None,
         },
         Event::Drop {
             timestamp: 500,
@@ -426,12 +431,14 @@ fn test_complex_lifetime_scenario() {
             var_name: "x".to_string(),
             var_id: "x_0".to_string(),
             type_name: "String".to_string(),
+            scope_depth: None,
         },
         Event::New {
             timestamp: 5,
             var_name: "y".to_string(),
             var_id: "y_0".to_string(),
             type_name: "String".to_string(),
+            scope_depth: None,
         },
         Event::Borrow {
             timestamp: 10,
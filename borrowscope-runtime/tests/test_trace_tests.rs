@@ -0,0 +1,65 @@
+use borrowscope_runtime::*;
+use std::path::PathBuf;
+
+fn output_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("borrowscope-test-trace-{}", std::process::id()));
+    std::env::set_var(TEST_OUTPUT_ENV, &dir);
+    dir
+}
+
+fn read_trace(dir: &std::path::Path, name: &str) -> serde_json::Value {
+    let contents = std::fs::read_to_string(dir.join(trace_file_name(name))).unwrap();
+    serde_json::from_str(&contents).unwrap()
+}
+
+#[test]
+fn test_trace_file_name() {
+    assert_eq!(trace_file_name("app::tests::adds"), "app.tests.adds.json");
+    assert_eq!(trace_file_name("it::odd name<T>"), "it.odd_name_T_.json");
+}
+
+#[test]
+fn test_trace_written_per_test() {
+    let dir = output_dir();
+
+    {
+        let _trace = TestTrace::start("app::tests::first");
+        let _x = track_new("x", 1);
+    }
+    {
+        let _trace = TestTrace::start("app::tests::second");
+        let _y = track_new("y", 2);
+        let _z = track_new("z", 3);
+    }
+
+    // Each trace only holds the events of its own test
+    let first = read_trace(&dir, "app::tests::first");
+    let second = read_trace(&dir, "app::tests::second");
+    assert_eq!(first["events"].as_array().unwrap().len(), 1);
+    assert_eq!(second["events"].as_array().unwrap().len(), 2);
+    assert_eq!(second["events"][0]["var_name"], "y");
+}
+
+#[test]
+fn test_trace_written_when_test_panics() {
+    let dir = output_dir();
+
+    let result = std::panic::catch_unwind(|| {
+        let _trace = TestTrace::start("app::tests::panics");
+        let _x = track_new("x", 1);
+        panic!("test failure");
+    });
+    assert!(result.is_err());
+
+    let trace = read_trace(&dir, "app::tests::panics");
+    assert_eq!(trace["events"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_trace_output_path() {
+    let dir = output_dir();
+
+    let trace = TestTrace::start("app::tests::path");
+    assert_eq!(trace.name(), "app::tests::path");
+    assert_eq!(trace.output_path(), Some(dir.join("app.tests.path.json")));
+}
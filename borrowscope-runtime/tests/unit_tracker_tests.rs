@@ -24,6 +24,23 @@ fn test_track_new_records_event() {
     assert!(events[0].is_new());
 }
 
+#[test]
+#[serial]
+fn test_track_new_in_scope_records_depth() {
+    reset();
+    let x = track_new_in_scope("x", 2, 42);
+    assert_eq!(x, 42);
+
+    let events = get_events();
+    assert!(matches!(
+        &events[0],
+        Event::New {
+            scope_depth: Some(2),
+            ..
+        }
+    ));
+}
+
 #[test]
 #[serial]
 fn test_track_borrow_preserves_reference() {
@@ -37,6 +37,32 @@ fn test_raw_ptr_mut_creation() {
     assert!(events[0].is_raw_ptr());
 }
 
+#[test]
+#[serial]
+fn test_raw_ptr_from_records_source() {
+    reset();
+
+    let mut x = track_new("x", 42);
+    let _ptr = track_raw_ptr_mut_from(
+        "ptr",
+        1,
+        "x_0",
+        "*mut i32",
+        "test.rs:25:5",
+        &mut x as *mut i32,
+    );
+
+    let events = get_events();
+    assert_eq!(events.len(), 2);
+    match &events[1] {
+        Event::RawPtrCreated { source_id, .. } => assert_eq!(source_id.as_deref(), Some("x_0")),
+        other => panic!("expected RawPtrCreated, got {:?}", other),
+    }
+
+    let json = serde_json::to_string(&events[1]).unwrap();
+    assert!(json.contains("\"source_id\":\"x_0\""));
+}
+
 #[test]
 #[serial]
 fn test_raw_ptr_deref_read() {
@@ -172,6 +198,28 @@ fn test_nested_unsafe_blocks() {
     assert_eq!(events.len(), 4);
 }
 
+#[test]
+#[serial]
+fn test_unsafe_block_guard_records_exit_on_early_return() {
+    fn first_byte(bytes: &[u8]) -> Option<u8> {
+        let _guard = track_unsafe_block(7, "test.rs:106:5");
+        if bytes.is_empty() {
+            return None;
+        }
+        Some(bytes[0])
+    }
+
+    reset();
+    assert_eq!(first_byte(&[]), None);
+
+    let events = get_events();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(
+        &events[1],
+        Event::UnsafeBlockExit { block_id, location, .. } if block_id == "7" && location == "test.rs:106:5"
+    ));
+}
+
 #[test]
 #[serial]
 fn test_unsafe_block_with_operations() {
@@ -284,6 +332,32 @@ fn test_transmute() {
     assert!(events[0].is_unsafe());
 }
 
+#[test]
+#[serial]
+fn test_transmute_call_records_actual_types() {
+    reset();
+
+    #[repr(transparent)]
+    struct Meters(f32);
+
+    let meters = track_transmute_call("test.rs:175:5", 1.5f32, |value| unsafe {
+        std::mem::transmute::<f32, Meters>(value)
+    });
+    assert_eq!(meters.0, 1.5);
+
+    let events = get_events();
+    assert_eq!(events.len(), 1);
+    match &events[0] {
+        Event::Transmute {
+            from_type, to_type, ..
+        } => {
+            assert_eq!(from_type, "f32");
+            assert!(to_type.ends_with("::Meters"), "{}", to_type);
+        }
+        other => panic!("expected a transmute, got {:?}", other),
+    }
+}
+
 #[test]
 #[serial]
 fn test_transmute_pointer_to_usize() {
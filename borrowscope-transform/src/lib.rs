@@ -0,0 +1,12 @@
+//! BorrowScope Transform
+//!
+//! The transformation behind `#[trace_borrow]`, kept in a library so that the
+//! `borrowscope` CLI can show what the macro does to a function and share its
+//! heuristics.
+
+pub mod self_borrow;
+pub mod smart_pointer;
+mod transform_visitor;
+pub mod unsafe_code;
+
+pub use transform_visitor::OwnershipVisitor;
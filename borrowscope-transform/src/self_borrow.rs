@@ -0,0 +1,61 @@
+//! Method-name heuristics for how a method call borrows its receiver
+//!
+//! `#[trace_borrow]` uses these lists to instrument method calls, and
+//! `borrowscope expand` to warn about methods they do not cover.
+
+/// Type of self borrow in method call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfBorrowType {
+    Immutable,
+    Mutable,
+    Consuming,
+}
+
+const IMMUTABLE_PREFIXES: &[&str] = &["as_", "to_", "is_", "get"];
+const IMMUTABLE_METHODS: &[&str] = &[
+    "len",
+    "capacity",
+    "iter",
+    "chars",
+    "bytes",
+    "lines",
+    "split",
+    "trim",
+    "contains",
+    "starts_with",
+    "ends_with",
+    "find",
+    "clone",
+    "first",
+    "last",
+];
+
+const MUTABLE_PREFIXES: &[&str] = &["push", "pop", "insert", "remove", "append"];
+const MUTABLE_METHODS: &[&str] = &[
+    "clear", "truncate", "extend", "drain", "sort", "reverse", "dedup", "retain", "tick", "recv",
+    "send", "changed", "wait", "acquire", "lock", "write",
+];
+
+const CONSUMING_PREFIXES: &[&str] = &["into_"];
+const CONSUMING_METHODS: &[&str] = &["unwrap", "expect"];
+
+/// How a method borrows its receiver, or `None` for a method name the
+/// heuristics do not know
+pub fn known_self_borrow(method_name: &str) -> Option<SelfBorrowType> {
+    let matches = |prefixes: &[&str], methods: &[&str]| {
+        prefixes
+            .iter()
+            .any(|prefix| method_name.starts_with(prefix))
+            || methods.contains(&method_name)
+    };
+
+    if matches(IMMUTABLE_PREFIXES, IMMUTABLE_METHODS) {
+        Some(SelfBorrowType::Immutable)
+    } else if matches(MUTABLE_PREFIXES, MUTABLE_METHODS) {
+        Some(SelfBorrowType::Mutable)
+    } else if matches(CONSUMING_PREFIXES, CONSUMING_METHODS) {
+        Some(SelfBorrowType::Consuming)
+    } else {
+        None
+    }
+}
@@ -3,37 +3,46 @@
 //! This module implements the OwnershipVisitor that transforms Rust code
 //! to inject runtime tracking calls.
 
+use crate::self_borrow::{known_self_borrow, SelfBorrowType};
 use crate::smart_pointer::{detect_rc_clone, detect_smart_pointer_new, SmartPointerType};
-use std::collections::HashMap;
+use crate::unsafe_code::{self, id_expr, RawPointer};
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use std::collections::{HashMap, HashSet};
 use syn::{
     spanned::Spanned,
     visit_mut::{self, VisitMut},
-    Block, Expr, ExprClosure, ExprMethodCall, ExprReference, Ident, Index, ItemFn, Local, Pat,
-    Stmt,
+    Arm, Block, Expr, ExprCall, ExprClosure, ExprMethodCall, ExprReference, ExprUnary, Ident,
+    Index, ItemFn, Local, Pat, Stmt,
 };
 
-/// Type of self borrow in method call
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum SelfBorrowType {
-    Immutable,
-    Mutable,
-    Consuming,
-}
-
 /// Visitor that transforms AST to inject tracking calls
 pub struct OwnershipVisitor {
-    /// Current scope depth (for future drop tracking)
+    /// Number of blocks entered, the function body included
     scope_depth: usize,
     /// Map variable names to their tracking IDs
     var_ids: HashMap<String, usize>,
-    /// Counter for generating unique IDs
+    /// Counter for generating IDs, unique within one call of the function;
+    /// see [`id_expr`]
     next_id: usize,
-    /// Stack of scopes, each containing variable names created in that scope
-    scope_stack: Vec<Vec<String>>,
+    /// Stack of scopes, each containing the drop calls to make at its end: by
+    /// runtime ID for each variable created in it, or by name when the ID is
+    /// not known
+    scope_stack: Vec<Vec<TokenStream>>,
+    /// Expressions for the runtime IDs of the variables in scope whose ID is
+    /// known at expansion time
+    tracking_ids: HashMap<String, TokenStream>,
     /// Current statement index for inserting statements
     current_stmt_index: usize,
     /// Statements to insert after current statement
     pending_inserts: Vec<(usize, Stmt)>,
+    /// Rewrite unsafe blocks, raw pointers, transmutes and FFI calls
+    track_unsafe: bool,
+    /// Map raw pointer variables to their tracking IDs
+    raw_ptrs: HashMap<String, usize>,
+    /// Functions declared in `extern` blocks inside the function body; those
+    /// declared outside it cannot be told apart from safe functions
+    extern_fns: HashSet<String>,
 }
 
 impl OwnershipVisitor {
@@ -44,11 +53,21 @@ impl OwnershipVisitor {
             var_ids: HashMap::new(),
             next_id: 1,
             scope_stack: vec![Vec::new()], // Start with root scope
+            tracking_ids: HashMap::new(),
             current_stmt_index: 0,
             pending_inserts: Vec::new(),
+            track_unsafe: false,
+            raw_ptrs: HashMap::new(),
+            extern_fns: HashSet::new(),
         }
     }
 
+    /// Also instrument unsafe code, as `#[trace_borrow(unsafe_code)]` does
+    pub fn with_unsafe_tracking(mut self) -> Self {
+        self.track_unsafe = true;
+        self
+    }
+
     /// Generate next unique ID
     fn gen_id(&mut self) -> usize {
         let id = self.next_id;
@@ -56,12 +75,9 @@ impl OwnershipVisitor {
         id
     }
 
-    /// Extract source location from span (simplified for proc_macro2)
-    fn extract_location(_span: proc_macro2::Span) -> String {
-        // proc_macro2::Span doesn't have start()/end() methods
-        // In a real proc macro, we'd use proc_macro::Span
-        // For now, return a placeholder
-        "unknown".to_string()
+    /// Expression evaluating to the `file:line:col` of `span` at compile time
+    fn location_expr(span: proc_macro2::Span) -> TokenStream {
+        quote_spanned! {span=> concat!(file!(), ":", line!(), ":", column!()) }
     }
 
     /// Extract variable name from pattern
@@ -144,9 +160,7 @@ impl OwnershipVisitor {
                         let access_expr = Self::build_access_expr(source, &new_indices, fields);
 
                         self.var_ids.insert(var_name.clone(), self.next_id);
-                        if let Some(current_scope) = self.scope_stack.last_mut() {
-                            current_scope.push(var_name.clone());
-                        }
+                        self.bind(&var_name, None);
 
                         let stmt: Stmt = syn::parse_quote! {
                             let #elem_pat = borrowscope_runtime::track_new(#var_name, #access_expr);
@@ -182,9 +196,7 @@ impl OwnershipVisitor {
                         let access_expr = Self::build_access_expr(source, indices, &new_fields);
 
                         self.var_ids.insert(var_name.clone(), self.next_id);
-                        if let Some(current_scope) = self.scope_stack.last_mut() {
-                            current_scope.push(var_name.clone());
-                        }
+                        self.bind(&var_name, None);
 
                         let pat = &field.pat;
                         let stmt: Stmt = syn::parse_quote! {
@@ -228,9 +240,7 @@ impl OwnershipVisitor {
             *init.expr = temp_expr;
 
             self.var_ids.insert(temp_name.clone(), self.next_id);
-            if let Some(current_scope) = self.scope_stack.last_mut() {
-                current_scope.push(temp_name);
-            }
+            self.bind(&temp_name, None);
             self.next_id += 1;
 
             // Generate destructuring statements
@@ -247,69 +257,8 @@ impl OwnershipVisitor {
 
     /// Infer self borrow type from method name using heuristics
     fn infer_self_borrow_type(method_name: &str) -> SelfBorrowType {
-        // Immutable borrows (common patterns)
-        if method_name.starts_with("as_")
-            || method_name.starts_with("to_")
-            || method_name.starts_with("is_")
-            || method_name.starts_with("get")
-            || matches!(
-                method_name,
-                "len"
-                    | "capacity"
-                    | "iter"
-                    | "chars"
-                    | "bytes"
-                    | "lines"
-                    | "split"
-                    | "trim"
-                    | "contains"
-                    | "starts_with"
-                    | "ends_with"
-                    | "find"
-                    | "clone"
-                    | "first"
-                    | "last"
-            )
-        {
-            return SelfBorrowType::Immutable;
-        }
-
-        // Mutable borrows (common patterns)
-        if method_name.starts_with("push")
-            || method_name.starts_with("pop")
-            || method_name.starts_with("insert")
-            || method_name.starts_with("remove")
-            || method_name.starts_with("append")
-            || matches!(
-                method_name,
-                "clear"
-                    | "truncate"
-                    | "extend"
-                    | "drain"
-                    | "sort"
-                    | "reverse"
-                    | "dedup"
-                    | "retain"
-                    | "tick"
-                    | "recv"
-                    | "send"
-                    | "changed"
-                    | "wait"
-                    | "acquire"
-                    | "lock"
-                    | "write"
-            )
-        {
-            return SelfBorrowType::Mutable;
-        }
-
-        // Consuming methods (common patterns)
-        if method_name.starts_with("into_") || matches!(method_name, "unwrap" | "expect") {
-            return SelfBorrowType::Consuming;
-        }
-
         // Default: immutable borrow
-        SelfBorrowType::Immutable
+        known_self_borrow(method_name).unwrap_or(SelfBorrowType::Immutable)
     }
 
     /// Check if expression is a simple variable (not a temporary or field access)
@@ -370,7 +319,7 @@ impl OwnershipVisitor {
                 SelfBorrowType::Consuming => unreachable!(),
             };
 
-            method_call.receiver = Box::new(wrapped_receiver);
+            *method_call.receiver = wrapped_receiver;
         }
 
         // Visit arguments
@@ -476,6 +425,9 @@ impl OwnershipVisitor {
         // We don't transform the closure body itself to avoid complexity
 
         // Just visit the closure body normally to handle any nested structures
+        for input in &mut closure.inputs {
+            self.visit_pat_mut(input);
+        }
         self.visit_expr_mut(&mut closure.body);
 
         // Note: We could add metadata tracking here for captured variables
@@ -487,6 +439,22 @@ impl OwnershipVisitor {
         matches!(expr, Expr::Path(_))
     }
 
+    /// Add a variable to the current scope, to be dropped by `tracking_id`
+    /// when the runtime ID is known and by name otherwise
+    fn bind(&mut self, name: &str, tracking_id: Option<TokenStream>) {
+        self.raw_ptrs.remove(name);
+        if let Some(current_scope) = self.scope_stack.last_mut() {
+            current_scope.push(match &tracking_id {
+                Some(id) => quote! { borrowscope_runtime::track_drop_id(#id) },
+                None => quote! { borrowscope_runtime::track_drop(#name) },
+            });
+        }
+        match tracking_id {
+            Some(id) => self.tracking_ids.insert(name.to_string(), id),
+            None => self.tracking_ids.remove(name),
+        };
+    }
+
     /// Transform a let statement to inject track_new_with_id
     fn transform_local(&mut self, local: &mut Local) {
         // Only transform if there's an initializer
@@ -499,16 +467,44 @@ impl OwnershipVisitor {
 
             let var_name = Self::extract_pattern_name(&local.pat);
             let var_id = self.gen_id();
-            let location = Self::extract_location(local.pat.span());
+            let id = id_expr(var_id);
+            let location = Self::location_expr(local.pat.span());
+            // Blocks around the variable inside the function body
+            let scope_depth = self.scope_depth.saturating_sub(1);
 
             // Store variable ID for later reference
             self.var_ids.insert(var_name.clone(), var_id);
 
-            // Add to current scope for drop tracking
-            if let Some(current_scope) = self.scope_stack.last_mut() {
-                current_scope.push(var_name.clone());
+            if self.track_unsafe {
+                let mut pointer_expr = unsafe_code::explicit_pointer_init(&local.pat, &init.expr)
+                    .unwrap_or_else(|| init.expr.as_ref().clone());
+                if let Some(tracked) =
+                    self.transform_raw_pointer(&var_name, var_id, &mut pointer_expr)
+                {
+                    *init.expr = tracked;
+                    self.bind(&var_name, Some(id));
+                    self.raw_ptrs.insert(var_name, var_id);
+                    if let Some((_, diverge)) = &mut init.diverge {
+                        self.visit_expr_mut(diverge);
+                    }
+                    return;
+                }
             }
 
+            // The `_with_id` tracking calls below record the variable as `name_id`,
+            // see `VarId`; only a move from an untracked variable leaves the ID to
+            // the runtime
+            let untracked_move = matches!(
+                init.expr.as_ref(),
+                Expr::Path(path) if path
+                    .path
+                    .get_ident()
+                    .is_some_and(|source| !self.var_ids.contains_key(&source.to_string()))
+            );
+            let tracking_id =
+                (!untracked_move).then(|| quote! { borrowscope_runtime::VarId(#var_name, #id) });
+            self.bind(&var_name, tracking_id);
+
             let original_expr = &init.expr;
 
             // Check for smart pointer operations first
@@ -516,39 +512,39 @@ impl OwnershipVisitor {
                 let new_expr = match sp_type {
                     SmartPointerType::Rc => {
                         syn::parse_quote! {
-                            borrowscope_runtime::track_rc_new_with_id(#var_id, #var_name, "Rc<T>", #location, #original_expr)
+                            borrowscope_runtime::track_rc_new_with_id(#id, #var_name, "Rc<T>", #location, #original_expr)
                         }
                     }
                     SmartPointerType::Arc => {
                         syn::parse_quote! {
-                            borrowscope_runtime::track_arc_new_with_id(#var_id, #var_name, "Arc<T>", #location, #original_expr)
+                            borrowscope_runtime::track_arc_new_with_id(#id, #var_name, "Arc<T>", #location, #original_expr)
                         }
                     }
                     _ => {
                         // Box and others use regular tracking
                         syn::parse_quote! {
-                            borrowscope_runtime::__track_new_with_id_helper(#var_id, #var_name, #location, #original_expr)
+                            borrowscope_runtime::__track_new_with_id_helper(#id, #var_name, #location, #scope_depth, #original_expr)
                         }
                     }
                 };
                 *init.expr = new_expr;
             } else if let Some(sp_type) = detect_rc_clone(original_expr) {
                 // Extract source ID from Rc::clone(&x) or Arc::clone(&x)
-                let source_id = self.extract_clone_source_id(original_expr);
+                let source_id = id_expr(self.extract_clone_source_id(original_expr));
                 let new_expr = match sp_type {
                     SmartPointerType::Rc => {
                         syn::parse_quote! {
-                            borrowscope_runtime::track_rc_clone_with_id(#var_id, #source_id, #var_name, #location, #original_expr)
+                            borrowscope_runtime::track_rc_clone_with_id(#id, #source_id, #var_name, #location, #original_expr)
                         }
                     }
                     SmartPointerType::Arc => {
                         syn::parse_quote! {
-                            borrowscope_runtime::track_arc_clone_with_id(#var_id, #source_id, #var_name, #location, #original_expr)
+                            borrowscope_runtime::track_arc_clone_with_id(#id, #source_id, #var_name, #location, #original_expr)
                         }
                     }
                     _ => {
                         syn::parse_quote! {
-                            borrowscope_runtime::__track_new_with_id_helper(#var_id, #var_name, #location, #original_expr)
+                            borrowscope_runtime::__track_new_with_id_helper(#id, #var_name, #location, #scope_depth, #original_expr)
                         }
                     }
                 };
@@ -560,9 +556,12 @@ impl OwnershipVisitor {
                     if let Some(source_ident) = path_expr.path.get_ident() {
                         let source_name = source_ident.to_string();
                         if let Some(&source_id) = self.var_ids.get(&source_name) {
+                            let source_id = id_expr(source_id);
+                            let source_id =
+                                quote! { borrowscope_runtime::VarId(#source_name, #source_id) };
                             // Use advanced move API with IDs
                             let new_expr: Expr = syn::parse_quote! {
-                                borrowscope_runtime::track_move_with_id(#source_id, #var_id, #var_name, #location, #original_expr)
+                                borrowscope_runtime::track_move_with_id(#source_id, #id, #var_name, #location, #original_expr)
                             };
                             *init.expr = new_expr;
                         } else {
@@ -575,27 +574,48 @@ impl OwnershipVisitor {
                     } else {
                         // Not a simple identifier - use helper function that extracts type
                         let new_expr: Expr = syn::parse_quote! {
-                            borrowscope_runtime::__track_new_with_id_helper(#var_id, #var_name, #location, #original_expr)
+                            borrowscope_runtime::__track_new_with_id_helper(#id, #var_name, #location, #scope_depth, #original_expr)
                         };
                         *init.expr = new_expr;
                     }
                 } else {
                     let new_expr: Expr = syn::parse_quote! {
-                        borrowscope_runtime::__track_new_with_id_helper(#var_id, #var_name, #location, #original_expr)
+                        borrowscope_runtime::__track_new_with_id_helper(#id, #var_name, #location, #scope_depth, #original_expr)
                     };
                     *init.expr = new_expr;
                 }
             } else {
                 // Regular variable creation - use helper function
                 let new_expr: Expr = syn::parse_quote! {
-                    borrowscope_runtime::__track_new_with_id_helper(#var_id, #var_name, #location, #original_expr)
+                    borrowscope_runtime::__track_new_with_id_helper(#id, #var_name, #location, #scope_depth, #original_expr)
                 };
                 *init.expr = new_expr;
             }
         }
 
-        // Continue visiting nested expressions
-        visit_mut::visit_local_mut(self, local);
+        // Continue visiting nested expressions; the pattern was bound above
+        match &mut local.init {
+            Some(init) => {
+                self.visit_expr_mut(&mut init.expr);
+                if let Some((_, diverge)) = &mut init.diverge {
+                    self.visit_expr_mut(diverge);
+                }
+            }
+            None => self.visit_pat_mut(&mut local.pat),
+        }
+    }
+
+    /// Bindings that a block, closure or pattern can shadow until it ends
+    fn bindings(&self) -> (HashMap<String, usize>, HashMap<String, TokenStream>) {
+        (self.raw_ptrs.clone(), self.tracking_ids.clone())
+    }
+
+    fn restore_bindings(
+        &mut self,
+        (raw_ptrs, tracking_ids): (HashMap<String, usize>, HashMap<String, TokenStream>),
+    ) {
+        self.raw_ptrs = raw_ptrs;
+        self.tracking_ids = tracking_ids;
     }
 
     /// Extract source variable ID from Rc::clone(&x) or Arc::clone(&x)
@@ -622,12 +642,13 @@ impl OwnershipVisitor {
 
         let is_mutable = ref_expr.mutability.is_some();
         let borrowed_expr = &ref_expr.expr;
-        let location = Self::extract_location(ref_expr.span());
+        let location = Self::location_expr(ref_expr.span());
 
         // Try to get owner ID
-        let owner_id = if let Expr::Path(path) = borrowed_expr.as_ref() {
+        let owner = if let Expr::Path(path) = borrowed_expr.as_ref() {
             if let Some(ident) = path.path.get_ident() {
-                self.var_ids.get(&ident.to_string()).copied()
+                let name = ident.to_string();
+                self.var_ids.get(&name).map(|&id| (name, id))
             } else {
                 None
             }
@@ -636,8 +657,11 @@ impl OwnershipVisitor {
         };
 
         // Generate tracking call
-        let tracking_call: Expr = if let Some(owner_id) = owner_id {
-            let borrower_id = self.gen_id();
+        let tracking_call: Expr = if let Some((owner_name, owner_id)) = owner {
+            // The runtime ID the owner's New event recorded
+            let owner_id = id_expr(owner_id);
+            let owner_id = quote! { borrowscope_runtime::VarId(#owner_name, #owner_id) };
+            let borrower_id = id_expr(self.gen_id());
             // Use advanced API with IDs
             if is_mutable {
                 syn::parse_quote! {
@@ -663,6 +687,144 @@ impl OwnershipVisitor {
 
         *expr = tracking_call;
     }
+
+    /// Wrap a cast to a raw pointer or a `&raw` expression with `track_raw_ptr`
+    fn transform_raw_pointer(&mut self, name: &str, id: usize, expr: &mut Expr) -> Option<Expr> {
+        let source = unsafe_code::raw_pointer_source(expr, |name| self.raw_ptrs.contains_key(name))
+            .and_then(|name| self.tracking_ids.get(&name).cloned());
+        let pointer = RawPointer::of(expr)?;
+        let (mutable, ptr_type) = (pointer.mutable, pointer.ptr_type);
+
+        // A borrow taken only to make the pointer is not tracked on its own
+        if source.is_none() {
+            self.visit_expr_mut(pointer.operand);
+        }
+
+        let location = Self::location_expr(expr.span());
+        Some(unsafe_code::track_raw_pointer(
+            name,
+            id_expr(id),
+            source,
+            mutable,
+            &ptr_type,
+            location,
+            expr,
+        ))
+    }
+
+    /// Record a dereference of a tracked raw pointer
+    fn transform_deref(&mut self, unary: &mut ExprUnary, is_write: bool) -> bool {
+        let Some(&ptr_id) =
+            unsafe_code::deref_target(unary).and_then(|name| self.raw_ptrs.get(&name))
+        else {
+            return false;
+        };
+        let location = Self::location_expr(unary.span());
+        unsafe_code::track_deref(unary, id_expr(ptr_id), location, is_write);
+        true
+    }
+
+    /// Record the dereference at the root of an assigned place, e.g. `(*p).x = 1`
+    fn transform_deref_write(&mut self, place: &mut Expr) {
+        if let Some(unary) = unsafe_code::assigned_deref(place) {
+            self.transform_deref(unary, true);
+        }
+    }
+
+    /// Record `mem::transmute` and calls into `extern` functions before they run
+    fn transform_unsafe_call(&mut self, call: &mut ExprCall) -> Option<Expr> {
+        let Expr::Path(func) = call.func.as_ref() else {
+            return None;
+        };
+        let location = Self::location_expr(call.span());
+
+        if unsafe_code::is_transmute(&func.path) {
+            visit_mut::visit_expr_call_mut(self, call);
+            return unsafe_code::track_transmute(call, location);
+        }
+
+        let name = unsafe_code::call_name(&func.path);
+        let is_extern = match func.path.get_ident() {
+            Some(ident) => self.extern_fns.contains(&ident.to_string()),
+            None => func.path.segments.iter().any(|seg| seg.ident == "libc"),
+        };
+        if !is_extern {
+            return None;
+        }
+
+        visit_mut::visit_expr_call_mut(self, call);
+        Some(unsafe_code::track_ffi_call(&name, call, location))
+    }
+
+    /// Instrument the unsafe constructs in `expr`, returning whether it was fully
+    /// handled here
+    fn transform_unsafe_expr(&mut self, expr: &mut Expr) -> bool {
+        match expr {
+            Expr::Unsafe(unsafe_expr) => {
+                self.visit_block_mut(&mut unsafe_expr.block);
+                let block_id = id_expr(self.gen_id());
+                let location = Self::location_expr(unsafe_expr.span());
+                let guard = unsafe_code::unsafe_block_guard(block_id, location);
+                unsafe_expr.block.stmts.insert(0, guard);
+                true
+            }
+            Expr::Cast(_) | Expr::RawAddr(_) => {
+                let id = self.gen_id();
+                match self.transform_raw_pointer("raw_ptr", id, expr) {
+                    Some(tracked) => {
+                        *expr = tracked;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Expr::Unary(unary) => self.transform_deref(unary, false),
+            Expr::Assign(assign) => {
+                self.transform_deref_write(&mut assign.left);
+                false
+            }
+            Expr::Binary(binary) if unsafe_code::is_compound_assign(&binary.op) => {
+                self.transform_deref_write(&mut binary.left);
+                false
+            }
+            Expr::Call(call) => match self.transform_unsafe_call(call) {
+                Some(tracked) => {
+                    *expr = tracked;
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Instrument an expression, handling closures, method calls and unsafe
+    /// constructs before the default traversal
+    fn transform_expr(&mut self, expr: &mut Expr) {
+        // Handle closures before default traversal
+        if let Expr::Closure(closure) = expr {
+            self.transform_closure(closure);
+            return;
+        }
+
+        // Handle method calls before default traversal
+        if let Expr::MethodCall(method_call) = expr {
+            self.transform_method_call(method_call);
+            return;
+        }
+
+        if self.track_unsafe && self.transform_unsafe_expr(expr) {
+            return;
+        }
+
+        // First recursively visit nested expressions
+        visit_mut::visit_expr_mut(self, expr);
+
+        // Then transform reference expressions at this level
+        if let Expr::Reference(ref_expr) = expr.clone() {
+            self.transform_reference(expr, &ref_expr);
+        }
+    }
 }
 
 impl Default for OwnershipVisitor {
@@ -673,15 +835,33 @@ impl Default for OwnershipVisitor {
 
 impl VisitMut for OwnershipVisitor {
     fn visit_item_fn_mut(&mut self, func: &mut ItemFn) {
+        if self.track_unsafe {
+            unsafe_code::rebind_pointer_params(&mut func.sig.inputs, &mut func.block);
+            // Only `extern` blocks in the body are known to declare foreign functions
+            self.extern_fns
+                .extend(unsafe_code::extern_fns(func.block.stmts.iter().filter_map(
+                    |stmt| match stmt {
+                        Stmt::Item(item) => Some(item),
+                        _ => None,
+                    },
+                )));
+        }
+
         // Only visit the function body, not nested items
         self.visit_block_mut(&mut func.block);
+
+        // IDs are numbered per function; offset them so each call gets its own
+        if let Some(reserve) = unsafe_code::reserve_ids(self.next_id - 1) {
+            func.block.stmts.insert(0, reserve);
+        }
     }
 
     fn visit_block_mut(&mut self, block: &mut Block) {
         self.scope_depth += 1;
 
-        // Push new scope
+        // Push new scope; bindings made in it go out of scope with it
         self.scope_stack.push(Vec::new());
+        let outer = self.bindings();
 
         // Clear pending inserts for this block
         self.pending_inserts.clear();
@@ -709,9 +889,9 @@ impl VisitMut for OwnershipVisitor {
             if has_trailing_expr && !scope_vars.is_empty() {
                 // Insert drops before the last expression
                 let last_stmt = block.stmts.pop();
-                for var_name in scope_vars.into_iter().rev() {
+                for drop_call in scope_vars.into_iter().rev() {
                     let drop_stmt: Stmt = syn::parse_quote! {
-                        borrowscope_runtime::track_drop(#var_name);
+                        #drop_call;
                     };
                     block.stmts.push(drop_stmt);
                 }
@@ -721,15 +901,16 @@ impl VisitMut for OwnershipVisitor {
                 }
             } else {
                 // No trailing expression, just append drops
-                for var_name in scope_vars.into_iter().rev() {
+                for drop_call in scope_vars.into_iter().rev() {
                     let drop_stmt: Stmt = syn::parse_quote! {
-                        borrowscope_runtime::track_drop(#var_name);
+                        #drop_call;
                     };
                     block.stmts.push(drop_stmt);
                 }
             }
         }
 
+        self.restore_bindings(outer);
         self.scope_depth -= 1;
     }
 
@@ -751,25 +932,34 @@ impl VisitMut for OwnershipVisitor {
     }
 
     fn visit_expr_mut(&mut self, expr: &mut Expr) {
-        // Handle closures before default traversal
-        if let Expr::Closure(closure) = expr {
-            self.transform_closure(closure);
-            return;
-        }
-
-        // Handle method calls before default traversal
-        if let Expr::MethodCall(method_call) = expr {
-            self.transform_method_call(method_call);
-            return;
+        // Closure parameters and `for`, `if let` and `while let` patterns bind
+        // names until the expression ends
+        if matches!(
+            expr,
+            Expr::Closure(_) | Expr::ForLoop(_) | Expr::If(_) | Expr::While(_)
+        ) {
+            let outer = self.bindings();
+            self.transform_expr(expr);
+            self.restore_bindings(outer);
+        } else {
+            self.transform_expr(expr);
         }
+    }
 
-        // First recursively visit nested expressions
-        visit_mut::visit_expr_mut(self, expr);
+    fn visit_arm_mut(&mut self, arm: &mut Arm) {
+        let outer = self.bindings();
+        visit_mut::visit_arm_mut(self, arm);
+        self.restore_bindings(outer);
+    }
 
-        // Then transform reference expressions at this level
-        if let Expr::Reference(ref_expr) = expr.clone() {
-            self.transform_reference(expr, &ref_expr);
+    /// A name bound by a pattern is no longer a tracked variable or pointer
+    fn visit_pat_mut(&mut self, pat: &mut Pat) {
+        if let Pat::Ident(binding) = pat {
+            let name = binding.ident.to_string();
+            self.raw_ptrs.remove(&name);
+            self.tracking_ids.remove(&name);
         }
+        visit_mut::visit_pat_mut(self, pat);
     }
 }
 
@@ -932,4 +1122,123 @@ mod tests {
         assert!(output.contains("b"));
         assert!(output.contains("c"));
     }
+
+    fn transform_unsafe(mut func: ItemFn) -> String {
+        let mut visitor = OwnershipVisitor::new().with_unsafe_tracking();
+        visitor.visit_item_fn_mut(&mut func);
+        func.to_token_stream().to_string()
+    }
+
+    #[test]
+    fn test_raw_addr_tracked_with_source() {
+        let output = transform_unsafe(parse_quote! {
+            fn example() {
+                let mut x = 1;
+                let p = &raw mut x;
+                unsafe { *p = 2; }
+            }
+        });
+
+        assert!(output.contains("track_raw_ptr_mut_from (\"p\""));
+        // The pointer records the runtime ID of `x`, which it is also dropped by
+        let x = "borrowscope_runtime :: VarId (\"x\" , _borrowscope_ids + 1usize)";
+        assert!(output.contains(&format!("{} , \"*mut _\"", x)));
+        assert!(output.contains(&format!("track_drop_id ({})", x)));
+        assert!(output.contains("track_unsafe_block"));
+        assert!(output.contains("track_raw_ptr_deref"));
+        // The borrow only makes the pointer, so it is not tracked separately
+        assert!(!output.contains("track_borrow"));
+    }
+
+    #[test]
+    fn test_pointer_source_is_the_binding_in_scope() {
+        let output = transform_unsafe(parse_quote! {
+            fn example() {
+                let mut x = 1;
+                {
+                    let x = 2;
+                    let _y = x;
+                }
+                let p = &raw mut x;
+                unsafe { *p = 3; }
+            }
+        });
+
+        assert!(output.contains("VarId (\"x\" , _borrowscope_ids + 2usize))"));
+        assert!(output.contains("VarId (\"x\" , _borrowscope_ids + 1usize) , \"*mut _\""));
+    }
+
+    #[test]
+    fn test_pattern_bindings_shadow_pointers() {
+        let output = transform_unsafe(parse_quote! {
+            fn example(values: Vec<i32>) {
+                let mut x = 1;
+                let p = &raw mut x;
+                let _f = |p: &i32| *p;
+                for p in &values {
+                    let _v = *p;
+                }
+                match values.first() {
+                    Some(p) => {
+                        let _v = *p;
+                    }
+                    None => {}
+                }
+                if let Some(p) = values.last() {
+                    let _v = *p;
+                }
+                unsafe { *p = 2; }
+            }
+        });
+
+        // Only the last dereference goes through the pointer
+        assert_eq!(output.matches("track_raw_ptr_deref").count(), 1);
+        assert!(output.contains("track_raw_ptr_deref (_borrowscope_ids + 2usize"));
+    }
+
+    #[test]
+    fn test_pointer_shadowed_in_block_is_restored() {
+        let output = transform_unsafe(parse_quote! {
+            fn example() {
+                let mut x = 1;
+                let p = &raw mut x;
+                {
+                    let p = 5;
+                    let _q = p;
+                }
+                unsafe { *p = 2; }
+            }
+        });
+
+        assert_eq!(output.matches("track_raw_ptr_deref").count(), 1);
+    }
+
+    #[test]
+    fn test_shadowed_pointer_deref_not_tracked() {
+        let output = transform_unsafe(parse_quote! {
+            fn example() {
+                let v = vec![1];
+                let p = &v as *const Vec<i32>;
+                let p = &v;
+                let _n = p.len();
+                let _m = (*p).len();
+            }
+        });
+
+        assert!(output.contains("track_raw_ptr_from"));
+        assert!(!output.contains("track_raw_ptr_deref"));
+    }
+
+    #[test]
+    fn test_libc_call_tracked_as_ffi() {
+        let output = transform_unsafe(parse_quote! {
+            fn example() {
+                let p = unsafe { libc::malloc(8) };
+                unsafe { libc::free(p) };
+            }
+        });
+
+        assert!(output.contains("track_ffi_call (\"libc::malloc\""));
+        assert!(output.contains("track_ffi_call (\"libc::free\""));
+    }
 }
@@ -0,0 +1,289 @@
+//! Tracking calls for unsafe code
+//!
+//! Both the `#[trace_borrow(unsafe_code)]` macro and the CLI instrumenter
+//! rewrite unsafe blocks, raw pointers, their dereferences, transmutes and FFI
+//! calls the same way. They keep track of which names are pointers and which
+//! runtime IDs they have; this module builds the calls.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    BinOp, Block, Expr, ExprCall, ExprUnary, FnArg, ForeignItem, Item, Pat, PointerMutability,
+    Stmt, Type, UnOp,
+};
+
+/// Expression for the runtime value of ID `id`: the offset the call got from
+/// `reserve_ids` plus `id`. ID 0 stands for an unknown variable and stays 0.
+pub fn id_expr(id: usize) -> TokenStream {
+    if id == 0 {
+        quote! { 0usize }
+    } else {
+        quote! { _borrowscope_ids + #id }
+    }
+}
+
+/// Statement reserving `count` IDs for one call of the function, or `None`
+/// when the function uses none
+pub fn reserve_ids(count: usize) -> Option<Stmt> {
+    (count > 0).then(|| {
+        syn::parse_quote! {
+            let _borrowscope_ids = borrowscope_runtime::reserve_ids(#count);
+        }
+    })
+}
+
+/// Variable named by a path expression
+pub fn path_ident(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Name of what the pointer made by `expr` is made from, see [`pointer_source`]
+pub fn raw_pointer_source(expr: &Expr, is_raw_ptr: impl Fn(&str) -> bool) -> Option<String> {
+    match expr {
+        Expr::Cast(cast) => pointer_source(&cast.expr, is_raw_ptr),
+        other => pointer_source(other, is_raw_ptr),
+    }
+}
+
+/// Name of what a cast pointer is made from: a variable behind `&` or `&raw`,
+/// or another raw pointer as told by `is_raw_ptr`
+pub fn pointer_source(operand: &Expr, is_raw_ptr: impl Fn(&str) -> bool) -> Option<String> {
+    match operand {
+        Expr::Paren(paren) => pointer_source(&paren.expr, is_raw_ptr),
+        Expr::Reference(reference) => path_ident(&reference.expr),
+        Expr::RawAddr(raw) => path_ident(&raw.expr),
+        other => path_ident(other).filter(|name| is_raw_ptr(name)),
+    }
+}
+
+/// A cast to a raw pointer or a `&raw` expression
+pub struct RawPointer<'a> {
+    /// `*const T` or `*mut T`
+    pub ptr_type: String,
+    pub mutable: bool,
+    /// What the pointer is made from
+    pub operand: &'a mut Expr,
+}
+
+impl RawPointer<'_> {
+    /// The pointer made by `expr`, if it makes one
+    pub fn of(expr: &mut Expr) -> Option<RawPointer<'_>> {
+        let (mutable, pointee, operand) = match expr {
+            Expr::Cast(cast) => {
+                let Type::Ptr(ptr) = cast.ty.as_ref() else {
+                    return None;
+                };
+                let elem = &ptr.elem;
+                let mutable = ptr.mutability.is_some();
+                (mutable, quote!(#elem).to_string(), &mut cast.expr)
+            }
+            Expr::RawAddr(raw) => {
+                let mutable = matches!(raw.mutability, PointerMutability::Mut(_));
+                (mutable, "_".to_string(), &mut raw.expr)
+            }
+            _ => return None,
+        };
+        let ptr_type = format!("*{} {}", if mutable { "mut" } else { "const" }, pointee);
+        Some(RawPointer {
+            ptr_type,
+            mutable,
+            operand,
+        })
+    }
+}
+
+/// Wrap `expr`, which makes a pointer of `ptr_type`, with `track_raw_ptr`
+/// or, when it is made from something tracked, `track_raw_ptr_from`
+pub fn track_raw_pointer(
+    name: &str,
+    id: TokenStream,
+    source: Option<TokenStream>,
+    mutable: bool,
+    ptr_type: &str,
+    location: TokenStream,
+    expr: &Expr,
+) -> Expr {
+    match (mutable, source) {
+        (false, None) => syn::parse_quote! {
+            borrowscope_runtime::track_raw_ptr(#name, #id, #ptr_type, #location, #expr)
+        },
+        (true, None) => syn::parse_quote! {
+            borrowscope_runtime::track_raw_ptr_mut(#name, #id, #ptr_type, #location, #expr)
+        },
+        (false, Some(source)) => syn::parse_quote! {
+            borrowscope_runtime::track_raw_ptr_from(#name, #id, #source, #ptr_type, #location, #expr)
+        },
+        (true, Some(source)) => syn::parse_quote! {
+            borrowscope_runtime::track_raw_ptr_mut_from(#name, #id, #source, #ptr_type, #location, #expr)
+        },
+    }
+}
+
+/// Turn `let p: *const T = init` into `let p = init as *const T` so the
+/// pointer is tracked like an explicit cast
+pub fn explicit_pointer_init(pat: &Pat, init: &Expr) -> Option<Expr> {
+    let Pat::Type(pat_type) = pat else {
+        return None;
+    };
+    let Type::Ptr(_) = pat_type.ty.as_ref() else {
+        return None;
+    };
+    if matches!(init, Expr::Cast(_) | Expr::RawAddr(_)) {
+        return None;
+    }
+    let ty = &pat_type.ty;
+    Some(syn::parse_quote! { #init as #ty })
+}
+
+/// Name of the pointer `unary` dereferences, if it is a dereference of a name
+pub fn deref_target(unary: &ExprUnary) -> Option<String> {
+    if !matches!(unary.op, UnOp::Deref(_)) {
+        return None;
+    }
+    path_ident(&unary.expr)
+}
+
+/// Record the dereference in `unary`: `*p` becomes
+/// `*{ track_raw_ptr_deref(..); p }`, which is still a place expression
+pub fn track_deref(
+    unary: &mut ExprUnary,
+    ptr_id: TokenStream,
+    location: TokenStream,
+    is_write: bool,
+) {
+    let pointer = &unary.expr;
+    *unary.expr = syn::parse_quote! {
+        {
+            borrowscope_runtime::track_raw_ptr_deref(#ptr_id, #location, #is_write);
+            #pointer
+        }
+    };
+}
+
+/// The dereference at the root of an assigned place, e.g. `*p` in `(*p).x = 1`
+pub fn assigned_deref(mut place: &mut Expr) -> Option<&mut ExprUnary> {
+    loop {
+        match place {
+            Expr::Field(field) => place = &mut field.base,
+            Expr::Index(index) => place = &mut index.expr,
+            Expr::Paren(paren) => place = &mut paren.expr,
+            Expr::Unary(unary) => return Some(unary),
+            _ => return None,
+        }
+    }
+}
+
+/// Whether `op` assigns to its left operand
+pub fn is_compound_assign(op: &BinOp) -> bool {
+    matches!(
+        op,
+        BinOp::AddAssign(_)
+            | BinOp::SubAssign(_)
+            | BinOp::MulAssign(_)
+            | BinOp::DivAssign(_)
+            | BinOp::RemAssign(_)
+            | BinOp::BitXorAssign(_)
+            | BinOp::BitAndAssign(_)
+            | BinOp::BitOrAssign(_)
+            | BinOp::ShlAssign(_)
+            | BinOp::ShrAssign(_)
+    )
+}
+
+/// Whether `path` names `mem::transmute`, `std::mem::transmute` or
+/// `core::mem::transmute`
+pub fn is_transmute(path: &syn::Path) -> bool {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|seg| seg.ident.to_string())
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    matches!(
+        segments.as_slice(),
+        ["mem", "transmute"] | ["std" | "core", "mem", "transmute"]
+    )
+}
+
+/// Run a `mem::transmute` call through `track_transmute_call`, which names the
+/// types it really converts between
+pub fn track_transmute(call: &ExprCall, location: TokenStream) -> Option<Expr> {
+    let [arg] = call.args.iter().collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let func = &call.func;
+    Some(syn::parse_quote! {
+        borrowscope_runtime::track_transmute_call(#location, #arg, |value| #func(value))
+    })
+}
+
+/// Record a call of the `extern` function `name` before it runs
+pub fn track_ffi_call(name: &str, call: &ExprCall, location: TokenStream) -> Expr {
+    syn::parse_quote! {
+        {
+            borrowscope_runtime::track_ffi_call(#name, #location);
+            #call
+        }
+    }
+}
+
+/// Full name of a called function, e.g. `libc::strlen`
+pub fn call_name(path: &syn::Path) -> String {
+    path.segments
+        .iter()
+        .map(|seg| seg.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// Guard recording the entry and exit of an unsafe block, to insert as its
+/// first statement
+pub fn unsafe_block_guard(block_id: TokenStream, location: TokenStream) -> Stmt {
+    syn::parse_quote! {
+        let _borrowscope_unsafe_guard =
+            borrowscope_runtime::track_unsafe_block(#block_id, #location);
+    }
+}
+
+/// Rebind raw pointer parameters at the top of `block` so they are tracked
+/// like pointers declared with `let`
+pub fn rebind_pointer_params<'a>(
+    inputs: impl IntoIterator<Item = &'a mut FnArg>,
+    block: &mut Block,
+) {
+    let mut rebinds: Vec<Stmt> = Vec::new();
+    for input in inputs {
+        let FnArg::Typed(pat_type) = input else {
+            continue;
+        };
+        let (Pat::Ident(pat_ident), Type::Ptr(_)) = (pat_type.pat.as_mut(), pat_type.ty.as_ref())
+        else {
+            continue;
+        };
+        // The rebinding takes over `mut`, leaving the parameter itself unused as mutable
+        let mutability = pat_ident.mutability.take();
+        let ident = &pat_ident.ident;
+        let ty = &pat_type.ty;
+        rebinds.push(syn::parse_quote! { let #mutability #ident: #ty = #ident; });
+    }
+    block.stmts.splice(0..0, rebinds);
+}
+
+/// Names of the functions declared in `extern` blocks among `items`
+pub fn extern_fns<'a>(items: impl IntoIterator<Item = &'a Item>) -> Vec<String> {
+    items
+        .into_iter()
+        .filter_map(|item| match item {
+            Item::ForeignMod(foreign) => Some(&foreign.items),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|item| match item {
+            ForeignItem::Fn(func) => Some(func.sig.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+}